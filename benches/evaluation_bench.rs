@@ -0,0 +1,81 @@
+//! Evaluation benchmarks for the common offset-0 signature rules
+//!
+//! The same ruleset is evaluated twice: as written, where `byte` and `string`
+//! equality rules at offset 0 take the evaluator's fast path, and with every
+//! offset rewritten to the equivalent `&0`, which forces offset resolution and
+//! typed reads through the general path.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use libmagic_rs::EvaluationConfig;
+use libmagic_rs::evaluator::evaluate_rules_with_config;
+use libmagic_rs::parser::parse_magic_string;
+
+/// Top-level signatures of common formats, as a system magic file writes them
+const SIGNATURES: &str = r"0	string	\x7fELF	ELF
+0	string	PK\x03\x04	Zip archive data
+0	string	%PDF-	PDF document
+0	string	\x89PNG\r\n\x1a\n	PNG image data
+0	string	GIF87a	GIF image data, version 87a
+0	string	GIF89a	GIF image data, version 89a
+0	string	\x1f\x8b	gzip compressed data
+0	string	BZh	bzip2 compressed data
+0	string	\xfd7zXZ\x00	XZ compressed data
+0	string	7z\xbc\xaf\x27\x1c	7-zip archive data
+0	string	MZ	MS-DOS executable
+0	string	RIFF	RIFF data
+0	string	OggS	Ogg data
+0	string	fLaC	FLAC audio bitstream data
+0	string	ID3	Audio file with ID3 version 2
+0	string	{\\rtf	Rich Text Format data
+0	byte	0xca	Java class data or Mach-O universal binary
+0	byte	0xcf	Mach-O 64-bit
+0	byte	0xef	UTF-8 text
+0	byte	0x00	data with a leading NUL
+";
+
+/// Inputs that match early, late, and not at all
+const INPUTS: &[(&str, &[u8])] = &[
+    (
+        "elf",
+        b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00",
+    ),
+    ("utf8", b"\xef\xbb\xbfhello, world\n"),
+    (
+        "unknown",
+        b"\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42",
+    ),
+];
+
+fn bench_offset_zero_rules(c: &mut Criterion) {
+    let fast = parse_magic_string(SIGNATURES).unwrap();
+    let relative: String = SIGNATURES
+        .lines()
+        .map(|line| format!("&{line}\n"))
+        .collect();
+    let general = parse_magic_string(&relative).unwrap();
+    let config = EvaluationConfig::default();
+
+    let mut group = c.benchmark_group("offset_zero_signatures");
+    for &(name, data) in INPUTS {
+        let expected = evaluate_rules_with_config(&fast, data, config.clone()).unwrap();
+        let actual = evaluate_rules_with_config(&general, data, config.clone()).unwrap();
+        assert_eq!(
+            format!("{expected:?}"),
+            format!("{actual:?}"),
+            "paths disagree on {name}"
+        );
+
+        group.bench_function(format!("fast_path/{name}"), |b| {
+            b.iter(|| evaluate_rules_with_config(&fast, black_box(data), config.clone()));
+        });
+        group.bench_function(format!("general_path/{name}"), |b| {
+            b.iter(|| evaluate_rules_with_config(&general, black_box(data), config.clone()));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_offset_zero_rules);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+//! Magic rules compiled into the library
+//!
+//! [`BUILTIN_MAGIC`] is a small magic file embedded in the binary, so common
+//! formats can be identified without a system magic database. The ELF rules
+//! are written the way a magic file would write them: the class and byte
+//! order select a subroutine, and each field hands over to the next one with
+//! `use`, so a single field that does not match ends the description there.
+//! Big-endian files reuse the little-endian subroutines through `use ^`. A
+//! shared object is reported as a `pie executable` when its first or second
+//! program header is `PT_INTERP`; the ELF specification places that header
+//! before any loadable segment, after `PT_PHDR` if there is one.
+
+use crate::Result;
+use crate::parser::{ast::MagicRule, parse_magic_string};
+
+/// Source of the built-in magic rules, in magic(5) syntax
+pub const BUILTIN_MAGIC: &str = r"# ELF executables, libraries and object files
+0	string	\x7fELF\x01\x01	ELF 32-bit LSB
+>0	use	elf-le
+0	string	\x7fELF\x02\x01	ELF 64-bit LSB
+>0	use	elf-le
+0	string	\x7fELF\x01\x02	ELF 32-bit MSB
+>0	use	^elf-le
+0	string	\x7fELF\x02\x02	ELF 64-bit MSB
+>0	use	^elf-le
+
+# e_type, then the machine and the OS/ABI
+0	name	elf-le
+>16	leshort	1	relocatable
+!:mime	application/x-object
+>>0	use	elf-le-machine
+>16	leshort	2	executable
+!:mime	application/x-executable
+>>0	use	elf-le-machine
+>16	leshort	3	\b
+>>0	use	elf-le-dyn
+>16	leshort	4	core file
+!:mime	application/x-coredump
+>>0	use	elf-le-machine
+
+# ET_DYN with a program interpreter is a position-independent executable;
+# the empty `\b` messages only select the class
+0	name	elf-le-dyn
+>4	ubyte	1	\b
+>>(28.l)	lelong	3	pie executable
+!:mime	application/x-pie-executable
+>>>0	use	elf-le-machine
+>>(28.l+32)	lelong	3	pie executable
+!:mime	application/x-pie-executable
+>>>0	use	elf-le-machine
+>>0	default	x	shared object
+!:mime	application/x-sharedlib
+>>>0	use	elf-le-machine
+>4	ubyte	2	\b
+>>(32.q)	lelong	3	pie executable
+!:mime	application/x-pie-executable
+>>>0	use	elf-le-machine
+>>(32.q+56)	lelong	3	pie executable
+!:mime	application/x-pie-executable
+>>>0	use	elf-le-machine
+>>0	default	x	shared object
+!:mime	application/x-sharedlib
+>>>0	use	elf-le-machine
+
+0	name	elf-le-machine
+>18	leshort	3	\b, Intel 80386
+>>0	use	elf-osabi
+>18	leshort	8	\b, MIPS
+>>0	use	elf-osabi
+>18	leshort	20	\b, PowerPC
+>>0	use	elf-osabi
+>18	leshort	21	\b, 64-bit PowerPC
+>>0	use	elf-osabi
+>18	leshort	40	\b, ARM
+>>0	use	elf-osabi
+>18	leshort	62	\b, x86-64
+>>0	use	elf-osabi
+>18	leshort	183	\b, ARM aarch64
+>>0	use	elf-osabi
+>18	leshort	243	\b, RISC-V
+>>0	use	elf-osabi
+>18	leshort	x	\b, machine %#x
+>>0	use	elf-osabi
+
+0	name	elf-osabi
+>6	ubyte	x	\b, version %d
+>>7	ubyte	0	(SYSV)
+>>7	ubyte	2	(NetBSD)
+>>7	ubyte	3	(GNU/Linux)
+>>7	ubyte	6	(Solaris)
+>>7	ubyte	9	(FreeBSD)
+>>7	ubyte	12	(OpenBSD)
+";
+
+/// Parse [`BUILTIN_MAGIC`] into rules
+///
+/// # Errors
+///
+/// Returns an error if the built-in source fails to parse, which would be a
+/// bug in this crate.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::builtin;
+///
+/// let rules = builtin::rules()?;
+/// assert!(!rules.is_empty());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn rules() -> Result<Vec<MagicRule>> {
+    parse_magic_string(BUILTIN_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ParseOptions, parse_magic_string_with_options};
+
+    #[test]
+    fn test_builtin_magic_parses_without_warnings() {
+        let (parsed, warnings) =
+            parse_magic_string_with_options(BUILTIN_MAGIC, ParseOptions::default()).unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(parsed.len(), rules().unwrap().len());
+    }
+}
@@ -0,0 +1,834 @@
+//! Evaluation settings and their presets
+//!
+//! [`EvaluationConfig`] bounds how much work one evaluation may do and selects
+//! optional behavior such as following symbolic links or reading devices.
+//! [`EvaluationConfig::validate`] rejects settings that would make evaluation
+//! unsafe or meaningless.
+
+use crate::{Endianness, LibmagicError, Result, io};
+
+/// Configuration for rule evaluation
+///
+/// This struct controls various aspects of magic rule evaluation behavior,
+/// including performance limits, output options, and matching strategies.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::EvaluationConfig;
+///
+/// // Use default configuration
+/// let config = EvaluationConfig::default();
+///
+/// // Create custom configuration
+/// let custom_config = EvaluationConfig {
+///     max_recursion_depth: 10,
+///     max_string_length: 4096,
+///     max_pattern_length: 4096,
+///     stop_at_first_match: false, // Get all matches
+///     enable_mime_types: true,
+///     timeout_ms: Some(5000), // 5 second timeout
+///     max_search_range: 4096,
+///     max_rule_evaluations: None,
+///     max_matches: None,
+///     soft_bounds: false,
+///     follow_symlinks: true,
+///     read_special_files: false,
+///     first_byte_index: false,
+///     native_endianness_override: None,
+///     use_mmap: true,
+///     detect_truncation: false,
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // Independent on/off settings, not a state machine
+pub struct EvaluationConfig {
+    /// Maximum recursion depth for nested rules
+    ///
+    /// This prevents infinite recursion in malformed magic files and limits
+    /// the depth of rule hierarchy traversal. Default is 20.
+    pub max_recursion_depth: u32,
+
+    /// Maximum string length to read
+    ///
+    /// This limits the amount of data read for string types to prevent
+    /// excessive memory usage. Default is 8192 bytes.
+    pub max_string_length: usize,
+
+    /// Maximum length of a string or search pattern in a rule
+    ///
+    /// Databases loaded from a file with a longer pattern are rejected with a
+    /// parse error, so a magic file cannot make every comparison or search
+    /// carry an outsized pattern. Unlike `max_string_length`, this bounds the
+    /// rules rather than what is read from the file. Default is 8192 bytes.
+    pub max_pattern_length: usize,
+
+    /// Stop at first match or continue for all matches
+    ///
+    /// When `true`, evaluation stops after the first matching rule.
+    /// When `false`, all rules are evaluated to find all matches.
+    /// Default is `true` for performance.
+    pub stop_at_first_match: bool,
+
+    /// Enable MIME type mapping in results
+    ///
+    /// When `true`, the evaluator will attempt to map file type descriptions
+    /// to standard MIME types. Default is `false`.
+    pub enable_mime_types: bool,
+
+    /// Timeout for evaluation in milliseconds
+    ///
+    /// If set, evaluation will be aborted if it takes longer than this duration.
+    /// `None` means no timeout. Default is `None`.
+    pub timeout_ms: Option<u64>,
+
+    /// Maximum number of bytes scanned by search-style rules
+    ///
+    /// This bounds how far a pattern search may look past its starting offset.
+    /// A search never scans past the end of the buffer, so a limit larger than
+    /// the data costs nothing. It must not exceed the largest file that can be
+    /// evaluated (1 GB). Default is 8192 bytes.
+    pub max_search_range: usize,
+
+    /// Maximum number of rules evaluated per buffer
+    ///
+    /// Once this many rules (including children) have been evaluated, evaluation
+    /// stops and reports the matches found so far, or `data` if there are none.
+    /// `None` means no budget.
+    /// Default is `None`.
+    pub max_rule_evaluations: Option<usize>,
+
+    /// Maximum number of matches reported per buffer
+    ///
+    /// Evaluation of a rule sequence stops once it has produced this many
+    /// matches, counting those of children, and the matches past the limit are
+    /// dropped. Only useful with `stop_at_first_match` disabled. `None` means no
+    /// limit. Default is `None`.
+    pub max_matches: Option<usize>,
+
+    /// Treat out-of-bounds reads as non-matches instead of errors
+    ///
+    /// When `true`, a rule whose offset or value lies outside the buffer simply
+    /// does not match. When `false`, such reads abort evaluation with an error.
+    /// Rules at an absolute offset past the end of the buffer, and their
+    /// children, are then skipped without counting toward
+    /// `max_rule_evaluations`. Default is `false`.
+    pub soft_bounds: bool,
+
+    /// Follow symbolic links when evaluating files
+    ///
+    /// When `false`, a symbolic link is reported as `symbolic link to TARGET`
+    /// rather than having its target's content evaluated. Default is `true`.
+    pub follow_symlinks: bool,
+
+    /// Read block devices, character devices and FIFOs like ordinary files
+    ///
+    /// When `true`, such special files are read with a bounded `read` of at
+    /// most the maximum file size instead of being rejected, like `file -s`.
+    /// Default is `false`.
+    pub read_special_files: bool,
+
+    /// Index top-level rules by the byte they require at offset 0
+    ///
+    /// When `true`, a [`MagicDatabase`] builds an [`evaluator::RuleIndex`] over
+    /// its rules and skips those the buffer's first byte rules out. Results are
+    /// identical either way; the index trades memory and load time for fewer
+    /// rule tests on large databases. Default is `false`.
+    pub first_byte_index: bool,
+
+    /// Byte order to use for types written without one (`short`, `long`, `quad`)
+    ///
+    /// `None` reads them in the host's byte order, like libmagic. Setting it
+    /// lets a database be checked for the behavior of a host with the other
+    /// byte order. Default is `None`.
+    pub native_endianness_override: Option<Endianness>,
+
+    /// Memory-map files opened by [`MagicDatabase::evaluate_file`]
+    ///
+    /// When `false`, files are always read into memory and `mmap` is never
+    /// called, for environments whose seccomp policy or sandbox forbids it.
+    /// Default is `true`; a file that cannot be mapped is read either way.
+    pub use_mmap: bool,
+
+    /// Mark files whose format has a known trailer that is missing
+    ///
+    /// When `true`, a ZIP archive or PDF document without its end-of-file
+    /// trailer gets ` (truncated)` appended to its description (see
+    /// [`evaluator::trailer`]). Only done when the whole file was read.
+    /// Default is `false`.
+    pub detect_truncation: bool,
+}
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        Self {
+            max_recursion_depth: 20,
+            max_string_length: 8192,
+            max_pattern_length: 8192,
+            stop_at_first_match: true,
+            enable_mime_types: false,
+            timeout_ms: None,
+            max_search_range: 8192,
+            max_rule_evaluations: None,
+            max_matches: None,
+            soft_bounds: false,
+            follow_symlinks: true,
+            read_special_files: false,
+            first_byte_index: false,
+            native_endianness_override: None,
+            use_mmap: true,
+            detect_truncation: false,
+        }
+    }
+}
+
+impl EvaluationConfig {
+    /// Create a new configuration with default values
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let config = EvaluationConfig::new();
+    /// assert_eq!(config.max_recursion_depth, 20);
+    /// assert_eq!(config.max_string_length, 8192);
+    /// assert!(config.stop_at_first_match);
+    /// assert!(!config.enable_mime_types);
+    /// assert_eq!(config.timeout_ms, None);
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a configuration optimized for performance
+    ///
+    /// This configuration prioritizes speed over completeness:
+    /// - Lower recursion depth limit
+    /// - Smaller string length limit
+    /// - Stop at first match
+    /// - No MIME type mapping
+    /// - Short timeout
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let config = EvaluationConfig::performance();
+    /// assert_eq!(config.max_recursion_depth, 10);
+    /// assert_eq!(config.max_string_length, 1024);
+    /// assert!(config.stop_at_first_match);
+    /// assert!(!config.enable_mime_types);
+    /// assert_eq!(config.timeout_ms, Some(1000));
+    /// ```
+    #[must_use]
+    pub const fn performance() -> Self {
+        Self {
+            max_recursion_depth: 10,
+            max_string_length: 1024,
+            max_pattern_length: 1024,
+            stop_at_first_match: true,
+            enable_mime_types: false,
+            timeout_ms: Some(1000), // 1 second
+            max_search_range: 1024,
+            max_rule_evaluations: None,
+            max_matches: None,
+            soft_bounds: false,
+            follow_symlinks: true,
+            read_special_files: false,
+            first_byte_index: false,
+            native_endianness_override: None,
+            use_mmap: true,
+            detect_truncation: false,
+        }
+    }
+
+    /// Create a configuration optimized for completeness
+    ///
+    /// This configuration prioritizes finding all matches over speed:
+    /// - Higher recursion depth limit
+    /// - Larger string length limit
+    /// - Find all matches
+    /// - Enable MIME type mapping
+    /// - Longer timeout
+    ///
+    /// Truncation detection stays off, as in every preset; enable
+    /// `detect_truncation` to have descriptions marked ` (truncated)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let config = EvaluationConfig::comprehensive();
+    /// assert_eq!(config.max_recursion_depth, 50);
+    /// assert_eq!(config.max_string_length, 32768);
+    /// assert!(!config.stop_at_first_match);
+    /// assert!(config.enable_mime_types);
+    /// assert_eq!(config.timeout_ms, Some(30000));
+    /// assert!(!config.detect_truncation);
+    /// ```
+    #[must_use]
+    pub const fn comprehensive() -> Self {
+        Self {
+            max_recursion_depth: 50,
+            max_string_length: 32768,
+            max_pattern_length: 32768,
+            stop_at_first_match: false,
+            enable_mime_types: true,
+            timeout_ms: Some(30000), // 30 seconds
+            max_search_range: 65536,
+            max_rule_evaluations: None,
+            max_matches: None,
+            soft_bounds: false,
+            follow_symlinks: true,
+            read_special_files: false,
+            first_byte_index: false,
+            native_endianness_override: None,
+            use_mmap: true,
+            detect_truncation: false,
+        }
+    }
+
+    /// Create a configuration for untrusted or hostile input
+    ///
+    /// This configuration keeps every resource limit tight so that a crafted
+    /// file cannot drive evaluation into excessive work:
+    /// - Very low recursion depth limit
+    /// - Small string and search limits
+    /// - Stop at first match
+    /// - Short timeout and a fixed rule-evaluation budget
+    /// - Out-of-bounds reads are non-matches rather than errors
+    /// - Symbolic links are not followed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let config = EvaluationConfig::hardened();
+    /// assert!(config.validate().is_ok());
+    /// assert_eq!(config.max_recursion_depth, 8);
+    /// assert!(config.soft_bounds);
+    /// assert!(!config.follow_symlinks);
+    /// ```
+    #[must_use]
+    pub const fn hardened() -> Self {
+        Self {
+            max_recursion_depth: 8,
+            max_string_length: 256,
+            max_pattern_length: 256,
+            stop_at_first_match: true,
+            enable_mime_types: false,
+            timeout_ms: Some(250),
+            max_search_range: 256,
+            max_rule_evaluations: Some(1000),
+            max_matches: None,
+            soft_bounds: true,
+            follow_symlinks: false,
+            read_special_files: false,
+            first_byte_index: false,
+            native_endianness_override: None,
+            use_mmap: true,
+            detect_truncation: false,
+        }
+    }
+
+    /// Validate the configuration settings
+    ///
+    /// Performs comprehensive security validation of all configuration values
+    /// to prevent malicious configurations that could lead to resource exhaustion,
+    /// denial of service, or other security issues.
+    ///
+    /// # Security
+    ///
+    /// This validation prevents:
+    /// - Stack overflow attacks through excessive recursion depth
+    /// - Memory exhaustion through oversized string limits
+    /// - Denial of service through excessive timeouts
+    /// - Integer overflow in configuration calculations
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::InvalidFormat` if any configuration values
+    /// are invalid or out of reasonable bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let config = EvaluationConfig::default();
+    /// assert!(config.validate().is_ok());
+    ///
+    /// let invalid_config = EvaluationConfig {
+    ///     max_recursion_depth: 0, // Invalid: must be > 0
+    ///     ..Default::default()
+    /// };
+    /// assert!(invalid_config.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        // Validate recursion depth to prevent stack overflow attacks
+        if self.max_recursion_depth == 0 {
+            return Err(LibmagicError::InvalidFormat(
+                "max_recursion_depth must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_recursion_depth > 1000 {
+            return Err(LibmagicError::InvalidFormat(
+                "max_recursion_depth must not exceed 1000 to prevent stack overflow".to_string(),
+            ));
+        }
+
+        // Validate string length to prevent memory exhaustion
+        if self.max_string_length == 0 {
+            return Err(LibmagicError::InvalidFormat(
+                "max_string_length must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_string_length > 1_048_576 {
+            // 1MB limit to prevent memory exhaustion attacks
+            return Err(LibmagicError::InvalidFormat(
+                "max_string_length must not exceed 1MB to prevent memory exhaustion".to_string(),
+            ));
+        }
+
+        // Validate pattern length for the same reason
+        if self.max_pattern_length == 0 {
+            return Err(LibmagicError::InvalidFormat(
+                "max_pattern_length must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_pattern_length > 1_048_576 {
+            return Err(LibmagicError::InvalidFormat(
+                "max_pattern_length must not exceed 1MB to prevent memory exhaustion".to_string(),
+            ));
+        }
+
+        // Validate timeout to prevent denial of service
+        if let Some(timeout) = self.timeout_ms {
+            if timeout == 0 {
+                return Err(LibmagicError::InvalidFormat(
+                    "timeout_ms must be greater than 0 if specified".to_string(),
+                ));
+            }
+
+            if timeout > 300_000 {
+                // 5 minute limit to prevent DoS through excessive timeouts
+                return Err(LibmagicError::InvalidFormat(
+                    "timeout_ms must not exceed 300000 (5 minutes) to prevent denial of service"
+                        .to_string(),
+                ));
+            }
+        }
+
+        // Validate search range to prevent unbounded scanning
+        if self.max_search_range == 0 {
+            return Err(LibmagicError::InvalidFormat(
+                "max_search_range must be greater than 0".to_string(),
+            ));
+        }
+
+        // A search cannot cover more than the largest file that is evaluated
+        if u64::try_from(self.max_search_range)
+            .map_or(true, |range| range > io::FileBuffer::MAX_FILE_SIZE)
+        {
+            return Err(LibmagicError::InvalidFormat(
+                "max_search_range must not exceed the 1GB maximum file size".to_string(),
+            ));
+        }
+
+        if self.max_rule_evaluations == Some(0) {
+            return Err(LibmagicError::InvalidFormat(
+                "max_rule_evaluations must be greater than 0 if specified".to_string(),
+            ));
+        }
+
+        if self.max_matches == Some(0) {
+            return Err(LibmagicError::InvalidFormat(
+                "max_matches must be greater than 0 if specified".to_string(),
+            ));
+        }
+
+        // Additional security checks for configuration consistency
+        if self.max_recursion_depth > 100 && self.max_string_length > 65536 {
+            return Err(LibmagicError::InvalidFormat(
+                "High recursion depth combined with large string length may cause resource exhaustion".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MagicDatabase, parser};
+
+    #[test]
+    fn test_evaluation_config_default() {
+        let config = EvaluationConfig::default();
+
+        assert_eq!(config.max_recursion_depth, 20);
+        assert_eq!(config.max_string_length, 8192);
+        assert!(config.stop_at_first_match);
+        assert!(!config.enable_mime_types);
+        assert_eq!(config.timeout_ms, None);
+    }
+
+    #[test]
+    fn test_evaluation_config_new() {
+        let config = EvaluationConfig::new();
+        let default_config = EvaluationConfig::default();
+
+        assert_eq!(config, default_config);
+    }
+
+    #[test]
+    fn test_evaluation_config_performance() {
+        let config = EvaluationConfig::performance();
+
+        assert_eq!(config.max_recursion_depth, 10);
+        assert_eq!(config.max_string_length, 1024);
+        assert!(config.stop_at_first_match);
+        assert!(!config.enable_mime_types);
+        assert_eq!(config.timeout_ms, Some(1000));
+    }
+
+    #[test]
+    fn test_evaluation_config_comprehensive() {
+        let config = EvaluationConfig::comprehensive();
+
+        assert_eq!(config.max_recursion_depth, 50);
+        assert_eq!(config.max_string_length, 32768);
+        assert!(!config.stop_at_first_match);
+        assert!(config.enable_mime_types);
+        assert_eq!(config.timeout_ms, Some(30000));
+    }
+
+    #[test]
+    fn test_evaluation_config_hardened() {
+        let hardened = EvaluationConfig::hardened();
+        let performance = EvaluationConfig::performance();
+
+        assert!(hardened.validate().is_ok());
+
+        assert!(hardened.max_recursion_depth <= performance.max_recursion_depth);
+        assert!(hardened.max_string_length <= performance.max_string_length);
+        assert!(hardened.max_search_range <= performance.max_search_range);
+        assert!(hardened.timeout_ms.unwrap() <= performance.timeout_ms.unwrap());
+        // `None` means an unlimited budget, so any budget is at or below it
+        assert!(
+            hardened.max_rule_evaluations.is_some()
+                && performance
+                    .max_rule_evaluations
+                    .is_none_or(|budget| hardened.max_rule_evaluations.unwrap() <= budget)
+        );
+
+        assert!(hardened.stop_at_first_match);
+        assert!(!hardened.enable_mime_types);
+        assert!(hardened.soft_bounds);
+        assert!(!hardened.follow_symlinks);
+    }
+
+    #[test]
+    fn test_hardened_config_classifies_unknown_file_past_rule_budget() {
+        use std::fmt::Write;
+
+        let budget = EvaluationConfig::hardened().max_rule_evaluations.unwrap();
+        let source = (0..budget * 2).fold(String::new(), |mut source, i| {
+            let _ = writeln!(source, "0 string MAGIC{i:05} format {i}");
+            source
+        });
+        let rules = parser::parse_magic_string(&source).unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::hardened());
+
+        let result = db.evaluate_bytes(b"unknown contents").unwrap();
+        assert_eq!(result.description, "data");
+        assert!(result.matches.is_empty());
+
+        let result = db.evaluate_bytes(b"MAGIC00007").unwrap();
+        assert_eq!(result.description, "format 7");
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_zero_search_range() {
+        let config = EvaluationConfig {
+            max_search_range: 0,
+            ..Default::default()
+        };
+
+        match config.validate().unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("max_search_range must be greater than 0"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_zero_rule_budget() {
+        let config = EvaluationConfig {
+            max_rule_evaluations: Some(0),
+            ..Default::default()
+        };
+
+        match config.validate().unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("max_rule_evaluations must be greater than 0"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_valid() {
+        let config = EvaluationConfig::default();
+        assert!(config.validate().is_ok());
+
+        let performance_config = EvaluationConfig::performance();
+        assert!(performance_config.validate().is_ok());
+
+        let comprehensive_config = EvaluationConfig::comprehensive();
+        assert!(comprehensive_config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_zero_recursion_depth() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 0,
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("max_recursion_depth must be greater than 0"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_excessive_recursion_depth() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 1001,
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("max_recursion_depth must not exceed 1000"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_zero_string_length() {
+        let config = EvaluationConfig {
+            max_string_length: 0,
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("max_string_length must be greater than 0"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_excessive_string_length() {
+        let config = EvaluationConfig {
+            max_string_length: 1_048_577, // 1MB + 1
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("max_string_length must not exceed 1MB"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_pattern_length() {
+        for (max_pattern_length, expected) in [
+            (0, "max_pattern_length must be greater than 0"),
+            (1_048_577, "max_pattern_length must not exceed 1MB"),
+        ] {
+            let config = EvaluationConfig {
+                max_pattern_length,
+                ..Default::default()
+            };
+            match config.validate() {
+                Err(LibmagicError::InvalidFormat(msg)) => assert!(msg.contains(expected), "{msg}"),
+                other => panic!("Expected InvalidFormat error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_search_range_beyond_file_size() {
+        let at_limit = EvaluationConfig {
+            max_search_range: 1024 * 1024 * 1024,
+            ..Default::default()
+        };
+        assert!(at_limit.validate().is_ok());
+
+        let beyond = EvaluationConfig {
+            max_search_range: 1024 * 1024 * 1024 + 1,
+            ..Default::default()
+        };
+        match beyond.validate() {
+            Err(LibmagicError::InvalidFormat(msg)) => {
+                assert!(msg.contains("max_search_range must not exceed"), "{msg}");
+            }
+            other => panic!("Expected InvalidFormat error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_zero_timeout() {
+        let config = EvaluationConfig {
+            timeout_ms: Some(0),
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("timeout_ms must be greater than 0 if specified"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_excessive_timeout() {
+        let config = EvaluationConfig {
+            timeout_ms: Some(300_001), // 5 minutes + 1ms
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("timeout_ms must not exceed 300000"));
+            }
+            _ => panic!("Expected InvalidFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_config_validate_boundary_values() {
+        // Test minimum valid values
+        let min_config = EvaluationConfig {
+            max_recursion_depth: 1,
+            max_string_length: 1,
+            timeout_ms: Some(1),
+            ..Default::default()
+        };
+        assert!(min_config.validate().is_ok());
+
+        // Test maximum valid values (avoiding the security constraint)
+        let max_config = EvaluationConfig {
+            max_recursion_depth: 100,     // Max allowed with large string length
+            max_string_length: 1_048_576, // 1MB
+            timeout_ms: Some(300_000),    // 5 minutes
+            ..Default::default()
+        };
+        assert!(max_config.validate().is_ok());
+
+        // Test maximum recursion depth with smaller string length
+        let max_recursion_config = EvaluationConfig {
+            max_recursion_depth: 1000,
+            max_string_length: 65536, // Max allowed with high recursion depth
+            timeout_ms: Some(300_000),
+            ..Default::default()
+        };
+        assert!(max_recursion_config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_evaluation_config_clone() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 15,
+            max_string_length: 4096,
+            stop_at_first_match: false,
+            enable_mime_types: true,
+            timeout_ms: Some(5000),
+            ..Default::default()
+        };
+
+        let cloned_config = config.clone();
+        assert_eq!(config, cloned_config);
+    }
+
+    #[test]
+    fn test_evaluation_config_debug() {
+        let config = EvaluationConfig::default();
+        let debug_str = format!("{config:?}");
+
+        assert!(debug_str.contains("EvaluationConfig"));
+        assert!(debug_str.contains("max_recursion_depth"));
+        assert!(debug_str.contains("max_string_length"));
+        assert!(debug_str.contains("stop_at_first_match"));
+        assert!(debug_str.contains("enable_mime_types"));
+        assert!(debug_str.contains("timeout_ms"));
+    }
+
+    #[test]
+    fn test_evaluation_config_partial_eq() {
+        let config1 = EvaluationConfig::default();
+        let config2 = EvaluationConfig::default();
+        let config3 = EvaluationConfig::performance();
+
+        assert_eq!(config1, config2);
+        assert_ne!(config1, config3);
+    }
+
+    #[test]
+    fn test_evaluation_config_custom_values() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 25,
+            max_string_length: 16384,
+            stop_at_first_match: false,
+            enable_mime_types: true,
+            timeout_ms: Some(10000),
+            ..Default::default()
+        };
+
+        assert_eq!(config.max_recursion_depth, 25);
+        assert_eq!(config.max_string_length, 16384);
+        assert!(!config.stop_at_first_match);
+        assert!(config.enable_mime_types);
+        assert_eq!(config.timeout_ms, Some(10000));
+
+        assert!(config.validate().is_ok());
+    }
+}
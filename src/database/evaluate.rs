@@ -0,0 +1,838 @@
+//! Evaluation of files, buffers and subroutines against a database
+
+use super::{LoadedRules, MagicDatabase};
+use crate::evaluator::{self, EvaluationContext, MatchResult};
+use crate::{EvaluationResult, LibmagicError, MagicRule, Result, io, output};
+use std::path::Path;
+use std::sync::Arc;
+
+impl MagicDatabase {
+    /// Evaluate magic rules against a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the file to evaluate
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::IoError` if the file cannot be accessed.
+    /// Returns `LibmagicError::EvaluationError` if rule evaluation fails.
+    ///
+    /// When the configuration disables `follow_symlinks`, a symbolic link is
+    /// reported as `symbolic link to TARGET` without reading the target. Block
+    /// devices, character devices and FIFOs are rejected unless
+    /// `read_special_files` is enabled, in which case a bounded prefix of them
+    /// is evaluated (see [`io::FileBuffer::new_special`]). With `use_mmap`
+    /// disabled, files are read into memory instead of being memory-mapped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::MagicDatabase;
+    ///
+    /// let db = MagicDatabase::load_from_file("magic.db")?;
+    /// let result = db.evaluate_file("sample.bin")?;
+    /// println!("File type: {}", result.description);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn evaluate_file<P: AsRef<Path>>(&self, path: P) -> Result<EvaluationResult> {
+        let path = path.as_ref();
+
+        // Report symlinks themselves rather than their targets when not following them
+        if !self.config.follow_symlinks && path.symlink_metadata()?.file_type().is_symlink() {
+            let target = std::fs::read_link(path)?;
+            return Ok(EvaluationResult {
+                description: format!("symbolic link to {}", target.display()),
+                mime_type: Some("inode/symlink".to_string()),
+                apple_type: None,
+                apple_creator: None,
+                extensions: Vec::new(),
+                rules_loaded: !self.rules().is_empty(),
+                confidence: 1.0,
+                matches: Vec::new(),
+                source_path: None,
+                metadata: output::EvaluationMetadata::default(),
+            });
+        }
+
+        let buffer = self.open_file(path, None)?;
+        self.evaluate_file_buffer(&buffer)
+    }
+
+    /// Load the file at `path` as [`MagicDatabase::evaluate_file`] reads it
+    ///
+    /// The `read_special_files` and `use_mmap` settings apply, so devices and
+    /// FIFOs are rejected before they are opened unless special files may be
+    /// read. At most `max_len` bytes are loaded when it is given. Symbolic
+    /// links are followed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::IoError` if the file cannot be loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    ///
+    /// let db = MagicDatabase::from_rules(Vec::new(), EvaluationConfig::default());
+    /// let header = db.open_file("sample.bin".as_ref(), Some(4096))?;
+    /// assert!(header.len() <= 4096);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn open_file(&self, path: &Path, max_len: Option<usize>) -> Result<io::FileBuffer> {
+        let buffer = match (self.config.read_special_files, self.config.use_mmap) {
+            (true, mmap) => io::FileBuffer::open_special(path, max_len, mmap),
+            (false, true) => max_len.map_or_else(
+                || io::FileBuffer::new(path),
+                |max_len| io::FileBuffer::new_with_max_len(path, max_len),
+            ),
+            (false, false) => io::FileBuffer::new_unmapped(path, max_len),
+        }
+        .map_err(std::io::Error::other)?;
+        Ok(buffer)
+    }
+
+    /// Evaluate magic rules against an already opened file buffer
+    ///
+    /// The buffer is only borrowed, so a file can be mapped once and evaluated
+    /// against several databases; the file name is available from
+    /// [`io::FileBuffer::path`].
+    ///
+    /// For a buffer created with [`io::FileBuffer::new_with_max_len`], from-end
+    /// offsets are resolved against the size of the whole file, and rules that
+    /// reach beyond the loaded window are out of bounds: a non-match with
+    /// `soft_bounds`, an evaluation error otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::EvaluationError` if rule evaluation fails.
+    /// Returns `LibmagicError::Timeout` if evaluation exceeds the configured timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::MagicDatabase;
+    /// use libmagic_rs::io::FileBuffer;
+    /// use std::path::Path;
+    ///
+    /// let db = MagicDatabase::load_from_file("magic.db")?;
+    /// let header = FileBuffer::new_with_max_len(Path::new("disk.img"), 64 * 1024)?;
+    /// let result = db.evaluate_file_buffer(&header)?;
+    /// println!("File type: {}", result.description);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn evaluate_file_buffer(&self, buffer: &io::FileBuffer) -> Result<EvaluationResult> {
+        let file_size = usize::try_from(buffer.file_size()).unwrap_or(usize::MAX);
+        self.evaluate_data(buffer.as_slice(), Some(file_size), 0)
+    }
+
+    /// Evaluate magic rules against an in-memory buffer
+    ///
+    /// The rule set is snapshotted when evaluation starts, so a concurrent
+    /// [`MagicDatabase::reload`] does not affect an evaluation in progress.
+    /// A leading Unicode byte-order mark is reported ahead of any rule matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::EvaluationError` if rule evaluation fails.
+    /// Returns `LibmagicError::Timeout` if evaluation exceeds the configured timeout.
+    pub fn evaluate_bytes(&self, data: &[u8]) -> Result<EvaluationResult> {
+        self.evaluate_data(data, None, 0)
+    }
+
+    /// Evaluate magic rules against the part of `data` that starts at `base`
+    ///
+    /// Rule offsets are measured from `base`, as if the file started there,
+    /// which suits carving tools that found an embedded file at a known
+    /// position. Offsets in the result are still positions in `data`, and
+    /// `metadata.file_size` is the length of the evaluated part.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::EvaluationError` if `base` is past the end of
+    /// `data` or rule evaluation fails.
+    /// Returns `LibmagicError::Timeout` if evaluation exceeds the configured timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 string %PDF- PDF document\n")?;
+    /// let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+    ///
+    /// let disk = b"....%PDF-1.7";
+    /// let result = db.evaluate_bytes_at(disk, 4)?;
+    /// assert_eq!(result.description, "PDF document");
+    /// assert_eq!(result.matches[0].offset, 4);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn evaluate_bytes_at(&self, data: &[u8], base: usize) -> Result<EvaluationResult> {
+        if base > data.len() {
+            return Err(LibmagicError::EvaluationError(format!(
+                "Base offset {base} is past the end of the {}-byte buffer",
+                data.len()
+            )));
+        }
+        self.evaluate_data(data, None, base)
+    }
+
+    /// Evaluate only the named subroutine against `buffer`
+    ///
+    /// The children of the `name` rule run with their offsets relative to
+    /// `base_offset`, as if invoked by a `use` rule resolving there; no
+    /// top-level rules are evaluated. This makes it possible to unit-test
+    /// subroutines in isolation. Match offsets are positions in `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::EvaluationError` if the subroutine is not defined,
+    /// `base_offset` is past the end of `buffer`, or rule evaluation fails.
+    /// Returns `LibmagicError::Timeout` if evaluation exceeds the configured timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 name chunk\n>0 string IHDR header chunk\n")?;
+    /// let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+    ///
+    /// let matches = db.evaluate_subroutine("chunk", b"....IHDR", 4)?;
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].message, "header chunk");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn evaluate_subroutine(
+        &self,
+        name: &str,
+        buffer: &[u8],
+        base_offset: usize,
+    ) -> Result<Vec<MatchResult>> {
+        let rules = self.rules();
+        let mut context = self.evaluation_context(&rules);
+        evaluator::evaluate_subroutine(name, buffer, base_offset, &mut context)
+    }
+
+    /// Create a context for evaluating `rules` with this database's settings
+    pub(super) fn evaluation_context(&self, rules: &Arc<Vec<MagicRule>>) -> EvaluationContext {
+        let mut context = EvaluationContext::new(self.config.clone());
+        context.set_database(Arc::clone(rules));
+        context.set_type_readers(Arc::clone(&self.type_readers));
+        context
+    }
+
+    /// Evaluate rules against `data`, the start of a file of `file_size` bytes if given
+    ///
+    /// Rule offsets are measured from `base`, which must be within `data`.
+    pub(super) fn evaluate_data(
+        &self,
+        data: &[u8],
+        file_size: Option<usize>,
+        base: usize,
+    ) -> Result<EvaluationResult> {
+        let LoadedRules { rules, index } = self.loaded_rules();
+        let rules_loaded = !rules.is_empty();
+        let mut context = self.evaluation_context(&rules);
+        if let Some(file_size) = file_size {
+            context.set_file_size(file_size);
+        }
+        context.set_base_offset(base);
+
+        let matches = match &index {
+            Some(index) => evaluator::evaluate_rules_indexed(index, data, &mut context)?,
+            None => evaluator::evaluate_rules(&rules, data, &mut context)?,
+        };
+        self.build_result(data, file_size, matches, &context, rules_loaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::tests::byte_rule;
+    use crate::{EvaluationConfig, OffsetSpec, Operator, TypeKind, Value, parser};
+
+    #[test]
+    fn test_magic_rule_new_builds_evaluable_tree() {
+        let child = MagicRule::new(
+            OffsetSpec::Absolute(1),
+            TypeKind::Byte { signed: false },
+            Operator::Equal,
+            Value::Uint(2),
+            "version 2",
+        )
+        .with_level(1);
+        let rule = byte_rule(0x7f, "header").with_children(vec![child]);
+        assert_eq!(rule.level, 0);
+        assert!(rule.mask.is_none() && rule.mime_type.is_none() && rule.extensions.is_empty());
+
+        let db = MagicDatabase::from_rules(vec![rule], EvaluationConfig::default());
+        assert_eq!(
+            db.evaluate_bytes(&[0x7f, 2]).unwrap().description,
+            "header version 2"
+        );
+        assert_eq!(db.evaluate_bytes(&[0x7f, 3]).unwrap().description, "header");
+    }
+
+    #[test]
+    fn test_magic_database_from_rules_evaluate_bytes() {
+        let db = MagicDatabase::from_rules(
+            vec![byte_rule(0x7f, "ELF magic")],
+            EvaluationConfig::default(),
+        );
+
+        let result = db.evaluate_bytes(&[0x7f, 0x45, 0x4c, 0x46]).unwrap();
+        assert_eq!(result.description, "ELF magic");
+        assert!((result.confidence - 1.0).abs() < f64::EPSILON);
+
+        let result = db.evaluate_bytes(&[0x00, 0x01]).unwrap();
+        assert_eq!(result.description, "data");
+        assert!(result.confidence.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_evaluate_bytes_at_detects_embedded_file() {
+        let db = MagicDatabase::builtin().unwrap();
+        let mut disk = vec![0u8; 100];
+        let mut header = vec![0u8; 64];
+        header[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+        header[16] = 2;
+        header[18] = 62;
+        disk.extend_from_slice(&header);
+
+        assert_eq!(db.evaluate_bytes(&disk).unwrap().description, "data");
+        let carved = db.evaluate_bytes_at(&disk, 100).unwrap();
+        assert_eq!(
+            carved.description,
+            db.evaluate_bytes(&header).unwrap().description
+        );
+        assert_eq!(carved.matches[0].offset, 100);
+        assert_eq!(carved.primary_match_bytes(&disk), Some(&header[..6]));
+        assert_eq!(carved.metadata.file_size, 64);
+
+        assert!(db.evaluate_bytes_at(&disk, disk.len() + 1).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_magic_database_reports_symlink_when_not_following() {
+        let dir = std::env::temp_dir().join(format!("libmagic_rs_symlink_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.bin");
+        let link = dir.join("link.bin");
+        std::fs::write(&target, [0x7f, 0x45, 0x4c, 0x46]).unwrap();
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let rules = vec![byte_rule(0x7f, "ELF magic")];
+
+        let following = MagicDatabase::from_rules(rules.clone(), EvaluationConfig::default());
+        assert_eq!(
+            following.evaluate_file(&link).unwrap().description,
+            "ELF magic"
+        );
+
+        let hardened = MagicDatabase::from_rules(rules, EvaluationConfig::hardened());
+        let result = hardened.evaluate_file(&link).unwrap();
+        assert_eq!(
+            result.description,
+            format!("symbolic link to {}", target.display())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_magic_database_evaluates_file_without_mmap() {
+        let path = std::env::temp_dir().join(format!("libmagic_rs_no_mmap_{}", std::process::id()));
+        std::fs::write(&path, b"\x7fELF\x02").unwrap();
+
+        let rules =
+            parser::parse_magic_string("0 string \\x7fELF ELF\n>4 byte 2 64-bit\n").unwrap();
+        let config = EvaluationConfig {
+            use_mmap: false,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(rules, config);
+        assert_eq!(db.evaluate_file(&path).unwrap().description, "ELF 64-bit");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_magic_database_evaluates_mapped_window_only() {
+        let path = std::env::temp_dir().join(format!("libmagic_rs_window_{}", std::process::id()));
+        let mut content = vec![0u8; 2048];
+        content[..4].copy_from_slice(b"HDR!");
+        content[1024..1028].copy_from_slice(b"DEEP");
+        content[2044..].copy_from_slice(b"TAIL");
+        std::fs::write(&path, &content).unwrap();
+
+        let rules = parser::parse_magic_string(
+            "0 string HDR! header\n1024 string DEEP deep\n-4 string TAIL tail\n",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            soft_bounds: true,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(rules, config);
+
+        let window = io::FileBuffer::new_with_max_len(&path, 512).unwrap();
+        assert_eq!(window.len(), 512);
+        assert_eq!(
+            db.evaluate_file_buffer(&window).unwrap().description,
+            "header"
+        );
+
+        let whole = io::FileBuffer::new(&path).unwrap();
+        assert_eq!(
+            db.evaluate_file_buffer(&whole).unwrap().description,
+            "header\n- deep\n- tail"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_buffer_evaluated_against_two_databases() {
+        let path = std::env::temp_dir().join(format!("libmagic_rs_shared_{}", std::process::id()));
+        std::fs::write(&path, b"%PDF-1.7\n").unwrap();
+
+        let documents = MagicDatabase::from_rules(
+            parser::parse_magic_string("0 string %PDF PDF document\n").unwrap(),
+            EvaluationConfig::default(),
+        );
+        let versions = MagicDatabase::from_rules(
+            parser::parse_magic_string("5 string 1.7 version 1.7\n").unwrap(),
+            EvaluationConfig::default(),
+        );
+
+        let buffer = io::FileBuffer::new(&path).unwrap();
+        assert_eq!(buffer.path(), path.as_path());
+        assert_eq!(
+            documents.evaluate_file_buffer(&buffer).unwrap().description,
+            "PDF document"
+        );
+        let result = versions.evaluate_file_buffer(&buffer).unwrap();
+        assert_eq!(result.description, "version 1.7");
+        assert_eq!(result.metadata.file_size, 9);
+
+        drop(buffer);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_magic_database_substitutes_any_value_fields() {
+        let rules =
+            parser::parse_magic_string("0 string FMT format\n>3 byte x version %d\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        assert_eq!(
+            db.evaluate_bytes(b"FMT\x07").unwrap().description,
+            "format version 7"
+        );
+        assert_eq!(
+            db.evaluate_bytes(b"FMT\x02").unwrap().description,
+            "format version 2"
+        );
+    }
+
+    #[test]
+    fn test_magic_database_compares_bytes_by_signedness() {
+        let rules =
+            parser::parse_magic_string("0 byte <0 negative\n0 byte 0xff all ones\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let result = db.evaluate_bytes(&[0x80]).unwrap();
+        assert_eq!(result.description, "negative");
+        assert_eq!(result.matches[0].value, Value::Int(-128));
+
+        let rules =
+            parser::parse_magic_string("0 ubyte <0 never\n0 ubyte >0x7f high bit\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let result = db.evaluate_bytes(&[0xff]).unwrap();
+        assert_eq!(result.description, "high bit");
+        assert_eq!(result.matches[0].value, Value::Uint(0xff));
+        assert_eq!(db.evaluate_bytes(&[0x7f]).unwrap().description, "data");
+    }
+
+    #[test]
+    fn test_magic_database_reports_integer_widths() {
+        let rules = parser::parse_magic_string(
+            "0 beshort 0x0102 short\n>2 byte 3 then byte\n>>3 belong 4 then long\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let result = db.evaluate_bytes(&[1, 2, 3, 0, 0, 0, 4]).unwrap();
+
+        assert_eq!(result.description, "short then byte then long");
+        let spans: Vec<(usize, usize)> = result
+            .matches
+            .iter()
+            .map(|m| (m.offset, m.length))
+            .collect();
+        // Integer matches report the width of their type, not of the value
+        assert_eq!(spans, [(0, 2), (2, 1), (3, 4)]);
+    }
+
+    #[test]
+    fn test_magic_database_matches_value_lists() {
+        let rules = parser::parse_magic_string("0 ubyte 1|2|3 known version %d\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        for version in 1..=3u8 {
+            assert_eq!(
+                db.evaluate_bytes(&[version]).unwrap().description,
+                format!("known version {version}")
+            );
+        }
+        assert_eq!(db.evaluate_bytes(&[4]).unwrap().description, "data");
+
+        // The list survives being written back out as magic source
+        let source = parser::codegen::to_magic_text(&db.rules());
+        assert!(source.contains("0x1|0x2|0x3"), "{source}");
+        let reparsed = parser::parse_magic_string(&source).unwrap();
+        assert_eq!(
+            format!("{:?}", reparsed[0].value),
+            format!("{:?}", db.rules()[0].value)
+        );
+    }
+
+    #[test]
+    fn test_magic_database_builtin_describes_elf_headers() {
+        let db = MagicDatabase::builtin().unwrap();
+
+        // Header of an x86-64 position-independent executable, followed by its
+        // PT_PHDR and PT_INTERP program headers
+        let mut x86_64 = [0u8; 176];
+        x86_64[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+        x86_64[16] = 3; // ET_DYN
+        x86_64[18] = 62; // EM_X86_64
+        x86_64[32] = 64; // e_phoff
+        x86_64[64] = 6; // PT_PHDR
+        x86_64[120] = 3; // PT_INTERP
+        let result = db.evaluate_bytes(&x86_64).unwrap();
+        assert_eq!(
+            result.description,
+            "ELF 64-bit LSB pie executable, x86-64, version 1 (SYSV)"
+        );
+        assert_eq!(
+            result.mime_type.as_deref(),
+            Some("application/x-pie-executable")
+        );
+
+        // Without a program interpreter it is a shared library
+        let mut library = x86_64;
+        library[120] = 1; // PT_LOAD
+        library[7] = 3;
+        let result = db.evaluate_bytes(&library).unwrap();
+        assert_eq!(
+            result.description,
+            "ELF 64-bit LSB shared object, x86-64, version 1 (GNU/Linux)"
+        );
+        assert_eq!(result.mime_type.as_deref(), Some("application/x-sharedlib"));
+
+        // Big-endian program headers are found through a byte-swapped pointer
+        let mut ppc = [0u8; 84];
+        ppc[..8].copy_from_slice(b"\x7fELF\x01\x02\x01\x00");
+        ppc[17] = 3; // ET_DYN
+        ppc[19] = 20; // EM_PPC
+        ppc[31] = 52; // e_phoff
+        ppc[55] = 3; // PT_INTERP
+        assert_eq!(
+            db.evaluate_bytes(&ppc).unwrap().description,
+            "ELF 32-bit MSB pie executable, PowerPC, version 1 (SYSV)"
+        );
+
+        // Big-endian fields are read through the byte-swapped subroutines
+        let mips: [u8; 20] = [
+            0x7f, 0x45, 0x4c, 0x46, 0x01, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x08,
+        ];
+        let result = db.evaluate_bytes(&mips).unwrap();
+        assert_eq!(
+            result.description,
+            "ELF 32-bit MSB executable, MIPS, version 1 (SYSV)"
+        );
+        assert_eq!(
+            result.mime_type.as_deref(),
+            Some("application/x-executable")
+        );
+
+        // An unknown machine is still reported, and the chain carries on
+        let mut unknown = x86_64;
+        unknown[7] = 0x09;
+        unknown[16] = 0x01;
+        unknown[18] = 0x99;
+        assert_eq!(
+            db.evaluate_bytes(&unknown).unwrap().description,
+            "ELF 64-bit LSB relocatable, machine 0x99, version 1 (FreeBSD)"
+        );
+    }
+
+    #[test]
+    fn test_magic_database_matches_zero_values() {
+        let rules = parser::parse_magic_string(
+            "0 byte 0 zero byte\n>1 beshort 0 zero short\n>>3 belong 0 zero long\n",
+        )
+        .unwrap();
+        assert_eq!(rules[0].value, Value::Int(0));
+
+        for first_byte_index in [false, true] {
+            let config = EvaluationConfig {
+                first_byte_index,
+                ..EvaluationConfig::default()
+            };
+            let db = MagicDatabase::from_rules(rules.clone(), config);
+
+            let result = db.evaluate_bytes(&[0; 7]).unwrap();
+            assert_eq!(result.description, "zero byte zero short zero long");
+
+            let result = db.evaluate_bytes(&[0, 0, 0, 0, 0, 0, 1]).unwrap();
+            assert_eq!(result.description, "zero byte zero short");
+
+            let result = db.evaluate_bytes(&[1, 0, 0, 0, 0, 0, 0]).unwrap();
+            assert!(result.matches.is_empty());
+        }
+
+        // A rule built with an unsigned zero behaves the same
+        let db = MagicDatabase::from_rules(vec![byte_rule(0, "zero")], EvaluationConfig::default());
+        assert_eq!(db.evaluate_bytes(&[0]).unwrap().description, "zero");
+        assert!(db.evaluate_bytes(&[7]).unwrap().matches.is_empty());
+    }
+
+    #[test]
+    fn test_magic_database_verifies_crc32_checksum() {
+        let rules = parser::parse_magic_string(
+            "0 string HDR header\n\
+             >3 crc32/0,9 0xcbf43926 \\b, checksum ok\n\
+             >3 crc32/0,9 !0xcbf43926 \\b, bad checksum %#x\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(b"HDR123456789").unwrap();
+        assert_eq!(result.matches[1].message, "\\b, checksum ok");
+        assert_eq!(result.matches[1].value, Value::Uint(0xcbf4_3926));
+        assert_eq!(result.matches[1].length, 9);
+
+        let result = db.evaluate_bytes(b"HDR123456780").unwrap();
+        assert_eq!(result.matches[1].message, "\\b, bad checksum %#x");
+
+        // A range past the end of the data cannot be checksummed
+        assert!(db.evaluate_bytes(b"HDR1234").is_err());
+        let config = EvaluationConfig {
+            soft_bounds: true,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(db.rules().as_ref().clone(), config);
+        assert_eq!(db.evaluate_bytes(b"HDR1234").unwrap().matches.len(), 1);
+    }
+
+    #[test]
+    fn test_magic_database_compares_masked_nibbles() {
+        let rules = parser::parse_magic_string(
+            "0 byte&0xf0 0x40 high nibble %#x\n\
+             >0 byte&0x0f 0x0c \\b, low nibble %#x\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(&[0x4c]).unwrap();
+        assert_eq!(result.matches[0].value, Value::Int(0x40));
+        assert_eq!(result.matches[1].value, Value::Int(0x0c));
+        assert_eq!(result.description, "high nibble 0x40, low nibble 0xc");
+
+        // Only the masked bits take part in the comparison
+        let result = db.evaluate_bytes(&[0x4d]).unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(db.evaluate_bytes(&[0x5c]).unwrap().description, "data");
+    }
+
+    #[test]
+    fn test_magic_database_masked_equals_differs_from_bit_test() {
+        let masked = MagicDatabase::from_rules(
+            parser::parse_magic_string("0 byte&0x7f 0x40 masked equals\n").unwrap(),
+            EvaluationConfig::default(),
+        );
+        let any_bit = MagicDatabase::from_rules(
+            parser::parse_magic_string("0 byte &0x40 any bit\n").unwrap(),
+            EvaluationConfig::default(),
+        );
+        let matches =
+            |db: &MagicDatabase, byte: u8| !db.evaluate_bytes(&[byte]).unwrap().matches.is_empty();
+
+        // The mask is applied first, then the value is compared for equality
+        assert!(matches(&masked, 0x40));
+        assert!(matches(&masked, 0xc0));
+        assert!(!matches(&masked, 0x41));
+        // `&` only asks whether any of its bits are set
+        assert!(matches(&any_bit, 0x41));
+        assert!(matches(&any_bit, 0xc0));
+        assert!(!matches(&any_bit, 0x01));
+    }
+
+    #[test]
+    fn test_magic_database_masked_value_keeps_sign() {
+        let rules = parser::parse_magic_string(
+            "0 byte&0x80 <0 negative byte\n\
+             0 ubyte&0x80 >0x7f high bit\n\
+             0 beshort&0xff00 -256 negative short\n",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(rules, config);
+
+        let result = db.evaluate_bytes(&[0xff, 0x12]).unwrap();
+        let messages: Vec<&str> = result.matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["negative byte", "high bit", "negative short"]);
+        assert_eq!(result.matches[0].value, Value::Int(-128));
+
+        assert!(db.evaluate_bytes(&[0x7f, 0x12]).unwrap().matches.is_empty());
+    }
+
+    #[test]
+    fn test_magic_database_matches_file_size() {
+        let rules = parser::parse_magic_string(
+            "0 filesize 512 boot sector image\n0 filesize >512 larger image of %d bytes\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(&[0u8; 512]).unwrap();
+        assert_eq!(result.description, "boot sector image");
+
+        let result = db.evaluate_bytes(&[0u8; 513]).unwrap();
+        assert_eq!(result.description, "larger image of 513 bytes");
+
+        let result = db.evaluate_bytes(&[0u8; 511]).unwrap();
+        assert_eq!(result.description, "data");
+    }
+
+    #[test]
+    fn test_magic_database_file_size_uses_whole_file_for_partial_buffers() {
+        let path =
+            std::env::temp_dir().join(format!("libmagic_rs_filesize_{}.bin", std::process::id()));
+        std::fs::write(&path, [0u8; 512]).unwrap();
+
+        let rules = parser::parse_magic_string("0 filesize 512 boot sector image\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let header = io::FileBuffer::new_with_max_len(&path, 16).unwrap();
+        let result = db.evaluate_file_buffer(&header).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.description, "boot sector image");
+    }
+
+    #[test]
+    fn test_magic_database_evaluate_subroutine() {
+        let source = "\
+0 name entry
+>0 string ENT entry
+>>3 ubyte x \\b, kind %d
+0 string ARCV archive
+>4 use entry
+";
+        let rules = parser::parse_magic_string(source).unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        // The subroutine runs at the given base without the top-level "archive" rule
+        let buffer = b"junkENT\x07";
+        let matches = db.evaluate_subroutine("entry", buffer, 4).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["entry", "\\b, kind %d"]);
+        assert_eq!(matches[1].value, Value::Uint(7));
+        assert_eq!(matches[1].offset, 7);
+
+        let matches = db.evaluate_subroutine("entry", buffer, 0).unwrap();
+        assert!(matches.is_empty());
+
+        assert!(db.evaluate_subroutine("missing", buffer, 0).is_err());
+        assert!(
+            db.evaluate_subroutine("entry", buffer, buffer.len() + 1)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_magic_database_reports_evaluation_metadata() {
+        use std::fmt::Write;
+
+        // Enough searches over a large buffer that the evaluation takes measurable time
+        let source = (0..200).fold(String::new(), |mut source, i| {
+            let _ = writeln!(source, "0 search/8192 NEEDLE{i:03} needle {i}");
+            source
+        });
+        let rules = parser::parse_magic_string(&source).unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let mut data = vec![b'x'; 8000];
+        data.extend_from_slice(b"NEEDLE199");
+        let result = db.evaluate_bytes(&data).unwrap();
+
+        assert_eq!(result.description, "needle 199");
+        assert_eq!(result.metadata.file_size, 8009);
+        assert_eq!(result.metadata.rules_evaluated, 200);
+        assert_eq!(result.metadata.rules_matched, 1);
+        assert!(result.metadata.evaluation_time_ms >= 0.0);
+        assert!(result.metadata.evaluation_time_ms > 0.0);
+    }
+
+    #[test]
+    fn test_magic_database_first_byte_index_matches_full_scan() {
+        use std::fmt::Write;
+
+        let mut source = (0u8..64).fold(String::new(), |mut source, i| {
+            let _ = writeln!(
+                source,
+                "0 byte {} byte {i}\n>1 byte x \\b, next %d",
+                i + 0x80
+            );
+            source
+        });
+        source.push_str("0 string ABC letters\n0 belong 0x41424344 long\n4 byte 0 zero\n");
+        let rules = parser::parse_magic_string(&source).unwrap();
+
+        let indexed_config = EvaluationConfig {
+            first_byte_index: true,
+            ..EvaluationConfig::default()
+        };
+        let plain = MagicDatabase::from_rules(rules.clone(), EvaluationConfig::default());
+        let indexed = MagicDatabase::from_rules(rules, indexed_config);
+
+        let buffers: [&[u8]; 6] = [b"\x85\x07", b"ABCD\0", b"ABC", b"AB", b"", b"zzzz\0"];
+        for buffer in buffers {
+            match (plain.evaluate_bytes(buffer), indexed.evaluate_bytes(buffer)) {
+                (Ok(expected), Ok(actual)) => {
+                    assert_eq!(actual.description, expected.description, "{buffer:?}");
+                    assert_eq!(actual.matches, expected.matches, "{buffer:?}");
+                }
+                // A read past the end fails the same way with or without the index
+                (Err(expected), Err(actual)) => {
+                    assert_eq!(actual.to_string(), expected.to_string(), "{buffer:?}");
+                }
+                (expected, actual) => panic!("{buffer:?}: {expected:?} != {actual:?}"),
+            }
+        }
+
+        // Only the rule for 0x85 and the unindexed rule are tested
+        let result = indexed.evaluate_bytes(b"\xff\x07\0\0\0").unwrap();
+        assert_eq!(result.description, "zero");
+        assert_eq!(result.metadata.rules_evaluated, 1);
+        let result = plain.evaluate_bytes(b"\xff\x07\0\0\0").unwrap();
+        assert!(result.metadata.rules_evaluated > 60);
+
+        // Replacing the rules rebuilds the index
+        indexed.replace_rules(parser::parse_magic_string("0 byte 0xff replaced\n").unwrap());
+        let result = indexed.evaluate_bytes(b"\xff").unwrap();
+        assert_eq!(result.description, "replaced");
+    }
+}
@@ -0,0 +1,113 @@
+//! Evaluation of data that arrives in pieces
+
+use super::MagicDatabase;
+use crate::evaluator;
+use crate::{EvaluationResult, MagicRule, Result};
+use std::sync::Arc;
+
+impl MagicDatabase {
+    /// Start evaluating data that arrives in pieces, such as a growing log or a download
+    ///
+    /// The rule set is snapshotted now and used for every later append.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 string LOG log file\n-4 string DONE complete\n")?;
+    /// let config = EvaluationConfig {
+    ///     stop_at_first_match: false,
+    ///     ..EvaluationConfig::default()
+    /// };
+    /// let db = MagicDatabase::from_rules(rules, config);
+    ///
+    /// let mut incremental = db.incremental();
+    /// assert_eq!(incremental.append(b"LOG data")?.description, "log file");
+    /// assert_eq!(incremental.append(b" DONE")?.description, "log file\n- complete");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn incremental(&self) -> IncrementalEvaluation<'_> {
+        IncrementalEvaluation {
+            database: self,
+            rules: self.rules(),
+            data: Vec::new(),
+            state: evaluator::incremental::IncrementalState::new(),
+        }
+    }
+}
+
+/// Evaluation of a buffer that grows at its end, created by [`MagicDatabase::incremental`]
+///
+/// Each append evaluates all data received so far, but top-level rules that
+/// only read fixed bytes at absolute offsets inside the data seen by the
+/// previous append keep their earlier outcome instead of being evaluated
+/// again; see [`evaluator::incremental`]. Rules with offsets from the end,
+/// `search`, indirect offsets and the like are always evaluated again, so a
+/// rule set made mostly of them gains little from appending incrementally.
+#[derive(Debug)]
+pub struct IncrementalEvaluation<'a> {
+    database: &'a MagicDatabase,
+    rules: Arc<Vec<MagicRule>>,
+    data: Vec<u8>,
+    state: evaluator::incremental::IncrementalState,
+}
+
+impl IncrementalEvaluation<'_> {
+    /// Append `bytes` and evaluate the data received so far
+    ///
+    /// The result is the same as [`MagicDatabase::evaluate_bytes`] gives for
+    /// [`IncrementalEvaluation::data`], except that `rules_evaluated` in its
+    /// metadata does not count reused rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::EvaluationError` if rule evaluation fails.
+    /// Returns `LibmagicError::Timeout` if evaluation exceeds the configured timeout.
+    pub fn append(&mut self, bytes: &[u8]) -> Result<EvaluationResult> {
+        self.data.extend_from_slice(bytes);
+        let data = self.data.as_slice();
+        let mut context = self.database.evaluation_context(&self.rules);
+
+        let matches = self.state.evaluate(&self.rules, data, &mut context)?;
+        self.database
+            .build_result(data, None, matches, &context, !self.rules.is_empty())
+    }
+
+    /// All data appended so far
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EvaluationConfig, MagicDatabase, parser};
+
+    #[test]
+    fn test_incremental_append_matches_rule_from_end() {
+        let rules =
+            parser::parse_magic_string("0 string LOG log file\n-4 string DONE complete\n").unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(rules, config);
+        let mut incremental = db.incremental();
+
+        let first = incremental.append(b"LOG data").unwrap();
+        assert_eq!(first.description, "log file");
+        assert_eq!(first.metadata.rules_evaluated, 2);
+
+        // Only the rule from the end is evaluated again
+        let second = incremental.append(b" DONE").unwrap();
+        assert_eq!(second.description, "log file\n- complete");
+        assert_eq!(second.metadata.rules_evaluated, 1);
+        let full = db.evaluate_bytes(incremental.data()).unwrap();
+        assert_eq!(second.description, full.description);
+        assert_eq!(second.matches, full.matches);
+    }
+}
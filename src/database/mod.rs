@@ -0,0 +1,760 @@
+//! The loaded rule set and the evaluation entry points
+//!
+//! A [`MagicDatabase`] owns a rule set, the configuration it is evaluated
+//! with, and optionally a fallback database. Loading and replacing rules live
+//! here; evaluating data, assembling an [`EvaluationResult`] and incremental
+//! evaluation live in the submodules.
+
+mod evaluate;
+mod incremental;
+mod result;
+
+pub use incremental::IncrementalEvaluation;
+pub use result::EvaluationResult;
+
+use crate::evaluator;
+use crate::parser::{self, ast::Value};
+use crate::{EvaluationConfig, FileCategory, LibmagicError, LintOptions, LintWarning};
+use crate::{MagicRule, Result, TypeKind, TypeReader, TypeReaderRegistry, builtin};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Main interface for magic rule database
+///
+/// The loaded rule set is held behind an `Arc` so it can be replaced atomically
+/// at runtime with [`MagicDatabase::reload`] or [`MagicDatabase::replace_rules`].
+/// Each evaluation takes a snapshot of the current rule set when it starts, so
+/// in-flight evaluations finish on the rules they started with while new
+/// evaluations pick up the replacement.
+#[derive(Debug)]
+pub struct MagicDatabase {
+    rules: RwLock<LoadedRules>,
+    config: EvaluationConfig,
+    source_path: Option<PathBuf>,
+    type_readers: Arc<TypeReaderRegistry>,
+    category_filter: Option<CategoryFilter>,
+    fallback: Option<Box<MagicDatabase>>,
+}
+
+/// Predicate deciding which categories of top-level rules a database keeps
+#[derive(Clone)]
+struct CategoryFilter(Arc<dyn Fn(FileCategory) -> bool + Send + Sync>);
+
+impl CategoryFilter {
+    /// Drop the top-level rules whose category is rejected
+    ///
+    /// Named subroutines are always kept, since kept rules may `use` them.
+    fn apply(&self, mut rules: Vec<MagicRule>) -> Vec<MagicRule> {
+        rules.retain(|rule| {
+            matches!(rule.typ, TypeKind::Name { .. }) || (self.0)(FileCategory::of_rule(rule))
+        });
+        rules
+    }
+
+    /// Returns `true` if detections of `category` are kept
+    fn allows(&self, category: FileCategory) -> bool {
+        (self.0)(category)
+    }
+}
+
+impl std::fmt::Debug for CategoryFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CategoryFilter")
+    }
+}
+
+impl MagicDatabase {
+    /// Load magic rules from a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the magic source file or compiled database to load
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::IoError` if the file cannot be read.
+    /// Returns `LibmagicError::ParseError` if the magic file format is invalid.
+    /// Returns `LibmagicError::InvalidFormat` if a compiled database is corrupt.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::MagicDatabase;
+    ///
+    /// let db = MagicDatabase::load_from_file("magic.db")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_from_file_with_config(path, EvaluationConfig::default())
+    }
+
+    /// Load magic rules from a file, evaluating them with a custom configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`MagicDatabase::load_from_file`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    ///
+    /// let config = EvaluationConfig {
+    ///     read_special_files: true,
+    ///     ..EvaluationConfig::default()
+    /// };
+    /// let db = MagicDatabase::load_from_file_with_config("magic.db", config)?;
+    /// let result = db.evaluate_file("/dev/sda")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn load_from_file_with_config<P: AsRef<Path>>(
+        path: P,
+        config: EvaluationConfig,
+    ) -> Result<Self> {
+        let rules = Self::parse_rules_from_file(path.as_ref(), &config)?;
+
+        Ok(Self {
+            rules: RwLock::new(LoadedRules::new(rules, &config)),
+            config,
+            source_path: Some(path.as_ref().to_path_buf()),
+            type_readers: Arc::default(),
+            category_filter: None,
+            fallback: None,
+        })
+    }
+
+    /// Load magic rules from a file, keeping only top-level rules whose category passes `filter`
+    ///
+    /// Each top-level rule's category comes from [`FileCategory::of_rule`], so
+    /// rules without a MIME type are [`FileCategory::Other`]. Rejected rules
+    /// never enter the database, which saves memory and keeps unwanted
+    /// detections out of the results. Named subroutines are always kept. The
+    /// filter is applied again by [`MagicDatabase::reload`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`MagicDatabase::load_from_file`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::{EvaluationConfig, FileCategory, MagicDatabase};
+    ///
+    /// let db = MagicDatabase::load_from_file_with_filter(
+    ///     "magic.db",
+    ///     EvaluationConfig::default(),
+    ///     |category| matches!(category, FileCategory::Image | FileCategory::Archive),
+    /// )?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn load_from_file_with_filter<P, F>(
+        path: P,
+        config: EvaluationConfig,
+        filter: F,
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        F: Fn(FileCategory) -> bool + Send + Sync + 'static,
+    {
+        let filter = CategoryFilter(Arc::new(filter));
+        let rules = filter.apply(Self::parse_rules_from_file(path.as_ref(), &config)?);
+
+        Ok(Self {
+            rules: RwLock::new(LoadedRules::new(rules, &config)),
+            config,
+            source_path: Some(path.as_ref().to_path_buf()),
+            type_readers: Arc::default(),
+            category_filter: Some(filter),
+            fallback: None,
+        })
+    }
+
+    /// Load magic rules from a file and run the enabled lint checks over them
+    ///
+    /// Lint warnings never prevent loading; they are returned alongside the
+    /// database so callers can report them. With [`LintOptions::default`] no
+    /// checks run and the warning list is always empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`MagicDatabase::load_from_file`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::lint::LintOptions;
+    ///
+    /// let (db, warnings) = MagicDatabase::load_from_file_with_warnings(
+    ///     "magic.db",
+    ///     EvaluationConfig::default(),
+    ///     LintOptions::all(),
+    /// )?;
+    /// for warning in &warnings {
+    ///     eprintln!("magic.db: {warning}");
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn load_from_file_with_warnings<P: AsRef<Path>>(
+        path: P,
+        config: EvaluationConfig,
+        lint: LintOptions,
+    ) -> Result<(Self, Vec<LintWarning>)> {
+        let db = Self::load_from_file_with_config(path, config)?;
+        let warnings = parser::lint::check_rules(&db.rules(), lint);
+        Ok((db, warnings))
+    }
+
+    /// Create a database from an already-built set of rules
+    ///
+    /// Databases created this way have no source file, so [`MagicDatabase::reload`]
+    /// will fail; use [`MagicDatabase::replace_rules`] to swap their rules instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::ast::{MagicRule, OffsetSpec, Operator, TypeKind, Value};
+    ///
+    /// let rule = MagicRule {
+    ///     offset: OffsetSpec::Absolute(0),
+    ///     typ: TypeKind::Byte { signed: false },
+    ///     op: Operator::Equal,
+    ///     value: Value::Uint(0x7f),
+    ///     message: "ELF magic".to_string(),
+    ///     children: vec![],
+    ///     level: 0,
+    ///     source_line: None,
+    ///     apple_type: None,
+    ///     apple_creator: None,
+    ///     mask: None,
+    ///     mime_type: None,
+    ///     extensions: Vec::new(),
+    ///     negate: false,
+    ///     store: false,
+    /// };
+    ///
+    /// let db = MagicDatabase::from_rules(vec![rule], EvaluationConfig::default());
+    /// let result = db.evaluate_bytes(&[0x7f, 0x45, 0x4c, 0x46])?;
+    /// assert_eq!(result.description, "ELF magic");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn from_rules(rules: Vec<MagicRule>, config: EvaluationConfig) -> Self {
+        Self {
+            rules: RwLock::new(LoadedRules::new(rules, &config)),
+            config,
+            source_path: None,
+            type_readers: Arc::default(),
+            category_filter: None,
+            fallback: None,
+        }
+    }
+
+    /// Create a database from the rules built into the library
+    ///
+    /// The rules come from [`builtin::BUILTIN_MAGIC`] and identify formats
+    /// without a magic file on disk, such as ELF binaries with their class,
+    /// byte order, type, machine and OS/ABI. Combine it with
+    /// [`with_fallback`](Self::with_fallback) to back a loaded database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::MagicDatabase;
+    ///
+    /// let db = MagicDatabase::builtin()?;
+    /// let mut header = vec![0u8; 64];
+    /// header[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+    /// header[16] = 2; // ET_EXEC
+    /// header[18] = 62; // EM_X86_64
+    ///
+    /// let result = db.evaluate_bytes(&header)?;
+    /// assert_eq!(result.description, "ELF 64-bit LSB executable, x86-64, version 1 (SYSV)");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the built-in rules fail to parse, which would be a
+    /// bug in this crate.
+    pub fn builtin() -> Result<Self> {
+        Ok(Self::from_rules(
+            builtin::rules()?,
+            EvaluationConfig::default(),
+        ))
+    }
+
+    /// Fall back to `other` for data that none of this database's rules match
+    ///
+    /// Evaluation tries this database first and only evaluates `other` when it
+    /// produced no match at all, so this database's rules take strict
+    /// precedence rather than being ranked together with `other`'s. Calling
+    /// this again appends to the end of the chain. Files are opened according
+    /// to this database's configuration; `other` evaluates the same data with
+    /// its own.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let custom = MagicDatabase::from_rules(
+    ///     parse_magic_string("0 string ACME ACME firmware\n")?,
+    ///     EvaluationConfig::default(),
+    /// );
+    /// let system = MagicDatabase::from_rules(
+    ///     parse_magic_string("0 string ACME generic\n0 string PK ZIP\n")?,
+    ///     EvaluationConfig::default(),
+    /// );
+    /// let db = custom.with_fallback(system);
+    ///
+    /// assert_eq!(db.evaluate_bytes(b"ACME")?.description, "ACME firmware");
+    /// assert_eq!(db.evaluate_bytes(b"PK\x03\x04")?.description, "ZIP");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn with_fallback(mut self, other: Self) -> Self {
+        match self.fallback.take() {
+            Some(fallback) => self.fallback = Some(Box::new(fallback.with_fallback(other))),
+            None => self.fallback = Some(Box::new(other)),
+        }
+        self
+    }
+
+    /// Get a snapshot of the currently loaded rules
+    ///
+    /// The returned `Arc` keeps the snapshot alive even if the database is
+    /// reloaded afterwards.
+    #[must_use]
+    pub fn rules(&self) -> Arc<Vec<MagicRule>> {
+        self.loaded_rules().rules
+    }
+
+    /// Get a snapshot of the loaded rules together with their index, if any
+    fn loaded_rules(&self) -> LoadedRules {
+        match self.rules.read() {
+            Ok(guard) => guard.clone(),
+            // A writer can only panic between taking the lock and storing a fully
+            // built value, so the stored value is always consistent
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Atomically replace the loaded rules
+    ///
+    /// Evaluations already in progress keep using the previous rule set; the
+    /// previous rules are returned so callers can inspect or drop them.
+    pub fn replace_rules(&self, rules: Vec<MagicRule>) -> Arc<Vec<MagicRule>> {
+        // Build any index before taking the lock so readers are not held up
+        let new_rules = LoadedRules::new(rules, &self.config);
+        let previous = match self.rules.write() {
+            Ok(mut guard) => std::mem::replace(&mut *guard, new_rules),
+            Err(poisoned) => std::mem::replace(&mut *poisoned.into_inner(), new_rules),
+        };
+        previous.rules
+    }
+
+    /// Re-parse the source magic file and atomically swap in the new rules
+    ///
+    /// The file is fully parsed before anything is replaced, so a parse failure
+    /// leaves the currently loaded rules untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::InvalidFormat` if the database was not loaded from a file.
+    /// Returns `LibmagicError::IoError` if the file cannot be read.
+    /// Returns `LibmagicError::ParseError` if the magic file format is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmagic_rs::MagicDatabase;
+    ///
+    /// let db = MagicDatabase::load_from_file("magic.db")?;
+    /// // ... magic.db is updated on disk ...
+    /// db.reload()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reload(&self) -> Result<()> {
+        let path = self.source_path.as_ref().ok_or_else(|| {
+            LibmagicError::InvalidFormat(
+                "Database was not loaded from a file and cannot be reloaded".to_string(),
+            )
+        })?;
+
+        let mut rules = Self::parse_rules_from_file(path, &self.config)?;
+        if let Some(filter) = &self.category_filter {
+            rules = filter.apply(rules);
+        }
+        self.replace_rules(rules);
+        Ok(())
+    }
+
+    /// Parse the rules contained in a magic source file or compiled database
+    ///
+    /// Rules with a pattern longer than the configured `max_pattern_length`
+    /// are rejected here, before the database is built.
+    fn parse_rules_from_file(path: &Path, config: &EvaluationConfig) -> Result<Vec<MagicRule>> {
+        let rules = parser::load_magic_file(path)?;
+        check_pattern_lengths(&rules, config.max_pattern_length)?;
+        Ok(rules)
+    }
+
+    /// Register a reader for a custom `x-` extension type
+    ///
+    /// Rules whose type is `name` read their value with `reader` during
+    /// evaluation. Registering a name again replaces the previous reader.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::InvalidFormat` if `name` does not start with `x-`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase, Value};
+    /// use libmagic_rs::io::IoError;
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 x-nibble 0x4 high nibble %d\n")?;
+    /// let mut db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+    /// db.register_type_reader("x-nibble", |buffer: &[u8], offset: usize| {
+    ///     buffer
+    ///         .get(offset)
+    ///         .map(|byte| Value::Uint(u64::from(byte >> 4)))
+    ///         .ok_or(IoError::BufferOverrun { offset, length: 1, buffer_size: buffer.len() })
+    /// })?;
+    ///
+    /// let result = db.evaluate_bytes(&[0x4f])?;
+    /// assert_eq!(result.description, "high nibble 4");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn register_type_reader(
+        &mut self,
+        name: impl Into<String>,
+        reader: impl TypeReader + 'static,
+    ) -> Result<()> {
+        Arc::make_mut(&mut self.type_readers).register(name, reader)
+    }
+
+    /// Path of the magic file this database was loaded from, if any
+    #[must_use]
+    pub fn source_path(&self) -> Option<&Path> {
+        self.source_path.as_deref()
+    }
+
+    /// Count the rules in the currently loaded rule set
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 byte 1 one\n>1 byte 2 two\n0 name sub\n")?;
+    /// let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+    /// let stats = db.stats();
+    /// assert_eq!(stats.top_level_rules, 1);
+    /// assert_eq!(stats.total_rules, 3);
+    /// assert_eq!(stats.named_subroutines, 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn stats(&self) -> DatabaseStats {
+        fn count(rules: &[MagicRule]) -> usize {
+            rules.iter().map(|rule| 1 + count(&rule.children)).sum()
+        }
+
+        let rules = self.rules();
+        let named_subroutines = rules
+            .iter()
+            .filter(|rule| matches!(rule.typ, TypeKind::Name { .. }))
+            .count();
+
+        DatabaseStats {
+            top_level_rules: rules.len() - named_subroutines,
+            total_rules: count(&rules),
+            named_subroutines,
+        }
+    }
+}
+
+/// A rule set and, when enabled, its first-byte index, swapped in together
+#[derive(Debug, Clone)]
+struct LoadedRules {
+    rules: Arc<Vec<MagicRule>>,
+    index: Option<Arc<evaluator::RuleIndex>>,
+}
+
+impl LoadedRules {
+    fn new(rules: Vec<MagicRule>, config: &EvaluationConfig) -> Self {
+        let rules = Arc::new(rules);
+        let index = config
+            .first_byte_index
+            .then(|| Arc::new(evaluator::RuleIndex::new(Arc::clone(&rules))));
+        Self { rules, index }
+    }
+}
+
+/// Reject the first rule, at any depth, whose pattern exceeds `max_length` bytes
+fn check_pattern_lengths(rules: &[MagicRule], max_length: usize) -> Result<()> {
+    for rule in rules {
+        let length = match &rule.value {
+            Value::String(pattern) => pattern.len(),
+            Value::Bytes(pattern) => pattern.len(),
+            _ => 0,
+        };
+        if length > max_length {
+            return Err(LibmagicError::ParseError {
+                line: rule.source_line.unwrap_or(0),
+                message: format!(
+                    "pattern of {length} bytes exceeds max_pattern_length of {max_length}"
+                ),
+            });
+        }
+        check_pattern_lengths(&rule.children, max_length)?;
+    }
+    Ok(())
+}
+
+/// Rule counts for a loaded magic database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatabaseStats {
+    /// Number of top-level rules, excluding named subroutine definitions
+    pub top_level_rules: usize,
+    /// Total number of rules, including children and subroutine bodies
+    pub total_rules: usize,
+    /// Number of named subroutines defined with `name`
+    pub named_subroutines: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OffsetSpec, Operator, io};
+
+    pub(super) fn byte_rule(value: u8, message: &str) -> MagicRule {
+        MagicRule::new(
+            OffsetSpec::Absolute(0),
+            TypeKind::Byte { signed: false },
+            Operator::Equal,
+            Value::Uint(u64::from(value)),
+            message,
+        )
+    }
+
+    #[test]
+    fn test_magic_database_replace_rules_between_evaluations() {
+        let buffer = [0x7f, 0x50, 0x4b];
+        let db = MagicDatabase::from_rules(
+            vec![byte_rule(0x7f, "first ruleset")],
+            EvaluationConfig::default(),
+        );
+
+        let before = db.evaluate_bytes(&buffer).unwrap();
+        assert_eq!(before.description, "first ruleset");
+
+        let old_rules = db.replace_rules(vec![byte_rule(0x7f, "second ruleset")]);
+        assert_eq!(old_rules.len(), 1);
+        assert_eq!(old_rules[0].message, "first ruleset");
+
+        let after = db.evaluate_bytes(&buffer).unwrap();
+        assert_eq!(after.description, "second ruleset");
+    }
+
+    #[test]
+    fn test_magic_database_snapshot_survives_replace() {
+        let db =
+            MagicDatabase::from_rules(vec![byte_rule(0x01, "old")], EvaluationConfig::default());
+
+        // An in-flight evaluation holds its own snapshot of the rules
+        let snapshot = db.rules();
+        db.replace_rules(vec![byte_rule(0x02, "new")]);
+
+        assert_eq!(snapshot[0].message, "old");
+        assert_eq!(db.rules()[0].message, "new");
+        assert_eq!(Arc::strong_count(&snapshot), 1);
+    }
+
+    #[test]
+    fn test_magic_database_reload_without_source_path() {
+        let db = MagicDatabase::from_rules(vec![], EvaluationConfig::default());
+
+        match db.reload().unwrap_err() {
+            LibmagicError::InvalidFormat(msg) => {
+                assert!(msg.contains("cannot be reloaded"));
+            }
+            other => panic!("Expected InvalidFormat error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_from_file_with_filter_keeps_only_archives() {
+        let path = std::env::temp_dir().join(format!("libmagic_rs_filter_{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "0 string \\x89PNG PNG image data\n!:mime image/png\n\
+             0 string PK\\x03\\x04 Zip archive data\n!:mime application/zip\n\
+             0 string GIF8 GIF image data\n>4 byte 0x39 version 89a\n!:mime image/gif\n\
+             0 name helper\n>0 byte 0 helper\n",
+        )
+        .unwrap();
+
+        let db = MagicDatabase::load_from_file_with_filter(
+            &path,
+            EvaluationConfig::default(),
+            |category| category == FileCategory::Archive,
+        )
+        .unwrap();
+        assert_eq!(db.stats().top_level_rules, 1);
+        assert_eq!(db.stats().named_subroutines, 1);
+        assert_eq!(
+            db.evaluate_bytes(b"PK\x03\x04").unwrap().description,
+            "Zip archive data"
+        );
+        assert_eq!(db.evaluate_bytes(b"\x89PNG").unwrap().description, "data");
+        assert_eq!(db.evaluate_bytes(b"GIF89a").unwrap().description, "data");
+
+        // Reloading applies the same filter
+        db.reload().unwrap();
+        assert_eq!(db.stats().top_level_rules, 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_from_file_rejects_unsupported_type() {
+        let path =
+            std::env::temp_dir().join(format!("libmagic_rs_bogus_{}.magic", std::process::id()));
+        std::fs::write(&path, "0 string GIF8 GIF image\n>4 bogustype 1 hello\n").unwrap();
+
+        let result = MagicDatabase::load_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // The type is rejected when loading, before any file is evaluated
+        match result {
+            Err(LibmagicError::ParseError { line, message }) => {
+                assert_eq!(line, 2);
+                assert!(
+                    message.contains("Unsupported type 'bogustype'"),
+                    "{message}"
+                );
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_from_file_rejects_pattern_over_max_pattern_length() {
+        let path =
+            std::env::temp_dir().join(format!("libmagic_rs_pattern_{}.magic", std::process::id()));
+        std::fs::write(
+            &path,
+            "0 string GIF8 GIF image\n>6 search/64 NETSCAPE2.0 animated\n",
+        )
+        .unwrap();
+
+        // The limit bounds the rule's pattern, not the range it searches
+        let fits = EvaluationConfig {
+            max_pattern_length: 11,
+            ..EvaluationConfig::default()
+        };
+        let too_short = EvaluationConfig {
+            max_pattern_length: 10,
+            ..EvaluationConfig::default()
+        };
+        let accepted = MagicDatabase::load_from_file_with_config(&path, fits);
+        let rejected = MagicDatabase::load_from_file_with_config(&path, too_short);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(accepted.is_ok());
+        match rejected {
+            Err(LibmagicError::ParseError { line, message }) => {
+                assert_eq!(line, 2);
+                assert!(message.contains("max_pattern_length"), "{message}");
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_from_file_with_warnings_reports_shadowed_rule() {
+        let path =
+            std::env::temp_dir().join(format!("libmagic_rs_lint_{}.magic", std::process::id()));
+        std::fs::write(
+            &path,
+            "# ELF\n0 string \\x7fELF ELF\n>4 byte 2 64-bit\n\n0 string \\x7fELF ELF executable\n",
+        )
+        .unwrap();
+
+        let (_, warnings) = MagicDatabase::load_from_file_with_warnings(
+            &path,
+            EvaluationConfig::default(),
+            LintOptions::default(),
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+
+        let (db, warnings) = MagicDatabase::load_from_file_with_warnings(
+            &path,
+            EvaluationConfig::default(),
+            LintOptions::all(),
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(db.stats().top_level_rules, 2);
+        assert_eq!(warnings.len(), 1);
+        let message = warnings[0].to_string();
+        assert!(message.contains("shadowed rule"), "{message}");
+        assert!(message.starts_with("line 5:"), "{message}");
+        assert!(message.contains("line 2"), "{message}");
+    }
+
+    #[test]
+    fn test_magic_database_custom_type_reader() {
+        /// Reads a 3-byte little-endian unsigned integer
+        struct U24Le;
+
+        impl TypeReader for U24Le {
+            fn read(
+                &self,
+                buffer: &[u8],
+                offset: usize,
+            ) -> std::result::Result<Value, io::IoError> {
+                let bytes = buffer
+                    .get(offset..offset + 3)
+                    .ok_or(io::IoError::BufferOverrun {
+                        offset,
+                        length: 3,
+                        buffer_size: buffer.len(),
+                    })?;
+                Ok(Value::Uint(
+                    bytes
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, &b| acc << 8 | u64::from(b)),
+                ))
+            }
+        }
+
+        let rules = parser::parse_magic_string(
+            "0 string CUST custom container\n>4 x-u24le >0x10000 large\n",
+        )
+        .unwrap();
+        let mut db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        // Without a registered reader the extension type cannot be evaluated
+        assert!(db.evaluate_bytes(b"CUST\x00\x00\x02").is_err());
+
+        db.register_type_reader("x-u24le", U24Le).unwrap();
+        let result = db.evaluate_bytes(b"CUST\x00\x00\x02").unwrap();
+        assert_eq!(result.description, "custom container large");
+        assert_eq!(result.matches[1].value, Value::Uint(0x02_0000));
+
+        let result = db.evaluate_bytes(b"CUST\x01\x00\x00").unwrap();
+        assert_eq!(result.description, "custom container");
+    }
+}
@@ -0,0 +1,803 @@
+//! Assembling an [`EvaluationResult`] from rule matches
+
+use super::MagicDatabase;
+use crate::evaluator::{self, EvaluationContext, MatchResult};
+use crate::{FileCategory, Result, output};
+use std::path::PathBuf;
+
+impl MagicDatabase {
+    /// Assemble the result for `data` from the rule matches found in it
+    ///
+    /// Only the part of `data` from the context's base offset was evaluated.
+    /// Without rule matches, the fallback database is consulted if there is
+    /// one. Otherwise a byte-order mark and a ZIP container subtype are
+    /// reported before the rule matches.
+    pub(super) fn build_result(
+        &self,
+        data: &[u8],
+        file_size: Option<usize>,
+        rule_matches: Vec<MatchResult>,
+        context: &EvaluationContext,
+        rules_loaded: bool,
+    ) -> Result<EvaluationResult> {
+        let base = context.base_offset();
+        let region = data.get(base..).unwrap_or_default();
+        if rule_matches.is_empty() {
+            if let Some(fallback) = &self.fallback {
+                return fallback.evaluate_data(data, file_size, base);
+            }
+        }
+
+        let mut matches = if rules_loaded {
+            self.builtin_matches(region, base)
+        } else {
+            Vec::new()
+        };
+        matches.extend(rule_matches);
+        let metadata = output::EvaluationMetadata {
+            file_size: u64::try_from(file_size.unwrap_or(data.len()).saturating_sub(base))
+                .unwrap_or(u64::MAX),
+            evaluation_time_ms: context.elapsed_ms(),
+            rules_evaluated: u32::try_from(context.rules_evaluated()).unwrap_or(u32::MAX),
+            rules_matched: u32::try_from(matches.len()).unwrap_or(u32::MAX),
+        };
+        log::debug!(
+            "evaluated {} rules with {} matches in {:.3} ms",
+            metadata.rules_evaluated,
+            metadata.rules_matched,
+            metadata.evaluation_time_ms
+        );
+
+        if matches.is_empty() {
+            return Ok(EvaluationResult {
+                description: "data".to_string(),
+                mime_type: None,
+                apple_type: None,
+                apple_creator: None,
+                extensions: Vec::new(),
+                rules_loaded,
+                confidence: 0.0,
+                matches: Vec::new(),
+                source_path: self.source_path.clone(),
+                metadata,
+            });
+        }
+
+        let (apple_type, apple_creator) = matches
+            .iter()
+            .find(|m| m.apple_type.is_some())
+            .map_or((None, None), |m| {
+                (m.apple_type.clone(), m.apple_creator.clone())
+            });
+        let extensions = matches
+            .iter()
+            .find(|m| !m.extensions.is_empty())
+            .map_or_else(Vec::new, |m| m.extensions.clone());
+
+        let matches: Vec<output::MatchResult> =
+            matches.into_iter().map(output::MatchResult::from).collect();
+        // Without stopping at the first match, each top-level match gets its own line
+        let description = if self.config.stop_at_first_match {
+            output::text::format_text_output(&matches)
+        } else {
+            output::text::format_keep_going_output(&matches)
+        };
+
+        let mut result = EvaluationResult {
+            description,
+            mime_type: None,
+            apple_type,
+            apple_creator,
+            extensions,
+            rules_loaded,
+            confidence: 1.0,
+            matches,
+            source_path: self.source_path.clone(),
+            metadata,
+        };
+        result.mime_type = result.mime_types().first().map(|&mime| mime.to_string());
+        // Only the whole file can show that its trailer is missing
+        if self.config.detect_truncation
+            && file_size.is_none_or(|size| size <= data.len())
+            && evaluator::trailer::is_truncated(region)
+        {
+            result.description.push_str(" (truncated)");
+        }
+        Ok(result)
+    }
+
+    /// Byte-order mark and ZIP container subtype matches for `region`, which starts at `base`
+    ///
+    /// A byte-order mark counts as [`FileCategory::Text`] and a ZIP subtype
+    /// takes the category of its MIME type, so the category filter drops them
+    /// like the rules it rejected.
+    fn builtin_matches(&self, region: &[u8], base: usize) -> Vec<MatchResult> {
+        let bom = evaluator::bom::detect_bom_match(region).map(|m| (m, FileCategory::Text));
+        let zip = evaluator::zip::detect_zip_subtype_match(region).map(|m| {
+            let category = m
+                .mime_type
+                .as_deref()
+                .map_or(FileCategory::Archive, FileCategory::from_mime_type);
+            (m, category)
+        });
+        bom.into_iter()
+            .chain(zip)
+            .filter(|(_, category)| {
+                self.category_filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.allows(*category))
+            })
+            .map(|(m, _)| MatchResult {
+                offset: m.offset + base,
+                ..m
+            })
+            .collect()
+    }
+}
+
+/// Result of magic rule evaluation
+#[derive(Debug, Clone)]
+pub struct EvaluationResult {
+    /// Human-readable file type description
+    pub description: String,
+    /// MIME type of the most confident match that has one, from its `!:mime` directive
+    ///
+    /// Use [`EvaluationResult::mime_types`] to see the MIME types of every match.
+    pub mime_type: Option<String>,
+    /// Apple file type from the `!:apple` directive of the first matching rule that has one
+    pub apple_type: Option<String>,
+    /// Apple creator code from the same `!:apple` directive as `apple_type`
+    pub apple_creator: Option<String>,
+    /// Likely file extensions from the `!:ext` directive of the first matching rule that has one
+    pub extensions: Vec<String>,
+    /// Whether the database contained any rules
+    ///
+    /// A `"data"` description with `rules_loaded` set to `false` means the
+    /// database was empty, which usually points at a wrong magic file path,
+    /// rather than that no rule matched.
+    pub rules_loaded: bool,
+    /// Confidence score (0.0 to 1.0)
+    pub confidence: f64,
+    /// Individual matches that make up the description, in output order
+    pub matches: Vec<output::MatchResult>,
+    /// Magic file of the database that produced the result, if it was loaded from one
+    ///
+    /// A fallback database only answers when the databases before it found no
+    /// rule match, so every rule match comes from this file. Their
+    /// [`source_line`](output::MatchResult::source_line) numbers refer to it.
+    pub source_path: Option<PathBuf>,
+    /// File size, rule counts and evaluation time
+    pub metadata: output::EvaluationMetadata,
+}
+
+impl EvaluationResult {
+    /// The distinct MIME types of all matches, most confident first
+    ///
+    /// Matches with equal confidence keep their output order. A polyglot file
+    /// evaluated with `stop_at_first_match` disabled can report several.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string(
+    ///     "0 string %PDF PDF document\n!:mime application/pdf\n\
+    ///      4 string PK ZIP archive\n!:mime application/zip\n",
+    /// )?;
+    /// let config = EvaluationConfig {
+    ///     stop_at_first_match: false,
+    ///     ..EvaluationConfig::default()
+    /// };
+    /// let db = MagicDatabase::from_rules(rules, config);
+    ///
+    /// let result = db.evaluate_bytes(b"%PDFPK")?;
+    /// assert_eq!(result.mime_types(), ["application/pdf", "application/zip"]);
+    /// assert_eq!(result.mime_type.as_deref(), Some("application/pdf"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn mime_types(&self) -> Vec<&str> {
+        let mut matches: Vec<&output::MatchResult> = self.matches.iter().collect();
+        matches.sort_by_key(|m| std::cmp::Reverse(m.confidence));
+
+        let mut mime_types = Vec::new();
+        for mime in matches.iter().filter_map(|m| m.mime_type.as_deref()) {
+            if !mime_types.contains(&mime) {
+                mime_types.push(mime);
+            }
+        }
+        mime_types
+    }
+
+    /// The broad category of the detected file type, for triage
+    ///
+    /// The first MIME type in [`Self::mime_types`] with a known category
+    /// decides it. Without one, keywords in the description are used, and a
+    /// file no rule matched is [`FileCategory::Other`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, FileCategory, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string(
+    ///     "0 string \\x89PNG PNG image data\n!:mime image/png\n0 string GIF8 GIF image\n",
+    /// )?;
+    /// let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+    ///
+    /// assert_eq!(db.evaluate_bytes(b"\x89PNG")?.category(), FileCategory::Image);
+    /// // Without a MIME type, the description decides
+    /// assert_eq!(db.evaluate_bytes(b"GIF89a")?.category(), FileCategory::Image);
+    /// assert_eq!(db.evaluate_bytes(b"none")?.category(), FileCategory::Other);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn category(&self) -> FileCategory {
+        self.mime_types()
+            .into_iter()
+            .map(FileCategory::from_mime_type)
+            .find(|&category| category != FileCategory::Other)
+            .unwrap_or_else(|| {
+                if self.matches.is_empty() {
+                    FileCategory::Other
+                } else {
+                    FileCategory::from_description(&self.description)
+                }
+            })
+    }
+
+    /// The bytes examined by the primary match, as a start offset and an exclusive end
+    ///
+    /// The primary match is the best one as ranked by
+    /// [`output::MatchResult::rank_cmp`]. `None` if no rule matched.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 string %PDF PDF document\n")?;
+    /// let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+    ///
+    /// assert_eq!(db.evaluate_bytes(b"%PDF-1.7")?.examined_range(), Some((0, 4)));
+    /// assert_eq!(db.evaluate_bytes(b"none")?.examined_range(), None);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn examined_range(&self) -> Option<(usize, usize)> {
+        self.matches
+            .iter()
+            .min_by(|a, b| a.rank_cmp(b))
+            .map(|m| (m.offset, m.offset.saturating_add(m.length)))
+    }
+
+    /// The bytes of `buffer` examined by the primary match
+    ///
+    /// `buffer` must be the data this result was evaluated from, or at least
+    /// start like it. `None` if no rule matched or the range of
+    /// [`Self::examined_range`] does not fit in `buffer`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 string \\x89PNG PNG image data\n")?;
+    /// let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+    ///
+    /// let data = b"\x89PNG\r\n\x1a\n";
+    /// let result = db.evaluate_bytes(data)?;
+    /// assert_eq!(result.primary_match_bytes(data), Some(&b"\x89PNG"[..]));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn primary_match_bytes<'a>(&self, buffer: &'a [u8]) -> Option<&'a [u8]> {
+        let (start, end) = self.examined_range()?;
+        buffer.get(start..end)
+    }
+
+    /// What changed in the classification from this result to `other`
+    ///
+    /// Compares the description, the primary MIME type and the number of
+    /// matches, typically for the same file evaluated with two rule sets.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::{EvaluationConfig, MagicDatabase};
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let old = MagicDatabase::from_rules(
+    ///     parse_magic_string("0 string %PDF PDF document\n")?,
+    ///     EvaluationConfig::default(),
+    /// );
+    /// let new = MagicDatabase::from_rules(
+    ///     parse_magic_string("0 string %PDF PDF document\n!:mime application/pdf\n")?,
+    ///     EvaluationConfig::default(),
+    /// );
+    ///
+    /// let diff = old.evaluate_bytes(b"%PDF-1.7")?.diff(&new.evaluate_bytes(b"%PDF-1.7")?);
+    /// assert!(diff.description.is_none());
+    /// assert_eq!(diff.mime_type.unwrap().after.as_deref(), Some("application/pdf"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> output::ResultDiff {
+        fn change<T: PartialEq + Clone>(before: &T, after: &T) -> Option<output::FieldChange<T>> {
+            (before != after).then(|| output::FieldChange {
+                before: before.clone(),
+                after: after.clone(),
+            })
+        }
+
+        output::ResultDiff {
+            description: change(&self.description, &other.description),
+            mime_type: change(&self.mime_type, &other.mime_type),
+            match_count: change(&self.matches.len(), &other.matches.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::tests::byte_rule;
+    use crate::{EvaluationConfig, parser};
+
+    #[test]
+    fn test_evaluation_result_diff_reports_changed_fields() {
+        let db = MagicDatabase::from_rules(
+            parser::parse_magic_string("0 string PK ZIP archive\n!:mime application/zip\n")
+                .unwrap(),
+            EvaluationConfig::default(),
+        );
+        let before = db.evaluate_bytes(b"PK\x03\x04").unwrap();
+        assert!(before.diff(&before.clone()).is_empty());
+
+        let mut after = before.clone();
+        after.description = "Java archive".to_string();
+        after.mime_type = Some("application/java-archive".to_string());
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.description,
+            Some(output::FieldChange {
+                before: "ZIP archive".to_string(),
+                after: "Java archive".to_string(),
+            })
+        );
+        assert_eq!(
+            diff.mime_type.as_ref().map(|c| c.before.as_deref()),
+            Some(Some("application/zip"))
+        );
+        assert!(diff.match_count.is_none());
+
+        after.matches.clear();
+        let json = serde_json::to_value(before.diff(&after)).unwrap();
+        assert_eq!(json["match_count"]["before"], 1);
+        assert_eq!(json["match_count"]["after"], 0);
+    }
+
+    #[test]
+    fn test_primary_match_bytes_returns_magic_bytes() {
+        let rules = parser::parse_magic_string(
+            "0 string PK\\x03\\x04 ZIP archive\n>4 leshort 20 \\b, version 2.0\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let data = b"PK\x03\x04\x14\x00rest";
+
+        let result = db.evaluate_bytes(data).unwrap();
+        assert_eq!(result.primary_match_bytes(data), Some(&b"PK\x03\x04"[..]));
+        // A buffer that is too short for the match gives nothing
+        assert_eq!(result.primary_match_bytes(b"PK"), None);
+        let unmatched = db.evaluate_bytes(b"none").unwrap();
+        assert_eq!(unmatched.primary_match_bytes(b"none"), None);
+    }
+
+    #[test]
+    fn test_magic_database_reports_bom() {
+        let db =
+            MagicDatabase::from_rules(vec![byte_rule(0x7f, "ELF")], EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(&[0xef, 0xbb, 0xbf, b'h', b'i']).unwrap();
+        assert_eq!(result.description, "Unicode text, UTF-8 (with BOM)");
+
+        let result = db.evaluate_bytes(&[0xff, 0xfe, b'h', 0x00]).unwrap();
+        assert_eq!(
+            result.description,
+            "Unicode text, UTF-16, little-endian (with BOM)"
+        );
+    }
+
+    #[test]
+    fn test_magic_database_bom_does_not_block_rules() {
+        let db = MagicDatabase::from_rules(
+            vec![byte_rule(0xef, "starts with 0xef")],
+            EvaluationConfig::default(),
+        );
+
+        let result = db.evaluate_bytes(&[0xef, 0xbb, 0xbf]).unwrap();
+        assert_eq!(
+            result.description,
+            "Unicode text, UTF-8 (with BOM) starts with 0xef"
+        );
+    }
+
+    #[test]
+    fn test_evaluation_result_distinguishes_empty_database() {
+        let empty = MagicDatabase::from_rules(Vec::new(), EvaluationConfig::default());
+        let result = empty.evaluate_bytes(b"anything").unwrap();
+        assert_eq!(result.description, "data");
+        assert!(!result.rules_loaded);
+        assert_eq!(result.metadata.rules_evaluated, 0);
+
+        let rules = parser::parse_magic_string("0 string PK ZIP archive\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let result = db.evaluate_bytes(b"anything").unwrap();
+        assert_eq!(result.description, "data");
+        assert!(result.rules_loaded);
+        assert_eq!(result.metadata.rules_evaluated, 1);
+
+        let result = db.evaluate_bytes(b"PK\x03\x04").unwrap();
+        assert!(result.rules_loaded);
+    }
+
+    #[test]
+    fn test_magic_database_joins_messages_like_file() {
+        let rules = parser::parse_magic_string(
+            "0 string GIF8 GIF image data\n\
+             >4 string 9a  \t animated\n\
+             >4 string 9a plain\n\
+             >4 string 9a\t\\b, version 89a\n",
+        )
+        .unwrap();
+        assert_eq!(rules[0].children[0].message, "animated");
+        let db = MagicDatabase::from_rules(
+            rules,
+            EvaluationConfig {
+                stop_at_first_match: false,
+                ..EvaluationConfig::default()
+            },
+        );
+
+        assert_eq!(
+            db.evaluate_bytes(b"GIF89a").unwrap().description,
+            "GIF image data animated plain, version 89a"
+        );
+    }
+
+    #[test]
+    fn test_evaluation_result_examined_range() {
+        let rules =
+            parser::parse_magic_string("0 belong 0x7f454c46 ELF\n>4 byte 2 64-bit\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(b"\x7fELF\x02").unwrap();
+        assert_eq!(result.examined_range(), Some((0, 4)));
+        assert_eq!(
+            db.evaluate_bytes(b"\0\0\0\0").unwrap().examined_range(),
+            None
+        );
+    }
+
+    /// A ZIP archive holding one stored file, `a.txt` containing `hi`
+    fn stored_zip() -> Vec<u8> {
+        let mut zip = b"PK\x03\x04\x14\x00\x00\x00\x00\x00".to_vec();
+        zip.extend_from_slice(&[0; 4]); // modification time and date
+        zip.extend_from_slice(&[0xac, 0x2a, 0x93, 0xd8]); // CRC-32 of "hi"
+        zip.extend_from_slice(&[2, 0, 0, 0, 2, 0, 0, 0, 5, 0, 0, 0]);
+        zip.extend_from_slice(b"a.txthi");
+        let directory_start = zip.len();
+        zip.extend_from_slice(b"PK\x01\x02\x14\x00\x14\x00\x00\x00\x00\x00");
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&[0xac, 0x2a, 0x93, 0xd8]);
+        zip.extend_from_slice(&[2, 0, 0, 0, 2, 0, 0, 0, 5, 0]);
+        zip.extend_from_slice(&[0; 16]); // extra, comment, disk, attributes, local offset
+        zip.extend_from_slice(b"a.txt");
+        let directory_len = zip.len() - directory_start;
+        zip.extend_from_slice(b"PK\x05\x06\x00\x00\x00\x00\x01\x00\x01\x00");
+        zip.extend_from_slice(&u32::try_from(directory_len).unwrap().to_le_bytes());
+        zip.extend_from_slice(&u32::try_from(directory_start).unwrap().to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    #[test]
+    fn test_magic_database_flags_zip_without_trailer_as_truncated() {
+        let rules = parser::parse_magic_string("0 string PK\\x03\\x04 Zip archive data\n").unwrap();
+        let config = EvaluationConfig {
+            detect_truncation: true,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(rules, config);
+
+        let complete = stored_zip();
+        assert_eq!(
+            db.evaluate_bytes(&complete).unwrap().description,
+            "Zip archive data"
+        );
+
+        let stripped = &complete[..complete.len() - 22];
+        assert_eq!(
+            db.evaluate_bytes(stripped).unwrap().description,
+            "Zip archive data (truncated)"
+        );
+
+        // Without the option the same file is described as usual, also by the presets
+        for config in [
+            EvaluationConfig::default(),
+            EvaluationConfig::comprehensive(),
+        ] {
+            let plain = MagicDatabase::from_rules(db.rules().to_vec(), config);
+            assert_eq!(
+                plain.evaluate_bytes(stripped).unwrap().description,
+                "Zip archive data"
+            );
+        }
+    }
+
+    #[test]
+    fn test_magic_database_reports_mime_parameters() {
+        let rules = parser::parse_magic_string(
+            "0 string BOM\\xef\\xbb\\xbf text with a marker\n!:mime text/plain; charset=utf-8\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(b"BOM\xef\xbb\xbfhello").unwrap();
+        assert_eq!(
+            result.mime_type.as_deref(),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(result.mime_types(), ["text/plain; charset=utf-8"]);
+        assert_eq!(result.category(), FileCategory::Text);
+    }
+
+    #[test]
+    fn test_magic_database_with_fallback_keeps_precedence() {
+        let primary = MagicDatabase::from_rules(
+            vec![byte_rule(0x7f, "custom ELF")],
+            EvaluationConfig::default(),
+        );
+        let fallback = MagicDatabase::from_rules(
+            vec![byte_rule(0x7f, "system ELF"), byte_rule(b'P', "system ZIP")],
+            EvaluationConfig::default(),
+        );
+        let last = MagicDatabase::from_rules(
+            vec![byte_rule(b'%', "last resort PDF")],
+            EvaluationConfig::default(),
+        );
+        let db = primary.with_fallback(fallback).with_fallback(last);
+
+        // The primary database wins even though the fallback also matches
+        assert_eq!(
+            db.evaluate_bytes(b"\x7fELF").unwrap().description,
+            "custom ELF"
+        );
+        // A miss in the primary database is answered by the fallbacks in order
+        assert_eq!(db.evaluate_bytes(b"PK").unwrap().description, "system ZIP");
+        assert_eq!(
+            db.evaluate_bytes(b"%PDF").unwrap().description,
+            "last resort PDF"
+        );
+        assert_eq!(db.evaluate_bytes(b"none").unwrap().description, "data");
+    }
+
+    #[test]
+    fn test_magic_database_builtin_matches_follow_rules_and_filter() {
+        let bom_text = b"\xef\xbb\xbfhello";
+
+        // An empty database reports nothing, not even a byte-order mark
+        let empty = MagicDatabase::from_rules(Vec::new(), EvaluationConfig::default());
+        let result = empty.evaluate_bytes(bom_text).unwrap();
+        assert_eq!(result.description, "data");
+        assert!(result.matches.is_empty());
+
+        let path = std::env::temp_dir().join(format!(
+            "libmagic_rs_builtin_filter_{}.magic",
+            std::process::id()
+        ));
+        std::fs::write(&path, "0 string \\x89PNG PNG image\n!:mime image/png\n").unwrap();
+        let images_only = MagicDatabase::load_from_file_with_filter(
+            &path,
+            EvaluationConfig::default(),
+            |category| category == FileCategory::Image,
+        )
+        .unwrap();
+        let everything =
+            MagicDatabase::load_from_file_with_filter(&path, EvaluationConfig::default(), |_| true)
+                .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Text is filtered out like a rejected rule would be
+        assert_eq!(
+            images_only.evaluate_bytes(bom_text).unwrap().description,
+            "data"
+        );
+        assert_eq!(
+            everything.evaluate_bytes(bom_text).unwrap().description,
+            "Unicode text, UTF-8 (with BOM)"
+        );
+    }
+
+    #[test]
+    fn test_magic_database_fallback_is_consulted_for_bom_files() {
+        let primary = MagicDatabase::from_rules(
+            vec![byte_rule(0x7f, "custom ELF")],
+            EvaluationConfig::default(),
+        );
+        let fallback = MagicDatabase::from_rules(
+            parser::parse_magic_string("3 string <?xml XML document\n").unwrap(),
+            EvaluationConfig::default(),
+        );
+        let db = primary.with_fallback(fallback);
+
+        // The byte-order mark is not a rule match, so the fallback still runs
+        let result = db.evaluate_bytes(b"\xef\xbb\xbf<?xml version").unwrap();
+        assert_eq!(
+            result.description,
+            "Unicode text, UTF-8 (with BOM) XML document"
+        );
+        assert_eq!(
+            db.evaluate_bytes(b"\xef\xbb\xbfplain").unwrap().description,
+            "Unicode text, UTF-8 (with BOM)"
+        );
+    }
+
+    #[test]
+    fn test_evaluation_result_category_from_mime_type() {
+        let rules = parser::parse_magic_string(
+            "0 string \\x89PNG PNG\n!:mime image/png\n\
+             0 string PK\\x03\\x04 Zip data\n!:mime application/zip\n\
+             0 string %PDF PDF\n!:mime application/pdf\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let category = |data: &[u8]| db.evaluate_bytes(data).unwrap().category();
+        assert_eq!(category(b"\x89PNG\r\n"), FileCategory::Image);
+        assert_eq!(category(b"PK\x03\x04"), FileCategory::Archive);
+        assert_eq!(category(b"%PDF-1.7"), FileCategory::Document);
+        assert_eq!(category(b"unknown"), FileCategory::Other);
+    }
+
+    #[test]
+    fn test_magic_database_reports_zip_subtype() {
+        // A single stored JAR manifest entry, without a central directory
+        let name = b"META-INF/MANIFEST.MF";
+        let mut jar = b"PK\x03\x04".to_vec();
+        jar.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        jar.extend_from_slice(&[0; 8]);
+        jar.extend_from_slice(&u16::try_from(name.len()).unwrap().to_le_bytes());
+        jar.extend_from_slice(&[0, 0]);
+        jar.extend_from_slice(name);
+
+        let db = MagicDatabase::from_rules(
+            vec![byte_rule(b'P', "Zip archive data")],
+            EvaluationConfig::default(),
+        );
+        assert_eq!(
+            db.evaluate_bytes(&jar).unwrap().description,
+            "Java archive data (JAR) Zip archive data"
+        );
+    }
+
+    #[test]
+    fn test_magic_database_reports_matched_source_lines() {
+        let source = "\
+# Portable Network Graphics
+0 string \\x89PNG PNG image data
+
+# Section with a child rule
+0 string GIF8 GIF image data
+>4 byte 0x39 version 89a
+";
+        let rules = parser::parse_magic_string(source).unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(b"GIF89a").unwrap();
+        assert_eq!(result.description, "GIF image data version 89a");
+        let lines: Vec<Option<usize>> = result.matches.iter().map(|m| m.source_line).collect();
+        assert_eq!(lines, vec![Some(5), Some(6)]);
+
+        let result = db.evaluate_bytes(b"\x89PNG").unwrap();
+        assert_eq!(result.matches[0].source_line, Some(2));
+    }
+
+    #[test]
+    fn test_evaluation_result_lists_all_mime_types() {
+        let rules = parser::parse_magic_string(
+            "0 string GIF8 GIF image data\n\
+             !:mime image/gif\n\
+             >4 byte 0x39 version 89a\n\
+             !:mime image/gif\n\
+             0 search/64 html HTML document\n\
+             !:mime text/html\n\
+             0 byte x no MIME\n",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(rules, config);
+
+        let result = db.evaluate_bytes(b"GIF89a<html>").unwrap();
+        assert_eq!(result.matches.len(), 4);
+        assert_eq!(result.mime_types(), ["image/gif", "text/html"]);
+        assert_eq!(result.mime_type.as_deref(), Some("image/gif"));
+
+        let result = db.evaluate_bytes(b"plain").unwrap();
+        assert!(result.mime_types().is_empty());
+        assert_eq!(result.mime_type, None);
+    }
+
+    #[test]
+    fn test_magic_database_reports_extensions() {
+        let rules = parser::parse_magic_string(
+            "0 beshort 0xffd8 JPEG image data\n\
+             !:ext jpg/jpeg\n\
+             >2 beshort 0xffe0 JFIF\n\
+             !:ext jfif\n\
+             0 string MZ DOS executable\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let result = db.evaluate_bytes(b"\xff\xd8\xff\xe0").unwrap();
+        assert_eq!(result.description, "JPEG image data JFIF");
+        assert_eq!(result.extensions, ["jpg", "jpeg"]);
+        assert!(db.evaluate_bytes(b"MZ").unwrap().extensions.is_empty());
+    }
+
+    #[test]
+    fn test_magic_database_reports_apple_codes() {
+        // AppleSingle: magic, version, 16 filler bytes, entry count, then
+        // (id, offset, length) entries; entry 9 is the Finder info
+        let rules = parser::parse_magic_string(
+            "0 belong 0x00051600 AppleSingle encoded Macintosh file\n\
+             >4 belong 0x00020000 version 2\n\
+             >>26 belong 9 with Finder info\n\
+             >>>(30.L) string TEXT text\n\
+             !:apple TEXTttxt\n\
+             >>>(30.L) string APPL application\n\
+             !:apple APPL????\n",
+        )
+        .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+
+        let applesingle = |file_type: &[u8; 4]| {
+            let mut data = vec![0x00, 0x05, 0x16, 0x00, 0x00, 0x02, 0x00, 0x00];
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&1u16.to_be_bytes());
+            for field in [9u32, 38, 32] {
+                data.extend_from_slice(&field.to_be_bytes());
+            }
+            data.extend_from_slice(file_type);
+            data.extend_from_slice(b"ttxt");
+            data.extend_from_slice(&[0; 24]);
+            data
+        };
+
+        let result = db.evaluate_bytes(&applesingle(b"TEXT")).unwrap();
+        assert_eq!(
+            result.description,
+            "AppleSingle encoded Macintosh file version 2 with Finder info text"
+        );
+        assert_eq!(result.apple_type.as_deref(), Some("TEXT"));
+        assert_eq!(result.apple_creator.as_deref(), Some("ttxt"));
+
+        let result = db.evaluate_bytes(&applesingle(b"APPL")).unwrap();
+        assert_eq!(result.apple_type.as_deref(), Some("APPL"));
+        assert_eq!(result.apple_creator.as_deref(), Some("????"));
+
+        let result = db.evaluate_bytes(&applesingle(b"PDF ")).unwrap();
+        assert_eq!(result.apple_type, None);
+        assert_eq!(result.apple_creator, None);
+    }
+}
@@ -0,0 +1,212 @@
+//! Byte-order mark detection
+//!
+//! Recognizes Unicode byte-order marks at the start of a buffer so that files
+//! beginning with a BOM can be reported with their encoding, independently of
+//! the loaded magic rules.
+
+use crate::evaluator::MatchResult;
+use crate::parser::ast::Value;
+
+/// A Unicode byte-order mark found at the start of a buffer
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::bom::ByteOrderMark;
+///
+/// let bom = ByteOrderMark::detect(&[0xef, 0xbb, 0xbf, b'h', b'i']);
+/// assert_eq!(bom, Some(ByteOrderMark::Utf8));
+/// assert_eq!(ByteOrderMark::detect(b"plain ascii"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrderMark {
+    /// UTF-8 BOM (`EF BB BF`)
+    Utf8,
+    /// UTF-16 little-endian BOM (`FF FE`)
+    Utf16Le,
+    /// UTF-16 big-endian BOM (`FE FF`)
+    Utf16Be,
+    /// UTF-32 little-endian BOM (`FF FE 00 00`)
+    Utf32Le,
+    /// UTF-32 big-endian BOM (`00 00 FE FF`)
+    Utf32Be,
+}
+
+impl ByteOrderMark {
+    /// Detect a byte-order mark at the start of `buffer`
+    ///
+    /// UTF-32 marks are checked before UTF-16 ones since the UTF-32
+    /// little-endian mark begins with the UTF-16 little-endian mark.
+    #[must_use]
+    pub fn detect(buffer: &[u8]) -> Option<Self> {
+        [
+            Self::Utf32Le,
+            Self::Utf32Be,
+            Self::Utf8,
+            Self::Utf16Le,
+            Self::Utf16Be,
+        ]
+        .into_iter()
+        .find(|bom| buffer.starts_with(bom.as_bytes()))
+    }
+
+    /// The raw bytes of this byte-order mark
+    #[must_use]
+    pub const fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => &[0xef, 0xbb, 0xbf],
+            Self::Utf16Le => &[0xff, 0xfe],
+            Self::Utf16Be => &[0xfe, 0xff],
+            Self::Utf32Le => &[0xff, 0xfe, 0x00, 0x00],
+            Self::Utf32Be => &[0x00, 0x00, 0xfe, 0xff],
+        }
+    }
+
+    /// The encoding label for this byte-order mark, in GNU `file` style
+    #[must_use]
+    pub const fn encoding(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16, little-endian",
+            Self::Utf16Be => "UTF-16, big-endian",
+            Self::Utf32Le => "UTF-32, little-endian",
+            Self::Utf32Be => "UTF-32, big-endian",
+        }
+    }
+
+    /// Human-readable description reported for a file starting with this mark
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::evaluator::bom::ByteOrderMark;
+    ///
+    /// assert_eq!(
+    ///     ByteOrderMark::Utf8.description(),
+    ///     "Unicode text, UTF-8 (with BOM)"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn description(self) -> String {
+        format!("Unicode text, {} (with BOM)", self.encoding())
+    }
+}
+
+/// Produce a match for a byte-order mark at the start of `buffer`, if present
+///
+/// The match is reported at offset 0 and level 0 with the BOM bytes as its
+/// value, so it can be placed ahead of the regular rule matches without
+/// affecting their evaluation.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::bom::detect_bom_match;
+///
+/// let m = detect_bom_match(&[0xfe, 0xff, 0x00, 0x41]).unwrap();
+/// assert_eq!(m.message, "Unicode text, UTF-16, big-endian (with BOM)");
+/// assert_eq!(m.offset, 0);
+/// ```
+#[must_use]
+pub fn detect_bom_match(buffer: &[u8]) -> Option<MatchResult> {
+    ByteOrderMark::detect(buffer).map(|bom| MatchResult {
+        message: bom.description(),
+        offset: 0,
+        length: bom.as_bytes().len(),
+        level: 0,
+        value: Value::Bytes(bom.as_bytes().to_vec()),
+        source_line: None,
+        apple_type: None,
+        apple_creator: None,
+        mime_type: None,
+        extensions: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_utf8_bom() {
+        assert_eq!(
+            ByteOrderMark::detect(&[0xef, 0xbb, 0xbf]),
+            Some(ByteOrderMark::Utf8)
+        );
+        assert_eq!(
+            ByteOrderMark::detect(&[0xef, 0xbb, 0xbf, b'a', b'b']),
+            Some(ByteOrderMark::Utf8)
+        );
+    }
+
+    #[test]
+    fn test_detect_utf16_boms() {
+        assert_eq!(
+            ByteOrderMark::detect(&[0xff, 0xfe, b'a', 0x00]),
+            Some(ByteOrderMark::Utf16Le)
+        );
+        assert_eq!(
+            ByteOrderMark::detect(&[0xfe, 0xff, 0x00, b'a']),
+            Some(ByteOrderMark::Utf16Be)
+        );
+    }
+
+    #[test]
+    fn test_detect_utf32_boms() {
+        assert_eq!(
+            ByteOrderMark::detect(&[0xff, 0xfe, 0x00, 0x00]),
+            Some(ByteOrderMark::Utf32Le)
+        );
+        assert_eq!(
+            ByteOrderMark::detect(&[0x00, 0x00, 0xfe, 0xff]),
+            Some(ByteOrderMark::Utf32Be)
+        );
+    }
+
+    #[test]
+    fn test_detect_no_bom() {
+        assert_eq!(ByteOrderMark::detect(&[]), None);
+        assert_eq!(ByteOrderMark::detect(b"hello"), None);
+        assert_eq!(ByteOrderMark::detect(&[0xef, 0xbb]), None);
+        assert_eq!(ByteOrderMark::detect(&[0x00, 0x00, 0xfe]), None);
+        // BOM bytes that are not at the start do not count
+        assert_eq!(ByteOrderMark::detect(&[b'x', 0xef, 0xbb, 0xbf]), None);
+    }
+
+    #[test]
+    fn test_bom_match_labels() {
+        let cases: [(&[u8], &str); 5] = [
+            (&[0xef, 0xbb, 0xbf], "Unicode text, UTF-8 (with BOM)"),
+            (
+                &[0xff, 0xfe, b'a', 0x00],
+                "Unicode text, UTF-16, little-endian (with BOM)",
+            ),
+            (&[0xfe, 0xff], "Unicode text, UTF-16, big-endian (with BOM)"),
+            (
+                &[0xff, 0xfe, 0x00, 0x00],
+                "Unicode text, UTF-32, little-endian (with BOM)",
+            ),
+            (
+                &[0x00, 0x00, 0xfe, 0xff],
+                "Unicode text, UTF-32, big-endian (with BOM)",
+            ),
+        ];
+
+        for (buffer, expected) in cases {
+            let m = detect_bom_match(buffer).unwrap();
+            assert_eq!(m.message, expected);
+            assert_eq!(m.offset, 0);
+            assert_eq!(m.level, 0);
+            assert!(m.length >= 2 && m.length <= buffer.len());
+            match m.value {
+                Value::Bytes(bytes) => assert!(buffer.starts_with(&bytes)),
+                other => panic!("Expected Bytes value, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_bom_match_absent() {
+        assert!(detect_bom_match(b"\x7fELF").is_none());
+    }
+}
@@ -0,0 +1,904 @@
+//! Evaluation state carried from rule to rule
+
+use super::MAX_INDIRECT_DEPTH;
+use super::extension::TypeReaderRegistry;
+use crate::parser::ast::MagicRule;
+use crate::{EvaluationConfig, LibmagicError};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Context for maintaining evaluation state during rule processing
+///
+/// The `EvaluationContext` tracks the current state of rule evaluation,
+/// including the current offset position, recursion depth for nested rules,
+/// and configuration settings that control evaluation behavior.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::EvaluationContext;
+/// use libmagic_rs::EvaluationConfig;
+///
+/// let config = EvaluationConfig::default();
+/// let context = EvaluationContext::new(config);
+///
+/// assert_eq!(context.current_offset(), 0);
+/// assert_eq!(context.recursion_depth(), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EvaluationContext {
+    /// File offset just past the parent rule's match
+    ///
+    /// Relative offsets (`&n`) are measured from here. It is set before each
+    /// rule's children are evaluated, so it points past the end of a `search`
+    /// or `string` match rather than at the offset the rule started from.
+    current_offset: usize,
+    /// Current recursion depth for nested rule evaluation
+    recursion_depth: u32,
+    /// Number of `indirect` regions currently being evaluated
+    indirect_depth: u32,
+    /// Number of rules evaluated so far, checked against the rule budget
+    rules_evaluated: usize,
+    /// File offset of the buffer currently being evaluated
+    ///
+    /// This is 0 for the file itself and the start of the sub-buffer while
+    /// evaluating an `indirect` region or a `use` subroutine.
+    base_offset: usize,
+    /// Full rule database used to resolve `indirect` and `use` rules
+    database: Option<Arc<Vec<MagicRule>>>,
+    /// Size of the whole file when the buffer holds only its first bytes
+    file_size: Option<usize>,
+    /// Whether integer reads use the opposite byte order (inside `use ^name`)
+    swap_endian: bool,
+    /// Value kept by the last matching rule with `store` set
+    register: Option<i64>,
+    /// When the outermost evaluation started, shared by the timeout and timing
+    start_time: Option<Instant>,
+    /// Readers for `x-` extension types
+    type_readers: Option<Arc<TypeReaderRegistry>>,
+    /// Configuration settings for evaluation behavior
+    config: EvaluationConfig,
+}
+
+impl EvaluationContext {
+    /// Create a new evaluation context with the given configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration settings for evaluation behavior
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::evaluator::EvaluationContext;
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let config = EvaluationConfig::default();
+    /// let context = EvaluationContext::new(config);
+    /// ```
+    #[must_use]
+    pub const fn new(config: EvaluationConfig) -> Self {
+        Self {
+            current_offset: 0,
+            recursion_depth: 0,
+            indirect_depth: 0,
+            rules_evaluated: 0,
+            base_offset: 0,
+            database: None,
+            file_size: None,
+            swap_endian: false,
+            register: None,
+            start_time: None,
+            type_readers: None,
+            config,
+        }
+    }
+
+    /// Get the file offset that relative (`&n`) offsets are measured from
+    ///
+    /// # Returns
+    ///
+    /// The file offset just past the parent rule's match, or 0 for top-level rules
+    #[must_use]
+    pub const fn current_offset(&self) -> usize {
+        self.current_offset
+    }
+
+    /// Set the file offset that relative (`&n`) offsets are measured from
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The new offset position
+    pub fn set_current_offset(&mut self, offset: usize) {
+        self.current_offset = offset;
+    }
+
+    /// Get the current recursion depth
+    ///
+    /// # Returns
+    ///
+    /// The current recursion depth for nested rule evaluation
+    #[must_use]
+    pub const fn recursion_depth(&self) -> u32 {
+        self.recursion_depth
+    }
+
+    /// Increment the recursion depth
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the recursion depth is within limits, or `Err(LibmagicError)`
+    /// if the maximum recursion depth would be exceeded
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::EvaluationError` if incrementing would exceed
+    /// the maximum recursion depth configured in the evaluation config.
+    pub fn increment_recursion_depth(&mut self) -> Result<(), LibmagicError> {
+        if self.recursion_depth >= self.config.max_recursion_depth {
+            return Err(LibmagicError::EvaluationError(
+                "Maximum recursion depth exceeded".to_string(),
+            ));
+        }
+        self.recursion_depth += 1;
+        Ok(())
+    }
+
+    /// Decrement the recursion depth
+    ///
+    /// # Panics
+    ///
+    /// Panics if the recursion depth is already 0, as this indicates
+    /// a programming error in the evaluation logic.
+    pub fn decrement_recursion_depth(&mut self) {
+        assert!(
+            self.recursion_depth != 0,
+            "Attempted to decrement recursion depth below 0"
+        );
+        self.recursion_depth -= 1;
+    }
+
+    /// Get the number of `indirect` regions currently being evaluated
+    #[must_use]
+    pub const fn indirect_depth(&self) -> u32 {
+        self.indirect_depth
+    }
+
+    /// Increment the indirect chain depth before evaluating an `indirect` region
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::EvaluationError` if incrementing would exceed
+    /// [`MAX_INDIRECT_DEPTH`], which happens when pointers form a loop.
+    pub fn increment_indirect_depth(&mut self) -> Result<(), LibmagicError> {
+        if self.indirect_depth >= MAX_INDIRECT_DEPTH {
+            return Err(LibmagicError::EvaluationError(
+                "indirect offset chain too deep".to_string(),
+            ));
+        }
+        self.indirect_depth += 1;
+        Ok(())
+    }
+
+    /// Decrement the indirect chain depth
+    ///
+    /// # Panics
+    ///
+    /// Panics if the indirect depth is already 0, as this indicates
+    /// a programming error in the evaluation logic.
+    pub fn decrement_indirect_depth(&mut self) {
+        assert!(
+            self.indirect_depth != 0,
+            "Attempted to decrement indirect depth below 0"
+        );
+        self.indirect_depth -= 1;
+    }
+
+    /// Get the file offset of the buffer currently being evaluated
+    ///
+    /// # Returns
+    ///
+    /// 0 at the top level, or the file offset of the sub-buffer origin while
+    /// evaluating an `indirect` region or a `use` subroutine
+    #[must_use]
+    pub const fn base_offset(&self) -> usize {
+        self.base_offset
+    }
+
+    /// Set the file offset of the buffer currently being evaluated
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The new base offset
+    pub fn set_base_offset(&mut self, offset: usize) {
+        self.base_offset = offset;
+    }
+
+    /// Get the rule database used to resolve `indirect` and `use` rules
+    #[must_use]
+    pub const fn database(&self) -> Option<&Arc<Vec<MagicRule>>> {
+        self.database.as_ref()
+    }
+
+    /// Set the rule database used to resolve `indirect` and `use` rules
+    ///
+    /// Without a database, evaluating an `indirect` or `use` rule is an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - The complete top-level rule set, including `name` definitions
+    pub fn set_database(&mut self, rules: Arc<Vec<MagicRule>>) {
+        self.database = Some(rules);
+    }
+
+    /// Get the readers used for `x-` extension types
+    #[must_use]
+    pub fn type_readers(&self) -> Option<&TypeReaderRegistry> {
+        self.type_readers.as_deref()
+    }
+
+    /// Set the readers used for `x-` extension types
+    ///
+    /// Without readers, evaluating a rule with an extension type is an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `readers` - Readers keyed by extension type name
+    pub fn set_type_readers(&mut self, readers: Arc<TypeReaderRegistry>) {
+        self.type_readers = Some(readers);
+    }
+
+    /// Get the size of the whole file, if it was set with [`Self::set_file_size`]
+    #[must_use]
+    pub const fn file_size(&self) -> Option<usize> {
+        self.file_size
+    }
+
+    /// Set the size of the whole file when the buffer holds only its first bytes
+    ///
+    /// From-end offsets are then resolved against the file size, and anything
+    /// beyond the buffer is out of bounds.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size of the file the evaluated buffer was read from
+    pub fn set_file_size(&mut self, size: usize) {
+        self.file_size = Some(size);
+    }
+
+    /// Whether integer reads use the opposite byte order
+    ///
+    /// This is set while evaluating a subroutine invoked with `use ^name`, and
+    /// a nested `use ^name` flips it back.
+    #[must_use]
+    pub const fn swap_endian(&self) -> bool {
+        self.swap_endian
+    }
+
+    /// Set whether integer reads use the opposite byte order
+    ///
+    /// # Arguments
+    ///
+    /// * `swap` - `true` to read integers with their byte order flipped
+    pub fn set_swap_endian(&mut self, swap: bool) {
+        self.swap_endian = swap;
+    }
+
+    /// Get the value kept by the last matching rule with `store` set
+    ///
+    /// There is one register for the whole evaluation, read by
+    /// [`OffsetSpec::Register`] offsets. Child rules and `use` subroutines
+    /// share it, and it is not restored after them, so a value stored anywhere
+    /// stays until the next storing rule matches.
+    #[must_use]
+    pub const fn register(&self) -> Option<i64> {
+        self.register
+    }
+
+    /// Replace the value in the register
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value later [`OffsetSpec::Register`] offsets are based on
+    pub fn set_register(&mut self, value: i64) {
+        self.register = Some(value);
+    }
+
+    /// Start the evaluation clock if it is not already running
+    ///
+    /// Nested evaluations of `indirect` and `use` rules keep the clock started
+    /// by the outermost call, so the timeout covers the whole evaluation.
+    pub(super) fn start_timer(&mut self) -> Instant {
+        *self.start_time.get_or_insert_with(Instant::now)
+    }
+
+    /// Run the evaluation clock from `start` rather than from the first evaluation
+    ///
+    /// Time already spent, such as reading the file, then counts toward the
+    /// timeout. [`Self::reset`] clears it again.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::evaluator::{EvaluationContext, evaluate_rules};
+    /// use libmagic_rs::parser::parse_magic_string;
+    /// use libmagic_rs::{EvaluationConfig, LibmagicError};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let rules = parse_magic_string("0 byte 1 one\n")?;
+    /// let config = EvaluationConfig { timeout_ms: Some(5), ..EvaluationConfig::default() };
+    /// let mut context = EvaluationContext::new(config);
+    /// context.set_start_time(Instant::now() - Duration::from_millis(50));
+    ///
+    /// let error = evaluate_rules(&rules, &[1], &mut context).unwrap_err();
+    /// assert!(matches!(error, LibmagicError::Timeout { timeout_ms: 5 }));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_start_time(&mut self, start: Instant) {
+        self.start_time = Some(start);
+    }
+
+    /// Time spent evaluating with this context, in fractional milliseconds
+    ///
+    /// Measured with a monotonic clock from the start of the first
+    /// [`evaluate_rules`] call since the context was created or reset, using
+    /// the same start as the timeout check. Returns 0.0 before evaluation starts.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::evaluator::{evaluate_rules, EvaluationContext};
+    /// use libmagic_rs::parser::parse_magic_string;
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let mut context = EvaluationContext::new(EvaluationConfig::default());
+    /// assert_eq!(context.elapsed_ms(), 0.0);
+    ///
+    /// let rules = parse_magic_string("0 byte 1 one\n")?;
+    /// evaluate_rules(&rules, &[1], &mut context)?;
+    /// assert!(context.elapsed_ms() >= 0.0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn elapsed_ms(&self) -> f64 {
+        self.start_time
+            .map_or(0.0, |start| start.elapsed().as_secs_f64() * 1000.0)
+    }
+
+    /// Length of the data that `buffer` is the start of at the current base offset
+    pub(super) fn data_len(&self, buffer: &[u8]) -> usize {
+        self.file_size
+            .map_or(buffer.len(), |size| size.saturating_sub(self.base_offset))
+    }
+
+    /// Get the number of rules evaluated so far
+    ///
+    /// # Returns
+    ///
+    /// The number of rules (including children) evaluated with this context
+    #[must_use]
+    pub const fn rules_evaluated(&self) -> usize {
+        self.rules_evaluated
+    }
+
+    /// Record that a rule is about to be evaluated
+    ///
+    /// # Returns
+    ///
+    /// `false`, without recording anything, if evaluating another rule would
+    /// exceed the `max_rule_evaluations` budget configured in the evaluation config
+    pub fn record_rule_evaluation(&mut self) -> bool {
+        if self
+            .config
+            .max_rule_evaluations
+            .is_some_and(|budget| self.rules_evaluated >= budget)
+        {
+            return false;
+        }
+        self.rules_evaluated += 1;
+        true
+    }
+
+    /// Get a reference to the evaluation configuration
+    ///
+    /// # Returns
+    ///
+    /// A reference to the `EvaluationConfig` used by this context
+    #[must_use]
+    pub const fn config(&self) -> &EvaluationConfig {
+        &self.config
+    }
+
+    /// Check if evaluation should stop at the first match
+    ///
+    /// # Returns
+    ///
+    /// `true` if evaluation should stop at the first match, `false` otherwise
+    #[must_use]
+    pub const fn should_stop_at_first_match(&self) -> bool {
+        self.config.stop_at_first_match
+    }
+
+    /// Get the maximum string length allowed
+    ///
+    /// # Returns
+    ///
+    /// The maximum string length that should be read during evaluation
+    #[must_use]
+    pub const fn max_string_length(&self) -> usize {
+        self.config.max_string_length
+    }
+
+    /// Check if MIME type mapping is enabled
+    ///
+    /// # Returns
+    ///
+    /// `true` if MIME type mapping should be performed, `false` otherwise
+    #[must_use]
+    pub const fn enable_mime_types(&self) -> bool {
+        self.config.enable_mime_types
+    }
+
+    /// Get the evaluation timeout in milliseconds
+    ///
+    /// # Returns
+    ///
+    /// The timeout duration in milliseconds, or `None` if no timeout is set
+    #[must_use]
+    pub const fn timeout_ms(&self) -> Option<u64> {
+        self.config.timeout_ms
+    }
+
+    /// Reset the context to initial state while preserving configuration
+    ///
+    /// This resets the current offset, recursion depth, rule count, and base
+    /// offset to 0 and clears the byte swap flag and the register, but keeps
+    /// the same configuration settings and database.
+    pub fn reset(&mut self) {
+        self.current_offset = 0;
+        self.recursion_depth = 0;
+        self.indirect_depth = 0;
+        self.rules_evaluated = 0;
+        self.base_offset = 0;
+        self.swap_endian = false;
+        self.register = None;
+        self.start_time = None;
+    }
+
+    /// Capture the position state that nested evaluation changes
+    ///
+    /// Pass the snapshot to [`Self::restore`] after evaluating child rules or
+    /// a sub-region to undo every change they made to the offset, recursion
+    /// depth, base offset and byte swap flag.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmagic_rs::evaluator::EvaluationContext;
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let mut context = EvaluationContext::new(EvaluationConfig::default());
+    /// let snapshot = context.snapshot();
+    ///
+    /// context.increment_recursion_depth()?;
+    /// context.set_base_offset(512);
+    /// context.restore(snapshot);
+    ///
+    /// assert_eq!(context.recursion_depth(), 0);
+    /// assert_eq!(context.base_offset(), 0);
+    /// # Ok::<(), libmagic_rs::LibmagicError>(())
+    /// ```
+    #[must_use]
+    pub const fn snapshot(&self) -> ContextSnapshot {
+        ContextSnapshot {
+            current_offset: self.current_offset,
+            recursion_depth: self.recursion_depth,
+            indirect_depth: self.indirect_depth,
+            base_offset: self.base_offset,
+            swap_endian: self.swap_endian,
+        }
+    }
+
+    /// Restore the position state captured by [`Self::snapshot`]
+    ///
+    /// The rule count, timer, database and configuration are left alone, so
+    /// the rule budget and timeout still cover the nested evaluation.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - State previously taken from this context
+    pub fn restore(&mut self, snapshot: ContextSnapshot) {
+        self.current_offset = snapshot.current_offset;
+        self.recursion_depth = snapshot.recursion_depth;
+        self.indirect_depth = snapshot.indirect_depth;
+        self.base_offset = snapshot.base_offset;
+        self.swap_endian = snapshot.swap_endian;
+    }
+}
+
+/// Position state of an [`EvaluationContext`] saved by [`EvaluationContext::snapshot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextSnapshot {
+    current_offset: usize,
+    recursion_depth: u32,
+    indirect_depth: u32,
+    base_offset: usize,
+    swap_endian: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::evaluate_rules;
+
+    // Tests for EvaluationContext
+    #[test]
+    fn test_evaluation_context_new() {
+        let config = EvaluationConfig::default();
+        let context = EvaluationContext::new(config.clone());
+
+        assert_eq!(context.current_offset(), 0);
+        assert_eq!(context.recursion_depth(), 0);
+        assert_eq!(
+            context.config().max_recursion_depth,
+            config.max_recursion_depth
+        );
+        assert_eq!(context.config().max_string_length, config.max_string_length);
+        assert_eq!(
+            context.config().stop_at_first_match,
+            config.stop_at_first_match
+        );
+    }
+
+    #[test]
+    fn test_evaluation_context_offset_management() {
+        let config = EvaluationConfig::default();
+        let mut context = EvaluationContext::new(config);
+
+        // Test initial offset
+        assert_eq!(context.current_offset(), 0);
+
+        // Test setting offset
+        context.set_current_offset(42);
+        assert_eq!(context.current_offset(), 42);
+
+        // Test setting different offset
+        context.set_current_offset(1024);
+        assert_eq!(context.current_offset(), 1024);
+
+        // Test setting offset to 0
+        context.set_current_offset(0);
+        assert_eq!(context.current_offset(), 0);
+    }
+
+    #[test]
+    fn test_evaluation_context_recursion_depth_management() {
+        let config = EvaluationConfig::default();
+        let mut context = EvaluationContext::new(config);
+
+        // Test initial recursion depth
+        assert_eq!(context.recursion_depth(), 0);
+
+        // Test incrementing recursion depth
+        context.increment_recursion_depth().unwrap();
+        assert_eq!(context.recursion_depth(), 1);
+
+        context.increment_recursion_depth().unwrap();
+        assert_eq!(context.recursion_depth(), 2);
+
+        // Test decrementing recursion depth
+        context.decrement_recursion_depth();
+        assert_eq!(context.recursion_depth(), 1);
+
+        context.decrement_recursion_depth();
+        assert_eq!(context.recursion_depth(), 0);
+    }
+
+    #[test]
+    fn test_evaluation_context_recursion_depth_limit() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 2,
+            ..Default::default()
+        };
+        let mut context = EvaluationContext::new(config);
+
+        // Should be able to increment up to the limit
+        assert!(context.increment_recursion_depth().is_ok());
+        assert_eq!(context.recursion_depth(), 1);
+
+        assert!(context.increment_recursion_depth().is_ok());
+        assert_eq!(context.recursion_depth(), 2);
+
+        // Should fail when exceeding the limit
+        let result = context.increment_recursion_depth();
+        assert!(result.is_err());
+        assert_eq!(context.recursion_depth(), 2); // Should not have changed
+
+        match result.unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Maximum recursion depth exceeded"));
+            }
+            _ => panic!("Expected EvaluationError"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Attempted to decrement recursion depth below 0")]
+    fn test_evaluation_context_recursion_depth_underflow() {
+        let config = EvaluationConfig::default();
+        let mut context = EvaluationContext::new(config);
+
+        // Should panic when trying to decrement below 0
+        context.decrement_recursion_depth();
+    }
+
+    #[test]
+    fn test_evaluation_context_config_access() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 10,
+            max_string_length: 4096,
+            stop_at_first_match: false,
+            enable_mime_types: true,
+            timeout_ms: Some(2000),
+            ..Default::default()
+        };
+
+        let context = EvaluationContext::new(config);
+
+        // Test config access
+        assert_eq!(context.config().max_recursion_depth, 10);
+        assert_eq!(context.config().max_string_length, 4096);
+        assert!(!context.config().stop_at_first_match);
+
+        // Test convenience methods
+        assert!(!context.should_stop_at_first_match());
+        assert_eq!(context.max_string_length(), 4096);
+    }
+
+    #[test]
+    fn test_evaluation_context_reset() {
+        let config = EvaluationConfig::default();
+        let mut context = EvaluationContext::new(config.clone());
+
+        // Modify the context state
+        context.set_current_offset(100);
+        context.increment_recursion_depth().unwrap();
+        context.increment_recursion_depth().unwrap();
+
+        assert_eq!(context.current_offset(), 100);
+        assert_eq!(context.recursion_depth(), 2);
+
+        // Reset should restore initial state but keep config
+        context.reset();
+
+        assert_eq!(context.current_offset(), 0);
+        assert_eq!(context.recursion_depth(), 0);
+        assert_eq!(
+            context.config().max_recursion_depth,
+            config.max_recursion_depth
+        );
+    }
+
+    #[test]
+    fn test_evaluation_context_snapshot_round_trip() {
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_current_offset(7);
+        context.increment_recursion_depth().unwrap();
+        context.set_base_offset(64);
+        context.set_swap_endian(true);
+        let snapshot = context.snapshot();
+
+        context.set_current_offset(100);
+        context.increment_recursion_depth().unwrap();
+        context.increment_recursion_depth().unwrap();
+        context.set_base_offset(4096);
+        context.set_swap_endian(false);
+        assert!(context.record_rule_evaluation());
+        context.restore(snapshot);
+
+        assert_eq!(context.current_offset(), 7);
+        assert_eq!(context.recursion_depth(), 1);
+        assert_eq!(context.base_offset(), 64);
+        assert!(context.swap_endian());
+        assert_eq!(context.snapshot(), snapshot);
+        // The rule count is not part of the position state
+        assert_eq!(context.rules_evaluated(), 1);
+    }
+
+    #[test]
+    fn test_evaluation_context_clone() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 5,
+            max_string_length: 2048,
+            ..Default::default()
+        };
+
+        let mut context = EvaluationContext::new(config);
+        context.set_current_offset(50);
+        context.increment_recursion_depth().unwrap();
+
+        // Clone the context
+        let cloned_context = context.clone();
+
+        // Both should have the same state
+        assert_eq!(context.current_offset(), cloned_context.current_offset());
+        assert_eq!(context.recursion_depth(), cloned_context.recursion_depth());
+        assert_eq!(
+            context.config().max_recursion_depth,
+            cloned_context.config().max_recursion_depth
+        );
+        assert_eq!(
+            context.config().max_string_length,
+            cloned_context.config().max_string_length
+        );
+
+        // Modifying one should not affect the other
+        context.set_current_offset(75);
+        assert_eq!(context.current_offset(), 75);
+        assert_eq!(cloned_context.current_offset(), 50);
+    }
+
+    #[test]
+    fn test_evaluation_context_with_custom_config() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 15,
+            max_string_length: 16384,
+            stop_at_first_match: false,
+            enable_mime_types: true,
+            timeout_ms: Some(5000),
+            ..Default::default()
+        };
+
+        let context = EvaluationContext::new(config);
+
+        assert_eq!(context.config().max_recursion_depth, 15);
+        assert_eq!(context.max_string_length(), 16384);
+        assert!(!context.should_stop_at_first_match());
+
+        // Test that we can increment up to the custom limit
+        let mut mutable_context = context;
+        for i in 1..=15 {
+            assert!(mutable_context.increment_recursion_depth().is_ok());
+            assert_eq!(mutable_context.recursion_depth(), i);
+        }
+
+        // Should fail on the 16th increment
+        let result = mutable_context.increment_recursion_depth();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_evaluation_context_mime_types_access() {
+        let config_with_mime = EvaluationConfig {
+            enable_mime_types: true,
+            ..Default::default()
+        };
+        let context_with_mime = EvaluationContext::new(config_with_mime);
+        assert!(context_with_mime.enable_mime_types());
+
+        let config_without_mime = EvaluationConfig {
+            enable_mime_types: false,
+            ..Default::default()
+        };
+        let context_without_mime = EvaluationContext::new(config_without_mime);
+        assert!(!context_without_mime.enable_mime_types());
+    }
+
+    #[test]
+    fn test_evaluation_context_timeout_access() {
+        let config_with_timeout = EvaluationConfig {
+            timeout_ms: Some(5000),
+            ..Default::default()
+        };
+        let context_with_timeout = EvaluationContext::new(config_with_timeout);
+        assert_eq!(context_with_timeout.timeout_ms(), Some(5000));
+
+        let config_without_timeout = EvaluationConfig {
+            timeout_ms: None,
+            ..Default::default()
+        };
+        let context_without_timeout = EvaluationContext::new(config_without_timeout);
+        assert_eq!(context_without_timeout.timeout_ms(), None);
+    }
+
+    #[test]
+    fn test_evaluation_context_comprehensive_config() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 30,
+            max_string_length: 16384,
+            stop_at_first_match: false,
+            enable_mime_types: true,
+            timeout_ms: Some(10000),
+            ..Default::default()
+        };
+        let context = EvaluationContext::new(config);
+
+        assert_eq!(context.config().max_recursion_depth, 30);
+        assert_eq!(context.config().max_string_length, 16384);
+        assert!(!context.should_stop_at_first_match());
+        assert!(context.enable_mime_types());
+        assert_eq!(context.timeout_ms(), Some(10000));
+        assert_eq!(context.max_string_length(), 16384);
+    }
+
+    #[test]
+    fn test_evaluation_context_performance_config() {
+        let config = EvaluationConfig {
+            max_recursion_depth: 5,
+            max_string_length: 512,
+            stop_at_first_match: true,
+            enable_mime_types: false,
+            timeout_ms: Some(1000),
+            ..Default::default()
+        };
+        let context = EvaluationContext::new(config);
+
+        assert_eq!(context.config().max_recursion_depth, 5);
+        assert_eq!(context.max_string_length(), 512);
+        assert!(context.should_stop_at_first_match());
+        assert!(!context.enable_mime_types());
+        assert_eq!(context.timeout_ms(), Some(1000));
+    }
+
+    #[test]
+    fn test_evaluation_context_elapsed_ms() {
+        let rules =
+            crate::parser::parse_magic_string("0 byte 1 one\n>1 use sub\n0 name sub\n").unwrap();
+        let rules = Arc::new(rules);
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_database(Arc::clone(&rules));
+        assert!(context.elapsed_ms().abs() < f64::EPSILON);
+
+        evaluate_rules(&rules, &[1, 2], &mut context).unwrap();
+        let first = context.elapsed_ms();
+        assert!(first >= 0.0);
+        // The clock keeps running from the first call rather than restarting
+        evaluate_rules(&rules, &[1, 2], &mut context).unwrap();
+        assert!(context.elapsed_ms() >= first);
+
+        context.reset();
+        assert!(context.elapsed_ms().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_evaluation_context_state_management_sequence() {
+        let config = EvaluationConfig::default();
+        let mut context = EvaluationContext::new(config);
+
+        // Simulate a sequence of evaluation operations
+        assert_eq!(context.current_offset(), 0);
+        assert_eq!(context.recursion_depth(), 0);
+
+        // Start evaluation at offset 10
+        context.set_current_offset(10);
+        assert_eq!(context.current_offset(), 10);
+
+        // Enter nested rule evaluation
+        context.increment_recursion_depth().unwrap();
+        assert_eq!(context.recursion_depth(), 1);
+
+        // Move to different offset during nested evaluation
+        context.set_current_offset(25);
+        assert_eq!(context.current_offset(), 25);
+
+        // Enter deeper nesting
+        context.increment_recursion_depth().unwrap();
+        assert_eq!(context.recursion_depth(), 2);
+
+        // Exit nested evaluation
+        context.decrement_recursion_depth();
+        assert_eq!(context.recursion_depth(), 1);
+
+        // Continue evaluation at different offset
+        context.set_current_offset(50);
+        assert_eq!(context.current_offset(), 50);
+
+        // Exit all nesting
+        context.decrement_recursion_depth();
+        assert_eq!(context.recursion_depth(), 0);
+
+        // Final state check
+        assert_eq!(context.current_offset(), 50);
+        assert_eq!(context.recursion_depth(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+//! Text encoding detection
+//!
+//! Classifies a buffer the way GNU `file --mime-encoding` does: a byte-order
+//! mark names its encoding outright, and otherwise the buffer is text only if
+//! every byte is a text character in the encoding tried. A single control
+//! byte that text does not contain makes the whole buffer `binary`.
+
+use crate::evaluator::bom::ByteOrderMark;
+
+/// Reported for empty buffers and anything that is not text
+pub const BINARY: &str = "binary";
+
+/// Whether `byte` is an ASCII character found in text files
+///
+/// These are the printable characters plus BEL, BS, HT, LF, VT, FF, CR and ESC.
+const fn is_text_ascii(byte: u8) -> bool {
+    matches!(byte, 0x07..=0x0d | 0x1b | 0x20..=0x7e)
+}
+
+/// The `--mime-encoding` token for `buffer`
+///
+/// `buffer` may be a prefix of a file: a multi-byte UTF-8 character cut off
+/// at its end does not make the buffer binary.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::encoding::mime_encoding;
+///
+/// assert_eq!(mime_encoding(b"plain text\n"), "us-ascii");
+/// assert_eq!(mime_encoding("na\u{ef}ve\n".as_bytes()), "utf-8");
+/// assert_eq!(mime_encoding(b"caf\xe9\n"), "iso-8859-1");
+/// assert_eq!(mime_encoding(b"\x7fELF\x02\x01\x01\x00"), "binary");
+/// ```
+#[must_use]
+pub fn mime_encoding(buffer: &[u8]) -> &'static str {
+    if let Some(bom) = ByteOrderMark::detect(buffer) {
+        return match bom {
+            ByteOrderMark::Utf8 => "utf-8",
+            ByteOrderMark::Utf16Le => "utf-16le",
+            ByteOrderMark::Utf16Be => "utf-16be",
+            ByteOrderMark::Utf32Le => "utf-32le",
+            ByteOrderMark::Utf32Be => "utf-32be",
+        };
+    }
+    if buffer.is_empty() {
+        return BINARY;
+    }
+
+    if buffer.iter().all(|&byte| is_text_ascii(byte)) {
+        return "us-ascii";
+    }
+    let utf8 = match std::str::from_utf8(buffer) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if utf8
+        && buffer
+            .iter()
+            .all(|&byte| byte >= 0x80 || is_text_ascii(byte))
+    {
+        return "utf-8";
+    }
+    // C1 control characters (0x80-0x9f) are not used in Latin-1 text
+    if buffer
+        .iter()
+        .all(|&byte| byte >= 0xa0 || is_text_ascii(byte))
+    {
+        return "iso-8859-1";
+    }
+    BINARY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mime_encoding_classifies_text_and_binary() {
+        assert_eq!(
+            mime_encoding(b"#!/bin/sh\necho \"hi\"\t\x1b[0m\r\n"),
+            "us-ascii"
+        );
+        assert_eq!(
+            mime_encoding("\u{201c}quoted\u{201d}\n".as_bytes()),
+            "utf-8"
+        );
+        assert_eq!(mime_encoding(b"\xef\xbb\xbfwith BOM"), "utf-8");
+        assert_eq!(mime_encoding(b"\xff\xfeh\x00i\x00"), "utf-16le");
+        assert_eq!(mime_encoding(b""), "binary");
+        assert_eq!(mime_encoding(b"text\x00with a NUL"), "binary");
+        assert_eq!(mime_encoding(b"\x85 C1 control"), "binary");
+    }
+
+    #[test]
+    fn test_mime_encoding_accepts_utf8_cut_short() {
+        let text = "r\u{e9}sum\u{e9}".as_bytes();
+        assert_eq!(mime_encoding(&text[..text.len() - 1]), "utf-8");
+        // An invalid sequence in the middle is not excused
+        assert_eq!(mime_encoding(b"\xc3(ok)"), "iso-8859-1");
+    }
+}
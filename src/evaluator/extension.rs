@@ -0,0 +1,175 @@
+//! Custom readers for extension types
+//!
+//! Rules can use field types that are not part of magic(5) by naming them with
+//! an `x-` prefix (for example `0 x-mytype 42 custom field`). The parser turns
+//! such names into [`TypeKind::Extension`](crate::parser::ast::TypeKind::Extension),
+//! and the evaluator reads their values with the [`TypeReader`] registered under
+//! the same name.
+
+use crate::io::IoError;
+use crate::parser::ast::Value;
+use crate::{LibmagicError, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Prefix that marks a type name as an extension type
+pub const EXTENSION_PREFIX: &str = "x-";
+
+/// Reads the value of a custom extension type from a buffer
+///
+/// Closures with the same signature as [`TypeReader::read`] implement this
+/// trait, so simple readers do not need a dedicated type.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::extension::TypeReader;
+/// use libmagic_rs::io::IoError;
+/// use libmagic_rs::parser::ast::Value;
+///
+/// /// Reads a 3-byte big-endian unsigned integer
+/// struct U24;
+///
+/// impl TypeReader for U24 {
+///     fn read(&self, buffer: &[u8], offset: usize) -> Result<Value, IoError> {
+///         let bytes = buffer.get(offset..offset + 3).ok_or(IoError::BufferOverrun {
+///             offset,
+///             length: 3,
+///             buffer_size: buffer.len(),
+///         })?;
+///         Ok(Value::Uint(bytes.iter().fold(0, |acc, &b| acc << 8 | u64::from(b))))
+///     }
+/// }
+///
+/// assert_eq!(U24.read(&[0, 1, 2, 3], 1).unwrap(), Value::Uint(0x01_0203));
+/// assert!(U24.read(&[0, 1], 0).is_err());
+/// ```
+pub trait TypeReader: Send + Sync {
+    /// Read the value at `offset` in `buffer`
+    ///
+    /// # Errors
+    ///
+    /// Returns `IoError::BufferOverrun` if the value extends past the end of
+    /// the buffer, which is a non-match when `soft_bounds` is enabled, or any
+    /// other `IoError` if the bytes cannot be interpreted.
+    fn read(&self, buffer: &[u8], offset: usize) -> std::result::Result<Value, IoError>;
+}
+
+impl<F> TypeReader for F
+where
+    F: Fn(&[u8], usize) -> std::result::Result<Value, IoError> + Send + Sync,
+{
+    fn read(&self, buffer: &[u8], offset: usize) -> std::result::Result<Value, IoError> {
+        self(buffer, offset)
+    }
+}
+
+/// Custom type readers keyed by extension type name
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::extension::TypeReaderRegistry;
+/// use libmagic_rs::parser::ast::Value;
+///
+/// let mut registry = TypeReaderRegistry::new();
+/// registry.register("x-zero", |_: &[u8], _: usize| Ok(Value::Uint(0)))?;
+/// assert!(registry.get("x-zero").is_some());
+///
+/// // Names without the `x-` prefix could never be referenced by a rule
+/// assert!(registry.register("zero", |_: &[u8], _: usize| Ok(Value::Uint(0))).is_err());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct TypeReaderRegistry {
+    readers: HashMap<String, Arc<dyn TypeReader>>,
+}
+
+impl TypeReaderRegistry {
+    /// Create an empty registry
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `reader` for the extension type `name`, replacing any previous reader
+    ///
+    /// # Errors
+    ///
+    /// Returns `LibmagicError::InvalidFormat` if `name` does not start with `x-`
+    /// or has nothing after it.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        reader: impl TypeReader + 'static,
+    ) -> Result<()> {
+        let name = name.into();
+        if name.len() <= EXTENSION_PREFIX.len() || !name.starts_with(EXTENSION_PREFIX) {
+            return Err(LibmagicError::InvalidFormat(format!(
+                "Extension type name '{name}' must start with '{EXTENSION_PREFIX}'"
+            )));
+        }
+
+        self.readers.insert(name, Arc::new(reader));
+        Ok(())
+    }
+
+    /// Get the reader registered for the extension type `name`
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn TypeReader> {
+        self.readers.get(name).map(AsRef::as_ref)
+    }
+
+    /// Returns `true` if no readers are registered
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.readers.is_empty()
+    }
+}
+
+impl fmt::Debug for TypeReaderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&str> = self.readers.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        f.debug_struct("TypeReaderRegistry")
+            .field("types", &names)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_register_and_replace() {
+        let mut registry = TypeReaderRegistry::new();
+        assert!(registry.is_empty());
+
+        registry
+            .register("x-one", |_: &[u8], _: usize| Ok(Value::Uint(1)))
+            .unwrap();
+        registry
+            .register("x-one", |_: &[u8], _: usize| Ok(Value::Uint(2)))
+            .unwrap();
+
+        let reader = registry.get("x-one").unwrap();
+        assert_eq!(reader.read(&[], 0).unwrap(), Value::Uint(2));
+        assert!(registry.get("x-two").is_none());
+        assert_eq!(
+            format!("{registry:?}"),
+            "TypeReaderRegistry { types: [\"x-one\"] }"
+        );
+    }
+
+    #[test]
+    fn test_registry_rejects_names_without_prefix() {
+        let mut registry = TypeReaderRegistry::new();
+        for name in ["mytype", "x-", "X-mytype"] {
+            let result = registry.register(name, |_: &[u8], _: usize| Ok(Value::Uint(0)));
+            assert!(matches!(result, Err(LibmagicError::InvalidFormat(_))));
+        }
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! The fast path for byte and literal pattern equality
+
+use super::rule::{RuleMatch, rule_operator_matches};
+use super::strings::{pattern_bytes, value_like};
+use super::types;
+use crate::parser::ast::{MagicRule, Operator, TypeKind, Value};
+
+/// Decide a `=` or `!=` test of a `byte`, `string` or `search` rule at `offset` without copying data
+///
+/// This is the fast path for the most common rules. The data compared is
+/// borrowed from `buffer`, and a string value is only built for a rule that
+/// matches, so its [`RuleMatch`] is the same as the one read through
+/// [`read_fixed`], [`read_string_window`] or [`read_search_match`] would give:
+/// neither type depends on byte order, and a matching pattern equals the bytes
+/// it was compared with. Returns `None` for other rules and when the data
+/// cannot be read, leaving errors and soft bounds to the general path.
+#[allow(clippy::option_option)]
+pub(super) fn match_in_place(
+    rule: &MagicRule,
+    typ: &TypeKind,
+    buffer: &[u8],
+    offset: usize,
+    max_search_range: usize,
+) -> Option<Option<RuleMatch>> {
+    let negated = match rule.op {
+        Operator::Equal => rule.negate,
+        Operator::NotEqual => !rule.negate,
+        _ => return None,
+    };
+    if rule.mask.is_some() {
+        return None;
+    }
+
+    if let TypeKind::Byte { signed } = typ {
+        let byte = *buffer.get(offset)?;
+        let value = if *signed {
+            Value::Int(i64::from(i8::from_ne_bytes([byte])))
+        } else {
+            Value::Uint(u64::from(byte))
+        };
+        let matched = rule_operator_matches(rule, typ, &value) != rule.negate;
+        return Some(matched.then_some(RuleMatch {
+            offset,
+            length: 1,
+            value,
+        }));
+    }
+
+    let pattern = pattern_bytes(&rule.value)?;
+    let (found, data) = match typ {
+        TypeKind::String { flags, .. } if !flags.relaxes_comparison() && !flags.trim => (
+            offset,
+            buffer.get(offset..offset.checked_add(pattern.len())?)?,
+        ),
+        TypeKind::Search { range } => {
+            match types::search_bytes(buffer, offset, (*range).min(max_search_range), pattern)
+                .ok()?
+            {
+                Some(found) => (found, &buffer[found..found + pattern.len()]),
+                None => (offset, &[][..]),
+            }
+        }
+        _ => return None,
+    };
+
+    // Bytes equal to the pattern are also valid UTF-8 when the pattern is a string
+    let equal = data == pattern;
+    Some((equal != negated).then(|| RuleMatch {
+        offset: found,
+        length: data.len(),
+        value: if equal {
+            rule.value.clone()
+        } else {
+            value_like(&rule.value, data)
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::rule::read_fixed;
+    use crate::evaluator::strings::{read_search_match, read_string_window};
+    use crate::parser::ast::{OffsetSpec, StringFlags};
+
+    /// Where a rule matched and the value it read, for comparing match paths
+    fn match_summary(found: Option<&RuleMatch>) -> Option<(usize, usize, Value)> {
+        found.map(|found| (found.offset, found.length, found.value.clone()))
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_in_place_fast_path_agrees_with_reading_the_value(
+            data in proptest::collection::vec(
+                proptest::sample::select(vec![0x00, 0x7f, b'E', b'L', 0xff]),
+                0..8,
+            ),
+            pattern in proptest::collection::vec(
+                proptest::sample::select(vec![0x00, 0x7f, b'E', b'L', 0xff]),
+                1..4,
+            ),
+            offset in 0usize..4,
+            negated: bool,
+        ) {
+            let op = if negated { Operator::NotEqual } else { Operator::Equal };
+            let first = pattern[0];
+            let string = TypeKind::String { max_length: None, flags: StringFlags::default() };
+            let search = TypeKind::Search { range: 4 };
+            let candidates = [
+                (TypeKind::Byte { signed: false }, Value::Uint(u64::from(first))),
+                (TypeKind::Byte { signed: true }, Value::Int(i64::from(i8::from_ne_bytes([first])))),
+                // A signed byte compared with an unsigned value goes through the operator
+                (TypeKind::Byte { signed: true }, Value::Uint(u64::from(first))),
+                (string.clone(), Value::Bytes(pattern.clone())),
+                (string, Value::String(String::from_utf8_lossy(&pattern).into_owned())),
+                (search.clone(), Value::Bytes(pattern.clone())),
+                (search, Value::String(String::from_utf8_lossy(&pattern).into_owned())),
+            ];
+
+            for (typ, value) in candidates {
+                let rule = MagicRule::new(OffsetSpec::Absolute(0), typ.clone(), op.clone(), value, "m");
+                let read = match (&typ, pattern_bytes(&rule.value)) {
+                    (TypeKind::Search { range }, Some(expected)) => {
+                        read_search_match(&data, offset, *range, expected, &rule.value)
+                    }
+                    (TypeKind::String { .. }, Some(expected)) => {
+                        read_string_window(&data, offset, expected, &rule.value)
+                    }
+                    _ => read_fixed(&data, offset, &typ),
+                };
+                let in_place = match_in_place(&rule, &typ, &data, offset, usize::MAX);
+                match read {
+                    Ok(read) => {
+                        let expected = rule_operator_matches(&rule, &typ, &read.value).then_some(read);
+                        proptest::prop_assert_eq!(
+                            match_summary(in_place.unwrap().as_ref()),
+                            match_summary(expected.as_ref())
+                        );
+                    }
+                    // Unreadable data is left to the general path
+                    Err(_) => proptest::prop_assert!(in_place.is_none()),
+                }
+            }
+        }
+    }
+}
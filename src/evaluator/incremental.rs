@@ -0,0 +1,211 @@
+//! Re-evaluation of a buffer that has grown at its end
+//!
+//! Appending bytes cannot change the outcome of a rule that only reads bytes
+//! that were already there. [`IncrementalState`] remembers the matches of each
+//! top-level rule, and on the next evaluation reuses them for rules whose
+//! whole subtree reads a fixed number of bytes at absolute offsets inside the
+//! previously evaluated data. Every other rule is evaluated again.
+//!
+//! The check is deliberately conservative. Rules with offsets from the end,
+//! relative, indirect or register offsets, `search`, `filesize`, `indirect`
+//! and `use` types, strings compared with anything but `=` or `!=`, and rules
+//! that store a value in the register are always re-evaluated, as is every
+//! rule that a previous evaluation did not reach.
+
+use crate::LibmagicError;
+use crate::evaluator::{EvaluationContext, MatchResult, evaluate_rules, pattern_bytes};
+use crate::parser::ast::{MagicRule, OffsetSpec, Operator, TypeKind};
+
+/// Number of leading bytes that decide the outcome of `rule` and its children
+///
+/// Returns `None` when the rule may read bytes at positions that depend on the
+/// data or its length, so appended bytes could change its outcome.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::incremental::fixed_extent;
+/// use libmagic_rs::parser::parse_magic_string;
+///
+/// let rules = parse_magic_string("0 string PK ZIP\n>4 leshort 20 v2.0\n-22 string PK end\n")?;
+/// assert_eq!(fixed_extent(&rules[0]), Some(6));
+/// assert_eq!(fixed_extent(&rules[1]), None);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[must_use]
+pub fn fixed_extent(rule: &MagicRule) -> Option<usize> {
+    let OffsetSpec::Absolute(offset) = rule.offset else {
+        return None;
+    };
+    let offset = usize::try_from(offset).ok()?;
+    if rule.store {
+        return None;
+    }
+
+    let size = match &rule.typ {
+        TypeKind::Byte { .. }
+        | TypeKind::Short { .. }
+        | TypeKind::Long { .. }
+        | TypeKind::Quad { .. } => rule.typ.fixed_size()?,
+        TypeKind::String { flags, .. }
+            if matches!(rule.op, Operator::Equal | Operator::NotEqual)
+                && !flags.affects_whitespace() =>
+        {
+            pattern_bytes(&rule.value)?.len()
+        }
+        _ => return None,
+    };
+
+    rule.children
+        .iter()
+        .try_fold(offset.checked_add(size)?, |extent, child| {
+            Some(extent.max(fixed_extent(child)?))
+        })
+}
+
+/// Matches of each top-level rule from the last evaluation of a growing buffer
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::EvaluationContext;
+/// use libmagic_rs::evaluator::incremental::IncrementalState;
+/// use libmagic_rs::parser::parse_magic_string;
+/// use libmagic_rs::EvaluationConfig;
+///
+/// let rules = parse_magic_string("0 string LOG log file\n")?;
+/// let mut state = IncrementalState::new();
+///
+/// let mut context = EvaluationContext::new(EvaluationConfig::default());
+/// assert_eq!(state.evaluate(&rules, b"LOG 1\n", &mut context)?.len(), 1);
+///
+/// // The rule only reads the first three bytes, so its match is reused
+/// let mut context = EvaluationContext::new(EvaluationConfig::default());
+/// assert_eq!(state.evaluate(&rules, b"LOG 1\nLOG 2\n", &mut context)?.len(), 1);
+/// assert_eq!(context.rules_evaluated(), 0);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalState {
+    /// Length of the data the outcomes were found in
+    evaluated_len: usize,
+    /// Matches of each top-level rule, `None` if it was not evaluated
+    outcomes: Vec<Option<Vec<MatchResult>>>,
+}
+
+impl IncrementalState {
+    /// Create a state that has not evaluated anything yet
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate `rules` against `buffer`, an extension of the last evaluated buffer
+    ///
+    /// The result is the same as [`evaluate_rules`] gives for the whole
+    /// buffer, provided the bytes evaluated last time are unchanged at its
+    /// start and `rules` are the same rules. Reused rules do not count toward
+    /// the context's rule evaluations. A buffer shorter than the last one
+    /// discards everything remembered.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`evaluate_rules`]. Outcomes remembered
+    /// before the error are kept.
+    pub fn evaluate(
+        &mut self,
+        rules: &[MagicRule],
+        buffer: &[u8],
+        context: &mut EvaluationContext,
+    ) -> Result<Vec<MatchResult>, LibmagicError> {
+        if buffer.len() < self.evaluated_len || self.outcomes.len() != rules.len() {
+            self.evaluated_len = 0;
+            self.outcomes = vec![None; rules.len()];
+        }
+
+        let mut matches = Vec::new();
+        let mut reached = rules.len();
+        for (index, (rule, outcome)) in rules.iter().zip(&mut self.outcomes).enumerate() {
+            let reusable = outcome.is_some()
+                && fixed_extent(rule).is_some_and(|extent| extent <= self.evaluated_len);
+            if !reusable {
+                *outcome = Some(evaluate_rules(std::slice::from_ref(rule), buffer, context)?);
+            }
+            let rule_matches = outcome.as_deref().unwrap_or_default();
+            matches.extend_from_slice(rule_matches);
+            if !rule_matches.is_empty() && context.should_stop_at_first_match() {
+                reached = index + 1;
+                break;
+            }
+            if let Some(max) = context
+                .config()
+                .max_matches
+                .filter(|&max| matches.len() >= max)
+            {
+                matches.truncate(max);
+                reached = index + 1;
+                break;
+            }
+        }
+        // Rules after the break were not evaluated against this buffer, so what
+        // an earlier evaluation found for them can no longer be trusted
+        for outcome in self.outcomes.iter_mut().skip(reached) {
+            *outcome = None;
+        }
+
+        self.evaluated_len = buffer.len();
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EvaluationConfig;
+    use crate::parser::parse_magic_string;
+
+    #[test]
+    fn test_incremental_state_rechecks_rules_reading_past_old_data() {
+        let rules = parse_magic_string("0 string ABCDEF long\n0 string AB short\n").unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            soft_bounds: true,
+            ..EvaluationConfig::default()
+        };
+        let mut state = IncrementalState::new();
+
+        let mut context = EvaluationContext::new(config.clone());
+        let first = state.evaluate(&rules, b"ABC", &mut context).unwrap();
+        assert_eq!(first.len(), 1);
+
+        // "long" needed bytes that were missing, so only it is evaluated again
+        let mut context = EvaluationContext::new(config);
+        let second = state.evaluate(&rules, b"ABCDEF", &mut context).unwrap();
+        let messages: Vec<&str> = second.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["long", "short"]);
+        assert_eq!(context.rules_evaluated(), 1);
+    }
+
+    #[test]
+    fn test_incremental_state_forgets_rules_skipped_after_a_match() {
+        let rules = parse_magic_string("-3 string END end\n4 string XY xy\n").unwrap();
+        let config = EvaluationConfig {
+            soft_bounds: true,
+            ..EvaluationConfig::default()
+        };
+        let mut state = IncrementalState::new();
+        let mut evaluate = |buffer: &[u8]| {
+            let mut context = EvaluationContext::new(config.clone());
+            let matches = state.evaluate(&rules, buffer, &mut context).unwrap();
+            let mut context = EvaluationContext::new(config.clone());
+            let full = evaluate_rules(&rules, buffer, &mut context).unwrap();
+            assert_eq!(matches, full, "{buffer:?}");
+            matches.into_iter().map(|m| m.message).collect::<Vec<_>>()
+        };
+
+        assert!(evaluate(b"abc").is_empty());
+        // "xy" is not evaluated once "end" matches
+        assert_eq!(evaluate(b"abcdXYEND"), ["end"]);
+        assert_eq!(evaluate(b"abcdXYENDzz"), ["xy"]);
+    }
+}
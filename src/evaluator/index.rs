@@ -0,0 +1,258 @@
+//! First-byte index over top-level rules
+//!
+//! Most top-level magic rules test a fixed signature at offset 0, so the first
+//! byte of the buffer already rules out nearly all of them. [`RuleIndex`]
+//! groups such rules by the first byte they require, letting evaluation skip
+//! them without reading anything, the same optimization libmagic applies.
+
+use super::{operators, pattern_bytes};
+use crate::parser::ast::{Endianness, MagicRule, OffsetSpec, Operator, TypeKind};
+use std::sync::Arc;
+
+/// The first byte a rule requires, and how many bytes its read needs
+#[derive(Debug, Clone, Copy)]
+struct FirstByte {
+    byte: u8,
+    min_len: usize,
+}
+
+/// Top-level rules grouped by the byte they require at offset 0
+///
+/// A rule is indexed when it compares an integer or a plain `string` for
+/// equality at absolute offset 0 and is not negated. All other rules, including indirect, relative
+/// and non-zero offsets, are kept in an unindexed list that is always scanned.
+/// Candidates are returned in their original order, so evaluation stops at the
+/// same first match as a full scan.
+///
+/// An indexed rule is only skipped when the buffer is long enough for its read,
+/// so a rule that would fail with an out-of-bounds error still does.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::RuleIndex;
+/// use libmagic_rs::parser::parse_magic_string;
+/// use std::sync::Arc;
+///
+/// let rules = parse_magic_string("0 string PK ZIP\n0 string %PDF PDF\n4 byte 1 other\n")?;
+/// let index = RuleIndex::new(Arc::new(rules));
+/// assert_eq!(index.indexed_rule_count(), 2);
+///
+/// let messages: Vec<&str> = index
+///     .candidates(b"PK\x03\x04")
+///     .map(|rule| rule.message.as_str())
+///     .collect();
+/// assert_eq!(messages, ["ZIP", "other"]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct RuleIndex {
+    rules: Arc<Vec<MagicRule>>,
+    keys: Vec<Option<FirstByte>>,
+    /// Positions of indexed rules, one list per first byte
+    by_first_byte: Vec<Vec<usize>>,
+    /// Positions of rules that must always be tested
+    unindexed: Vec<usize>,
+    /// Longest read needed by any indexed rule
+    max_min_len: usize,
+}
+
+impl RuleIndex {
+    /// Build the index for a set of top-level rules
+    #[must_use]
+    pub fn new(rules: Arc<Vec<MagicRule>>) -> Self {
+        let keys: Vec<Option<FirstByte>> = rules.iter().map(first_byte_key).collect();
+        let mut by_first_byte = vec![Vec::new(); 256];
+        let mut unindexed = Vec::new();
+        let mut max_min_len = 0;
+
+        for (position, key) in keys.iter().enumerate() {
+            match key {
+                Some(key) => {
+                    by_first_byte[usize::from(key.byte)].push(position);
+                    max_min_len = max_min_len.max(key.min_len);
+                }
+                None => unindexed.push(position),
+            }
+        }
+
+        Self {
+            rules,
+            keys,
+            by_first_byte,
+            unindexed,
+            max_min_len,
+        }
+    }
+
+    /// The rules this index was built for
+    #[must_use]
+    pub const fn rules(&self) -> &Arc<Vec<MagicRule>> {
+        &self.rules
+    }
+
+    /// Number of rules grouped by their first byte
+    #[must_use]
+    pub fn indexed_rule_count(&self) -> usize {
+        self.rules.len() - self.unindexed.len()
+    }
+
+    /// The rules that can match `buffer`, in their original order
+    #[must_use]
+    pub fn candidates<'a>(&'a self, buffer: &[u8]) -> Box<dyn Iterator<Item = &'a MagicRule> + 'a> {
+        match buffer.first() {
+            // Every indexed read fits, so only the rules for this byte can match
+            Some(&first) if buffer.len() >= self.max_min_len => Box::new(
+                merge_positions(&self.by_first_byte[usize::from(first)], &self.unindexed)
+                    .map(|position| &self.rules[position]),
+            ),
+            // A short buffer keeps rules whose read would go out of bounds
+            _ => {
+                let first = buffer.first().copied();
+                let len = buffer.len();
+                Box::new(
+                    self.rules
+                        .iter()
+                        .zip(&self.keys)
+                        .filter(move |(_, key)| {
+                            key.is_none_or(|key| len < key.min_len || first == Some(key.byte))
+                        })
+                        .map(|(rule, _)| rule),
+                )
+            }
+        }
+    }
+}
+
+/// Merge two ascending position lists into one ascending sequence
+fn merge_positions<'a>(left: &'a [usize], right: &'a [usize]) -> impl Iterator<Item = usize> + 'a {
+    let (mut l, mut r) = (0, 0);
+    std::iter::from_fn(move || {
+        let next = match (left.get(l), right.get(r)) {
+            (Some(&a), Some(&b)) if a < b => {
+                l += 1;
+                a
+            }
+            (_, Some(&b)) => {
+                r += 1;
+                b
+            }
+            (Some(&a), None) => {
+                l += 1;
+                a
+            }
+            (None, None) => return None,
+        };
+        Some(next)
+    })
+}
+
+/// The byte `rule` requires at offset 0, if it can be determined without reading
+fn first_byte_key(rule: &MagicRule) -> Option<FirstByte> {
+    if rule.offset != OffsetSpec::Absolute(0)
+        || rule.op != Operator::Equal
+        || rule.mask.is_some()
+        || rule.negate
+    {
+        return None;
+    }
+
+    match &rule.typ {
+        // Relaxed whitespace or case lets a leading byte match other bytes
+        TypeKind::String { flags, .. } if !flags.relaxes_comparison() => {
+            let pattern = pattern_bytes(&rule.value)?;
+            Some(FirstByte {
+                byte: *pattern.first()?,
+                min_len: pattern.len(),
+            })
+        }
+        TypeKind::Byte { .. }
+        | TypeKind::Short { .. }
+        | TypeKind::Long { .. }
+        | TypeKind::Quad { .. } => {
+            let size = rule.typ.fixed_size()?;
+            let bits = operators::integer_bits(&rule.value)?;
+            let little_endian = match rule.typ {
+                TypeKind::Short { endian, .. }
+                | TypeKind::Long { endian, .. }
+                | TypeKind::Quad { endian, .. } => match endian {
+                    Endianness::Little => true,
+                    Endianness::Big => false,
+                    Endianness::Native => cfg!(target_endian = "little"),
+                },
+                _ => true,
+            };
+            let shift = if little_endian { 0 } else { 8 * (size - 1) };
+            Some(FirstByte {
+                byte: (bits >> shift).to_le_bytes()[0],
+                min_len: size,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_magic_string;
+
+    fn messages<'a>(index: &'a RuleIndex, buffer: &[u8]) -> Vec<&'a str> {
+        index
+            .candidates(buffer)
+            .map(|rule| rule.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_index_keys_integers_by_byte_order() {
+        let rules = parse_magic_string(
+            "0 belong 0x7f454c46 be\n\
+             0 lelong 0x464c457f le\n\
+             0 beshort -2 negative\n\
+             0 byte >1 ordered\n\
+             0 string/w \\ x relaxed\n",
+        )
+        .unwrap();
+        let index = RuleIndex::new(Arc::new(rules));
+
+        assert_eq!(index.indexed_rule_count(), 3);
+        assert_eq!(
+            messages(&index, b"\x7fELF"),
+            ["be", "le", "ordered", "relaxed"]
+        );
+        assert_eq!(
+            messages(&index, b"\xff\xfe\0\0"),
+            ["negative", "ordered", "relaxed"]
+        );
+    }
+
+    #[test]
+    fn test_index_keeps_rules_that_would_read_out_of_bounds() {
+        let rules = parse_magic_string("0 string ABCD long\n0 byte 0x42 byte\n").unwrap();
+        let index = RuleIndex::new(Arc::new(rules));
+
+        // "ABCD" needs 4 bytes, so a shorter buffer must still test it
+        assert_eq!(messages(&index, b"B"), ["long", "byte"]);
+        assert_eq!(messages(&index, b""), ["long", "byte"]);
+        assert_eq!(messages(&index, b"BBBB"), ["byte"]);
+    }
+
+    #[test]
+    fn test_index_keeps_negated_rules_unindexed() {
+        let mut rules = parse_magic_string("0 string PK zip\n0 string PK not zip\n").unwrap();
+        rules[1].negate = true;
+        let index = RuleIndex::new(Arc::new(rules));
+
+        // A negated rule matches exactly the buffers its first byte rules out
+        assert_eq!(index.indexed_rule_count(), 1);
+        assert_eq!(messages(&index, b"MZ"), ["not zip"]);
+    }
+
+    #[test]
+    fn test_merge_positions_keeps_order() {
+        let merged: Vec<usize> = merge_positions(&[1, 4, 5], &[0, 2, 6]).collect();
+        assert_eq!(merged, [0, 1, 2, 4, 5, 6]);
+        assert_eq!(merge_positions(&[], &[3]).collect::<Vec<_>>(), [3]);
+    }
+}
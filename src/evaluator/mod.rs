@@ -3,189 +3,37 @@
 //! This module contains the core evaluation logic for executing magic rules
 //! against file buffers to identify file types.
 
-use crate::parser::ast::MagicRule;
+use crate::parser::ast::{Endianness, MagicRule, TypeKind, Value};
 use crate::{EvaluationConfig, LibmagicError};
-
+pub use context::{ContextSnapshot, EvaluationContext};
+pub use index::RuleIndex;
+use rule::{
+    RuleMatch, is_static_offset, is_unreachable, match_rule, register_value, resolve_rule_offset,
+};
+pub(crate) use strings::pattern_bytes;
+
+pub mod bom;
+mod context;
+pub mod encoding;
+pub mod extension;
+mod fast_path;
+pub mod incremental;
+pub mod index;
 pub mod offset;
 pub mod operators;
+mod rule;
+mod strings;
+pub mod trailer;
 pub mod types;
+pub mod zip;
 
-/// Context for maintaining evaluation state during rule processing
-///
-/// The `EvaluationContext` tracks the current state of rule evaluation,
-/// including the current offset position, recursion depth for nested rules,
-/// and configuration settings that control evaluation behavior.
-///
-/// # Examples
-///
-/// ```rust
-/// use libmagic_rs::evaluator::EvaluationContext;
-/// use libmagic_rs::EvaluationConfig;
-///
-/// let config = EvaluationConfig::default();
-/// let context = EvaluationContext::new(config);
+/// Maximum number of `indirect` regions that can be nested inside each other
 ///
-/// assert_eq!(context.current_offset(), 0);
-/// assert_eq!(context.recursion_depth(), 0);
-/// ```
-#[derive(Debug, Clone)]
-pub struct EvaluationContext {
-    /// Current offset position in the file buffer
-    current_offset: usize,
-    /// Current recursion depth for nested rule evaluation
-    recursion_depth: u32,
-    /// Configuration settings for evaluation behavior
-    config: EvaluationConfig,
-}
-
-impl EvaluationContext {
-    /// Create a new evaluation context with the given configuration
-    ///
-    /// # Arguments
-    ///
-    /// * `config` - Configuration settings for evaluation behavior
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use libmagic_rs::evaluator::EvaluationContext;
-    /// use libmagic_rs::EvaluationConfig;
-    ///
-    /// let config = EvaluationConfig::default();
-    /// let context = EvaluationContext::new(config);
-    /// ```
-    #[must_use]
-    pub const fn new(config: EvaluationConfig) -> Self {
-        Self {
-            current_offset: 0,
-            recursion_depth: 0,
-            config,
-        }
-    }
-
-    /// Get the current offset position
-    ///
-    /// # Returns
-    ///
-    /// The current offset position in the file buffer
-    #[must_use]
-    pub const fn current_offset(&self) -> usize {
-        self.current_offset
-    }
-
-    /// Set the current offset position
-    ///
-    /// # Arguments
-    ///
-    /// * `offset` - The new offset position
-    pub fn set_current_offset(&mut self, offset: usize) {
-        self.current_offset = offset;
-    }
-
-    /// Get the current recursion depth
-    ///
-    /// # Returns
-    ///
-    /// The current recursion depth for nested rule evaluation
-    #[must_use]
-    pub const fn recursion_depth(&self) -> u32 {
-        self.recursion_depth
-    }
-
-    /// Increment the recursion depth
-    ///
-    /// # Returns
-    ///
-    /// `Ok(())` if the recursion depth is within limits, or `Err(LibmagicError)`
-    /// if the maximum recursion depth would be exceeded
-    ///
-    /// # Errors
-    ///
-    /// Returns `LibmagicError::EvaluationError` if incrementing would exceed
-    /// the maximum recursion depth configured in the evaluation config.
-    pub fn increment_recursion_depth(&mut self) -> Result<(), LibmagicError> {
-        if self.recursion_depth >= self.config.max_recursion_depth {
-            return Err(LibmagicError::EvaluationError(
-                "Maximum recursion depth exceeded".to_string(),
-            ));
-        }
-        self.recursion_depth += 1;
-        Ok(())
-    }
-
-    /// Decrement the recursion depth
-    ///
-    /// # Panics
-    ///
-    /// Panics if the recursion depth is already 0, as this indicates
-    /// a programming error in the evaluation logic.
-    pub fn decrement_recursion_depth(&mut self) {
-        assert!(
-            self.recursion_depth != 0,
-            "Attempted to decrement recursion depth below 0"
-        );
-        self.recursion_depth -= 1;
-    }
-
-    /// Get a reference to the evaluation configuration
-    ///
-    /// # Returns
-    ///
-    /// A reference to the `EvaluationConfig` used by this context
-    #[must_use]
-    pub const fn config(&self) -> &EvaluationConfig {
-        &self.config
-    }
-
-    /// Check if evaluation should stop at the first match
-    ///
-    /// # Returns
-    ///
-    /// `true` if evaluation should stop at the first match, `false` otherwise
-    #[must_use]
-    pub const fn should_stop_at_first_match(&self) -> bool {
-        self.config.stop_at_first_match
-    }
-
-    /// Get the maximum string length allowed
-    ///
-    /// # Returns
-    ///
-    /// The maximum string length that should be read during evaluation
-    #[must_use]
-    pub const fn max_string_length(&self) -> usize {
-        self.config.max_string_length
-    }
-
-    /// Check if MIME type mapping is enabled
-    ///
-    /// # Returns
-    ///
-    /// `true` if MIME type mapping should be performed, `false` otherwise
-    #[must_use]
-    pub const fn enable_mime_types(&self) -> bool {
-        self.config.enable_mime_types
-    }
-
-    /// Get the evaluation timeout in milliseconds
-    ///
-    /// # Returns
-    ///
-    /// The timeout duration in milliseconds, or `None` if no timeout is set
-    #[must_use]
-    pub const fn timeout_ms(&self) -> Option<u64> {
-        self.config.timeout_ms
-    }
-
-    /// Reset the context to initial state while preserving configuration
-    ///
-    /// This resets the current offset and recursion depth to 0, but keeps
-    /// the same configuration settings.
-    pub fn reset(&mut self) {
-        self.current_offset = 0;
-        self.recursion_depth = 0;
-    }
-}
+/// A pointer that leads back to itself would otherwise re-run the database at
+/// the same offset until the recursion limit. This bound is separate from
+/// [`EvaluationConfig::max_recursion_depth`], so deep rule nesting does not
+/// raise it.
+pub const MAX_INDIRECT_DEPTH: u32 = 10;
 
 /// Result of evaluating a magic rule
 ///
@@ -195,12 +43,28 @@ pub fn reset(&mut self) {
 pub struct MatchResult {
     /// The message associated with the matching rule
     pub message: String,
-    /// The offset where the match occurred
+    /// The file offset where the match occurred
+    ///
+    /// For `search` rules this is where the pattern was found, not the start of
+    /// the searched range. Matches inside an `indirect` or `use` region are
+    /// still reported from the start of the file, not the start of the region.
     pub offset: usize,
+    /// Number of bytes covered by the match
+    pub length: usize,
     /// The rule level (depth in hierarchy)
     pub level: u32,
     /// The matched value
     pub value: crate::parser::ast::Value,
+    /// Line number of the matching rule in its magic source file, if known
+    pub source_line: Option<usize>,
+    /// Apple file type of the matching rule, from its `!:apple` directive
+    pub apple_type: Option<String>,
+    /// Apple creator code of the matching rule, from its `!:apple` directive
+    pub apple_creator: Option<String>,
+    /// MIME type of the matching rule, from its `!:mime` directive
+    pub mime_type: Option<String>,
+    /// Likely file extensions of the matching rule, from its `!:ext` directive
+    pub extensions: Vec<String>,
 }
 
 /// Evaluate a single magic rule against a file buffer
@@ -229,12 +93,20 @@ pub struct MatchResult {
 /// // Create a rule to check for ELF magic bytes at offset 0
 /// let rule = MagicRule {
 ///     offset: OffsetSpec::Absolute(0),
-///     typ: TypeKind::Byte,
+///     typ: TypeKind::Byte { signed: false },
 ///     op: Operator::Equal,
 ///     value: Value::Uint(0x7f),
 ///     message: "ELF magic".to_string(),
 ///     children: vec![],
 ///     level: 0,
+///     source_line: None,
+///     apple_type: None,
+///     apple_creator: None,
+///     mask: None,
+///     mime_type: None,
+///     extensions: Vec::new(),
+///     negate: false,
+///     store: false,
 /// };
 ///
 /// let elf_buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
@@ -251,17 +123,8 @@ pub struct MatchResult {
 /// * `LibmagicError::EvaluationError` - If offset resolution fails, buffer access is out of bounds,
 ///   or type interpretation fails
 pub fn evaluate_single_rule(rule: &MagicRule, buffer: &[u8]) -> Result<bool, LibmagicError> {
-    // Step 1: Resolve the offset specification to an absolute position
-    let absolute_offset = offset::resolve_offset(&rule.offset, buffer)?;
-
-    // Step 2: Read and interpret bytes at the resolved offset according to the rule's type
-    let read_value = types::read_typed_value(buffer, absolute_offset, &rule.typ)
-        .map_err(|e| LibmagicError::EvaluationError(e.to_string()))?;
-
-    // Step 3: Apply the operator to compare the read value with the expected value
-    let matches = operators::apply_operator(&rule.op, &read_value, &rule.value);
-
-    Ok(matches)
+    let context = EvaluationContext::new(EvaluationConfig::default());
+    Ok(match_rule(rule, buffer, 0, buffer.len(), &context)?.is_some())
 }
 
 /// Evaluate a list of magic rules against a file buffer with hierarchical processing
@@ -277,6 +140,16 @@ pub fn evaluate_single_rule(rule: &MagicRule, buffer: &[u8]) -> Result<bool, Lib
 /// - Child rules provide refinement and additional detail
 /// - Evaluation can stop at first match or continue for all matches
 /// - Recursion depth is limited to prevent infinite loops
+/// - `name` rules are skipped; they only run when invoked by a `use` rule
+/// - `indirect` and `use` rules evaluate against the region starting at
+///   their resolved offset, using the database set with
+///   [`EvaluationContext::set_database`]
+///
+/// `buffer` always holds the data from the start of the file. Rules are
+/// evaluated in the region starting at [`EvaluationContext::base_offset`], which
+/// is 0 unless evaluating inside an `indirect` region or `use` subroutine; absolute
+/// indirect offsets still read from the start of the file (see
+/// [`offset::resolve_indirect_offset`]).
 ///
 /// # Arguments
 ///
@@ -299,22 +172,38 @@ pub fn evaluate_single_rule(rule: &MagicRule, buffer: &[u8]) -> Result<bool, Lib
 /// // Create a hierarchical rule set for ELF files
 /// let parent_rule = MagicRule {
 ///     offset: OffsetSpec::Absolute(0),
-///     typ: TypeKind::Byte,
+///     typ: TypeKind::Byte { signed: false },
 ///     op: Operator::Equal,
 ///     value: Value::Uint(0x7f),
 ///     message: "ELF".to_string(),
 ///     children: vec![
 ///         MagicRule {
 ///             offset: OffsetSpec::Absolute(4),
-///             typ: TypeKind::Byte,
+///             typ: TypeKind::Byte { signed: false },
 ///             op: Operator::Equal,
 ///             value: Value::Uint(2),
 ///             message: "64-bit".to_string(),
 ///             children: vec![],
 ///             level: 1,
+///             source_line: None,
+///             apple_type: None,
+///             apple_creator: None,
+///             mask: None,
+///             mime_type: None,
+///             extensions: Vec::new(),
+///             negate: false,
+///             store: false,
 ///         }
 ///     ],
 ///     level: 0,
+///     source_line: None,
+///     apple_type: None,
+///     apple_creator: None,
+///     mask: None,
+///     mime_type: None,
+///     extensions: Vec::new(),
+///     negate: false,
+///     store: false,
 /// };
 ///
 /// let rules = vec![parent_rule];
@@ -335,14 +224,80 @@ pub fn evaluate_rules(
     rules: &[MagicRule],
     buffer: &[u8],
     context: &mut EvaluationContext,
+) -> Result<Vec<MatchResult>, LibmagicError> {
+    evaluate_rule_sequence(rules, buffer, context)
+}
+
+/// Evaluate top-level rules, skipping those the index rules out by the first byte
+///
+/// This produces the same matches and errors as [`evaluate_rules`] over
+/// `index.rules()`, while testing fewer rules when many of them start with a
+/// fixed byte at offset 0. See [`RuleIndex`] for which rules are indexed.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::{evaluate_rules_indexed, EvaluationContext, RuleIndex};
+/// use libmagic_rs::parser::parse_magic_string;
+/// use libmagic_rs::EvaluationConfig;
+/// use std::sync::Arc;
+///
+/// let rules = parse_magic_string("0 string PK ZIP\n0 string %PDF PDF\n0 byte x data\n")?;
+/// let index = RuleIndex::new(Arc::new(rules));
+/// let mut context = EvaluationContext::new(EvaluationConfig::default());
+///
+/// let matches = evaluate_rules_indexed(&index, b"%PDF-1.7", &mut context)?;
+/// assert_eq!(matches[0].message, "PDF");
+/// // The ZIP rule was never tested
+/// assert_eq!(context.rules_evaluated(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns the same errors as [`evaluate_rules`].
+pub fn evaluate_rules_indexed(
+    index: &RuleIndex,
+    buffer: &[u8],
+    context: &mut EvaluationContext,
+) -> Result<Vec<MatchResult>, LibmagicError> {
+    // The index keys native integers by the host's byte order
+    if overrides_host_byte_order(context.config()) {
+        return evaluate_rules(index.rules(), buffer, context);
+    }
+
+    // Offset 0 of a rule is the context's base offset, not the buffer start
+    let base = context.base_offset().min(buffer.len());
+    evaluate_rule_sequence(index.candidates(&buffer[base..]), buffer, context)
+}
+
+/// Returns `true` if `config` reads native integers in the byte order the host does not use
+const fn overrides_host_byte_order(config: &EvaluationConfig) -> bool {
+    match config.native_endianness_override {
+        Some(Endianness::Little) => cfg!(target_endian = "big"),
+        Some(Endianness::Big) => cfg!(target_endian = "little"),
+        Some(Endianness::Native) | None => false,
+    }
+}
+
+/// Evaluate `rules` in order against `buffer`, as described for [`evaluate_rules`]
+fn evaluate_rule_sequence<'a>(
+    rules: impl IntoIterator<Item = &'a MagicRule>,
+    buffer: &[u8],
+    context: &mut EvaluationContext,
 ) -> Result<Vec<MatchResult>, LibmagicError> {
     let mut matches = Vec::new();
-    let start_time = std::time::Instant::now();
+    let start_time = context.start_timer();
+    let base = context.base_offset().min(buffer.len());
+    let data_len = context.data_len(&buffer[base..]);
+    // Whether a rule of this sequence has matched since the start or the last `clear`
+    let mut sibling_matched = false;
 
     for rule in rules {
         // Check timeout if configured
         if let Some(timeout_ms) = context.timeout_ms() {
             if start_time.elapsed().as_millis() > u128::from(timeout_ms) {
+                log::debug!("evaluation timed out after {timeout_ms} ms");
                 return Err(LibmagicError::Timeout { timeout_ms });
             }
         }
@@ -350,58 +305,279 @@ pub fn evaluate_rules(
         // TODO: Add error handling for malformed rules
         // - Validate rule structure before evaluation
         // - Handle cases where rule.message is empty or contains invalid characters
-        // - Add context about which rule failed during evaluation
-
-        // Evaluate the current rule
-        // TODO: Add more specific error context for rule evaluation failures
-        // - Include rule message and offset in error messages
-        // - Add rule validation before evaluation
-        // - Handle edge cases like empty rule messages or invalid offsets
-        let rule_matches = evaluate_single_rule(rule, buffer).map_err(|e| match e {
+
+        // Subroutine definitions only run when invoked through `use`
+        if matches!(rule.typ, TypeKind::Name { .. }) {
+            continue;
+        }
+
+        match rule.typ {
+            TypeKind::Default if sibling_matched => continue,
+            TypeKind::Clear => sibling_matched = false,
+            _ => {}
+        }
+
+        // With soft bounds, a rule at an offset past the end can never match
+        if context.config().soft_bounds && is_unreachable(rule, buffer.len() - base) {
+            continue;
+        }
+
+        // Once the rule budget is spent, report what has matched so far
+        if !context.record_rule_evaluation() {
+            break;
+        }
+
+        // Evaluate the current rule; errors carry its message and offset
+        log::trace!(
+            "evaluating rule at line {:?}, level {}",
+            rule.source_line,
+            rule.level
+        );
+        let add_context = |e| match e {
             LibmagicError::EvaluationError(msg) => LibmagicError::EvaluationError(format!(
                 "Rule '{}' at offset {:?}: {}",
                 rule.message, rule.offset, msg
             )),
             other => other,
-        })?;
-
-        if rule_matches {
-            // Create match result for this rule
-            let absolute_offset = offset::resolve_offset(&rule.offset, buffer)?;
-            let read_value = types::read_typed_value(buffer, absolute_offset, &rule.typ)
-                .map_err(|e| LibmagicError::EvaluationError(e.to_string()))?;
-
-            let match_result = MatchResult {
-                message: rule.message.clone(),
-                offset: absolute_offset,
-                level: rule.level,
-                value: read_value,
-            };
-            matches.push(match_result);
+        };
+
+        // File offset just past the match, or `None` if the rule did not match
+        let match_end = if matches!(rule.typ, TypeKind::Indirect | TypeKind::Use { .. }) {
+            match evaluate_embedded(rule, buffer, context).map_err(add_context)? {
+                Some((rule_match, embedded_matches)) => {
+                    let end = base + rule_match.offset + rule_match.length;
+                    if !rule.message.is_empty() {
+                        matches.push(match_result(rule, base, rule_match));
+                    }
+                    matches.extend(embedded_matches);
+                    Some(end)
+                }
+                None => None,
+            }
+        } else {
+            match match_rule(rule, buffer, base, data_len, context).map_err(add_context)? {
+                Some(rule_match) => {
+                    let end = base + rule_match.offset + rule_match.length;
+                    if rule.store {
+                        if let Some(value) = register_value(&rule_match.value) {
+                            context.set_register(value);
+                        }
+                    }
+                    // Create match result for this rule; a `clear` or `default`
+                    // without a message only serves its children
+                    if rule.typ.requires_value() || !rule.message.is_empty() {
+                        matches.push(match_result(rule, base, rule_match));
+                    }
+                    Some(end)
+                }
+                None => None,
+            }
+        };
 
+        if let Some(match_end) = match_end {
+            // A `clear` does not count as a match for a later `default`
+            sibling_matched |= !matches!(rule.typ, TypeKind::Clear);
+            log::debug!(
+                "rule at line {:?} matched: {:?}",
+                rule.source_line,
+                rule.message
+            );
             // If this rule has children, evaluate them recursively
             if !rule.children.is_empty() {
+                let snapshot = context.snapshot();
+                // Children's relative offsets are measured from the end of this match
+                context.set_current_offset(match_end);
+
                 // Check recursion depth limit
                 context.increment_recursion_depth()?;
 
                 // Recursively evaluate child rules
-                let child_matches = evaluate_rules(&rule.children, buffer, context)?;
-                matches.extend(child_matches);
-
-                // Restore recursion depth
-                context.decrement_recursion_depth();
+                let child_matches = evaluate_rules(&rule.children, buffer, context);
+                context.restore(snapshot);
+                matches.extend(child_matches?);
             }
 
             // Stop at first match if configured to do so
             if context.should_stop_at_first_match() {
                 break;
             }
+            if let Some(max) = context
+                .config()
+                .max_matches
+                .filter(|&max| matches.len() >= max)
+            {
+                matches.truncate(max);
+                break;
+            }
         }
     }
 
     Ok(matches)
 }
 
+/// The result reported for `rule` matching in the region starting at `base`
+fn match_result(rule: &MagicRule, base: usize, rule_match: RuleMatch) -> MatchResult {
+    MatchResult {
+        message: rule.message.clone(),
+        offset: base + rule_match.offset,
+        length: rule_match.length,
+        level: rule.level,
+        value: rule_match.value,
+        source_line: rule.source_line,
+        apple_type: rule.apple_type.clone(),
+        apple_creator: rule.apple_creator.clone(),
+        mime_type: rule.mime_type.clone(),
+        extensions: rule.extensions.clone(),
+    }
+}
+
+/// Evaluate an `indirect` or `use` rule against the region at its offset
+///
+/// The context's base offset is advanced to the resolved offset for the
+/// duration of the call, so the rules' offsets are relative to it, while
+/// absolute indirect offsets still see the whole of `buffer`. `indirect` re-runs
+/// the whole database; `use` runs the children of the named subroutine.
+///
+/// Returns `None` when nothing inside the sub-buffer matched.
+fn evaluate_embedded(
+    rule: &MagicRule,
+    buffer: &[u8],
+    context: &mut EvaluationContext,
+) -> Result<Option<(RuleMatch, Vec<MatchResult>)>, LibmagicError> {
+    let saved_base = context.base_offset().min(buffer.len());
+    let data_len = context.data_len(&buffer[saved_base..]);
+    let sub_offset = match resolve_rule_offset(&rule.offset, buffer, saved_base, data_len, context)
+    {
+        Ok(resolved) => resolved,
+        Err(_) if context.config().soft_bounds && is_static_offset(&rule.offset) => {
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+
+    let database = context.database().cloned().ok_or_else(|| {
+        LibmagicError::EvaluationError(
+            "No rule database available for indirect or use rules".to_string(),
+        )
+    })?;
+
+    let (targets, byte_swap): (&[MagicRule], bool) = match &rule.typ {
+        TypeKind::Use { name, byte_swap } => (subroutine_body(&database, name)?, *byte_swap),
+        _ => (&database, false),
+    };
+
+    // Only `indirect` follows pointers; `use` recursion is bounded by the recursion depth
+    let indirect = matches!(rule.typ, TypeKind::Indirect);
+    if indirect {
+        context.increment_indirect_depth()?;
+    }
+    let embedded_matches =
+        evaluate_in_region(targets, buffer, saved_base + sub_offset, byte_swap, context);
+    if indirect {
+        context.decrement_indirect_depth();
+    }
+    let embedded_matches = embedded_matches?;
+    if embedded_matches.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some((
+        RuleMatch {
+            offset: sub_offset,
+            length: 0,
+            value: Value::Bytes(Vec::new()),
+        },
+        embedded_matches,
+    )))
+}
+
+/// Evaluate `rules` with the base offset moved to `base`, one level deeper
+///
+/// The context's position state is restored afterwards, even if evaluation
+/// fails. With `byte_swap`, the byte order of integer reads is
+/// flipped relative to the enclosing rules.
+fn evaluate_in_region(
+    rules: &[MagicRule],
+    buffer: &[u8],
+    base: usize,
+    byte_swap: bool,
+    context: &mut EvaluationContext,
+) -> Result<Vec<MatchResult>, LibmagicError> {
+    let snapshot = context.snapshot();
+
+    context.increment_recursion_depth()?;
+    context.set_base_offset(base);
+    context.set_swap_endian(context.swap_endian() ^ byte_swap);
+    let result = evaluate_rules(rules, buffer, context);
+    context.restore(snapshot);
+
+    result
+}
+
+/// Evaluate a single named subroutine with its offsets relative to `base_offset`
+///
+/// `base_offset` is relative to the context's current base offset, which is 0
+/// for a fresh context. This runs the children of the `name` rule in the context's database exactly
+/// as a `use` rule resolving to `base_offset` would, without evaluating any
+/// top-level rules. It is mainly useful for testing subroutines in isolation.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::{evaluate_subroutine, EvaluationContext};
+/// use libmagic_rs::parser::parse_magic_string;
+/// use libmagic_rs::EvaluationConfig;
+/// use std::sync::Arc;
+///
+/// let rules = parse_magic_string("0 name tag\n>0 string ID tagged\n")?;
+/// let mut context = EvaluationContext::new(EvaluationConfig::default());
+/// context.set_database(Arc::new(rules));
+///
+/// let matches = evaluate_subroutine("tag", b"xxID", 2, &mut context)?;
+/// assert_eq!(matches[0].message, "tagged");
+/// assert_eq!(matches[0].offset, 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// * `LibmagicError::EvaluationError` - If the context has no database, the
+///   subroutine is not defined, `base_offset` is past the end of `buffer`, or
+///   evaluating the subroutine fails
+/// * `LibmagicError::Timeout` - If evaluation exceeds the configured timeout
+pub fn evaluate_subroutine(
+    name: &str,
+    buffer: &[u8],
+    base_offset: usize,
+    context: &mut EvaluationContext,
+) -> Result<Vec<MatchResult>, LibmagicError> {
+    let database = context.database().cloned().ok_or_else(|| {
+        LibmagicError::EvaluationError("No rule database available for use rules".to_string())
+    })?;
+    let targets = subroutine_body(&database, name)?;
+    let base = context.base_offset().saturating_add(base_offset);
+    if base > buffer.len() {
+        return Err(LibmagicError::EvaluationError(format!(
+            "Subroutine base offset {base} is past the end of the {}-byte buffer",
+            buffer.len()
+        )));
+    }
+
+    evaluate_in_region(targets, buffer, base, false, context)
+}
+
+/// Get the rules of the top-level `name` rule defining the subroutine `name`
+fn subroutine_body<'a>(
+    rules: &'a [MagicRule],
+    name: &str,
+) -> Result<&'a [MagicRule], LibmagicError> {
+    rules
+        .iter()
+        .find(|rule| matches!(&rule.typ, TypeKind::Name { name: defined } if defined == name))
+        .map(|rule| rule.children.as_slice())
+        .ok_or_else(|| LibmagicError::EvaluationError(format!("Unknown subroutine '{name}'")))
+}
+
 /// Evaluate magic rules with a fresh context
 ///
 /// This is a convenience function that creates a new evaluation context
@@ -427,12 +603,20 @@ pub fn evaluate_rules(
 ///
 /// let rule = MagicRule {
 ///     offset: OffsetSpec::Absolute(0),
-///     typ: TypeKind::Byte,
+///     typ: TypeKind::Byte { signed: false },
 ///     op: Operator::Equal,
 ///     value: Value::Uint(0x7f),
 ///     message: "ELF magic".to_string(),
 ///     children: vec![],
 ///     level: 0,
+///     source_line: None,
+///     apple_type: None,
+///     apple_creator: None,
+///     mask: None,
+///     mime_type: None,
+///     extensions: Vec::new(),
+///     negate: false,
+///     store: false,
 /// };
 ///
 /// let rules = vec![rule];
@@ -460,942 +644,61 @@ pub fn evaluate_rules_with_config(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::ast::StringFlags;
     use crate::parser::ast::{Endianness, OffsetSpec, Operator, TypeKind, Value};
+    use std::sync::Arc;
 
     #[test]
-    fn test_evaluate_single_rule_byte_equal_match() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x7f),
-            message: "ELF magic".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_byte_equal_no_match() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x7f),
-            message: "ELF magic".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x50, 0x4b, 0x03, 0x04]; // ZIP magic bytes
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(!result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_byte_not_equal_match() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::NotEqual,
-            value: Value::Uint(0x00),
-            message: "Non-zero byte".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46];
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // 0x7f != 0x00
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_byte_not_equal_no_match() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::NotEqual,
-            value: Value::Uint(0x7f),
-            message: "Not ELF magic".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46];
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(!result); // 0x7f == 0x7f, so NotEqual is false
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_byte_bitwise_and_match() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::BitwiseAnd,
-            value: Value::Uint(0x80), // Check if high bit is set
-            message: "High bit set".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0xff, 0x45, 0x4c, 0x46]; // 0xff has high bit set
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // 0xff & 0x80 = 0x80 (non-zero)
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_byte_bitwise_and_no_match() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::BitwiseAnd,
-            value: Value::Uint(0x80), // Check if high bit is set
-            message: "High bit set".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // 0x7f has high bit clear
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(!result); // 0x7f & 0x80 = 0x00 (zero)
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_short_little_endian() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Short {
-                endian: Endianness::Little,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0x1234),
-            message: "Little-endian short".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x34, 0x12, 0x56, 0x78]; // 0x1234 in little-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_short_big_endian() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Short {
-                endian: Endianness::Big,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0x1234),
-            message: "Big-endian short".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x12, 0x34, 0x56, 0x78]; // 0x1234 in big-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_short_signed_positive() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Short {
-                endian: Endianness::Little,
-                signed: true,
-            },
-            op: Operator::Equal,
-            value: Value::Int(32767), // 0x7fff
-            message: "Positive signed short".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0xff, 0x7f, 0x00, 0x00]; // 0x7fff in little-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_short_signed_negative() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Short {
-                endian: Endianness::Little,
-                signed: true,
-            },
-            op: Operator::Equal,
-            value: Value::Int(-1), // 0xffff as signed
-            message: "Negative signed short".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0xff, 0xff, 0x00, 0x00]; // 0xffff in little-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_long_little_endian() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Little,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0x1234_5678),
-            message: "Little-endian long".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x78, 0x56, 0x34, 0x12, 0x00]; // 0x12345678 in little-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_long_big_endian() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Big,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0x1234_5678),
-            message: "Big-endian long".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x12, 0x34, 0x56, 0x78, 0x00]; // 0x12345678 in big-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_long_signed_positive() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Little,
-                signed: true,
-            },
-            op: Operator::Equal,
-            value: Value::Int(2_147_483_647), // 0x7fffffff
-            message: "Positive signed long".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0xff, 0xff, 0xff, 0x7f, 0x00]; // 0x7fffffff in little-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_long_signed_negative() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Little,
-                signed: true,
-            },
-            op: Operator::Equal,
-            value: Value::Int(-1), // 0xffffffff as signed
-            message: "Negative signed long".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0xff, 0xff, 0xff, 0xff, 0x00]; // 0xffffffff in little-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_different_offsets() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(2), // Read from offset 2
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x4c),
-            message: "ELF class byte".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // buffer[2] == 0x4c
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_negative_offset() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(-1), // Last byte
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x46),
-            message: "Last byte".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // Last byte is 0x46
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_from_end_offset() {
-        let rule = MagicRule {
-            offset: OffsetSpec::FromEnd(-2), // Second to last byte
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x4c),
-            message: "Second to last byte".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // buffer[2] == 0x4c (second to last)
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_offset_out_of_bounds() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(10), // Beyond buffer
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x00),
-            message: "Out of bounds".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // Only 4 bytes
-        let result = evaluate_single_rule(&rule, buffer);
-        assert!(result.is_err());
-
-        match result.unwrap_err() {
-            LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Buffer overrun"));
-            }
-            _ => panic!("Expected EvaluationError"),
-        }
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_short_insufficient_bytes() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(3), // Only 1 byte left
-            typ: TypeKind::Short {
-                endian: Endianness::Little,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0x1234),
-            message: "Insufficient bytes".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // 4 bytes total
-        let result = evaluate_single_rule(&rule, buffer);
-        assert!(result.is_err());
-
-        match result.unwrap_err() {
-            LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Buffer overrun"));
-            }
-            _ => panic!("Expected EvaluationError"),
-        }
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_long_insufficient_bytes() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(2), // Only 2 bytes left
-            typ: TypeKind::Long {
-                endian: Endianness::Little,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0x1234_5678),
-            message: "Insufficient bytes".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // 4 bytes total
-        let result = evaluate_single_rule(&rule, buffer);
-        assert!(result.is_err());
-
-        match result.unwrap_err() {
-            LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Buffer overrun"));
-            }
-            _ => panic!("Expected EvaluationError"),
-        }
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_empty_buffer() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x00),
-            message: "Empty buffer".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[]; // Empty buffer
-        let result = evaluate_single_rule(&rule, buffer);
-        assert!(result.is_err());
-
-        match result.unwrap_err() {
-            LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Buffer overrun"));
-            }
-            _ => panic!("Expected EvaluationError"),
-        }
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_string_type_unsupported() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::String { max_length: None },
-            op: Operator::Equal,
-            value: Value::String("test".to_string()),
-            message: "String type".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = b"test data";
-        let result = evaluate_single_rule(&rule, buffer);
-        assert!(result.is_err());
-
-        match result.unwrap_err() {
-            LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Unsupported type"));
-                assert!(msg.contains("String"));
-            }
-            _ => panic!("Expected EvaluationError for unsupported type"),
-        }
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_cross_type_comparison() {
-        // Test that cross-type comparisons work correctly (should not match)
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Int(42), // Int value vs Uint from byte read
-            message: "Cross-type comparison".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[42]; // Byte value 42
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(!result); // Should not match due to type mismatch (Uint vs Int)
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_bitwise_and_with_shorts() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Short {
-                endian: Endianness::Little,
-                signed: false,
-            },
-            op: Operator::BitwiseAnd,
-            value: Value::Uint(0xff00), // Check high byte
-            message: "High byte check".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x34, 0x12]; // 0x1234 in little-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // 0x1234 & 0xff00 = 0x1200 (non-zero)
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_bitwise_and_with_longs() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Big,
-                signed: false,
-            },
-            op: Operator::BitwiseAnd,
-            value: Value::Uint(0xffff_0000), // Check high word
-            message: "High word check".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x12, 0x34, 0x56, 0x78]; // 0x12345678 in big-endian
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // 0x12345678 & 0xffff0000 = 0x12340000 (non-zero)
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_comprehensive_elf_check() {
-        // Test a comprehensive ELF magic check
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Little,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0x464c_457f), // ELF magic as 32-bit little-endian
-            message: "ELF executable".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let elf_buffer = &[0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01]; // ELF64 header start
-        let result = evaluate_single_rule(&rule, elf_buffer).unwrap();
-        assert!(result);
-
-        let non_elf_buffer = &[0x50, 0x4b, 0x03, 0x04, 0x14, 0x00]; // ZIP header
-        let result = evaluate_single_rule(&rule, non_elf_buffer).unwrap();
-        assert!(!result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_native_endianness() {
-        let rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Short {
-                endian: Endianness::Native,
-                signed: false,
-            },
-            op: Operator::NotEqual,
-            value: Value::Uint(0),
-            message: "Non-zero native short".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let buffer = &[0x01, 0x02]; // Non-zero bytes
-        let result = evaluate_single_rule(&rule, buffer).unwrap();
-        assert!(result); // Should be non-zero regardless of endianness
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_all_operators() {
-        let buffer = &[0x42, 0x00, 0xff, 0x80];
-
-        // Test Equal operator
-        let equal_rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0x42),
-            message: "Equal test".to_string(),
-            children: vec![],
-            level: 0,
-        };
-        assert!(evaluate_single_rule(&equal_rule, buffer).unwrap());
-
-        // Test NotEqual operator
-        let not_equal_rule = MagicRule {
-            offset: OffsetSpec::Absolute(1),
-            typ: TypeKind::Byte,
-            op: Operator::NotEqual,
-            value: Value::Uint(0x42),
-            message: "NotEqual test".to_string(),
-            children: vec![],
-            level: 0,
-        };
-        assert!(evaluate_single_rule(&not_equal_rule, buffer).unwrap()); // 0x00 != 0x42
-
-        // Test BitwiseAnd operator
-        let bitwise_and_rule = MagicRule {
-            offset: OffsetSpec::Absolute(3),
-            typ: TypeKind::Byte,
-            op: Operator::BitwiseAnd,
-            value: Value::Uint(0x80),
-            message: "BitwiseAnd test".to_string(),
-            children: vec![],
-            level: 0,
-        };
-        assert!(evaluate_single_rule(&bitwise_and_rule, buffer).unwrap()); // 0x80 & 0x80 = 0x80
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_edge_case_values() {
-        // Test with maximum values
-        let max_uint_rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Little,
-                signed: false,
-            },
-            op: Operator::Equal,
-            value: Value::Uint(0xffff_ffff),
-            message: "Max uint32".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let max_buffer = &[0xff, 0xff, 0xff, 0xff];
-        let result = evaluate_single_rule(&max_uint_rule, max_buffer).unwrap();
-        assert!(result);
-
-        // Test with minimum signed value
-        let min_int_rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Long {
-                endian: Endianness::Little,
-                signed: true,
-            },
-            op: Operator::Equal,
-            value: Value::Int(-2_147_483_648), // i32::MIN
-            message: "Min int32".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let min_buffer = &[0x00, 0x00, 0x00, 0x80]; // 0x80000000 in little-endian
-        let result = evaluate_single_rule(&min_int_rule, min_buffer).unwrap();
-        assert!(result);
-    }
-
-    #[test]
-    fn test_evaluate_single_rule_various_buffer_sizes() {
-        // Test with single byte buffer
-        let single_byte_rule = MagicRule {
-            offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint(0xaa),
-            message: "Single byte".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let single_buffer = &[0xaa];
-        let result = evaluate_single_rule(&single_byte_rule, single_buffer).unwrap();
-        assert!(result);
-
-        // Test with large buffer
-        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-        let large_buffer: Vec<u8> = (0..1024).map(|i| (i % 256) as u8).collect();
-        let large_rule = MagicRule {
-            offset: OffsetSpec::Absolute(1000),
-            typ: TypeKind::Byte,
-            op: Operator::Equal,
-            value: Value::Uint((1000 % 256) as u64),
-            message: "Large buffer".to_string(),
-            children: vec![],
-            level: 0,
-        };
-
-        let result = evaluate_single_rule(&large_rule, &large_buffer).unwrap();
-        assert!(result);
-    }
-
-    // Tests for EvaluationContext
-    #[test]
-    fn test_evaluation_context_new() {
-        let config = EvaluationConfig::default();
-        let context = EvaluationContext::new(config.clone());
-
-        assert_eq!(context.current_offset(), 0);
-        assert_eq!(context.recursion_depth(), 0);
-        assert_eq!(
-            context.config().max_recursion_depth,
-            config.max_recursion_depth
-        );
-        assert_eq!(context.config().max_string_length, config.max_string_length);
-        assert_eq!(
-            context.config().stop_at_first_match,
-            config.stop_at_first_match
-        );
-    }
-
-    #[test]
-    fn test_evaluation_context_offset_management() {
-        let config = EvaluationConfig::default();
-        let mut context = EvaluationContext::new(config);
-
-        // Test initial offset
-        assert_eq!(context.current_offset(), 0);
-
-        // Test setting offset
-        context.set_current_offset(42);
-        assert_eq!(context.current_offset(), 42);
-
-        // Test setting different offset
-        context.set_current_offset(1024);
-        assert_eq!(context.current_offset(), 1024);
-
-        // Test setting offset to 0
-        context.set_current_offset(0);
-        assert_eq!(context.current_offset(), 0);
-    }
-
-    #[test]
-    fn test_evaluation_context_recursion_depth_management() {
-        let config = EvaluationConfig::default();
-        let mut context = EvaluationContext::new(config);
-
-        // Test initial recursion depth
-        assert_eq!(context.recursion_depth(), 0);
-
-        // Test incrementing recursion depth
-        context.increment_recursion_depth().unwrap();
-        assert_eq!(context.recursion_depth(), 1);
-
-        context.increment_recursion_depth().unwrap();
-        assert_eq!(context.recursion_depth(), 2);
-
-        // Test decrementing recursion depth
-        context.decrement_recursion_depth();
-        assert_eq!(context.recursion_depth(), 1);
-
-        context.decrement_recursion_depth();
-        assert_eq!(context.recursion_depth(), 0);
-    }
-
-    #[test]
-    fn test_evaluation_context_recursion_depth_limit() {
-        let config = EvaluationConfig {
-            max_recursion_depth: 2,
-            ..Default::default()
-        };
-        let mut context = EvaluationContext::new(config);
-
-        // Should be able to increment up to the limit
-        assert!(context.increment_recursion_depth().is_ok());
-        assert_eq!(context.recursion_depth(), 1);
-
-        assert!(context.increment_recursion_depth().is_ok());
-        assert_eq!(context.recursion_depth(), 2);
-
-        // Should fail when exceeding the limit
-        let result = context.increment_recursion_depth();
-        assert!(result.is_err());
-        assert_eq!(context.recursion_depth(), 2); // Should not have changed
-
-        match result.unwrap_err() {
-            LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Maximum recursion depth exceeded"));
-            }
-            _ => panic!("Expected EvaluationError"),
-        }
-    }
-
-    #[test]
-    #[should_panic(expected = "Attempted to decrement recursion depth below 0")]
-    fn test_evaluation_context_recursion_depth_underflow() {
-        let config = EvaluationConfig::default();
-        let mut context = EvaluationContext::new(config);
-
-        // Should panic when trying to decrement below 0
-        context.decrement_recursion_depth();
-    }
-
-    #[test]
-    fn test_evaluation_context_config_access() {
-        let config = EvaluationConfig {
-            max_recursion_depth: 10,
-            max_string_length: 4096,
-            stop_at_first_match: false,
-            enable_mime_types: true,
-            timeout_ms: Some(2000),
-        };
-
-        let context = EvaluationContext::new(config);
-
-        // Test config access
-        assert_eq!(context.config().max_recursion_depth, 10);
-        assert_eq!(context.config().max_string_length, 4096);
-        assert!(!context.config().stop_at_first_match);
-
-        // Test convenience methods
-        assert!(!context.should_stop_at_first_match());
-        assert_eq!(context.max_string_length(), 4096);
-    }
-
-    #[test]
-    fn test_evaluation_context_reset() {
-        let config = EvaluationConfig::default();
-        let mut context = EvaluationContext::new(config.clone());
-
-        // Modify the context state
-        context.set_current_offset(100);
-        context.increment_recursion_depth().unwrap();
-        context.increment_recursion_depth().unwrap();
-
-        assert_eq!(context.current_offset(), 100);
-        assert_eq!(context.recursion_depth(), 2);
-
-        // Reset should restore initial state but keep config
-        context.reset();
-
-        assert_eq!(context.current_offset(), 0);
-        assert_eq!(context.recursion_depth(), 0);
-        assert_eq!(
-            context.config().max_recursion_depth,
-            config.max_recursion_depth
-        );
-    }
-
-    #[test]
-    fn test_evaluation_context_clone() {
-        let config = EvaluationConfig {
-            max_recursion_depth: 5,
-            max_string_length: 2048,
-            ..Default::default()
-        };
-
-        let mut context = EvaluationContext::new(config);
-        context.set_current_offset(50);
-        context.increment_recursion_depth().unwrap();
-
-        // Clone the context
-        let cloned_context = context.clone();
-
-        // Both should have the same state
-        assert_eq!(context.current_offset(), cloned_context.current_offset());
-        assert_eq!(context.recursion_depth(), cloned_context.recursion_depth());
-        assert_eq!(
-            context.config().max_recursion_depth,
-            cloned_context.config().max_recursion_depth
-        );
-        assert_eq!(
-            context.config().max_string_length,
-            cloned_context.config().max_string_length
-        );
-
-        // Modifying one should not affect the other
-        context.set_current_offset(75);
-        assert_eq!(context.current_offset(), 75);
-        assert_eq!(cloned_context.current_offset(), 50);
-    }
-
-    #[test]
-    fn test_evaluation_context_with_custom_config() {
-        let config = EvaluationConfig {
-            max_recursion_depth: 15,
-            max_string_length: 16384,
-            stop_at_first_match: false,
-            enable_mime_types: true,
-            timeout_ms: Some(5000),
-        };
-
-        let context = EvaluationContext::new(config);
-
-        assert_eq!(context.config().max_recursion_depth, 15);
-        assert_eq!(context.max_string_length(), 16384);
-        assert!(!context.should_stop_at_first_match());
-
-        // Test that we can increment up to the custom limit
-        let mut mutable_context = context;
-        for i in 1..=15 {
-            assert!(mutable_context.increment_recursion_depth().is_ok());
-            assert_eq!(mutable_context.recursion_depth(), i);
-        }
-
-        // Should fail on the 16th increment
-        let result = mutable_context.increment_recursion_depth();
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn test_evaluation_context_mime_types_access() {
-        let config_with_mime = EvaluationConfig {
-            enable_mime_types: true,
-            ..Default::default()
-        };
-        let context_with_mime = EvaluationContext::new(config_with_mime);
-        assert!(context_with_mime.enable_mime_types());
-
-        let config_without_mime = EvaluationConfig {
-            enable_mime_types: false,
-            ..Default::default()
-        };
-        let context_without_mime = EvaluationContext::new(config_without_mime);
-        assert!(!context_without_mime.enable_mime_types());
-    }
-
-    #[test]
-    fn test_evaluation_context_timeout_access() {
-        let config_with_timeout = EvaluationConfig {
-            timeout_ms: Some(5000),
-            ..Default::default()
-        };
-        let context_with_timeout = EvaluationContext::new(config_with_timeout);
-        assert_eq!(context_with_timeout.timeout_ms(), Some(5000));
-
-        let config_without_timeout = EvaluationConfig {
-            timeout_ms: None,
-            ..Default::default()
-        };
-        let context_without_timeout = EvaluationContext::new(config_without_timeout);
-        assert_eq!(context_without_timeout.timeout_ms(), None);
-    }
-
-    #[test]
-    fn test_evaluation_context_comprehensive_config() {
-        let config = EvaluationConfig {
-            max_recursion_depth: 30,
-            max_string_length: 16384,
-            stop_at_first_match: false,
-            enable_mime_types: true,
-            timeout_ms: Some(10000),
+    fn test_evaluate_rules_negated_rule_keeps_its_children() {
+        let mut rules =
+            crate::parser::parse_magic_string("0 string PK not ZIP\n>2 byte 0x21 shouting\n")
+                .unwrap();
+        rules[0].negate = true;
+        let messages = |buffer: &[u8]| -> Vec<String> {
+            evaluate_rules_with_config(&rules, buffer, EvaluationConfig::default())
+                .unwrap()
+                .into_iter()
+                .map(|m| m.message)
+                .collect()
         };
-        let context = EvaluationContext::new(config);
-
-        assert_eq!(context.config().max_recursion_depth, 30);
-        assert_eq!(context.config().max_string_length, 16384);
-        assert!(!context.should_stop_at_first_match());
-        assert!(context.enable_mime_types());
-        assert_eq!(context.timeout_ms(), Some(10000));
-        assert_eq!(context.max_string_length(), 16384);
+
+        assert_eq!(messages(b"MZ!"), ["not ZIP", "shouting"]);
+        assert_eq!(messages(b"MZ?"), ["not ZIP"]);
+        assert!(messages(b"PK!").is_empty());
     }
 
     #[test]
-    fn test_evaluation_context_performance_config() {
-        let config = EvaluationConfig {
-            max_recursion_depth: 5,
-            max_string_length: 512,
-            stop_at_first_match: true,
-            enable_mime_types: false,
-            timeout_ms: Some(1000),
+    fn test_evaluate_rules_seeks_by_stored_length() {
+        // A little-endian length, that many bytes of body, then a trailer
+        let mut rules = crate::parser::parse_magic_string(
+            "0 uleshort x body of %d bytes\n>0 string END with trailer\n",
+        )
+        .unwrap();
+        rules[0].store = true;
+        rules[0].children[0].offset = OffsetSpec::Register(2);
+        let messages = |buffer: &[u8]| -> Vec<String> {
+            evaluate_rules_with_config(&rules, buffer, EvaluationConfig::default())
+                .unwrap()
+                .into_iter()
+                .map(|m| m.message)
+                .collect()
         };
-        let context = EvaluationContext::new(config);
 
-        assert_eq!(context.config().max_recursion_depth, 5);
-        assert_eq!(context.max_string_length(), 512);
-        assert!(context.should_stop_at_first_match());
-        assert!(!context.enable_mime_types());
-        assert_eq!(context.timeout_ms(), Some(1000));
+        assert_eq!(
+            messages(b"\x03\x00abcEND"),
+            ["body of %d bytes", "with trailer"]
+        );
+        assert_eq!(
+            messages(b"\x04\x00abcdEND"),
+            ["body of %d bytes", "with trailer"]
+        );
+        assert_eq!(messages(b"\x02\x00abcEND"), ["body of %d bytes"]);
+
+        // Nothing is stored without the flag
+        rules[0].store = false;
+        let result =
+            evaluate_rules_with_config(&rules, b"\x03\x00abcEND", EvaluationConfig::default());
+        assert!(result.is_err());
     }
 
     #[test]
@@ -1403,8 +706,14 @@ fn test_match_result_creation() {
         let match_result = MatchResult {
             message: "ELF executable".to_string(),
             offset: 0,
+            length: 1,
             level: 0,
             value: Value::Uint(0x7f),
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mime_type: None,
+            extensions: Vec::new(),
         };
 
         assert_eq!(match_result.message, "ELF executable");
@@ -1418,8 +727,14 @@ fn test_match_result_clone() {
         let original = MatchResult {
             message: "Test message".to_string(),
             offset: 42,
+            length: 4,
             level: 1,
             value: Value::String("test".to_string()),
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mime_type: None,
+            extensions: Vec::new(),
         };
 
         let cloned = original.clone();
@@ -1431,8 +746,14 @@ fn test_match_result_debug() {
         let match_result = MatchResult {
             message: "Debug test".to_string(),
             offset: 10,
+            length: 2,
             level: 2,
             value: Value::Bytes(vec![0x01, 0x02]),
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mime_type: None,
+            extensions: Vec::new(),
         };
 
         let debug_str = format!("{match_result:?}");
@@ -1457,12 +778,20 @@ fn test_evaluate_rules_empty_list() {
     fn test_evaluate_rules_single_matching_rule() {
         let rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF magic".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![rule];
@@ -1482,12 +811,20 @@ fn test_evaluate_rules_single_matching_rule() {
     fn test_evaluate_rules_single_non_matching_rule() {
         let rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x50), // ZIP magic, not ELF
             message: "ZIP magic".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![rule];
@@ -1503,22 +840,38 @@ fn test_evaluate_rules_single_non_matching_rule() {
     fn test_evaluate_rules_multiple_rules_stop_at_first() {
         let rule1 = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "First match".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rule2 = MagicRule {
             offset: OffsetSpec::Absolute(1),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x45),
             message: "Second match".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rule_list = vec![rule1, rule2];
@@ -1538,22 +891,38 @@ fn test_evaluate_rules_multiple_rules_stop_at_first() {
     fn test_evaluate_rules_multiple_rules_find_all() {
         let rule1 = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "First match".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rule2 = MagicRule {
             offset: OffsetSpec::Absolute(1),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x45),
             message: "Second match".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rule_set = vec![rule1, rule2];
@@ -1574,22 +943,38 @@ fn test_evaluate_rules_multiple_rules_find_all() {
     fn test_evaluate_rules_hierarchical_parent_child() {
         let child_rule = MagicRule {
             offset: OffsetSpec::Absolute(4),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x02), // ELF class 64-bit
             message: "64-bit".to_string(),
             children: vec![],
             level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let parent_rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF".to_string(),
             children: vec![child_rule],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![parent_rule];
@@ -1609,22 +994,38 @@ fn test_evaluate_rules_hierarchical_parent_child() {
     fn test_evaluate_rules_hierarchical_parent_no_match() {
         let child_rule = MagicRule {
             offset: OffsetSpec::Absolute(4),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x02),
             message: "64-bit".to_string(),
             children: vec![],
             level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let parent_rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x50), // ZIP magic, not ELF
             message: "ZIP".to_string(),
             children: vec![child_rule],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![parent_rule];
@@ -1640,22 +1041,38 @@ fn test_evaluate_rules_hierarchical_parent_no_match() {
     fn test_evaluate_rules_hierarchical_parent_match_child_no_match() {
         let child_rule = MagicRule {
             offset: OffsetSpec::Absolute(4),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x01), // ELF class 32-bit, but buffer has 64-bit
             message: "32-bit".to_string(),
             children: vec![],
             level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let parent_rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF".to_string(),
             children: vec![child_rule],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![parent_rule];
@@ -1673,32 +1090,56 @@ fn test_evaluate_rules_hierarchical_parent_match_child_no_match() {
     fn test_evaluate_rules_deep_hierarchy() {
         let grandchild_rule = MagicRule {
             offset: OffsetSpec::Absolute(5),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x01), // Little endian
             message: "little-endian".to_string(),
             children: vec![],
             level: 2,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let child_rule = MagicRule {
             offset: OffsetSpec::Absolute(4),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x02), // 64-bit
             message: "64-bit".to_string(),
             children: vec![grandchild_rule],
             level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let parent_rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF".to_string(),
             children: vec![child_rule],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![parent_rule];
@@ -1720,32 +1161,56 @@ fn test_evaluate_rules_deep_hierarchy() {
     fn test_evaluate_rules_multiple_children() {
         let child1 = MagicRule {
             offset: OffsetSpec::Absolute(4),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x02),
             message: "64-bit".to_string(),
             children: vec![],
             level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let child2 = MagicRule {
             offset: OffsetSpec::Absolute(5),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x01),
             message: "little-endian".to_string(),
             children: vec![],
             level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let parent_rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF".to_string(),
             children: vec![child1, child2],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![parent_rule];
@@ -1768,24 +1233,40 @@ fn test_evaluate_rules_recursion_depth_limit() {
         // Create a deeply nested rule structure that exceeds the limit
         let mut current_rule = MagicRule {
             offset: OffsetSpec::Absolute(10),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x00),
             message: "Deep level".to_string(),
             children: vec![],
             level: 10,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         // Build a chain of nested rules
         for i in (0u32..10u32).rev() {
             current_rule = MagicRule {
                 offset: OffsetSpec::Absolute(i64::from(i)),
-                typ: TypeKind::Byte,
+                typ: TypeKind::Byte { signed: false },
                 op: Operator::Equal,
                 value: Value::Uint(u64::from(i)),
                 message: format!("Level {i}"),
                 children: vec![current_rule],
                 level: i,
+                source_line: None,
+                apple_type: None,
+                apple_creator: None,
+                mask: None,
+                mime_type: None,
+                extensions: Vec::new(),
+                negate: false,
+                store: false,
             };
         }
 
@@ -1808,16 +1289,742 @@ fn test_evaluate_rules_recursion_depth_limit() {
         }
     }
 
+    /// Logger that keeps the records of each thread, so parallel tests do not mix
+    struct CapturingLogger;
+
+    thread_local! {
+        static RECORDS: std::cell::RefCell<Vec<(log::Level, String)>> =
+            const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let entry = (record.level(), record.args().to_string());
+            RECORDS.with(|records| records.borrow_mut().push(entry));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_evaluate_rules_logs_match_without_file_contents() {
+        static LOGGER: CapturingLogger = CapturingLogger;
+        // Another test may have installed it already
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let rules = crate::parser::parse_magic_string("0 string SECRET data file\n").unwrap();
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        let matches = evaluate_rules(&rules, b"SECRET-payload", &mut context).unwrap();
+        assert_eq!(matches.len(), 1);
+
+        let records = RECORDS.with(std::cell::RefCell::take);
+        assert!(
+            records
+                .iter()
+                .any(|(level, text)| *level == log::Level::Debug && text.contains("matched")),
+            "{records:?}"
+        );
+        assert!(
+            records.iter().all(|(_, text)| !text.contains("payload")),
+            "{records:?}"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_rules_soft_bounds() {
+        let rules = vec![
+            MagicRule {
+                offset: OffsetSpec::Absolute(100),
+                typ: TypeKind::Byte { signed: false },
+                op: Operator::Equal,
+                value: Value::Uint(0x00),
+                message: "Beyond buffer".to_string(),
+                children: vec![],
+                level: 0,
+                source_line: None,
+                apple_type: None,
+                apple_creator: None,
+                mask: None,
+                mime_type: None,
+                extensions: Vec::new(),
+                negate: false,
+                store: false,
+            },
+            MagicRule {
+                offset: OffsetSpec::Absolute(2),
+                typ: TypeKind::Long {
+                    endian: Endianness::Little,
+                    signed: false,
+                },
+                op: Operator::Equal,
+                value: Value::Uint(0),
+                message: "Truncated long".to_string(),
+                children: vec![],
+                level: 0,
+                source_line: None,
+                apple_type: None,
+                apple_creator: None,
+                mask: None,
+                mime_type: None,
+                extensions: Vec::new(),
+                negate: false,
+                store: false,
+            },
+            MagicRule {
+                offset: OffsetSpec::Absolute(0),
+                typ: TypeKind::Byte { signed: false },
+                op: Operator::Equal,
+                value: Value::Uint(0x7f),
+                message: "ELF magic".to_string(),
+                children: vec![],
+                level: 0,
+                source_line: None,
+                apple_type: None,
+                apple_creator: None,
+                mask: None,
+                mime_type: None,
+                extensions: Vec::new(),
+                negate: false,
+                store: false,
+            },
+        ];
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46];
+
+        // Strict mode reports the out-of-bounds read as an error
+        assert!(evaluate_rules_with_config(&rules, buffer, EvaluationConfig::default()).is_err());
+
+        // Soft mode skips it and keeps evaluating
+        let config = EvaluationConfig {
+            soft_bounds: true,
+            ..Default::default()
+        };
+        let matches = evaluate_rules_with_config(&rules, buffer, config).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].message, "ELF magic");
+    }
+
+    #[test]
+    fn test_evaluate_rules_soft_bounds_keeps_unsupported_errors() {
+        // An extension type without a registered reader fails regardless of bounds
+        let rules = crate::parser::parse_magic_string("0 x-missing 0 unsupported\n").unwrap();
+        let config = EvaluationConfig {
+            soft_bounds: true,
+            ..Default::default()
+        };
+
+        assert!(evaluate_rules_with_config(&rules, &[0; 8], config).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_rules_relative_offset_follows_search_match() {
+        let rules = crate::parser::parse_magic_string(
+            "0 search/64 MARK marker\n\
+             >&0 byte 0x07 version %d\n\
+             >&1 string OK \\b, status OK\n\
+             >>&-7 string MARK \\b, marker again\n",
+        )
+        .unwrap();
+        assert_eq!(rules[0].children[0].offset, OffsetSpec::Relative(0));
+
+        // Keep going so sibling children are evaluated after the first one matches
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..Default::default()
+        };
+        let buffer = b"header bytes..MARK\x07OK";
+        let matches = evaluate_rules_with_config(&rules, buffer, config.clone()).unwrap();
+        let found: Vec<(&str, usize)> = matches
+            .iter()
+            .map(|m| (m.message.as_str(), m.offset))
+            .collect();
+        // The search found MARK at 14, so `&0` is the byte right after it at 18
+        assert_eq!(
+            found,
+            [
+                ("marker", 14),
+                ("version %d", 18),
+                ("\\b, status OK", 19),
+                ("\\b, marker again", 14),
+            ]
+        );
+
+        // Without the marker at the same place, the children read elsewhere
+        let shifted = b"MARK\x08OK";
+        let matches = evaluate_rules_with_config(&rules, shifted, config).unwrap();
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[1].message, "\\b, status OK");
+    }
+
+    #[test]
+    fn test_evaluate_rules_soft_bounds_skips_unreachable_children() {
+        let rules = crate::parser::parse_magic_string(
+            "0 string PK ZIP archive\n\
+             >1000 belong 1 far away\n\
+             >>0 byte x never reached\n\
+             >0 filesize >1 bigger than one byte\n",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            soft_bounds: true,
+            stop_at_first_match: false,
+            ..Default::default()
+        };
+        let mut context = EvaluationContext::new(config);
+
+        let matches = evaluate_rules(&rules, b"PK\x03\x04", &mut context).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["ZIP archive", "bigger than one byte"]);
+        // The unreachable child is skipped without being evaluated
+        assert_eq!(context.rules_evaluated(), 2);
+    }
+
+    #[test]
+    fn test_evaluate_rules_rule_budget() {
+        let rules: Vec<MagicRule> = (0..5)
+            .map(|i| MagicRule {
+                offset: OffsetSpec::Absolute(0),
+                typ: TypeKind::Byte { signed: false },
+                op: Operator::Equal,
+                value: Value::Uint(0xff),
+                message: format!("Rule {i}"),
+                children: vec![],
+                level: 0,
+                source_line: None,
+                apple_type: None,
+                apple_creator: None,
+                mask: None,
+                mime_type: None,
+                extensions: Vec::new(),
+                negate: false,
+                store: false,
+            })
+            .collect();
+        let buffer = &[0x00];
+
+        let config = EvaluationConfig {
+            max_rule_evaluations: Some(5),
+            ..Default::default()
+        };
+        let mut context = EvaluationContext::new(config.clone());
+        assert!(
+            evaluate_rules(&rules, buffer, &mut context)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(context.rules_evaluated(), 5);
+
+        let config = EvaluationConfig {
+            max_rule_evaluations: Some(4),
+            ..config
+        };
+        let mut context = EvaluationContext::new(config);
+        assert!(
+            evaluate_rules(&rules, buffer, &mut context)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(context.rules_evaluated(), 4);
+    }
+
+    #[test]
+    fn test_evaluate_rules_keeps_matches_found_within_budget() {
+        let rules = crate::parser::parse_magic_string(
+            "0 byte 1 one
+>1 byte 2 two
+>>2 byte 3 three
+1 byte 2 sibling
+",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            max_rule_evaluations: Some(2),
+            stop_at_first_match: false,
+            ..Default::default()
+        };
+
+        let matches = evaluate_rules_with_config(&rules, &[1, 2, 3], config).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["one", "two"]);
+    }
+
+    #[test]
+    fn test_evaluate_rules_stops_at_max_matches() {
+        let rules = crate::parser::parse_magic_string(
+            "0 byte 1 one
+>0 byte 1 child
+1 byte 2 two
+2 byte 3 three
+",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            max_matches: Some(3),
+            ..EvaluationConfig::default()
+        };
+
+        let mut context = EvaluationContext::new(config);
+        let matches = evaluate_rules(&rules, &[1, 2, 3], &mut context).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["one", "child", "two"]);
+        assert_eq!(context.rules_evaluated(), 3);
+    }
+
+    pub(super) fn string_rule(offset: i64, pattern: &str, message: &str, level: u32) -> MagicRule {
+        MagicRule::new(
+            OffsetSpec::Absolute(offset),
+            TypeKind::String {
+                max_length: None,
+                flags: StringFlags::default(),
+            },
+            Operator::Equal,
+            Value::String(pattern.to_string()),
+            message,
+        )
+        .with_level(level)
+    }
+
+    fn control_rule(offset: i64, typ: TypeKind, children: Vec<MagicRule>, level: u32) -> MagicRule {
+        MagicRule::new(
+            OffsetSpec::Absolute(offset),
+            typ,
+            Operator::Equal,
+            Value::Uint(0),
+            "",
+        )
+        .with_children(children)
+        .with_level(level)
+    }
+
+    #[test]
+    fn test_evaluate_rules_absolute_indirect_at_file_start() {
+        let rules = crate::parser::parse_magic_string("(0.b) string DATA payload\n").unwrap();
+        let buffer = b"\x05....DATA";
+
+        let matches =
+            evaluate_rules_with_config(&rules, buffer, EvaluationConfig::default()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].message, "payload");
+        assert_eq!(matches[0].offset, 5);
+
+        let miss = b"\x01....DATA";
+        let matches =
+            evaluate_rules_with_config(&rules, miss, EvaluationConfig::default()).unwrap();
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_rules_indirect_origins_in_rebased_subroutine() {
+        let rules = Arc::new(
+            crate::parser::parse_magic_string(
+                "\
+0 name hdr
+>(&0.b) string SUB! relative payload
+>(4.b) string TOP! absolute payload
+0 string HEAD header
+>8 use hdr
+",
+            )
+            .unwrap(),
+        );
+
+        // File offset 4 points at file offset 12; the region at 8 starts with a
+        // pointer to region offset 8 (file offset 16)
+        let mut buffer = b"HEAD\x0c...".to_vec();
+        buffer.extend_from_slice(b"\x08...TOP!SUB!");
+
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+        let mut context = EvaluationContext::new(config);
+        context.set_database(Arc::clone(&rules));
+        let matches = evaluate_rules(&rules, &buffer, &mut context).unwrap();
+
+        let found: Vec<(&str, usize)> = matches
+            .iter()
+            .map(|m| (m.message.as_str(), m.offset))
+            .collect();
+        // Offsets are reported from the start of the file, not the region
+        assert_eq!(
+            found,
+            [
+                ("header", 0),
+                ("relative payload", 16),
+                ("absolute payload", 12)
+            ]
+        );
+        assert_eq!(context.base_offset(), 0);
+    }
+
+    #[test]
+    fn test_evaluate_rules_use_rebases_subroutine_offsets() {
+        let subroutine = control_rule(
+            0,
+            TypeKind::Name {
+                name: "sig".to_string(),
+            },
+            vec![string_rule(4, "SIG!", "signature", 1)],
+            0,
+        );
+        let mut header = string_rule(0, "HEAD", "header", 0);
+        header.children = vec![control_rule(
+            8,
+            TypeKind::Use {
+                name: "sig".to_string(),
+                byte_swap: false,
+            },
+            vec![],
+            1,
+        )];
+        let rules = Arc::new(vec![subroutine, header]);
+
+        // The signature sits 4 bytes after the `use` offset, not at file offset 4
+        let buffer = b"HEAD....xxxxSIG!";
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_database(Arc::clone(&rules));
+
+        let matches = evaluate_rules(&rules, buffer, &mut context).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["header", "signature"]);
+        assert_eq!(context.base_offset(), 0);
+        assert_eq!(context.recursion_depth(), 0);
+    }
+
+    #[test]
+    fn test_evaluate_rules_indirect_reports_file_offsets() {
+        let rules = Arc::new(
+            crate::parser::parse_magic_string(
+                "0 string CONT container\n\
+                 >1024 indirect x\n\
+                 0 string MEMB member\n\
+                 >4 string SIG! signature\n",
+            )
+            .unwrap(),
+        );
+
+        let mut buffer = b"CONT".to_vec();
+        buffer.resize(1024, 0);
+        buffer.extend_from_slice(b"MEMBSIG!");
+
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_database(Arc::clone(&rules));
+        let matches = evaluate_rules(&rules, &buffer, &mut context).unwrap();
+
+        let found: Vec<(&str, usize)> = matches
+            .iter()
+            .map(|m| (m.message.as_str(), m.offset))
+            .collect();
+        // The region starts at 1024, so its offsets 0 and 4 are file offsets 1024 and 1028
+        assert_eq!(
+            found,
+            [("container", 0), ("member", 1024), ("signature", 1024 + 4)]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_rules_relative_indirect_at_current_position() {
+        let rules = Arc::new(
+            crate::parser::parse_magic_string(
+                "0 string ARCH archive\n\
+                 >8 string BLK: block\n\
+                 >>0 indirect/r x\n\
+                 0 string \\x89PNG PNG image\n",
+            )
+            .unwrap(),
+        );
+
+        // The block header sits at a position only its parent knows
+        let buffer = b"ARCH\0\0\0\0BLK:\x89PNG\r\n".to_vec();
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_database(Arc::clone(&rules));
+        let matches = evaluate_rules(&rules, &buffer, &mut context).unwrap();
+
+        let found: Vec<(&str, usize)> = matches
+            .iter()
+            .map(|m| (m.message.as_str(), m.offset))
+            .collect();
+        assert_eq!(found, [("archive", 0), ("block", 8), ("PNG image", 12)]);
+    }
+
+    #[test]
+    fn test_evaluate_rules_default_matches_only_when_siblings_did_not() {
+        let rules = crate::parser::parse_magic_string(
+            "0 string IMG image
+             >3 byte 1 version 1
+             >3 default x unknown version
+             >3 clear
+             >3 default x no longer known
+",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+
+        let messages = |buffer: &[u8]| {
+            let mut context = EvaluationContext::new(config.clone());
+            evaluate_rules(&rules, buffer, &mut context)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.message)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(b"IMG\x01"),
+            ["image", "version 1", "no longer known"]
+        );
+        assert_eq!(
+            messages(b"IMG\x07"),
+            ["image", "unknown version", "no longer known"]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_rules_indirect_invokes_subroutine() {
+        // 0 name sig / >4 string SIG! signature
+        let subroutine = control_rule(
+            0,
+            TypeKind::Name {
+                name: "sig".to_string(),
+            },
+            vec![string_rule(4, "SIG!", "signature", 1)],
+            0,
+        );
+        // 0 string CONT container / >16 indirect
+        let mut container = string_rule(0, "CONT", "container", 0);
+        container.children = vec![control_rule(16, TypeKind::Indirect, vec![], 1)];
+        // 0 string MEMB member / >0 use sig
+        let mut member = string_rule(0, "MEMB", "member", 0);
+        member.children = vec![control_rule(
+            0,
+            TypeKind::Use {
+                name: "sig".to_string(),
+                byte_swap: false,
+            },
+            vec![],
+            1,
+        )];
+        let rules = Arc::new(vec![subroutine, container, member]);
+
+        let mut buffer = b"CONT".to_vec();
+        buffer.extend_from_slice(b"junk"); // file offset 4 is not the signature
+        buffer.resize(16, 0);
+        buffer.extend_from_slice(b"MEMBSIG!");
+
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_database(Arc::clone(&rules));
+
+        let matches = evaluate_rules(&rules, &buffer, &mut context).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["container", "member", "signature"]);
+        // Offsets inside the region are reported as file offsets
+        assert_eq!(matches[2].offset, 20);
+        assert_eq!(context.base_offset(), 0);
+    }
+
+    #[test]
+    fn test_evaluate_rules_use_byte_swap_flips_endianness() {
+        let rules = crate::parser::parse_magic_string(
+            "0 name le16\n>0 leshort 0x1234 marker\n\
+             0 string BE big-endian\n>2 use le16\n>2 use ^le16\n",
+        )
+        .unwrap();
+        let rules = Arc::new(rules);
+        let buffer = b"BE\x12\x34";
+
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_database(Arc::clone(&rules));
+
+        // Only the swapped invocation reads the big-endian 0x1234
+        let matches = evaluate_rules(&rules, buffer, &mut context).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages, ["big-endian", "marker"]);
+        assert_eq!(matches[1].value, Value::Int(0x1234));
+        assert!(!context.swap_endian());
+    }
+
+    #[test]
+    fn test_evaluate_rules_out_of_bounds_error_names_offset_and_type() {
+        let rules =
+            crate::parser::parse_magic_string("0 byte 1 one\n>10 beshort 1 far field\n").unwrap();
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+
+        match evaluate_rules(&rules, b"\x01abc", &mut context).unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("'far field'"), "{msg}");
+                assert!(msg.contains("Cannot read beshort: "), "{msg}");
+                assert!(msg.contains("offset 10 is beyond buffer length 4"), "{msg}");
+            }
+            other => panic!("Expected EvaluationError, got {other:?}"),
+        }
+
+        // A read that starts in bounds but runs past the end reports its file offset
+        let rules = crate::parser::parse_magic_string("0 byte 1 one\n>3 belong 1 tail\n").unwrap();
+        match evaluate_rules(&rules, b"\x01abc", &mut context).unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Cannot read belong at offset 3"), "{msg}");
+            }
+            other => panic!("Expected EvaluationError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rules_native_endianness_override() {
+        let rules = crate::parser::parse_magic_string(
+            "0 short 0x0102 one-two
+0 short x %#x
+",
+        )
+        .unwrap();
+        let buffer = b"\x01\x02";
+        let read = |native| {
+            let config = EvaluationConfig {
+                stop_at_first_match: false,
+                native_endianness_override: native,
+                ..EvaluationConfig::default()
+            };
+            let mut context = EvaluationContext::new(config);
+            let matches = evaluate_rules(&rules, buffer, &mut context).unwrap();
+            let indexed = RuleIndex::new(Arc::new(rules.clone()));
+            let mut context = EvaluationContext::new(context.config().clone());
+            assert_eq!(
+                evaluate_rules_indexed(&indexed, buffer, &mut context).unwrap(),
+                matches
+            );
+            matches
+        };
+
+        // Forcing big-endian native reads the bytes the other way round on a little-endian host
+        let big = read(Some(Endianness::Big));
+        assert_eq!(big[0].message, "one-two");
+        assert_eq!(big[1].value, Value::Int(0x0102));
+        let little = read(Some(Endianness::Little));
+        assert_eq!(little.len(), 1);
+        assert_eq!(little[0].value, Value::Int(0x0201));
+
+        let host = if cfg!(target_endian = "little") {
+            little
+        } else {
+            big
+        };
+        assert_eq!(read(None), host);
+    }
+
+    #[test]
+    fn test_evaluate_rules_name_rules_are_skipped() {
+        let subroutine = control_rule(
+            0,
+            TypeKind::Name {
+                name: "sig".to_string(),
+            },
+            vec![string_rule(0, "AB", "should not run", 1)],
+            0,
+        );
+
+        let matches =
+            evaluate_rules_with_config(&[subroutine], b"AB", EvaluationConfig::default()).unwrap();
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_rules_use_errors() {
+        let use_rule = control_rule(
+            0,
+            TypeKind::Use {
+                name: "missing".to_string(),
+                byte_swap: false,
+            },
+            vec![],
+            0,
+        );
+
+        // No database configured
+        let result = evaluate_rules_with_config(
+            std::slice::from_ref(&use_rule),
+            b"data",
+            EvaluationConfig::default(),
+        );
+        assert!(result.is_err());
+
+        // Database without the subroutine
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        context.set_database(Arc::new(vec![use_rule.clone()]));
+        match evaluate_rules(&[use_rule], b"data", &mut context).unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Unknown subroutine 'missing'"));
+            }
+            _ => panic!("Expected EvaluationError for unknown subroutine"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rules_self_indirect_hits_recursion_limit() {
+        let rules = Arc::new(vec![control_rule(0, TypeKind::Indirect, vec![], 0)]);
+        let config = EvaluationConfig {
+            max_recursion_depth: 5,
+            ..EvaluationConfig::default()
+        };
+        let mut context = EvaluationContext::new(config);
+        context.set_database(Arc::clone(&rules));
+
+        match evaluate_rules(&rules, b"loop", &mut context).unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Maximum recursion depth exceeded"));
+            }
+            _ => panic!("Expected EvaluationError for recursion limit"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rules_indirect_pointer_loop_is_bounded() {
+        // The pointer at offset 0 is 0, so each region re-runs the rule at the same place
+        let rules = crate::parser::parse_magic_string(
+            "(0.b) indirect x
+",
+        )
+        .unwrap();
+        let rules = Arc::new(rules);
+        let config = EvaluationConfig {
+            max_recursion_depth: 1000,
+            ..EvaluationConfig::default()
+        };
+        let mut context = EvaluationContext::new(config);
+        context.set_database(Arc::clone(&rules));
+
+        match evaluate_rules(&rules, b"\0loop", &mut context).unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.ends_with("indirect offset chain too deep"));
+            }
+            _ => panic!("Expected EvaluationError for indirect chain limit"),
+        }
+        assert_eq!(context.indirect_depth(), 0);
+    }
+
     #[test]
     fn test_evaluate_rules_with_config_convenience() {
         let rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF magic".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![rule];
@@ -1833,12 +2040,20 @@ fn test_evaluate_rules_with_config_convenience() {
     fn test_evaluate_rules_timeout() {
         let rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF magic".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![rule];
@@ -1862,12 +2077,20 @@ fn test_evaluate_rules_timeout() {
     fn test_evaluate_rules_empty_buffer() {
         let rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "Should not match".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![rule];
@@ -1890,32 +2113,56 @@ fn test_evaluate_rules_empty_buffer() {
     fn test_evaluate_rules_mixed_matching_non_matching() {
         let rule1 = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "Matches".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rule2 = MagicRule {
             offset: OffsetSpec::Absolute(1),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x99), // Doesn't match
             message: "Doesn't match".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rule3 = MagicRule {
             offset: OffsetSpec::Absolute(2),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x4c),
             message: "Also matches".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rule_collection = vec![rule1, rule2, rule3];
@@ -1936,12 +2183,20 @@ fn test_evaluate_rules_mixed_matching_non_matching() {
     fn test_evaluate_rules_context_state_preservation() {
         let rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF magic".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let rules = vec![rule];
@@ -1961,46 +2216,4 @@ fn test_evaluate_rules_context_state_preservation() {
         assert_eq!(context.current_offset(), initial_offset);
         assert_eq!(context.recursion_depth(), initial_depth);
     }
-
-    #[test]
-    fn test_evaluation_context_state_management_sequence() {
-        let config = EvaluationConfig::default();
-        let mut context = EvaluationContext::new(config);
-
-        // Simulate a sequence of evaluation operations
-        assert_eq!(context.current_offset(), 0);
-        assert_eq!(context.recursion_depth(), 0);
-
-        // Start evaluation at offset 10
-        context.set_current_offset(10);
-        assert_eq!(context.current_offset(), 10);
-
-        // Enter nested rule evaluation
-        context.increment_recursion_depth().unwrap();
-        assert_eq!(context.recursion_depth(), 1);
-
-        // Move to different offset during nested evaluation
-        context.set_current_offset(25);
-        assert_eq!(context.current_offset(), 25);
-
-        // Enter deeper nesting
-        context.increment_recursion_depth().unwrap();
-        assert_eq!(context.recursion_depth(), 2);
-
-        // Exit nested evaluation
-        context.decrement_recursion_depth();
-        assert_eq!(context.recursion_depth(), 1);
-
-        // Continue evaluation at different offset
-        context.set_current_offset(50);
-        assert_eq!(context.current_offset(), 50);
-
-        // Exit all nesting
-        context.decrement_recursion_depth();
-        assert_eq!(context.recursion_depth(), 0);
-
-        // Final state check
-        assert_eq!(context.current_offset(), 50);
-        assert_eq!(context.recursion_depth(), 0);
-    }
 }
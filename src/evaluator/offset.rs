@@ -4,7 +4,8 @@
 //! into absolute byte positions within file buffers, with proper bounds checking.
 
 use crate::LibmagicError;
-use crate::parser::ast::OffsetSpec;
+use crate::evaluator::types;
+use crate::parser::ast::{OffsetSpec, Value};
 
 /// Error types specific to offset resolution
 #[derive(Debug, thiserror::Error)]
@@ -72,7 +73,38 @@ pub enum OffsetError {
 /// * `OffsetError::BufferOverrun` - If the resolved offset is beyond buffer bounds
 /// * `OffsetError::ArithmeticOverflow` - If offset calculation overflows
 pub fn resolve_absolute_offset(offset: i64, buffer: &[u8]) -> Result<usize, OffsetError> {
+    resolve_absolute_offset_in_file(offset, buffer, buffer.len())
+}
+
+/// Resolve an absolute offset in a buffer that may hold only the start of the data
+///
+/// `data_len` is the full length of the data that `buffer` is a prefix of, such
+/// as the size of a file of which only the first bytes are mapped. Negative
+/// offsets count back from `data_len`, and any position outside `buffer` is an
+/// overrun even if it lies within the data.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::offset::resolve_absolute_offset_in_file;
+///
+/// let window = b"header";
+/// assert_eq!(resolve_absolute_offset_in_file(2, window, 1000).unwrap(), 2);
+/// // The last byte of the data lies beyond the loaded window
+/// assert!(resolve_absolute_offset_in_file(-1, window, 1000).is_err());
+/// ```
+///
+/// # Errors
+///
+/// * `OffsetError::BufferOverrun` - If the resolved offset is outside `buffer`
+/// * `OffsetError::ArithmeticOverflow` - If offset calculation overflows
+pub fn resolve_absolute_offset_in_file(
+    offset: i64,
+    buffer: &[u8],
+    data_len: usize,
+) -> Result<usize, OffsetError> {
     let buffer_len = buffer.len();
+    let data_len = data_len.max(buffer_len);
 
     if offset >= 0 {
         // Positive offset from start
@@ -94,24 +126,135 @@ pub fn resolve_absolute_offset(offset: i64, buffer: &[u8]) -> Result<usize, Offs
         let offset_from_end =
             usize::try_from(-offset).map_err(|_| OffsetError::ArithmeticOverflow)?;
 
-        if offset_from_end > buffer_len {
+        if offset_from_end > data_len {
             return Err(OffsetError::BufferOverrun {
-                offset: buffer_len.saturating_sub(offset_from_end),
+                offset: data_len.saturating_sub(offset_from_end),
                 buffer_len,
             });
         }
 
         // Calculate position from end
-        let resolved_offset = buffer_len - offset_from_end;
+        let resolved_offset = data_len - offset_from_end;
+        if resolved_offset >= buffer_len {
+            return Err(OffsetError::BufferOverrun {
+                offset: resolved_offset,
+                buffer_len,
+            });
+        }
         Ok(resolved_offset)
     }
 }
 
+/// Resolve an indirect offset to a position in the whole file buffer
+///
+/// `file` holds the data from the start of the file and `base` is the file
+/// offset of the region being evaluated: 0 at the top level, or the start of
+/// an `indirect` region or `use` subroutine.
+///
+/// - An absolute indirect offset reads its pointer at `base_offset` from the
+///   start of the file, and `pointer + adjustment` is a file offset.
+/// - A relative indirect offset (`relative: true`) reads its pointer at
+///   `base_offset` from `base`, and `pointer + adjustment` is measured from
+///   `base` as well.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::offset::resolve_indirect_offset;
+/// use libmagic_rs::parser::ast::{Endianness, OffsetSpec, TypeKind};
+///
+/// // Pointers at file offsets 0 and 4, each pointing two bytes further on
+/// let file = [4, 0, 0, 0, 2, b'x', b'y', b'z'];
+/// let spec = |relative| OffsetSpec::Indirect {
+///     base_offset: 0,
+///     pointer_type: TypeKind::Byte { signed: false },
+///     adjustment: 1,
+///     endian: Endianness::Little,
+///     relative,
+/// };
+///
+/// // Inside a region starting at file offset 4
+/// assert_eq!(resolve_indirect_offset(&spec(false), &file, 4).unwrap(), 5);
+/// assert_eq!(resolve_indirect_offset(&spec(true), &file, 4).unwrap(), 7);
+/// ```
+///
+/// # Errors
+///
+/// * `LibmagicError::EvaluationError` - If `spec` is not an indirect offset, the
+///   pointer cannot be read, or the resulting offset lies outside `file`
+pub fn resolve_indirect_offset(
+    spec: &OffsetSpec,
+    file: &[u8],
+    base: usize,
+) -> Result<usize, LibmagicError> {
+    let OffsetSpec::Indirect {
+        base_offset,
+        pointer_type,
+        adjustment,
+        relative,
+        ..
+    } = spec
+    else {
+        return Err(LibmagicError::EvaluationError(
+            OffsetError::InvalidOffset {
+                reason: format!("{spec:?} is not an indirect offset"),
+            }
+            .to_string(),
+        ));
+    };
+
+    let origin = if *relative { base } else { 0 };
+    let overflow = || LibmagicError::EvaluationError(OffsetError::ArithmeticOverflow.to_string());
+    let origin = i64::try_from(origin).map_err(|_| overflow())?;
+
+    let pointer_at = origin.checked_add(*base_offset).ok_or_else(overflow)?;
+    let pointer_at = usize::try_from(pointer_at).map_err(|_| {
+        LibmagicError::EvaluationError(
+            OffsetError::InvalidOffset {
+                reason: format!("indirect pointer offset {pointer_at} is before the file start"),
+            }
+            .to_string(),
+        )
+    })?;
+    let pointer = match types::read_typed_value(file, pointer_at, pointer_type)
+        .map_err(|e| LibmagicError::EvaluationError(e.to_string()))?
+    {
+        Value::Uint(value) => i64::try_from(value).map_err(|_| overflow())?,
+        Value::Int(value) => value,
+        other => {
+            return Err(LibmagicError::EvaluationError(format!(
+                "Indirect pointer is not an integer: {other:?}"
+            )));
+        }
+    };
+
+    let target = origin
+        .checked_add(pointer)
+        .and_then(|target| target.checked_add(*adjustment))
+        .ok_or_else(overflow)?;
+    match usize::try_from(target) {
+        Ok(target) if target < file.len() => Ok(target),
+        _ => Err(LibmagicError::EvaluationError(
+            OffsetError::InvalidOffset {
+                reason: format!(
+                    "indirect offset {target} is outside the file buffer of length {}",
+                    file.len()
+                ),
+            }
+            .to_string(),
+        )),
+    }
+}
+
 /// Resolve any offset specification to an absolute position
 ///
 /// This is a higher-level function that handles all types of offset specifications.
-/// Currently only supports absolute offsets, but will be extended to handle indirect,
-/// relative, and from-end offsets in future tasks.
+/// `buffer` is taken to be the whole file, so indirect offsets resolve the same
+/// whether they are absolute or relative; see [`resolve_indirect_offset`] for
+/// resolving them inside a region. Relative and register offsets depend on
+/// earlier matches, so they are resolved through the
+/// [`EvaluationContext`](crate::evaluator::EvaluationContext) during rule
+/// evaluation and are an error here.
 ///
 /// # Arguments
 ///
@@ -139,26 +282,37 @@ pub fn resolve_absolute_offset(offset: i64, buffer: &[u8]) -> Result<usize, Offs
 ///
 /// * `LibmagicError::EvaluationError` - If offset resolution fails
 pub fn resolve_offset(spec: &OffsetSpec, buffer: &[u8]) -> Result<usize, LibmagicError> {
+    resolve_offset_in_file(spec, buffer, buffer.len())
+}
+
+/// Resolve an offset specification in a buffer holding only the start of the data
+///
+/// Like [`resolve_offset`], but from-end offsets count back from `data_len`
+/// rather than from the end of `buffer`. See [`resolve_absolute_offset_in_file`].
+///
+/// # Errors
+///
+/// * `LibmagicError::EvaluationError` - If offset resolution fails
+pub fn resolve_offset_in_file(
+    spec: &OffsetSpec,
+    buffer: &[u8],
+    data_len: usize,
+) -> Result<usize, LibmagicError> {
     match spec {
-        OffsetSpec::Absolute(offset) => resolve_absolute_offset(*offset, buffer)
+        OffsetSpec::Absolute(offset) => resolve_absolute_offset_in_file(*offset, buffer, data_len)
             .map_err(|e| LibmagicError::EvaluationError(e.to_string())),
-        OffsetSpec::Indirect { .. } => {
-            // TODO: Implement indirect offset resolution in task 15.2
-            Err(LibmagicError::EvaluationError(
-                "Indirect offsets not yet implemented".to_string(),
-            ))
-        }
-        OffsetSpec::Relative(_) => {
-            // TODO: Implement relative offset resolution in future task
-            Err(LibmagicError::EvaluationError(
-                "Relative offsets not yet implemented".to_string(),
-            ))
-        }
+        OffsetSpec::Indirect { .. } => resolve_indirect_offset(spec, buffer, 0),
+        OffsetSpec::Relative(_) => Err(LibmagicError::EvaluationError(
+            "Relative offsets can only be resolved during rule evaluation".to_string(),
+        )),
         OffsetSpec::FromEnd(offset) => {
             // FromEnd is handled the same as negative Absolute offsets
-            resolve_absolute_offset(*offset, buffer)
+            resolve_absolute_offset_in_file(*offset, buffer, data_len)
                 .map_err(|e| LibmagicError::EvaluationError(e.to_string()))
         }
+        OffsetSpec::Register(_) => Err(LibmagicError::EvaluationError(
+            "Register offsets can only be resolved during rule evaluation".to_string(),
+        )),
     }
 }
 
@@ -251,6 +405,35 @@ fn test_resolve_absolute_offset_edge_cases() {
         assert!(resolve_absolute_offset(-2, buffer).is_err());
     }
 
+    #[test]
+    fn test_resolve_absolute_offset_in_truncated_buffer() {
+        let window = [0u8; 16];
+
+        // Positive offsets only depend on the loaded window
+        assert_eq!(
+            resolve_absolute_offset_in_file(15, &window, 100).unwrap(),
+            15
+        );
+        assert!(resolve_absolute_offset_in_file(16, &window, 100).is_err());
+
+        // Negative offsets count from the end of the full data
+        assert_eq!(
+            resolve_absolute_offset_in_file(-90, &window, 100).unwrap(),
+            10
+        );
+        match resolve_absolute_offset_in_file(-4, &window, 100).unwrap_err() {
+            OffsetError::BufferOverrun { offset, buffer_len } => {
+                assert_eq!(offset, 96);
+                assert_eq!(buffer_len, 16);
+            }
+            other => panic!("Expected BufferOverrun, got {other:?}"),
+        }
+        assert!(resolve_absolute_offset_in_file(-101, &window, 100).is_err());
+
+        // A data length shorter than the buffer is ignored
+        assert_eq!(resolve_absolute_offset_in_file(-1, &window, 4).unwrap(), 15);
+    }
+
     #[test]
     fn test_resolve_offset_absolute() {
         let buffer = b"Test data for offset resolution";
@@ -294,29 +477,65 @@ fn test_resolve_offset_absolute_out_of_bounds() {
         }
     }
 
-    #[test]
-    fn test_resolve_offset_indirect_not_implemented() {
-        let buffer = b"Test data";
-        let spec = OffsetSpec::Indirect {
-            base_offset: 0,
-            pointer_type: crate::parser::ast::TypeKind::Byte,
-            adjustment: 0,
+    fn indirect(base_offset: i64, adjustment: i64, relative: bool) -> OffsetSpec {
+        OffsetSpec::Indirect {
+            base_offset,
+            pointer_type: crate::parser::ast::TypeKind::Short {
+                endian: crate::parser::ast::Endianness::Little,
+                signed: false,
+            },
+            adjustment,
             endian: crate::parser::ast::Endianness::Little,
-        };
+            relative,
+        }
+    }
 
-        let result = resolve_offset(&spec, buffer);
-        assert!(result.is_err());
+    #[test]
+    fn test_resolve_offset_indirect_at_file_start() {
+        // Little-endian pointer at offset 0 to the "DATA" at offset 6
+        let buffer = b"\x06\x00....DATA";
+
+        assert_eq!(resolve_offset(&indirect(0, 0, false), buffer).unwrap(), 6);
+        assert_eq!(resolve_offset(&indirect(0, 2, false), buffer).unwrap(), 8);
+        // At the top level the file is the region, so relative is the same
+        assert_eq!(resolve_offset(&indirect(0, 0, true), buffer).unwrap(), 6);
+    }
 
-        match result.unwrap_err() {
-            LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Indirect offsets not yet implemented"));
-            }
-            _ => panic!("Expected EvaluationError for unimplemented feature"),
-        }
+    #[test]
+    fn test_resolve_indirect_offset_origins_in_region() {
+        // File header pointer at 0 -> 2; region at 8 holds a pointer at +0 -> +4
+        let mut file = vec![0x02, 0x00, 0, 0, 0, 0, 0, 0];
+        file.extend_from_slice(&[0x04, 0x00, 0, 0, b'S', b'U', b'B']);
+
+        // Absolute: pointer read at file offset 0, result is a file offset
+        assert_eq!(
+            resolve_indirect_offset(&indirect(0, 0, false), &file, 8).unwrap(),
+            2
+        );
+        // Relative: pointer read at region offset 0, result is region-relative
+        assert_eq!(
+            resolve_indirect_offset(&indirect(0, 0, true), &file, 8).unwrap(),
+            12
+        );
+        assert_eq!(
+            resolve_indirect_offset(&indirect(0, 1, true), &file, 8).unwrap(),
+            13
+        );
+    }
+
+    #[test]
+    fn test_resolve_indirect_offset_out_of_bounds() {
+        let buffer = b"\xff\x00ab";
+        assert!(resolve_offset(&indirect(0, 0, false), buffer).is_err());
+        // The pointer itself cannot be read
+        assert!(resolve_offset(&indirect(3, 0, false), buffer).is_err());
+        // A negative result is never a valid offset
+        assert!(resolve_offset(&indirect(0, -0x200, false), buffer).is_err());
+        assert!(resolve_indirect_offset(&OffsetSpec::Absolute(0), buffer, 0).is_err());
     }
 
     #[test]
-    fn test_resolve_offset_relative_not_implemented() {
+    fn test_resolve_offset_relative_needs_evaluation_context() {
         let buffer = b"Test data";
         let spec = OffsetSpec::Relative(4);
 
@@ -325,9 +544,11 @@ fn test_resolve_offset_relative_not_implemented() {
 
         match result.unwrap_err() {
             LibmagicError::EvaluationError(msg) => {
-                assert!(msg.contains("Relative offsets not yet implemented"));
+                assert!(
+                    msg.contains("Relative offsets can only be resolved during rule evaluation")
+                );
             }
-            _ => panic!("Expected EvaluationError for unimplemented feature"),
+            _ => panic!("Expected EvaluationError for a relative offset"),
         }
     }
 
@@ -3,8 +3,24 @@
 //! This module provides functions for applying comparison and bitwise operators
 //! to values during magic rule evaluation. It handles type-safe comparisons
 //! between different Value variants.
+//!
+//! The module is re-exported as `libmagic_rs::operators`, which is the stable
+//! path for code that evaluates values directly instead of going through
+//! `MagicDatabase`.
+//!
+//! # Examples
+//!
+//! ```
+//! use libmagic_rs::operators::{apply_greater_than, apply_operator};
+//! use libmagic_rs::{Operator, Value};
+//!
+//! assert!(apply_operator(&Operator::Equal, &Value::Uint(0x7f), &Value::Uint(0x7f)));
+//! assert!(apply_operator(&Operator::BitwiseAnd, &Value::Uint(0x81), &Value::Uint(0x80)));
+//! assert!(apply_greater_than(&Value::Int(2), &Value::Int(-2)));
+//! ```
 
 use crate::parser::ast::{Operator, Value};
+use std::cmp::Ordering;
 
 /// Apply equality comparison between two values
 ///
@@ -18,7 +34,11 @@
 ///
 /// # Returns
 ///
-/// `true` if the values are equal and of the same type, `false` otherwise
+/// `true` if the values are equal, `false` otherwise. Integers compare by
+/// numeric value, so a `Uint` and an `Int` of the same magnitude are equal when
+/// the `Int` is not negative; other values must be of the same type. A
+/// [`Value::OneOf`] on the right is equal to any value equal to one of its
+/// members.
 ///
 /// # Examples
 ///
@@ -32,23 +52,30 @@
 /// // Same type, different value
 /// assert!(!apply_equal(&Value::Uint(42), &Value::Uint(24)));
 ///
-/// // Different types, same numeric value
-/// assert!(!apply_equal(&Value::Uint(42), &Value::Int(42)));
+/// // Integers of either signedness compare numerically
+/// assert!(apply_equal(&Value::Uint(42), &Value::Int(42)));
+/// assert!(!apply_equal(&Value::Uint(u64::MAX), &Value::Int(-1)));
 ///
 /// // String comparison
 /// assert!(apply_equal(
 ///     &Value::String("hello".to_string()),
 ///     &Value::String("hello".to_string())
 /// ));
+///
+/// // Membership in a list of alternatives
+/// let allowed = Value::OneOf(vec![Value::Uint(1), Value::Uint(2)]);
+/// assert!(apply_equal(&Value::Uint(2), &allowed));
+/// assert!(!apply_equal(&Value::Uint(3), &allowed));
 /// ```
 #[must_use]
 pub fn apply_equal(left: &Value, right: &Value) -> bool {
     match (left, right) {
-        // Unsigned integer comparison
-        (Value::Uint(a), Value::Uint(b)) => a == b,
+        (_, Value::OneOf(members)) => members.iter().any(|member| apply_equal(left, member)),
 
-        // Signed integer comparison
-        (Value::Int(a), Value::Int(b)) => a == b,
+        // Integer comparison, across signedness
+        (Value::Uint(_) | Value::Int(_), Value::Uint(_) | Value::Int(_)) => {
+            compare_integers(left, right) == Some(Ordering::Equal)
+        }
 
         // Byte sequence comparison
         (Value::Bytes(a), Value::Bytes(b)) => a == b,
@@ -89,7 +116,7 @@ pub fn apply_equal(left: &Value, right: &Value) -> bool {
 /// assert!(!apply_not_equal(&Value::Uint(42), &Value::Uint(42)));
 ///
 /// // Different types (always not equal)
-/// assert!(apply_not_equal(&Value::Uint(42), &Value::Int(42)));
+/// assert!(apply_not_equal(&Value::Uint(42), &Value::String("42".to_string())));
 ///
 /// // String comparison
 /// assert!(apply_not_equal(
@@ -158,6 +185,130 @@ pub fn apply_bitwise_and(left: &Value, right: &Value) -> bool {
     }
 }
 
+/// Order two values for the `<` and `>` operators
+///
+/// Integers compare numerically regardless of signedness, and strings and byte
+/// sequences compare lexicographically. Other combinations are unordered.
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Uint(_) | Value::Int(_), Value::Uint(_) | Value::Int(_)) => {
+            compare_integers(left, right)
+        }
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bytes(a), Value::Bytes(b)) => Some(a.cmp(b)),
+        (Value::String(a), Value::Bytes(b)) => Some(a.as_bytes().cmp(b.as_slice())),
+        (Value::Bytes(a), Value::String(b)) => Some(a.as_slice().cmp(b.as_bytes())),
+        _ => None,
+    }
+}
+
+/// Order two integer values numerically, whatever their signedness
+///
+/// Both values are compared as `i64` when they fit and as `u64` otherwise; if
+/// neither fits, one is negative and the other above `i64::MAX`. Returns `None`
+/// if either value is not an integer.
+fn compare_integers(left: &Value, right: &Value) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (left.as_i64(), right.as_i64()) {
+        return Some(a.cmp(&b));
+    }
+    if let (Some(a), Some(b)) = (left.as_u64(), right.as_u64()) {
+        return Some(a.cmp(&b));
+    }
+    match (left, right) {
+        (Value::Int(_), Value::Uint(_)) => Some(Ordering::Less),
+        (Value::Uint(_), Value::Int(_)) => Some(Ordering::Greater),
+        _ => None,
+    }
+}
+
+/// Reinterpret an integer value as its two's-complement bit pattern
+#[allow(clippy::cast_sign_loss)]
+pub(crate) const fn integer_bits(value: &Value) -> Option<u64> {
+    match value {
+        Value::Uint(v) => Some(*v),
+        Value::Int(v) => Some(*v as u64),
+        Value::Bytes(_) | Value::String(_) | Value::OneOf(_) => None,
+    }
+}
+
+/// Apply less-than comparison between two values
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::ast::Value;
+/// use libmagic_rs::evaluator::operators::apply_less_than;
+///
+/// assert!(apply_less_than(&Value::Uint(5), &Value::Uint(10)));
+/// assert!(apply_less_than(&Value::Int(-1), &Value::Uint(0)));
+/// assert!(apply_less_than(&Value::String("abc".to_string()), &Value::String("abd".to_string())));
+/// assert!(!apply_less_than(&Value::Uint(10), &Value::String("10".to_string())));
+/// ```
+#[must_use]
+pub fn apply_less_than(left: &Value, right: &Value) -> bool {
+    compare_values(left, right) == Some(Ordering::Less)
+}
+
+/// Apply greater-than comparison between two values
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::ast::Value;
+/// use libmagic_rs::evaluator::operators::apply_greater_than;
+///
+/// assert!(apply_greater_than(&Value::Uint(10), &Value::Uint(5)));
+/// assert!(!apply_greater_than(&Value::Int(-1), &Value::Uint(0)));
+/// ```
+#[must_use]
+pub fn apply_greater_than(left: &Value, right: &Value) -> bool {
+    compare_values(left, right) == Some(Ordering::Greater)
+}
+
+/// Apply the `^` operator: true if any bit set in `right` is clear in `left`
+///
+/// Equivalent to `(left ^ right) & right != 0`. Only integer values match.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::ast::Value;
+/// use libmagic_rs::evaluator::operators::apply_bitwise_xor;
+///
+/// assert!(apply_bitwise_xor(&Value::Uint(0x01), &Value::Uint(0x03)));
+/// assert!(!apply_bitwise_xor(&Value::Uint(0x07), &Value::Uint(0x03)));
+/// ```
+#[must_use]
+pub fn apply_bitwise_xor(left: &Value, right: &Value) -> bool {
+    match (integer_bits(left), integer_bits(right)) {
+        (Some(a), Some(b)) => (a ^ b) & b != 0,
+        _ => false,
+    }
+}
+
+/// Apply the `~` operator: true if `left` equals the bitwise complement of `right`
+///
+/// The complement is taken over 64 bits; rule evaluation narrows it to the
+/// width of the rule's type before comparing. Only integer values match.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::ast::Value;
+/// use libmagic_rs::evaluator::operators::apply_bitwise_not;
+///
+/// assert!(apply_bitwise_not(&Value::Uint(!0x0f), &Value::Uint(0x0f)));
+/// assert!(apply_bitwise_not(&Value::Int(-1), &Value::Uint(0)));
+/// assert!(!apply_bitwise_not(&Value::Uint(0xf0), &Value::Uint(0x0f)));
+/// ```
+#[must_use]
+pub fn apply_bitwise_not(left: &Value, right: &Value) -> bool {
+    match (integer_bits(left), integer_bits(right)) {
+        (Some(a), Some(b)) => a == !b,
+        _ => false,
+    }
+}
+
 /// Apply operator to two values using the specified operator type
 ///
 /// This is the main operator application interface that dispatches to the appropriate
@@ -166,7 +317,7 @@ pub fn apply_bitwise_and(left: &Value, right: &Value) -> bool {
 ///
 /// # Arguments
 ///
-/// * `operator` - The operator to apply (`Equal`, `NotEqual`, or `BitwiseAnd`)
+/// * `operator` - The operator to apply
 /// * `left` - The left-hand side value (typically from file data)
 /// * `right` - The right-hand side value (typically from magic rule)
 ///
@@ -202,11 +353,16 @@ pub fn apply_bitwise_and(left: &Value, right: &Value) -> bool {
 /// ));
 ///
 /// // Cross-type comparisons
-/// assert!(!apply_operator(
+/// assert!(apply_operator(
 ///     &Operator::Equal,
 ///     &Value::Uint(42),
 ///     &Value::Int(42)
 /// ));
+/// assert!(!apply_operator(
+///     &Operator::Equal,
+///     &Value::Uint(42),
+///     &Value::String("42".to_string())
+/// ));
 /// ```
 #[must_use]
 pub fn apply_operator(operator: &Operator, left: &Value, right: &Value) -> bool {
@@ -214,6 +370,11 @@ pub fn apply_operator(operator: &Operator, left: &Value, right: &Value) -> bool
         Operator::Equal => apply_equal(left, right),
         Operator::NotEqual => apply_not_equal(left, right),
         Operator::BitwiseAnd => apply_bitwise_and(left, right),
+        Operator::LessThan => apply_less_than(left, right),
+        Operator::GreaterThan => apply_greater_than(left, right),
+        Operator::BitwiseXor => apply_bitwise_xor(left, right),
+        Operator::BitwiseNot => apply_bitwise_not(left, right),
+        Operator::Always => true,
     }
 }
 
@@ -377,16 +538,25 @@ fn test_apply_equal_string_whitespace() {
         assert!(!apply_equal(&left, &right));
     }
 
-    // Cross-type comparison tests (should all return false)
+    // Cross-type comparison tests (integers compare numerically, others never match)
     #[test]
     fn test_apply_equal_uint_vs_int() {
         let left = Value::Uint(42);
         let right = Value::Int(42);
-        assert!(!apply_equal(&left, &right));
+        assert!(apply_equal(&left, &right));
+        assert!(apply_equal(&right, &left));
 
         let left = Value::Uint(0);
         let right = Value::Int(0);
-        assert!(!apply_equal(&left, &right));
+        assert!(apply_equal(&left, &right));
+
+        // A negative value never equals an unsigned one, even with the same bits
+        assert!(!apply_equal(&Value::Uint(u64::MAX), &Value::Int(-1)));
+        assert!(!apply_equal(&Value::Int(-1), &Value::Uint(u64::MAX)));
+        assert!(apply_equal(
+            &Value::Uint(i64::MAX as u64),
+            &Value::Int(i64::MAX)
+        ));
     }
 
     #[test]
@@ -433,13 +603,15 @@ fn test_apply_equal_all_cross_type_combinations() {
             Value::String("42".to_string()),
         ];
 
-        // Test that no cross-type comparisons return true
+        // Only the two integers of equal magnitude compare equal across types
         for (i, left) in values.iter().enumerate() {
             for (j, right) in values.iter().enumerate() {
                 if i != j {
-                    assert!(
-                        !apply_equal(left, right),
-                        "Cross-type comparison should be false: {left:?} vs {right:?}"
+                    let both_integers = i < 2 && j < 2;
+                    assert_eq!(
+                        apply_equal(left, right),
+                        both_integers,
+                        "Unexpected cross-type comparison result: {left:?} vs {right:?}"
                     );
                 }
             }
@@ -688,16 +860,18 @@ fn test_apply_not_equal_string_whitespace() {
         assert!(apply_not_equal(&left, &right));
     }
 
-    // Cross-type comparison tests for not_equal (should all return true)
+    // Cross-type comparison tests for not_equal (true unless integers are numerically equal)
     #[test]
     fn test_apply_not_equal_uint_vs_int() {
         let left = Value::Uint(42);
         let right = Value::Int(42);
-        assert!(apply_not_equal(&left, &right));
+        assert!(!apply_not_equal(&left, &right));
 
         let left = Value::Uint(0);
         let right = Value::Int(0);
-        assert!(apply_not_equal(&left, &right));
+        assert!(!apply_not_equal(&left, &right));
+
+        assert!(apply_not_equal(&Value::Uint(u64::MAX), &Value::Int(-1)));
     }
 
     #[test]
@@ -744,13 +918,15 @@ fn test_apply_not_equal_all_cross_type_combinations() {
             Value::String("42".to_string()),
         ];
 
-        // Test that all cross-type comparisons return true for not_equal
+        // All cross-type comparisons are not equal, except the two integers
         for (i, left) in values.iter().enumerate() {
             for (j, right) in values.iter().enumerate() {
                 if i != j {
-                    assert!(
+                    let both_integers = i < 2 && j < 2;
+                    assert_eq!(
                         apply_not_equal(left, right),
-                        "Cross-type comparison should be true for not_equal: {left:?} vs {right:?}"
+                        !both_integers,
+                        "Unexpected cross-type not_equal result: {left:?} vs {right:?}"
                     );
                 }
             }
@@ -1134,12 +1310,17 @@ fn test_apply_operator_equal() {
             &Value::String("world".to_string())
         ));
 
-        // Cross-type should be false
-        assert!(!apply_operator(
+        // Integers compare numerically across signedness
+        assert!(apply_operator(
             &Operator::Equal,
             &Value::Uint(42),
             &Value::Int(42)
         ));
+        assert!(!apply_operator(
+            &Operator::Equal,
+            &Value::Uint(42),
+            &Value::Int(-42)
+        ));
     }
 
     #[test]
@@ -1168,12 +1349,17 @@ fn test_apply_operator_not_equal() {
             &Value::String("world".to_string())
         ));
 
-        // Cross-type should be true (not equal)
-        assert!(apply_operator(
+        // Integers compare numerically across signedness
+        assert!(!apply_operator(
             &Operator::NotEqual,
             &Value::Uint(42),
             &Value::Int(42)
         ));
+        assert!(apply_operator(
+            &Operator::NotEqual,
+            &Value::Uint(u64::MAX),
+            &Value::Int(-1)
+        ));
     }
 
     #[test]
@@ -1285,7 +1471,7 @@ fn test_apply_operator_all_operators_with_different_values() {
             ),
             (Value::Bytes(vec![1, 2, 3]), Value::Bytes(vec![4, 5, 6])),
             // Different types
-            (Value::Uint(42), Value::Int(42)),
+            (Value::Uint(42), Value::Int(-42)),
             (Value::Uint(42), Value::String("42".to_string())),
             (Value::Int(42), Value::Bytes(vec![42])),
         ];
@@ -1496,16 +1682,25 @@ fn test_apply_operator_edge_cases() {
             &zero_signed,
             &Value::Int(0xFF)
         ));
-        assert!(apply_operator(
+        assert!(!apply_operator(
             &Operator::NotEqual,
             &zero_uint,
             &zero_signed
-        )); // Different types
+        )); // Same numeric value
     }
 
     #[test]
     fn test_apply_operator_all_combinations() {
-        let operators = [Operator::Equal, Operator::NotEqual, Operator::BitwiseAnd];
+        let operators = [
+            Operator::Equal,
+            Operator::NotEqual,
+            Operator::BitwiseAnd,
+            Operator::LessThan,
+            Operator::GreaterThan,
+            Operator::BitwiseXor,
+            Operator::BitwiseNot,
+            Operator::Always,
+        ];
         let values = [
             Value::Uint(42),
             Value::Int(-42),
@@ -1525,6 +1720,11 @@ fn test_apply_operator_all_combinations() {
                         Operator::Equal => apply_equal(left, right),
                         Operator::NotEqual => apply_not_equal(left, right),
                         Operator::BitwiseAnd => apply_bitwise_and(left, right),
+                        Operator::LessThan => apply_less_than(left, right),
+                        Operator::GreaterThan => apply_greater_than(left, right),
+                        Operator::BitwiseXor => apply_bitwise_xor(left, right),
+                        Operator::BitwiseNot => apply_bitwise_not(left, right),
+                        Operator::Always => true,
                     };
 
                     assert_eq!(
@@ -1535,4 +1735,45 @@ fn test_apply_operator_all_combinations() {
             }
         }
     }
+
+    #[test]
+    fn test_apply_ordering_operators() {
+        assert!(apply_less_than(&Value::Uint(1), &Value::Uint(2)));
+        assert!(!apply_less_than(&Value::Uint(2), &Value::Uint(2)));
+        assert!(apply_greater_than(&Value::Int(3), &Value::Int(-3)));
+        assert!(apply_greater_than(&Value::Uint(u64::MAX), &Value::Int(-1)));
+        assert!(apply_less_than(
+            &Value::Bytes(vec![0x01, 0x02]),
+            &Value::Bytes(vec![0x01, 0x03])
+        ));
+        assert!(!apply_less_than(&Value::Uint(1), &Value::Bytes(vec![2])));
+        assert!(!apply_greater_than(
+            &Value::String("b".to_string()),
+            &Value::Uint(0)
+        ));
+    }
+
+    #[test]
+    fn test_apply_bit_test_operators() {
+        // `^`: at least one of the mask bits is clear
+        assert!(apply_bitwise_xor(
+            &Value::Uint(0b1010),
+            &Value::Uint(0b0110)
+        ));
+        assert!(!apply_bitwise_xor(
+            &Value::Uint(0b1110),
+            &Value::Uint(0b0110)
+        ));
+        assert!(apply_bitwise_xor(&Value::Int(0), &Value::Int(1)));
+        assert!(!apply_bitwise_xor(
+            &Value::String("a".to_string()),
+            &Value::Uint(1)
+        ));
+
+        // `~`: equal to the complement of the operand
+        assert!(apply_bitwise_not(&Value::Uint(u64::MAX), &Value::Uint(0)));
+        assert!(apply_bitwise_not(&Value::Int(-2), &Value::Int(1)));
+        assert!(!apply_bitwise_not(&Value::Uint(0), &Value::Uint(0)));
+        assert!(!apply_bitwise_not(&Value::Bytes(vec![0]), &Value::Uint(0)));
+    }
 }
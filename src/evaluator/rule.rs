@@ -0,0 +1,1533 @@
+//! Matching a single rule: offset resolution, typed reads and comparison
+
+use super::fast_path::match_in_place;
+use super::strings::{
+    extend_to_nul, pattern_bytes, read_search_match, read_string_flexible, read_string_window,
+    trim_string_value,
+};
+use super::{EvaluationContext, extension, offset, operators, types};
+use crate::LibmagicError;
+use crate::io::IoError;
+use crate::parser::ast::{MagicRule, OffsetSpec, Operator, TypeKind, Value};
+
+/// The location and value of a successful rule match
+pub(super) struct RuleMatch {
+    pub(super) offset: usize,
+    pub(super) length: usize,
+    pub(super) value: Value,
+}
+
+/// Evaluate a single rule, returning where it matched and the value read
+///
+/// Rules that compare the first bytes of the region for equality, by far the
+/// most common form, are answered by [`match_in_place`] without resolving an
+/// offset; everything else goes through [`match_rule_general`].
+pub(super) fn match_rule(
+    rule: &MagicRule,
+    file: &[u8],
+    base: usize,
+    data_len: usize,
+    context: &EvaluationContext,
+) -> Result<Option<RuleMatch>, LibmagicError> {
+    if matches!(rule.offset, OffsetSpec::Absolute(0)) {
+        let max_search_range = context.config().max_search_range;
+        if let Some(fast) = match_in_place(rule, &rule.typ, &file[base..], 0, max_search_range) {
+            return Ok(fast);
+        }
+    }
+    match_rule_general(rule, file, base, data_len, context)
+}
+
+/// Evaluate a single rule through offset resolution and typed reads
+///
+/// The rule is evaluated in the region of `file` starting at `base`, and the
+/// returned offset is relative to that region. When `soft_bounds` is enabled in
+/// `config`, an offset or read that falls outside the buffer is treated as a
+/// non-match instead of an error. `data_len` is the length of the data the
+/// region is the start of, used for from-end offsets. Inside a `use ^name`
+/// subroutine, integers are read with the opposite of the rule type's byte order,
+/// and extension types are read with the context's registered readers. Types
+/// without a byte order use `native_endianness_override` when it is set.
+fn match_rule_general(
+    rule: &MagicRule,
+    file: &[u8],
+    base: usize,
+    data_len: usize,
+    context: &EvaluationContext,
+) -> Result<Option<RuleMatch>, LibmagicError> {
+    let config = context.config();
+    let buffer = &file[base..];
+
+    // The data length is known without reading, wherever the offset points
+    if matches!(rule.typ, TypeKind::FileSize) {
+        return Ok(match_file_size(rule, data_len));
+    }
+    let resolved;
+    let typ = match config.native_endianness_override {
+        Some(native) => {
+            resolved = rule.typ.with_native_endianness(native);
+            &resolved
+        }
+        None => &rule.typ,
+    };
+    let swapped;
+    let typ = if context.swap_endian() {
+        swapped = typ.byte_swapped();
+        &swapped
+    } else {
+        typ
+    };
+
+    // Step 1: Resolve the offset specification to a position in the region
+    let absolute_offset = match resolve_rule_offset(&rule.offset, file, base, data_len, context) {
+        Ok(resolved) => {
+            log::trace!("offset {:?} resolved to {}", rule.offset, base + resolved);
+            resolved
+        }
+        Err(_) if config.soft_bounds && is_static_offset(&rule.offset) => return Ok(None),
+        Err(LibmagicError::EvaluationError(msg)) => {
+            return Err(LibmagicError::EvaluationError(format!(
+                "Cannot read {}: {msg}",
+                crate::parser::codegen::format_type(typ)
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Whether `default` and `clear` run is decided by their siblings
+    if matches!(rule.typ, TypeKind::Default | TypeKind::Clear) {
+        return Ok(Some(RuleMatch {
+            offset: absolute_offset,
+            length: 0,
+            value: Value::Bytes(Vec::new()),
+        }));
+    }
+
+    // Equality with a byte or literal pattern is decided on bytes borrowed from
+    // the buffer, so the many comparisons that fail allocate nothing
+    if let Some(decided) =
+        match_in_place(rule, typ, buffer, absolute_offset, config.max_search_range)
+    {
+        return Ok(decided);
+    }
+
+    // Step 2: Read and interpret bytes at the resolved offset according to the rule's type.
+    // `string` is anchored at the resolved offset; only `search` scans forward.
+    let read = match (typ, pattern_bytes(&rule.value)) {
+        (TypeKind::String { flags, .. }, Some(pattern)) if flags.relaxes_comparison() => {
+            read_string_flexible(buffer, absolute_offset, pattern, *flags, &rule.value)
+        }
+        (TypeKind::String { .. }, Some(pattern)) => {
+            read_string_window(buffer, absolute_offset, pattern, &rule.value)
+        }
+        (TypeKind::Search { range }, Some(pattern)) => read_search_match(
+            buffer,
+            absolute_offset,
+            (*range).min(config.max_search_range),
+            pattern,
+            &rule.value,
+        ),
+        (TypeKind::Extension { name }, _) => {
+            let reader = context
+                .type_readers()
+                .and_then(|readers| readers.get(name))
+                .ok_or_else(|| {
+                    LibmagicError::EvaluationError(format!(
+                        "No reader registered for extension type '{name}'"
+                    ))
+                })?;
+            read_extension(reader, name, buffer, absolute_offset)
+        }
+        // Strings read for display stop at the configured length limit
+        (TypeKind::String { max_length, flags }, None) => {
+            let limit = config.max_string_length;
+            let limited = TypeKind::String {
+                max_length: Some(max_length.map_or(limit, |max| max.min(limit))),
+                flags: *flags,
+            };
+            read_fixed(buffer, absolute_offset, &limited)
+        }
+        _ => read_fixed(buffer, absolute_offset, typ),
+    };
+    let mut read = match read {
+        Ok(read) => read,
+        Err(types::TypeReadError::BufferOverrun { .. }) if config.soft_bounds => return Ok(None),
+        // The offset is reported within the file, and the type as written in magic(5)
+        Err(e) => {
+            return Err(LibmagicError::EvaluationError(format!(
+                "Cannot read {} at offset {}: {e}",
+                crate::parser::codegen::format_type(typ),
+                base + absolute_offset
+            )));
+        }
+    };
+
+    trim_string_value(typ, &mut read.value);
+
+    // A type mask is applied before comparison, and the masked value is displayed
+    if let Some(mask) = rule.mask {
+        read.value = apply_mask(&read.value, mask, typ.fixed_size());
+    }
+
+    // Step 3: Apply the operator to compare the read value with the expected value
+    let matched = rule_operator_matches(rule, typ, &read.value) != rule.negate;
+    Ok(matched.then(|| extend_to_nul(rule, typ, buffer, read, config.max_string_length)))
+}
+
+/// Compare `data_len` with the value of a `filesize` rule
+fn match_file_size(rule: &MagicRule, data_len: usize) -> Option<RuleMatch> {
+    let value = Value::Uint(u64::try_from(data_len).unwrap_or(u64::MAX));
+    (rule_operator_matches(rule, &rule.typ, &value) != rule.negate).then_some(RuleMatch {
+        offset: 0,
+        length: 0,
+        value,
+    })
+}
+
+/// Resolve a rule offset to a position in the region of `file` starting at `base`
+///
+/// Indirect offsets are resolved against the whole file, and relative offsets
+/// against the context's current offset just past the parent's match, so
+/// their target must not lie before the start of the region. Register offsets
+/// add to the context's register within the region.
+pub(super) fn resolve_rule_offset(
+    spec: &OffsetSpec,
+    file: &[u8],
+    base: usize,
+    data_len: usize,
+    context: &EvaluationContext,
+) -> Result<usize, LibmagicError> {
+    let anchor = context.current_offset();
+    let target = match spec {
+        OffsetSpec::Register(delta) => {
+            let stored = context.register().ok_or_else(|| {
+                LibmagicError::EvaluationError(
+                    "Register offset used before any value was stored".to_string(),
+                )
+            })?;
+            let offset = stored
+                .checked_add(*delta)
+                .filter(|offset| *offset >= 0)
+                .ok_or_else(|| {
+                    LibmagicError::EvaluationError(format!(
+                        "Register offset {stored}{delta:+} is before the start of the region"
+                    ))
+                })?;
+            return offset::resolve_offset_in_file(
+                &OffsetSpec::Absolute(offset),
+                &file[base..],
+                data_len,
+            );
+        }
+        // `use ^name` reads pointers in the swapped byte order, like the values
+        OffsetSpec::Indirect { .. } if context.swap_endian() => {
+            offset::resolve_indirect_offset(&spec.byte_swapped(), file, base)?
+        }
+        OffsetSpec::Indirect { .. } => offset::resolve_indirect_offset(spec, file, base)?,
+        OffsetSpec::Relative(delta) => i64::try_from(anchor)
+            .ok()
+            .and_then(|anchor| anchor.checked_add(*delta))
+            .and_then(|target| usize::try_from(target).ok())
+            .ok_or_else(|| {
+                LibmagicError::EvaluationError(format!(
+                    "Relative offset {delta} from {anchor} is before the start of the file"
+                ))
+            })?,
+        _ => return offset::resolve_offset_in_file(spec, &file[base..], data_len),
+    };
+    target.checked_sub(base).ok_or_else(|| {
+        LibmagicError::EvaluationError(format!(
+            "Indirect offset {target} is before the current region at {base}"
+        ))
+    })
+}
+
+/// The integer a storing rule keeps in the register, if its value is one that fits
+pub(super) fn register_value(value: &Value) -> Option<i64> {
+    match value {
+        Value::Uint(v) => i64::try_from(*v).ok(),
+        Value::Int(v) => Some(*v),
+        Value::Bytes(_) | Value::String(_) | Value::OneOf(_) => None,
+    }
+}
+
+/// Keep only the bits of an integer value that are set in `mask`
+///
+/// A signed value of `size` bytes stays signed at that width, as in libmagic:
+/// `byte&0x80` of `0x80` is -128, so it still compares as negative.
+fn apply_mask(value: &Value, mask: u64, size: Option<usize>) -> Value {
+    match value {
+        Value::Uint(v) => Value::Uint(v & mask),
+        #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+        Value::Int(v) => {
+            let masked = (*v as u64) & mask;
+            match size {
+                Some(size @ 1..8) => {
+                    let unused = 64 - 8 * size;
+                    Value::Int(((masked << unused) as i64) >> unused)
+                }
+                _ => Value::Int(masked as i64),
+            }
+        }
+        Value::Bytes(_) | Value::String(_) | Value::OneOf(_) => value.clone(),
+    }
+}
+
+/// Apply the rule's operator, taking `~` complements at the width of `typ`
+///
+/// `typ` is the type the value was read with, after any byte order override or
+/// `use ^name` swap. A byte-sequence operand of an integer type is compared as
+/// the integer `typ` reads from those bytes, see [`integer_operand`].
+pub(super) fn rule_operator_matches(rule: &MagicRule, typ: &TypeKind, value: &Value) -> bool {
+    let decoded = integer_operand(&rule.value, typ);
+    let operand = decoded.as_ref().unwrap_or(&rule.value);
+    match (&rule.op, typ.fixed_size()) {
+        (Operator::BitwiseNot, Some(size)) if size < 8 => {
+            let mask = (1u64 << (size * 8)) - 1;
+            match (
+                operators::integer_bits(value),
+                operators::integer_bits(operand),
+            ) {
+                (Some(actual), Some(operand)) => actual & mask == !operand & mask,
+                _ => false,
+            }
+        }
+        _ => operators::apply_operator(&rule.op, value, operand),
+    }
+}
+
+/// A byte-sequence operand as the integer an integer `typ` reads from the same bytes
+///
+/// A fixed-width magic number is often written as its bytes (`\x7fELF`) but
+/// read as an integer. Decoding the bytes with the type's width, sign and byte
+/// order makes the rule match exactly when the file holds those bytes. Returns
+/// `None` for other operands and types, and when the lengths differ.
+fn integer_operand(operand: &Value, typ: &TypeKind) -> Option<Value> {
+    let Value::Bytes(bytes) = operand else {
+        return None;
+    };
+    let integer = matches!(
+        typ,
+        TypeKind::Byte { .. }
+            | TypeKind::Short { .. }
+            | TypeKind::Long { .. }
+            | TypeKind::Quad { .. }
+    );
+    if !integer || typ.fixed_size() != Some(bytes.len()) {
+        return None;
+    }
+    types::read_typed_value(bytes, 0, typ).ok()
+}
+
+/// Whether `rule` has an absolute offset at or past the end of a region of `region_len` bytes
+///
+/// Such a rule cannot be read, so it is known not to match without resolving
+/// its offset. `filesize` rules never read at their offset and are not
+/// unreachable.
+pub(super) fn is_unreachable(rule: &MagicRule, region_len: usize) -> bool {
+    match rule.offset {
+        OffsetSpec::Absolute(offset) if !matches!(rule.typ, TypeKind::FileSize) => {
+            usize::try_from(offset).is_ok_and(|offset| offset >= region_len)
+        }
+        _ => false,
+    }
+}
+
+/// Whether an offset only fails to resolve when it lies outside the buffer
+pub(super) const fn is_static_offset(spec: &OffsetSpec) -> bool {
+    matches!(spec, OffsetSpec::Absolute(_) | OffsetSpec::FromEnd(_))
+}
+
+/// Read a fixed-width or NUL-terminated value at `offset`
+pub(super) fn read_fixed(
+    buffer: &[u8],
+    offset: usize,
+    typ: &TypeKind,
+) -> Result<RuleMatch, types::TypeReadError> {
+    let value = types::read_typed_value(buffer, offset, typ)?;
+    let length = match typ {
+        TypeKind::Checksum {
+            range: (start, end),
+            ..
+        } => end - start,
+        _ => typ.fixed_size().unwrap_or(match &value {
+            Value::String(s) => s.len(),
+            Value::Bytes(b) => b.len(),
+            Value::Uint(_) | Value::Int(_) | Value::OneOf(_) => 0,
+        }),
+    };
+
+    Ok(RuleMatch {
+        offset,
+        length,
+        value,
+    })
+}
+
+/// Read an extension type value with its registered reader
+///
+/// Out-of-bounds reads are reported as a buffer overrun so that `soft_bounds`
+/// applies to them like to built-in types.
+fn read_extension(
+    reader: &dyn extension::TypeReader,
+    name: &str,
+    buffer: &[u8],
+    offset: usize,
+) -> Result<RuleMatch, types::TypeReadError> {
+    let value = reader.read(buffer, offset).map_err(|e| match e {
+        IoError::BufferOverrun { buffer_size, .. } => types::TypeReadError::BufferOverrun {
+            offset,
+            buffer_len: buffer_size,
+        },
+        other => types::TypeReadError::ExtensionReadFailed {
+            type_name: name.to_string(),
+            message: other.to_string(),
+        },
+    })?;
+    let length = match &value {
+        Value::Bytes(bytes) => bytes.len(),
+        Value::String(text) => text.len(),
+        Value::Uint(_) | Value::Int(_) | Value::OneOf(_) => 0,
+    };
+
+    Ok(RuleMatch {
+        offset,
+        length,
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EvaluationConfig;
+    use crate::evaluator::{evaluate_rules_with_config, evaluate_single_rule};
+    use crate::parser::ast::Endianness;
+
+    #[test]
+    fn test_evaluate_single_rule_byte_equal_match() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x7f),
+            message: "ELF magic".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_byte_equal_no_match() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x7f),
+            message: "ELF magic".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x50, 0x4b, 0x03, 0x04]; // ZIP magic bytes
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_byte_not_equal_match() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::NotEqual,
+            value: Value::Uint(0x00),
+            message: "Non-zero byte".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46];
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // 0x7f != 0x00
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_byte_not_equal_no_match() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::NotEqual,
+            value: Value::Uint(0x7f),
+            message: "Not ELF magic".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46];
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(!result); // 0x7f == 0x7f, so NotEqual is false
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_negated_byte() {
+        let rule = MagicRule::new(
+            OffsetSpec::Absolute(0),
+            TypeKind::Byte { signed: false },
+            Operator::Equal,
+            Value::Uint(0x7f),
+            "not ELF",
+        )
+        .negated();
+
+        assert!(evaluate_single_rule(&rule, &[0x50, 0x4b]).unwrap());
+        assert!(!evaluate_single_rule(&rule, &[0x7f, 0x45]).unwrap());
+        // The byte is still read, so a missing one is an error, not a match
+        assert!(evaluate_single_rule(&rule, &[]).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_byte_bitwise_and_match() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::BitwiseAnd,
+            value: Value::Uint(0x80), // Check if high bit is set
+            message: "High bit set".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0xff, 0x45, 0x4c, 0x46]; // 0xff has high bit set
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // 0xff & 0x80 = 0x80 (non-zero)
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_byte_bitwise_and_no_match() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::BitwiseAnd,
+            value: Value::Uint(0x80), // Check if high bit is set
+            message: "High bit set".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // 0x7f has high bit clear
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(!result); // 0x7f & 0x80 = 0x00 (zero)
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_long_against_byte_literal() {
+        let long = |endian, value| {
+            MagicRule::new(
+                OffsetSpec::Absolute(0),
+                TypeKind::Long {
+                    endian,
+                    signed: false,
+                },
+                Operator::Equal,
+                value,
+                "ELF",
+            )
+        };
+        let elf = b"\x7fELF\x02\x01";
+        let magic = Value::Bytes(vec![0x7f, 0x45, 0x4c, 0x46]);
+
+        // The little-endian read of the header is the same number as the literal
+        let integer = long(Endianness::Little, Value::Uint(0x464c_457f));
+        assert!(evaluate_single_rule(&integer, elf).unwrap());
+        let bytes = long(Endianness::Little, magic.clone());
+        assert!(evaluate_single_rule(&bytes, elf).unwrap());
+        // The bytes are decoded in the rule's byte order, so they match either way
+        let bytes = long(Endianness::Big, magic);
+        assert!(evaluate_single_rule(&bytes, elf).unwrap());
+
+        assert!(!evaluate_single_rule(&bytes, b"\x7fELG").unwrap());
+        // A literal of another width is not an integer of this type
+        let short_literal = long(Endianness::Little, Value::Bytes(vec![0x7f, 0x45]));
+        assert!(!evaluate_single_rule(&short_literal, elf).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_byte_against_byte_literal() {
+        let rule = MagicRule::new(
+            OffsetSpec::Absolute(0),
+            TypeKind::Byte { signed: true },
+            Operator::Equal,
+            Value::Bytes(vec![0xff]),
+            "all ones",
+        );
+        assert!(evaluate_single_rule(&rule, &[0xff]).unwrap());
+        assert!(!evaluate_single_rule(&rule, &[0x7f]).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_short_little_endian() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Short {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x1234),
+            message: "Little-endian short".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x34, 0x12, 0x56, 0x78]; // 0x1234 in little-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_short_big_endian() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Short {
+                endian: Endianness::Big,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x1234),
+            message: "Big-endian short".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x12, 0x34, 0x56, 0x78]; // 0x1234 in big-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_short_signed_positive() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Short {
+                endian: Endianness::Little,
+                signed: true,
+            },
+            op: Operator::Equal,
+            value: Value::Int(32767), // 0x7fff
+            message: "Positive signed short".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0xff, 0x7f, 0x00, 0x00]; // 0x7fff in little-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_short_signed_negative() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Short {
+                endian: Endianness::Little,
+                signed: true,
+            },
+            op: Operator::Equal,
+            value: Value::Int(-1), // 0xffff as signed
+            message: "Negative signed short".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0xff, 0xff, 0x00, 0x00]; // 0xffff in little-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_long_little_endian() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x1234_5678),
+            message: "Little-endian long".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x78, 0x56, 0x34, 0x12, 0x00]; // 0x12345678 in little-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_long_big_endian() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Big,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x1234_5678),
+            message: "Big-endian long".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x12, 0x34, 0x56, 0x78, 0x00]; // 0x12345678 in big-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_long_signed_positive() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Little,
+                signed: true,
+            },
+            op: Operator::Equal,
+            value: Value::Int(2_147_483_647), // 0x7fffffff
+            message: "Positive signed long".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0xff, 0xff, 0xff, 0x7f, 0x00]; // 0x7fffffff in little-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_long_signed_negative() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Little,
+                signed: true,
+            },
+            op: Operator::Equal,
+            value: Value::Int(-1), // 0xffffffff as signed
+            message: "Negative signed long".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0xff, 0xff, 0xff, 0xff, 0x00]; // 0xffffffff in little-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_different_offsets() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(2), // Read from offset 2
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x4c),
+            message: "ELF class byte".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // buffer[2] == 0x4c
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_negative_offset() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(-1), // Last byte
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x46),
+            message: "Last byte".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // Last byte is 0x46
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_from_end_offset() {
+        let rule = MagicRule {
+            offset: OffsetSpec::FromEnd(-2), // Second to last byte
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x4c),
+            message: "Second to last byte".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // ELF magic bytes
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // buffer[2] == 0x4c (second to last)
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_offset_out_of_bounds() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(10), // Beyond buffer
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x00),
+            message: "Out of bounds".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // Only 4 bytes
+        let result = evaluate_single_rule(&rule, buffer);
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Buffer overrun"));
+            }
+            _ => panic!("Expected EvaluationError"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_short_insufficient_bytes() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(3), // Only 1 byte left
+            typ: TypeKind::Short {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x1234),
+            message: "Insufficient bytes".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // 4 bytes total
+        let result = evaluate_single_rule(&rule, buffer);
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Buffer overrun"));
+            }
+            _ => panic!("Expected EvaluationError"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_long_insufficient_bytes() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(2), // Only 2 bytes left
+            typ: TypeKind::Long {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x1234_5678),
+            message: "Insufficient bytes".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x7f, 0x45, 0x4c, 0x46]; // 4 bytes total
+        let result = evaluate_single_rule(&rule, buffer);
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Buffer overrun"));
+            }
+            _ => panic!("Expected EvaluationError"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_empty_buffer() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x00),
+            message: "Empty buffer".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[]; // Empty buffer
+        let result = evaluate_single_rule(&rule, buffer);
+        assert!(result.is_err());
+
+        match result.unwrap_err() {
+            LibmagicError::EvaluationError(msg) => {
+                assert!(msg.contains("Buffer overrun"));
+            }
+            _ => panic!("Expected EvaluationError"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rules_integer_match_length() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Short {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x457f),
+            message: "Short".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let matches = evaluate_rules_with_config(
+            &[rule],
+            &[0x7f, 0x45, 0x4c, 0x46],
+            EvaluationConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(matches[0].offset, 0);
+        assert_eq!(matches[0].length, 2);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_cross_type_comparison() {
+        // An Int rule value compares numerically with the Uint read from the byte
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Int(42), // Int value vs Uint from byte read
+            message: "Cross-type comparison".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[42]; // Byte value 42
+        assert!(evaluate_single_rule(&rule, buffer).unwrap());
+        assert!(!evaluate_single_rule(&rule, &[43]).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_bitwise_and_with_shorts() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Short {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::BitwiseAnd,
+            value: Value::Uint(0xff00), // Check high byte
+            message: "High byte check".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x34, 0x12]; // 0x1234 in little-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // 0x1234 & 0xff00 = 0x1200 (non-zero)
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_bitwise_and_with_longs() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Big,
+                signed: false,
+            },
+            op: Operator::BitwiseAnd,
+            value: Value::Uint(0xffff_0000), // Check high word
+            message: "High word check".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x12, 0x34, 0x56, 0x78]; // 0x12345678 in big-endian
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // 0x12345678 & 0xffff0000 = 0x12340000 (non-zero)
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_comprehensive_elf_check() {
+        // Test a comprehensive ELF magic check
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0x464c_457f), // ELF magic as 32-bit little-endian
+            message: "ELF executable".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let elf_buffer = &[0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01]; // ELF64 header start
+        let result = evaluate_single_rule(&rule, elf_buffer).unwrap();
+        assert!(result);
+
+        let non_elf_buffer = &[0x50, 0x4b, 0x03, 0x04, 0x14, 0x00]; // ZIP header
+        let result = evaluate_single_rule(&rule, non_elf_buffer).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_native_endianness() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Short {
+                endian: Endianness::Native,
+                signed: false,
+            },
+            op: Operator::NotEqual,
+            value: Value::Uint(0),
+            message: "Non-zero native short".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let buffer = &[0x01, 0x02]; // Non-zero bytes
+        let result = evaluate_single_rule(&rule, buffer).unwrap();
+        assert!(result); // Should be non-zero regardless of endianness
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_all_operators() {
+        let buffer = &[0x42, 0x00, 0xff, 0x80];
+
+        // Test Equal operator
+        let equal_rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0x42),
+            message: "Equal test".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        assert!(evaluate_single_rule(&equal_rule, buffer).unwrap());
+
+        // Test NotEqual operator
+        let not_equal_rule = MagicRule {
+            offset: OffsetSpec::Absolute(1),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::NotEqual,
+            value: Value::Uint(0x42),
+            message: "NotEqual test".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        assert!(evaluate_single_rule(&not_equal_rule, buffer).unwrap()); // 0x00 != 0x42
+
+        // Test BitwiseAnd operator
+        let bitwise_and_rule = MagicRule {
+            offset: OffsetSpec::Absolute(3),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::BitwiseAnd,
+            value: Value::Uint(0x80),
+            message: "BitwiseAnd test".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        assert!(evaluate_single_rule(&bitwise_and_rule, buffer).unwrap()); // 0x80 & 0x80 = 0x80
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_edge_case_values() {
+        // Test with maximum values
+        let max_uint_rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Little,
+                signed: false,
+            },
+            op: Operator::Equal,
+            value: Value::Uint(0xffff_ffff),
+            message: "Max uint32".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let max_buffer = &[0xff, 0xff, 0xff, 0xff];
+        let result = evaluate_single_rule(&max_uint_rule, max_buffer).unwrap();
+        assert!(result);
+
+        // Test with minimum signed value
+        let min_int_rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Long {
+                endian: Endianness::Little,
+                signed: true,
+            },
+            op: Operator::Equal,
+            value: Value::Int(-2_147_483_648), // i32::MIN
+            message: "Min int32".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let min_buffer = &[0x00, 0x00, 0x00, 0x80]; // 0x80000000 in little-endian
+        let result = evaluate_single_rule(&min_int_rule, min_buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_evaluate_single_rule_various_buffer_sizes() {
+        // Test with single byte buffer
+        let single_byte_rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint(0xaa),
+            message: "Single byte".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let single_buffer = &[0xaa];
+        let result = evaluate_single_rule(&single_byte_rule, single_buffer).unwrap();
+        assert!(result);
+
+        // Test with large buffer
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let large_buffer: Vec<u8> = (0..1024).map(|i| (i % 256) as u8).collect();
+        let large_rule = MagicRule {
+            offset: OffsetSpec::Absolute(1000),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Equal,
+            value: Value::Uint((1000 % 256) as u64),
+            message: "Large buffer".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        let result = evaluate_single_rule(&large_rule, &large_buffer).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_bitwise_not_uses_type_width() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::BitwiseNot,
+            value: Value::Uint(0x0f),
+            message: "complement".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        assert!(evaluate_single_rule(&rule, &[0xf0]).unwrap());
+        assert!(!evaluate_single_rule(&rule, &[0x0f]).unwrap());
+
+        let rule = MagicRule {
+            typ: TypeKind::Short {
+                endian: Endianness::Big,
+                signed: true,
+            },
+            value: Value::Int(1),
+            ..rule
+        };
+        assert!(evaluate_single_rule(&rule, &[0xff, 0xfe]).unwrap());
+    }
+
+    #[test]
+    fn test_ordering_operators_on_read_values() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::LessThan,
+            value: Value::Uint(0x20),
+            message: "control".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        assert!(evaluate_single_rule(&rule, &[0x1f]).unwrap());
+        assert!(!evaluate_single_rule(&rule, &[0x20]).unwrap());
+
+        let rule = MagicRule {
+            op: Operator::GreaterThan,
+            ..rule
+        };
+        assert!(evaluate_single_rule(&rule, &[0x7f]).unwrap());
+    }
+
+    #[test]
+    fn test_always_operator_matches_and_reads_value() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(1),
+            typ: TypeKind::Byte { signed: false },
+            op: Operator::Always,
+            value: Value::Uint(0),
+            message: "version %d".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        for byte in [0u8, 5, 0xff] {
+            let matches = evaluate_rules_with_config(
+                std::slice::from_ref(&rule),
+                &[0, byte],
+                EvaluationConfig::default(),
+            )
+            .unwrap();
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].value, Value::Uint(u64::from(byte)));
+            assert_eq!(
+                crate::output::text::format_magic_message(&matches[0].message, &matches[0].value),
+                format!("version {byte}")
+            );
+        }
+
+        // The field still has to be readable
+        assert!(evaluate_single_rule(&rule, &[0]).is_err());
+    }
+
+    #[test]
+    fn test_signed_reads_compare_as_negative_values() {
+        let rules = crate::parser::parse_magic_string(
+            "\
+0 beshort -1 short minus one
+0 beshort 0xffff short all ones
+0 belong -1 long minus one
+0 belong <0 long negative
+0 bequad -1 quad minus one
+0 ubeshort 0xffff unsigned short
+",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+
+        let matches = evaluate_rules_with_config(&rules, &[0xff; 8], config).unwrap();
+        let messages: Vec<&str> = matches.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "short minus one",
+                "short all ones",
+                "long minus one",
+                "long negative",
+                "quad minus one",
+                "unsigned short"
+            ]
+        );
+        assert_eq!(matches[0].value, Value::Int(-1));
+        assert_eq!(matches[5].value, Value::Uint(0xffff));
+    }
+}
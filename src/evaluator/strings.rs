@@ -0,0 +1,524 @@
+//! Reading and matching `string` and `search` values
+
+use super::rule::RuleMatch;
+use super::types;
+use crate::parser::ast::{MagicRule, Operator, StringFlags, TypeKind, Value};
+
+/// Report the whole NUL-terminated string for a matched `string` `<` or `>` rule
+///
+/// Like libmagic, these compare only as many bytes as the pattern has, so
+/// `string >\0` tests for a non-empty string, but the value they report runs
+/// up to the NUL terminator, within `max_length` and `limit`. Relative offsets
+/// of children then continue after the string, however long it is.
+pub(super) fn extend_to_nul(
+    rule: &MagicRule,
+    typ: &TypeKind,
+    buffer: &[u8],
+    read: RuleMatch,
+    limit: usize,
+) -> RuleMatch {
+    let TypeKind::String { max_length, flags } = typ else {
+        return read;
+    };
+    if !matches!(rule.op, Operator::LessThan | Operator::GreaterThan) {
+        return read;
+    }
+    let limit = max_length.map_or(limit, |max| max.min(limit));
+    let Ok(Value::Bytes(bytes)) = types::read_binary_string(buffer, read.offset, Some(limit))
+    else {
+        return read;
+    };
+
+    let mut value = if flags.binary {
+        Value::Bytes(bytes.clone())
+    } else {
+        Value::String(String::from_utf8_lossy(&bytes).into_owned())
+    };
+    trim_string_value(typ, &mut value);
+    RuleMatch {
+        offset: read.offset,
+        length: bytes.len(),
+        value,
+    }
+}
+
+/// Remove surrounding whitespace from a string read by a `string/T` rule
+///
+/// The trimmed string is what gets compared and displayed; the match still
+/// covers the bytes that were read.
+pub(super) fn trim_string_value(typ: &TypeKind, value: &mut Value) {
+    if let (TypeKind::String { flags, .. }, Value::String(s)) = (typ, &*value) {
+        if flags.trim {
+            *value = Value::String(s.trim().to_string());
+        }
+    }
+}
+
+/// The raw bytes of a string or byte-sequence rule value
+pub(crate) fn pattern_bytes(value: &Value) -> Option<&[u8]> {
+    match value {
+        Value::String(s) => Some(s.as_bytes()),
+        Value::Bytes(b) => Some(b),
+        Value::Uint(_) | Value::Int(_) | Value::OneOf(_) => None,
+    }
+}
+
+/// Wrap bytes read from the buffer in the same `Value` variant as the pattern
+///
+/// Bytes that are not valid UTF-8 can never equal a string pattern, so they are
+/// kept as `Value::Bytes` and compare unequal.
+pub(super) fn value_like(pattern: &Value, bytes: &[u8]) -> Value {
+    match pattern {
+        Value::String(_) => String::from_utf8(bytes.to_vec())
+            .map_or_else(|_| Value::Bytes(bytes.to_vec()), Value::String),
+        Value::Uint(_) | Value::Int(_) | Value::Bytes(_) | Value::OneOf(_) => {
+            Value::Bytes(bytes.to_vec())
+        }
+    }
+}
+
+/// Read exactly as many bytes as the pattern at `offset` for a `string` comparison
+pub(super) fn read_string_window(
+    buffer: &[u8],
+    offset: usize,
+    pattern: &[u8],
+    pattern_value: &Value,
+) -> Result<RuleMatch, types::TypeReadError> {
+    let bytes = offset
+        .checked_add(pattern.len())
+        .and_then(|end| buffer.get(offset..end))
+        .ok_or(types::TypeReadError::BufferOverrun {
+            offset,
+            buffer_len: buffer.len(),
+        })?;
+
+    Ok(RuleMatch {
+        offset,
+        length: pattern.len(),
+        value: value_like(pattern_value, bytes),
+    })
+}
+
+/// Match a `string/w`, `string/W`, `string/c` or `string/C` pattern at `offset`
+///
+/// When the pattern matches, the rule's own value is reported so that `=`
+/// succeeds, with the length of the data it covered. Otherwise the exact-width
+/// window is read as for a plain `string`; it cannot equal the pattern, since an
+/// exact match would also have matched with relaxed whitespace or case.
+pub(super) fn read_string_flexible(
+    buffer: &[u8],
+    offset: usize,
+    pattern: &[u8],
+    flags: StringFlags,
+    pattern_value: &Value,
+) -> Result<RuleMatch, types::TypeReadError> {
+    match buffer
+        .get(offset..)
+        .and_then(|data| match_relaxed(data, pattern, flags))
+    {
+        Some(length) => Ok(RuleMatch {
+            offset,
+            length,
+            value: pattern_value.clone(),
+        }),
+        None => read_string_window(buffer, offset, pattern, pattern_value),
+    }
+}
+
+/// Match `pattern` at the start of `data`, returning the number of data bytes covered
+///
+/// With a whitespace flag, each run of blanks (spaces, tabs, line breaks,
+/// vertical tabs and form feeds) in the pattern matches a whole run of blanks
+/// in the data, whatever its length or mix of characters. With
+/// `compact_whitespace` alone the data run must not be empty; with
+/// `optional_blanks` it may be. Blanks in the data where the pattern has none
+/// never match. With `ignore_lower_case`, a lower case ASCII letter in the
+/// pattern also matches its upper case form, and with `ignore_upper_case` the
+/// other way round.
+fn match_relaxed(data: &[u8], pattern: &[u8], flags: StringFlags) -> Option<usize> {
+    let is_blank = |byte: u8| byte.is_ascii_whitespace() || byte == 0x0b;
+    let same_letter = |expected: u8, actual: u8| {
+        expected == actual
+            || (flags.ignore_lower_case
+                && expected.is_ascii_lowercase()
+                && actual == expected.to_ascii_uppercase())
+            || (flags.ignore_upper_case
+                && expected.is_ascii_uppercase()
+                && actual == expected.to_ascii_lowercase())
+    };
+    let (mut p, mut d) = (0, 0);
+
+    while p < pattern.len() {
+        if flags.affects_whitespace() && is_blank(pattern[p]) {
+            while p < pattern.len() && is_blank(pattern[p]) {
+                p += 1;
+            }
+            let run_start = d;
+            while d < data.len() && is_blank(data[d]) {
+                d += 1;
+            }
+            if d == run_start && !flags.optional_blanks {
+                return None;
+            }
+        } else if data
+            .get(d)
+            .is_some_and(|&byte| same_letter(pattern[p], byte))
+        {
+            p += 1;
+            d += 1;
+        } else {
+            return None;
+        }
+    }
+
+    Some(d)
+}
+
+/// Scan for the pattern of a `search` rule, reporting where it was found
+///
+/// When the pattern is not found the match is reported as empty at `offset`, so
+/// `!=` comparisons still succeed.
+pub(super) fn read_search_match(
+    buffer: &[u8],
+    offset: usize,
+    range: usize,
+    pattern: &[u8],
+    pattern_value: &Value,
+) -> Result<RuleMatch, types::TypeReadError> {
+    match types::search_bytes(buffer, offset, range, pattern)? {
+        Some(found) => Ok(RuleMatch {
+            offset: found,
+            length: pattern.len(),
+            value: value_like(pattern_value, &buffer[found..found + pattern.len()]),
+        }),
+        None => Ok(RuleMatch {
+            offset,
+            length: 0,
+            value: value_like(pattern_value, &[]),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::string_rule;
+    use crate::evaluator::{
+        EvaluationContext, evaluate_rules, evaluate_rules_with_config, evaluate_single_rule,
+    };
+    use crate::parser::ast::OffsetSpec;
+    use crate::{EvaluationConfig, LibmagicError};
+
+    #[test]
+    fn test_evaluate_single_rule_string_type() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::String {
+                max_length: None,
+                flags: StringFlags::default(),
+            },
+            op: Operator::Equal,
+            value: Value::String("test".to_string()),
+            message: "String type".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        assert!(evaluate_single_rule(&rule, b"test data").unwrap());
+        assert!(!evaluate_single_rule(&rule, b"text data").unwrap());
+
+        // A buffer too short for the pattern is out of bounds
+        let result = evaluate_single_rule(&rule, b"tes");
+        match result.unwrap_err() {
+            LibmagicError::EvaluationError(msg) => assert!(msg.contains("Buffer overrun")),
+            _ => panic!("Expected EvaluationError for short buffer"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rules_search_reports_match_offset() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Search { range: 64 },
+            op: Operator::Equal,
+            value: Value::String("MARK".to_string()),
+            message: "Marker".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        let mut buffer = vec![0u8; 64];
+        buffer[20..24].copy_from_slice(b"MARK");
+
+        let matches =
+            evaluate_rules_with_config(&[rule], &buffer, EvaluationConfig::default()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].offset, 20);
+        assert_eq!(matches[0].length, 4);
+        assert_eq!(matches[0].value, Value::String("MARK".to_string()));
+    }
+
+    #[test]
+    fn test_evaluate_rules_search_from_range_start() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(8),
+            typ: TypeKind::Search { range: 32 },
+            op: Operator::Equal,
+            value: Value::Bytes(vec![0xde, 0xad]),
+            message: "Marker".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        let mut buffer = vec![0u8; 64];
+        buffer[28] = 0xde;
+        buffer[29] = 0xad;
+
+        let matches =
+            evaluate_rules_with_config(&[rule], &buffer, EvaluationConfig::default()).unwrap();
+        assert_eq!(matches[0].offset, 28);
+        assert_eq!(matches[0].length, 2);
+    }
+
+    #[test]
+    fn test_evaluate_rules_search_limited_by_max_search_range() {
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::Search { range: 64 },
+            op: Operator::Equal,
+            value: Value::String("MARK".to_string()),
+            message: "Marker".to_string(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        let mut buffer = vec![0u8; 64];
+        buffer[20..24].copy_from_slice(b"MARK");
+
+        let config = EvaluationConfig {
+            max_search_range: 16,
+            ..Default::default()
+        };
+        let matches = evaluate_rules_with_config(&[rule], &buffer, config).unwrap();
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_rules_search_range_clamped_to_small_buffer() {
+        let rules = crate::parser::parse_magic_string("0 search/1073741824 MARK Marker\n").unwrap();
+        let config = EvaluationConfig {
+            max_search_range: 1024 * 1024 * 1024,
+            ..Default::default()
+        };
+
+        // The window ends at the buffer, so the huge range neither overruns nor errors
+        let mut buffer = [0u8; 16];
+        buffer[10..14].copy_from_slice(b"MARK");
+        let matches = evaluate_rules_with_config(&rules, &buffer, config.clone()).unwrap();
+        assert_eq!(matches[0].offset, 10);
+
+        let cut_short = &buffer[..12];
+        let matches = evaluate_rules_with_config(&rules, cut_short, config).unwrap();
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_string_whitespace_flags() {
+        let rule = |line: &str| crate::parser::parse_rule_line(line).unwrap().1;
+        let plain = rule("0 string hello\\ world greeting");
+        let compact = rule("0 string/W hello\\ world greeting");
+        let optional = rule("0 string/w hello\\ world greeting");
+
+        assert!(evaluate_single_rule(&plain, b"hello world").unwrap());
+        assert!(!evaluate_single_rule(&plain, b"hello   world").unwrap());
+
+        // `W` collapses runs of blanks, including tabs and line breaks, but needs one
+        assert!(evaluate_single_rule(&compact, b"hello   world").unwrap());
+        assert!(evaluate_single_rule(&compact, b"hello \t\r\nworld!").unwrap());
+        assert!(!evaluate_single_rule(&compact, b"helloworld!!").unwrap());
+        assert!(!evaluate_single_rule(&compact, b"hello   w0rld").unwrap());
+
+        // `w` lets the pattern's blanks match no blanks at all
+        assert!(evaluate_single_rule(&optional, b"helloworld").unwrap());
+        assert!(evaluate_single_rule(&optional, b"hello\t\tworld").unwrap());
+        assert!(!evaluate_single_rule(&optional, b"hel lo world").unwrap());
+
+        // The usual shell script rule, with and without the blank
+        let script = rule("0 string/wt #!\\ /bin/sh shell script");
+        assert!(evaluate_single_rule(&script, b"#!/bin/sh\n").unwrap());
+        assert!(evaluate_single_rule(&script, b"#! /bin/sh\n").unwrap());
+
+        // `!=` is the exact opposite of the relaxed match
+        let mut not_equal = compact.clone();
+        not_equal.op = Operator::NotEqual;
+        assert!(!evaluate_single_rule(&not_equal, b"hello   world").unwrap());
+        assert!(evaluate_single_rule(&not_equal, b"hello   w0rld").unwrap());
+    }
+
+    #[test]
+    fn test_string_case_flags() {
+        let rule = |line: &str| crate::parser::parse_rule_line(line).unwrap().1;
+        let plain = rule("0 string =<html> HTML document");
+        let lower = rule("0 string/c =<html> HTML document");
+        let upper = rule("0 string/C =<HTML> HTML document");
+
+        assert!(!evaluate_single_rule(&plain, b"<HTML>").unwrap());
+
+        // `c` lets lower case in the pattern match either case
+        assert!(evaluate_single_rule(&lower, b"<html>").unwrap());
+        assert!(evaluate_single_rule(&lower, b"<HtMl>").unwrap());
+        assert!(!evaluate_single_rule(&lower, b"<htm!>").unwrap());
+
+        // `C` does the same for upper case, but lower case stays exact
+        assert!(evaluate_single_rule(&upper, b"<html>").unwrap());
+        let mixed = rule("0 string/C Ab text");
+        assert!(evaluate_single_rule(&mixed, b"ab").unwrap());
+        assert!(!evaluate_single_rule(&mixed, b"AB").unwrap());
+        let mixed = rule("0 string/c Ab text");
+        assert!(evaluate_single_rule(&mixed, b"AB").unwrap());
+        assert!(!evaluate_single_rule(&mixed, b"ab").unwrap());
+
+        // Case and whitespace flags combine
+        let both = rule("0 string/cW =<!doctype\\ html> HTML document");
+        assert!(evaluate_single_rule(&both, b"<!DOCTYPE   HTML>").unwrap());
+    }
+
+    #[test]
+    fn test_string_trim_and_binary_flags() {
+        let read = |source: &str, data: &[u8]| {
+            let rules = crate::parser::parse_magic_string(source).unwrap();
+            let mut context = EvaluationContext::new(EvaluationConfig::default());
+            let matches = evaluate_rules(&rules, data, &mut context).unwrap();
+            (matches[0].value.clone(), matches[0].length)
+        };
+
+        // `T` drops the padding, but the match still covers the bytes read
+        let (value, length) = read("0 string/T x title %s\n", b"  Report   \0");
+        assert_eq!(value, Value::String("Report".to_string()));
+        assert_eq!(length, 11);
+        let (value, _) = read("0 string x title %s\n", b"  Report   \0");
+        assert_eq!(value, Value::String("  Report   ".to_string()));
+
+        // `b` keeps bytes that are not text instead of decoding them lossily
+        let (value, _) = read("0 string/b x raw\n", b"\xff\xfeA\0");
+        assert_eq!(value, Value::Bytes(vec![0xff, 0xfe, b'A']));
+        let (value, _) = read("0 string x raw\n", b"\xff\xfeA\0");
+        assert_eq!(value, Value::String("\u{fffd}\u{fffd}A".to_string()));
+    }
+
+    #[test]
+    fn test_string_reads_nul_terminated_name() {
+        let rules = crate::parser::parse_magic_string(
+            "0 string >\\0 name %s\n>&1 string v1\\0 version 1\n",
+        )
+        .unwrap();
+        let evaluate = |data: &[u8]| {
+            let mut context = EvaluationContext::new(EvaluationConfig::default());
+            evaluate_rules(&rules, data, &mut context).unwrap()
+        };
+
+        // The name is reported up to its NUL, and the child reads after it
+        let matches = evaluate(b"libexample\0v1\0");
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].value, Value::String("libexample".to_string()));
+        assert_eq!(matches[0].length, 10);
+        assert_eq!(matches[1].offset, 11);
+        assert_eq!(evaluate(b"lib\0v1\0").len(), 2);
+        // An empty name is not greater than the terminator
+        assert!(evaluate(b"\0v1\0").is_empty());
+
+        // A pattern ending in NUL only matches the whole name
+        let exact = crate::parser::parse_magic_string("0 string libfoo\\0 exact\n").unwrap();
+        let mut context = EvaluationContext::new(EvaluationConfig::default());
+        assert_eq!(
+            evaluate_rules(&exact, b"libfoo\0", &mut context)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            evaluate_rules(&exact, b"libfoobar\0", &mut context)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_match_relaxed_whitespace_edges() {
+        let compact = StringFlags::from_letters("W");
+        let optional = StringFlags::from_letters("w");
+
+        // The reported length covers the whole blank run in the data
+        assert_eq!(match_relaxed(b"a   b", b"a b", compact), Some(5));
+        // Leading and trailing pattern blanks consume the data's blanks there
+        assert_eq!(match_relaxed(b"\t\tab  ", b" ab ", compact), Some(6));
+        assert_eq!(match_relaxed(b"ab", b" ab ", compact), None);
+        assert_eq!(match_relaxed(b"ab", b" ab ", optional), Some(2));
+        // Data that ends inside the pattern does not match
+        assert_eq!(match_relaxed(b"a ", b"a b", compact), None);
+        assert_eq!(match_relaxed(b"", b"", compact), Some(0));
+    }
+
+    #[test]
+    fn test_string_is_anchored_but_search_scans() {
+        let buffer = b"xfoo";
+
+        let string = string_rule(0, "foo", "string foo", 0);
+        assert!(!evaluate_single_rule(&string, buffer).unwrap());
+
+        let search = MagicRule {
+            typ: TypeKind::Search { range: 16 },
+            message: "search foo".to_string(),
+            ..string.clone()
+        };
+        assert!(evaluate_single_rule(&search, buffer).unwrap());
+
+        // The string rule still matches when the pattern is exactly at its offset
+        let anchored = string_rule(1, "foo", "string foo", 0);
+        assert!(evaluate_single_rule(&anchored, buffer).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_rules_string_read_stops_at_max_string_length() {
+        let rules = crate::parser::parse_magic_string("0 string x title %s\n").unwrap();
+        let config = EvaluationConfig {
+            max_string_length: 4,
+            ..EvaluationConfig::default()
+        };
+        let mut context = EvaluationContext::new(config);
+
+        // The limit falls after the first byte of the two-byte "é"
+        let matches = evaluate_rules(&rules, "café au lait".as_bytes(), &mut context).unwrap();
+        assert_eq!(matches[0].value, Value::String("caf".to_string()));
+        assert_eq!(matches[0].length, 3);
+    }
+}
@@ -0,0 +1,87 @@
+//! Truncation detection for formats with an end-of-file trailer
+//!
+//! ZIP archives end with an end of central directory record and PDF documents
+//! with an `%%EOF` marker, so a file that starts like one of them but has lost
+//! its trailer was most likely cut short. The checks are magic rules: each
+//! top-level rule recognizes a format, and its children look for the trailer
+//! at an offset from the end of the file. A file shorter than the area they
+//! search is searched from its start instead.
+
+use std::sync::LazyLock;
+
+use crate::EvaluationConfig;
+use crate::evaluator::evaluate_rules_with_config;
+use crate::parser::{ast::MagicRule, parse_magic_string};
+
+/// Formats with a trailer, and where to look for it
+pub const TRAILER_MAGIC: &str = r"# The record is followed by a comment of at most 65535 bytes
+0	string	PK\x03\x04	ZIP
+>-65557	search/65557	PK\x05\x06	end of central directory
+>0	filesize	<65557
+>>0	search/65557	PK\x05\x06	end of central directory
+
+# Writers may leave a little whitespace or padding after the marker
+0	string	%PDF-	PDF
+>-1024	search/1024	%%EOF	end of file marker
+>0	filesize	<1024
+>>0	search/1024	%%EOF	end of file marker
+";
+
+/// Longest search in [`TRAILER_MAGIC`], so the configured limit never cuts it short
+const MAX_TRAILER_SEARCH: usize = 65557;
+
+static TRAILER_RULES: LazyLock<Vec<MagicRule>> =
+    LazyLock::new(|| parse_magic_string(TRAILER_MAGIC).unwrap_or_default());
+
+/// Whether `buffer` holds a format with a known trailer that is missing
+///
+/// `buffer` must be the whole file; a prefix of a complete file looks
+/// truncated. Files of other formats are never reported.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::trailer::is_truncated;
+///
+/// assert!(!is_truncated(b"%PDF-1.7\n...\n%%EOF\n"));
+/// assert!(is_truncated(b"%PDF-1.7\n..."));
+/// assert!(!is_truncated(b"plain text"));
+/// ```
+#[must_use]
+pub fn is_truncated(buffer: &[u8]) -> bool {
+    let config = EvaluationConfig {
+        soft_bounds: true,
+        max_search_range: MAX_TRAILER_SEARCH,
+        ..EvaluationConfig::default()
+    };
+    // A trailer rule that fails to evaluate cannot vouch for the file either
+    let matches = evaluate_rules_with_config(&TRAILER_RULES, buffer, config).unwrap_or_default();
+    let format_found = matches.iter().any(|m| m.level == 0);
+    let trailer_found = matches.iter().any(|m| m.level > 0 && !m.message.is_empty());
+    format_found && !trailer_found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailer_magic_parses() {
+        let rules = parse_magic_string(TRAILER_MAGIC).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(TRAILER_RULES.len(), rules.len());
+    }
+
+    #[test]
+    fn test_is_truncated_finds_pdf_marker_near_the_end() {
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.resize(4096, b' ');
+        pdf.extend_from_slice(b"%%EOF\r\n");
+        assert!(!is_truncated(&pdf));
+
+        // An earlier marker from an incremental update does not count
+        let mut updated = b"%PDF-1.4\n%%EOF\n".to_vec();
+        updated.resize(4096, b' ');
+        assert!(is_truncated(&updated));
+    }
+}
@@ -3,12 +3,12 @@
 //! This module provides functions for safely reading different data types from byte buffers
 //! with proper bounds checking and error handling.
 
-use crate::parser::ast::{Endianness, TypeKind, Value};
+use crate::parser::ast::{ChecksumAlgo, Endianness, TypeKind, Value};
 use byteorder::{BigEndian, ByteOrder, LittleEndian, NativeEndian};
 use thiserror::Error;
 
 /// Errors that can occur during type reading operations
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum TypeReadError {
     /// Buffer access beyond available data
     #[error(
@@ -26,6 +26,14 @@ pub enum TypeReadError {
         /// The name of the unsupported type
         type_name: String,
     },
+    /// A custom reader for an extension type failed
+    #[error("Reader for extension type '{type_name}' failed: {message}")]
+    ExtensionReadFailed {
+        /// The name of the extension type
+        type_name: String,
+        /// The reader's error message
+        message: String,
+    },
 }
 
 /// Safely reads a single byte from the buffer at the specified offset
@@ -214,6 +222,319 @@ pub fn read_long(
     }
 }
 
+/// Safely reads a 64-bit integer from the buffer at the specified offset
+///
+/// # Arguments
+///
+/// * `buffer` - The byte buffer to read from
+/// * `offset` - The offset position to read the 64-bit value from
+/// * `endian` - The byte order to use for interpretation
+/// * `signed` - Whether to interpret the value as signed or unsigned
+///
+/// # Returns
+///
+/// Returns `Ok(Value::Uint(value))` for unsigned values or `Ok(Value::Int(value))` for signed values
+/// if the read is successful, or `Err(TypeReadError::BufferOverrun)` if there are insufficient bytes.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::evaluator::types::read_quad;
+/// use libmagic_rs::parser::ast::{Endianness, Value};
+///
+/// let buffer = &[0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01];
+///
+/// // Read unsigned little-endian quad (0x0123456789abcdef)
+/// let result = read_quad(buffer, 0, Endianness::Little, false).unwrap();
+/// assert_eq!(result, Value::Uint(0x0123_4567_89ab_cdef));
+///
+/// // Read signed big-endian quad (0xefcdab8967452301 is negative)
+/// let result = read_quad(buffer, 0, Endianness::Big, true).unwrap();
+/// assert_eq!(result, Value::Int(0xefcd_ab89_6745_2301_u64 as i64));
+/// ```
+///
+/// # Errors
+///
+/// Returns `TypeReadError::BufferOverrun` if there are fewer than 8 bytes available
+/// starting at the specified offset.
+pub fn read_quad(
+    buffer: &[u8],
+    offset: usize,
+    endian: Endianness,
+    signed: bool,
+) -> Result<Value, TypeReadError> {
+    let bytes = offset
+        .checked_add(8)
+        .and_then(|end| buffer.get(offset..end))
+        .ok_or(TypeReadError::BufferOverrun {
+            offset,
+            buffer_len: buffer.len(),
+        })?;
+
+    let value = match endian {
+        Endianness::Little => LittleEndian::read_u64(bytes),
+        Endianness::Big => BigEndian::read_u64(bytes),
+        Endianness::Native => NativeEndian::read_u64(bytes),
+    };
+
+    if signed {
+        #[allow(clippy::cast_possible_wrap)]
+        Ok(Value::Int(value as i64))
+    } else {
+        Ok(Value::Uint(value))
+    }
+}
+
+/// Safely reads a NUL-terminated string from the buffer at the specified offset
+///
+/// Reading stops at the first NUL byte, after `max_length` bytes, or at the end of
+/// the buffer, whichever comes first. A character cut off by `max_length` is
+/// dropped rather than returned partially, and other invalid UTF-8 sequences are
+/// replaced with U+FFFD.
+///
+/// # Arguments
+///
+/// * `buffer` - The byte buffer to read from
+/// * `offset` - The offset position to start reading from
+/// * `max_length` - Optional maximum number of bytes to read
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::evaluator::types::read_string;
+/// use libmagic_rs::parser::ast::Value;
+///
+/// let buffer = b"Hello\0World";
+///
+/// assert_eq!(read_string(buffer, 0, None).unwrap(), Value::String("Hello".to_string()));
+/// assert_eq!(read_string(buffer, 6, None).unwrap(), Value::String("World".to_string()));
+/// assert_eq!(read_string(buffer, 0, Some(3)).unwrap(), Value::String("Hel".to_string()));
+///
+/// // The limit falls inside the two-byte "é"
+/// assert_eq!(read_string("héllo".as_bytes(), 0, Some(2)).unwrap(), Value::String("h".to_string()));
+/// ```
+///
+/// # Errors
+///
+/// Returns `TypeReadError::BufferOverrun` if `offset` is at or beyond the end of the buffer.
+pub fn read_string(
+    buffer: &[u8],
+    offset: usize,
+    max_length: Option<usize>,
+) -> Result<Value, TypeReadError> {
+    let (bytes, cut_short) = string_bytes(buffer, offset, max_length)?;
+    let bytes = if cut_short {
+        trim_partial_char(bytes)
+    } else {
+        bytes
+    };
+
+    Ok(Value::String(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+/// Reads a NUL-terminated string as raw bytes, for `string/b`
+///
+/// Reading stops where it does for [`read_string`], but the bytes are not
+/// decoded, so they compare and display exactly as they are in the buffer.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::evaluator::types::read_binary_string;
+/// use libmagic_rs::parser::ast::Value;
+///
+/// assert_eq!(
+///     read_binary_string(b"\xff\xfeA\0rest", 0, None).unwrap(),
+///     Value::Bytes(vec![0xff, 0xfe, b'A'])
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Returns `TypeReadError::BufferOverrun` if `offset` is at or beyond the end of the buffer.
+pub fn read_binary_string(
+    buffer: &[u8],
+    offset: usize,
+    max_length: Option<usize>,
+) -> Result<Value, TypeReadError> {
+    let (bytes, _) = string_bytes(buffer, offset, max_length)?;
+    Ok(Value::Bytes(bytes.to_vec()))
+}
+
+/// The bytes of the string at `offset`, and whether `max_length` cut it short
+fn string_bytes(
+    buffer: &[u8],
+    offset: usize,
+    max_length: Option<usize>,
+) -> Result<(&[u8], bool), TypeReadError> {
+    let remaining = buffer.get(offset..).filter(|rest| !rest.is_empty()).ok_or(
+        TypeReadError::BufferOverrun {
+            offset,
+            buffer_len: buffer.len(),
+        },
+    )?;
+
+    let limit = max_length.map_or(remaining.len(), |max| max.min(remaining.len()));
+    let limited = &remaining[..limit];
+    Ok(match limited.iter().position(|&b| b == 0) {
+        Some(nul) => (&limited[..nul], false),
+        None => (limited, limit < remaining.len()),
+    })
+}
+
+/// Drop a multi-byte UTF-8 character that is cut off at the end of `bytes`
+fn trim_partial_char(bytes: &[u8]) -> &[u8] {
+    // A UTF-8 character is at most 4 bytes, so its lead byte is in the last 3
+    let tail_start = bytes.len().saturating_sub(3);
+    for start in (tail_start..bytes.len()).rev() {
+        let lead = bytes[start];
+        if lead & 0xc0 == 0x80 {
+            continue;
+        }
+        let needed = match lead {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        return if bytes.len() - start < needed {
+            &bytes[..start]
+        } else {
+            bytes
+        };
+    }
+    bytes
+}
+
+/// Searches for a byte pattern starting at the specified offset
+///
+/// The pattern is tried at each of the `range` positions beginning at `offset`,
+/// and the offset of the first occurrence is returned. Positions near the end of
+/// the buffer are only tried if the whole pattern still fits.
+///
+/// # Arguments
+///
+/// * `buffer` - The byte buffer to search
+/// * `offset` - The first position to try
+/// * `range` - The number of starting positions to try
+/// * `pattern` - The bytes to look for
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::evaluator::types::search_bytes;
+///
+/// let buffer = b"....MARK....";
+///
+/// assert_eq!(search_bytes(buffer, 0, 16, b"MARK").unwrap(), Some(4));
+/// assert_eq!(search_bytes(buffer, 0, 4, b"MARK").unwrap(), None);
+/// ```
+///
+/// # Errors
+///
+/// Returns `TypeReadError::BufferOverrun` if `offset` is beyond the end of the buffer.
+pub fn search_bytes(
+    buffer: &[u8],
+    offset: usize,
+    range: usize,
+    pattern: &[u8],
+) -> Result<Option<usize>, TypeReadError> {
+    let remaining = buffer.get(offset..).ok_or(TypeReadError::BufferOverrun {
+        offset,
+        buffer_len: buffer.len(),
+    })?;
+
+    if pattern.is_empty() {
+        return Ok(Some(offset));
+    }
+
+    let window_len = range
+        .saturating_sub(1)
+        .saturating_add(pattern.len())
+        .min(remaining.len());
+
+    Ok(remaining[..window_len]
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|position| offset + position))
+}
+
+/// Computes a checksum over a byte range measured from `offset`
+///
+/// The range covers the bytes from `offset + start` up to, but not including,
+/// `offset + end`, and must lie entirely within the buffer.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::evaluator::types::read_checksum;
+/// use libmagic_rs::parser::ast::{ChecksumAlgo, Value};
+///
+/// let buffer = b"--123456789--";
+///
+/// assert_eq!(
+///     read_checksum(buffer, 2, ChecksumAlgo::Crc32, (0, 9)).unwrap(),
+///     Value::Uint(0xcbf4_3926)
+/// );
+/// assert_eq!(
+///     read_checksum(buffer, 0, ChecksumAlgo::Adler32, (2, 11)).unwrap(),
+///     Value::Uint(0x091e_01de)
+/// );
+/// assert!(read_checksum(buffer, 2, ChecksumAlgo::Crc32, (0, 12)).is_err());
+/// ```
+///
+/// # Errors
+///
+/// Returns `TypeReadError::BufferOverrun` if any byte of the range is beyond the
+/// end of the buffer.
+pub fn read_checksum(
+    buffer: &[u8],
+    offset: usize,
+    algo: ChecksumAlgo,
+    (start, end): (usize, usize),
+) -> Result<Value, TypeReadError> {
+    let overrun = TypeReadError::BufferOverrun {
+        offset: offset.saturating_add(start),
+        buffer_len: buffer.len(),
+    };
+    let first = offset.checked_add(start).ok_or_else(|| overrun.clone())?;
+    let last = offset.checked_add(end).ok_or_else(|| overrun.clone())?;
+    let bytes = buffer.get(first..last).ok_or(overrun)?;
+
+    let checksum = match algo {
+        ChecksumAlgo::Crc32 => crc32(bytes),
+        ChecksumAlgo::Adler32 => adler32(bytes),
+    };
+    Ok(Value::Uint(u64::from(checksum)))
+}
+
+/// CRC-32 with the reflected IEEE polynomial, as used by ZIP and PNG
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Adler-32, as used by zlib
+fn adler32(bytes: &[u8]) -> u32 {
+    const MODULUS: u32 = 65_521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % MODULUS;
+        b = (b + a) % MODULUS;
+    }
+    (b << 16) | a
+}
+
 /// Reads and interprets bytes according to the specified `TypeKind`
 ///
 /// This is the main interface for type interpretation that dispatches to the appropriate
@@ -238,9 +559,13 @@ pub fn read_long(
 /// let buffer = &[0x7f, 0x45, 0x4c, 0x46, 0x34, 0x12];
 ///
 /// // Read a byte
-/// let byte_result = read_typed_value(buffer, 0, &TypeKind::Byte).unwrap();
+/// let byte_result = read_typed_value(buffer, 0, &TypeKind::Byte { signed: false }).unwrap();
 /// assert_eq!(byte_result, Value::Uint(0x7f));
 ///
+/// // A signed byte is sign-extended
+/// let signed_byte = read_typed_value(&[0xff], 0, &TypeKind::Byte { signed: true }).unwrap();
+/// assert_eq!(signed_byte, Value::Int(-1));
+///
 /// // Read a little-endian short
 /// let short_type = TypeKind::Short {
 ///     endian: Endianness::Little,
@@ -253,7 +578,8 @@ pub fn read_long(
 /// # Errors
 ///
 /// Returns `TypeReadError::BufferOverrun` if there are insufficient bytes for the requested type,
-/// or `TypeReadError::UnsupportedType` for type variants that are not yet implemented.
+/// or `TypeReadError::UnsupportedType` for `TypeKind::Search`, which can only be evaluated
+/// against a pattern (see [`search_bytes`]).
 pub fn read_typed_value(
     buffer: &[u8],
     offset: usize,
@@ -267,22 +593,44 @@ pub fn read_typed_value(
     // - Consider adding support for partial reads when buffer is truncated
 
     match type_kind {
-        TypeKind::Byte => read_byte(buffer, offset),
+        TypeKind::Byte { signed } => {
+            let value = read_byte(buffer, offset)?;
+            match value {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+                Value::Uint(byte) if *signed => Ok(Value::Int(i64::from(byte as i8))),
+                other => Ok(other),
+            }
+        }
         TypeKind::Short { endian, signed } => read_short(buffer, offset, *endian, *signed),
         TypeKind::Long { endian, signed } => read_long(buffer, offset, *endian, *signed),
-        TypeKind::String { max_length: _ } => {
-            // TODO: Implement string type reading in task 12.2
-            // For now, return an error for unsupported string type
-            Err(TypeReadError::UnsupportedType {
-                type_name: "String".to_string(),
-            })
+        TypeKind::Quad { endian, signed } => read_quad(buffer, offset, *endian, *signed),
+        TypeKind::String { max_length, flags } if flags.binary => {
+            read_binary_string(buffer, offset, *max_length)
         }
+        TypeKind::String { max_length, .. } => read_string(buffer, offset, *max_length),
+        TypeKind::Checksum { algo, range } => read_checksum(buffer, offset, *algo, *range),
+        // A search needs a pattern to look for, so it cannot be read on its own
+        TypeKind::Search { .. } => Err(TypeReadError::UnsupportedType {
+            type_name: "Search".to_string(),
+        }),
+        // Control types direct evaluation rather than reading a value, the file
+        // size is not in the buffer, and extension types need their registered reader
+        TypeKind::Indirect
+        | TypeKind::Default
+        | TypeKind::Clear
+        | TypeKind::Name { .. }
+        | TypeKind::Use { .. }
+        | TypeKind::FileSize
+        | TypeKind::Extension { .. } => Err(TypeReadError::UnsupportedType {
+            type_name: format!("{type_kind:?}"),
+        }),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::ast::StringFlags;
 
     #[test]
     fn test_read_byte_success() {
@@ -756,6 +1104,59 @@ fn test_read_long_extreme_values() {
         assert_eq!(zero_result, Value::Uint(0));
     }
 
+    #[test]
+    fn test_read_quad_endianness() {
+        let buffer = &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+        let little = read_quad(buffer, 0, Endianness::Little, false).unwrap();
+        let big = read_quad(buffer, 0, Endianness::Big, false).unwrap();
+
+        assert_eq!(little, Value::Uint(0x0807_0605_0403_0201));
+        assert_eq!(big, Value::Uint(0x0102_0304_0506_0708));
+    }
+
+    #[test]
+    fn test_read_quad_signed() {
+        let buffer = &[0xff; 8];
+
+        assert_eq!(
+            read_quad(buffer, 0, Endianness::Little, true).unwrap(),
+            Value::Int(-1)
+        );
+        assert_eq!(
+            read_quad(buffer, 0, Endianness::Little, false).unwrap(),
+            Value::Uint(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_read_quad_buffer_overrun() {
+        let buffer = &[0x00; 7];
+
+        assert_eq!(
+            read_quad(buffer, 0, Endianness::Little, false).unwrap_err(),
+            TypeReadError::BufferOverrun {
+                offset: 0,
+                buffer_len: 7
+            }
+        );
+        assert!(read_quad(&[0x00; 8], usize::MAX, Endianness::Big, false).is_err());
+    }
+
+    #[test]
+    fn test_read_typed_value_quad() {
+        let buffer = &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a];
+        let quad_type = TypeKind::Quad {
+            endian: Endianness::Big,
+            signed: false,
+        };
+
+        assert_eq!(
+            read_typed_value(buffer, 0, &quad_type).unwrap(),
+            Value::Uint(42)
+        );
+    }
+
     #[test]
     fn test_read_short_extreme_values() {
         // Test maximum unsigned 16-bit value
@@ -833,7 +1234,7 @@ fn test_unsupported_type_error_equality() {
     #[test]
     fn test_read_typed_value_byte() {
         let buffer = &[0x7f, 0x45, 0x4c, 0x46];
-        let type_kind = TypeKind::Byte;
+        let type_kind = TypeKind::Byte { signed: false };
 
         let result = read_typed_value(buffer, 0, &type_kind).unwrap();
         assert_eq!(result, Value::Uint(0x7f));
@@ -940,37 +1341,146 @@ fn test_read_typed_value_native_endian() {
     }
 
     #[test]
-    fn test_read_typed_value_string_unsupported() {
+    fn test_read_typed_value_string() {
         let buffer = &[0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x00]; // "Hello\0"
-        let type_kind = TypeKind::String { max_length: None };
+        let type_kind = TypeKind::String {
+            max_length: None,
+            flags: StringFlags::default(),
+        };
 
-        let result = read_typed_value(buffer, 0, &type_kind);
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err(),
-            TypeReadError::UnsupportedType {
-                type_name: "String".to_string()
-            }
+            read_typed_value(buffer, 0, &type_kind).unwrap(),
+            Value::String("Hello".to_string())
         );
     }
 
     #[test]
-    fn test_read_typed_value_string_with_max_length_unsupported() {
+    fn test_read_typed_value_string_with_max_length() {
         let buffer = &[0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x00];
         let type_kind = TypeKind::String {
-            max_length: Some(10),
+            max_length: Some(2),
+            flags: StringFlags::default(),
         };
 
-        let result = read_typed_value(buffer, 0, &type_kind);
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err(),
+            read_typed_value(buffer, 0, &type_kind).unwrap(),
+            Value::String("He".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_typed_value_search_unsupported() {
+        let type_kind = TypeKind::Search { range: 16 };
+
+        assert_eq!(
+            read_typed_value(b"Hello", 0, &type_kind).unwrap_err(),
             TypeReadError::UnsupportedType {
-                type_name: "String".to_string()
+                type_name: "Search".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_string_unterminated_and_invalid_utf8() {
+        // Runs to the end of the buffer without a NUL
+        assert_eq!(
+            read_string(b"abc", 1, None).unwrap(),
+            Value::String("bc".to_string())
+        );
+        // Invalid UTF-8 is replaced rather than rejected
+        assert_eq!(
+            read_string(&[0x61, 0xff, 0x62], 0, None).unwrap(),
+            Value::String("a\u{fffd}b".to_string())
+        );
+        assert_eq!(
+            read_string(b"abc", 3, None).unwrap_err(),
+            TypeReadError::BufferOverrun {
+                offset: 3,
+                buffer_len: 3
             }
         );
     }
 
+    #[test]
+    fn test_read_string_limit_inside_character() {
+        // "a€b": the euro sign is 3 bytes, so limits 2 and 3 cut it off
+        let buffer = "a\u{20ac}b".as_bytes();
+        for (limit, expected) in [
+            (1, "a"),
+            (2, "a"),
+            (3, "a"),
+            (4, "a\u{20ac}"),
+            (5, "a\u{20ac}b"),
+        ] {
+            assert_eq!(
+                read_string(buffer, 0, Some(limit)).unwrap(),
+                Value::String(expected.to_string()),
+                "limit {limit}"
+            );
+        }
+        // A truncated four-byte character at the limit, and a stray continuation byte
+        assert_eq!(
+            read_string(&[0x61, 0xf0, 0x9f, 0x98, 0x80], 0, Some(4)).unwrap(),
+            Value::String("a".to_string())
+        );
+        assert_eq!(
+            read_string(&[0x61, 0x80, 0x62, 0x63], 0, Some(2)).unwrap(),
+            Value::String("a\u{fffd}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_checksum_range_is_bounds_checked() {
+        let mut buffer = vec![0u8; 16];
+        buffer[4..13].copy_from_slice(b"123456789");
+
+        assert_eq!(
+            read_checksum(&buffer, 4, ChecksumAlgo::Crc32, (0, 9)).unwrap(),
+            Value::Uint(0xcbf4_3926)
+        );
+        assert_eq!(
+            read_typed_value(
+                &buffer,
+                0,
+                &TypeKind::Checksum {
+                    algo: ChecksumAlgo::Crc32,
+                    range: (4, 13)
+                }
+            )
+            .unwrap(),
+            Value::Uint(0xcbf4_3926)
+        );
+        // An empty range has the checksum of no data
+        assert_eq!(
+            read_checksum(&buffer, 16, ChecksumAlgo::Adler32, (0, 0)).unwrap(),
+            Value::Uint(1)
+        );
+
+        assert_eq!(
+            read_checksum(&buffer, 4, ChecksumAlgo::Crc32, (0, 13)).unwrap_err(),
+            TypeReadError::BufferOverrun {
+                offset: 4,
+                buffer_len: 16
+            }
+        );
+        assert!(read_checksum(&buffer, usize::MAX, ChecksumAlgo::Crc32, (1, 2)).is_err());
+    }
+
+    #[test]
+    fn test_search_bytes() {
+        let mut buffer = vec![0u8; 64];
+        buffer[20..24].copy_from_slice(b"MARK");
+
+        assert_eq!(search_bytes(&buffer, 0, 64, b"MARK").unwrap(), Some(20));
+        assert_eq!(search_bytes(&buffer, 10, 64, b"MARK").unwrap(), Some(20));
+        // The last position tried is offset + range - 1
+        assert_eq!(search_bytes(&buffer, 0, 21, b"MARK").unwrap(), Some(20));
+        assert_eq!(search_bytes(&buffer, 0, 20, b"MARK").unwrap(), None);
+        assert_eq!(search_bytes(&buffer, 21, 64, b"MARK").unwrap(), None);
+        assert_eq!(search_bytes(&buffer, 5, 8, b"").unwrap(), Some(5));
+        assert!(search_bytes(&buffer, 65, 8, b"MARK").is_err());
+    }
+
     #[test]
     fn test_read_typed_value_buffer_overrun() {
         let buffer = &[0x12, 0x34];
@@ -1012,7 +1522,7 @@ fn test_read_typed_value_all_supported_types() {
 
         // Test all supported TypeKind variants
         let test_cases = vec![
-            (TypeKind::Byte, 0, Value::Uint(0x7f)),
+            (TypeKind::Byte { signed: false }, 0, Value::Uint(0x7f)),
             (
                 TypeKind::Short {
                     endian: Endianness::Little,
@@ -1095,7 +1605,7 @@ fn test_read_typed_value_consistency_with_direct_calls() {
         let buffer = &[0x34, 0x12, 0x78, 0x56, 0xbc, 0x9a, 0xde, 0xf0];
 
         // Test that read_typed_value gives same results as direct function calls
-        let byte_type = TypeKind::Byte;
+        let byte_type = TypeKind::Byte { signed: false };
         let direct_byte = read_byte(buffer, 0).unwrap();
         let typed_byte = read_typed_value(buffer, 0, &byte_type).unwrap();
         assert_eq!(direct_byte, typed_byte);
@@ -1123,7 +1633,7 @@ fn test_read_typed_value_empty_buffer() {
 
         // All types should fail on empty buffer
         let types = vec![
-            TypeKind::Byte,
+            TypeKind::Byte { signed: false },
             TypeKind::Short {
                 endian: Endianness::Little,
                 signed: false,
@@ -1142,7 +1652,7 @@ fn test_read_typed_value_empty_buffer() {
                     assert_eq!(offset, 0);
                     assert_eq!(buffer_len, 0);
                 }
-                TypeReadError::UnsupportedType { .. } => panic!("Expected BufferOverrun error"),
+                other => panic!("Expected BufferOverrun error, got {other:?}"),
             }
         }
     }
@@ -0,0 +1,440 @@
+//! ZIP container subtype detection
+//!
+//! Many document and package formats (OOXML, `OpenDocument`, EPUB, JAR) are ZIP
+//! archives that can only be told apart by the entries they contain. This
+//! module reads entry names from the ZIP central directory, falling back to
+//! walking local file headers when the directory is unavailable (for example
+//! when only the start of the file is loaded), and classifies the container
+//! from well-known marker entries.
+
+use crate::evaluator::MatchResult;
+use crate::parser::ast::Value;
+
+/// Signature of a local file header (`PK\x03\x04`)
+const LOCAL_HEADER_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// Signature of a central directory file header (`PK\x01\x02`)
+const CENTRAL_HEADER_SIGNATURE: &[u8] = b"PK\x01\x02";
+
+/// Signature of the end of central directory record (`PK\x05\x06`)
+const END_OF_DIRECTORY_SIGNATURE: &[u8] = b"PK\x05\x06";
+
+/// Fixed size of the end of central directory record
+const END_OF_DIRECTORY_LEN: usize = 22;
+
+/// Longest comment that may follow the end of central directory record
+const MAX_COMMENT_LEN: usize = u16::MAX as usize;
+
+/// Fixed size of a central directory file header
+const CENTRAL_HEADER_LEN: usize = 46;
+
+/// Fixed size of a local file header
+const LOCAL_HEADER_LEN: usize = 30;
+
+/// Maximum number of entries inspected, bounding work on hostile archives
+const MAX_ENTRIES: usize = 1024;
+
+/// A file format stored in a ZIP container
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::zip::ZipSubtype;
+///
+/// assert_eq!(ZipSubtype::Jar.description(), "Java archive data (JAR)");
+/// assert_eq!(ZipSubtype::Jar.mime_type(), "application/java-archive");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZipSubtype {
+    /// Office Open XML word processing document
+    Docx,
+    /// Office Open XML spreadsheet
+    Xlsx,
+    /// Office Open XML presentation
+    Pptx,
+    /// Office Open XML package of another kind
+    Ooxml,
+    /// Java archive
+    Jar,
+    /// `OpenDocument` text document
+    OpenDocumentText,
+    /// `OpenDocument` spreadsheet
+    OpenDocumentSpreadsheet,
+    /// `OpenDocument` presentation
+    OpenDocumentPresentation,
+    /// EPUB electronic publication
+    Epub,
+}
+
+impl ZipSubtype {
+    /// Human-readable description, in GNU `file` style
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Docx => "Microsoft Word 2007+",
+            Self::Xlsx => "Microsoft Excel 2007+",
+            Self::Pptx => "Microsoft PowerPoint 2007+",
+            Self::Ooxml => "Microsoft OOXML",
+            Self::Jar => "Java archive data (JAR)",
+            Self::OpenDocumentText => "OpenDocument Text",
+            Self::OpenDocumentSpreadsheet => "OpenDocument Spreadsheet",
+            Self::OpenDocumentPresentation => "OpenDocument Presentation",
+            Self::Epub => "EPUB document",
+        }
+    }
+
+    /// MIME type of the format
+    #[must_use]
+    pub const fn mime_type(self) -> &'static str {
+        match self {
+            Self::Docx => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            Self::Pptx => {
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+            }
+            Self::Ooxml => "application/octet-stream",
+            Self::Jar => "application/java-archive",
+            Self::OpenDocumentText => "application/vnd.oasis.opendocument.text",
+            Self::OpenDocumentSpreadsheet => "application/vnd.oasis.opendocument.spreadsheet",
+            Self::OpenDocumentPresentation => "application/vnd.oasis.opendocument.presentation",
+            Self::Epub => "application/epub+zip",
+        }
+    }
+
+    /// Classify a ZIP archive held in `buffer`
+    ///
+    /// Returns `None` if the buffer is not a ZIP archive or contains none of
+    /// the known marker entries.
+    #[must_use]
+    pub fn detect(buffer: &[u8]) -> Option<Self> {
+        if !buffer.starts_with(LOCAL_HEADER_SIGNATURE) {
+            return None;
+        }
+
+        // An `OpenDocument` or EPUB container starts with an uncompressed
+        // `mimetype` entry holding its MIME type
+        if let Some(subtype) = first_entry_mimetype(buffer).and_then(Self::from_mimetype) {
+            return Some(subtype);
+        }
+
+        let names = central_directory_names(buffer).unwrap_or_else(|| local_header_names(buffer));
+        Self::from_entry_names(&names)
+    }
+
+    /// Map the contents of a `mimetype` entry to a subtype
+    fn from_mimetype(mimetype: &[u8]) -> Option<Self> {
+        match mimetype {
+            b"application/vnd.oasis.opendocument.text" => Some(Self::OpenDocumentText),
+            b"application/vnd.oasis.opendocument.spreadsheet" => {
+                Some(Self::OpenDocumentSpreadsheet)
+            }
+            b"application/vnd.oasis.opendocument.presentation" => {
+                Some(Self::OpenDocumentPresentation)
+            }
+            b"application/epub+zip" => Some(Self::Epub),
+            _ => None,
+        }
+    }
+
+    /// Classify an archive from the names of its entries
+    fn from_entry_names(names: &[&[u8]]) -> Option<Self> {
+        let has_prefix = |prefix: &[u8]| names.iter().any(|name| name.starts_with(prefix));
+
+        if names.contains(&b"[Content_Types].xml".as_slice()) {
+            return Some(if has_prefix(b"word/") {
+                Self::Docx
+            } else if has_prefix(b"xl/") {
+                Self::Xlsx
+            } else if has_prefix(b"ppt/") {
+                Self::Pptx
+            } else {
+                Self::Ooxml
+            });
+        }
+
+        if names.contains(&b"META-INF/MANIFEST.MF".as_slice()) {
+            return Some(Self::Jar);
+        }
+
+        None
+    }
+}
+
+/// Produce a match describing the ZIP container subtype of `buffer`, if any
+///
+/// The match is reported at offset 0 and level 0 with the local file header
+/// signature as its value, and carries the subtype's MIME type. A generic OOXML
+/// package has no specific MIME type, leaving that of any matching ZIP rule.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::evaluator::zip::detect_zip_subtype_match;
+///
+/// assert!(detect_zip_subtype_match(b"not a zip").is_none());
+/// ```
+#[must_use]
+pub fn detect_zip_subtype_match(buffer: &[u8]) -> Option<MatchResult> {
+    ZipSubtype::detect(buffer).map(|subtype| MatchResult {
+        message: subtype.description().to_string(),
+        offset: 0,
+        length: LOCAL_HEADER_SIGNATURE.len(),
+        level: 0,
+        value: Value::Bytes(LOCAL_HEADER_SIGNATURE.to_vec()),
+        source_line: None,
+        apple_type: None,
+        apple_creator: None,
+        mime_type: Some(subtype.mime_type())
+            .filter(|&mime| mime != "application/octet-stream")
+            .map(str::to_string),
+        extensions: Vec::new(),
+    })
+}
+
+/// Read a little-endian `u16` at `offset`
+fn read_u16(buffer: &[u8], offset: usize) -> Option<usize> {
+    let bytes = buffer.get(offset..offset.checked_add(2)?)?;
+    Some(usize::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+}
+
+/// Read a little-endian `u32` at `offset`
+fn read_u32(buffer: &[u8], offset: usize) -> Option<usize> {
+    let bytes = buffer.get(offset..offset.checked_add(4)?)?;
+    usize::try_from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).ok()
+}
+
+/// The contents of the first entry if it is a stored entry named `mimetype`
+fn first_entry_mimetype(buffer: &[u8]) -> Option<&[u8]> {
+    let compression = read_u16(buffer, 8)?;
+    let compressed_size = read_u32(buffer, 18)?;
+    let name_len = read_u16(buffer, 26)?;
+    let extra_len = read_u16(buffer, 28)?;
+
+    let name_start = LOCAL_HEADER_LEN;
+    if compression != 0 || buffer.get(name_start..name_start + name_len)? != b"mimetype" {
+        return None;
+    }
+
+    let data_start = name_start + name_len + extra_len;
+    buffer.get(data_start..data_start.checked_add(compressed_size)?)
+}
+
+/// Entry names listed in the central directory, if it can be located
+fn central_directory_names(buffer: &[u8]) -> Option<Vec<&[u8]>> {
+    let search_start = buffer
+        .len()
+        .saturating_sub(END_OF_DIRECTORY_LEN + MAX_COMMENT_LEN);
+    let end_record = buffer
+        .get(search_start..)?
+        .windows(END_OF_DIRECTORY_SIGNATURE.len())
+        .rposition(|window| window == END_OF_DIRECTORY_SIGNATURE)?
+        + search_start;
+
+    let entry_count = read_u16(buffer, end_record + 10)?;
+    let mut position = read_u32(buffer, end_record + 16)?;
+    let mut names = Vec::new();
+
+    for _ in 0..entry_count.min(MAX_ENTRIES) {
+        if buffer.get(position..position.checked_add(4)?)? != CENTRAL_HEADER_SIGNATURE {
+            return None;
+        }
+        let name_len = read_u16(buffer, position + 28)?;
+        let extra_len = read_u16(buffer, position + 30)?;
+        let comment_len = read_u16(buffer, position + 32)?;
+
+        let name_start = position + CENTRAL_HEADER_LEN;
+        names.push(buffer.get(name_start..name_start + name_len)?);
+        position = name_start + name_len + extra_len + comment_len;
+    }
+
+    Some(names)
+}
+
+/// Entry names found by walking local file headers from the start of the buffer
+///
+/// The walk stops at the first entry whose size is not recorded in its header
+/// (entries written with a trailing data descriptor) or that runs past the end
+/// of the buffer.
+fn local_header_names(buffer: &[u8]) -> Vec<&[u8]> {
+    let mut names = Vec::new();
+    let mut position = 0;
+
+    while names.len() < MAX_ENTRIES
+        && buffer
+            .get(position..)
+            .is_some_and(|rest| rest.starts_with(LOCAL_HEADER_SIGNATURE))
+    {
+        let Some(header) = local_header(buffer, position) else {
+            break;
+        };
+        names.push(header.0);
+        match header.1 {
+            Some(next) => position = next,
+            None => break,
+        }
+    }
+
+    names
+}
+
+/// The name of the local header entry at `position` and the position of the next one
+fn local_header(buffer: &[u8], position: usize) -> Option<(&[u8], Option<usize>)> {
+    let flags = read_u16(buffer, position + 6)?;
+    let compressed_size = read_u32(buffer, position + 18)?;
+    let name_len = read_u16(buffer, position + 26)?;
+    let extra_len = read_u16(buffer, position + 28)?;
+
+    let name_start = position + LOCAL_HEADER_LEN;
+    let name = buffer.get(name_start..name_start + name_len)?;
+
+    // Bit 3: sizes are in a data descriptor after the entry data
+    let next = if flags & 0x08 == 0 {
+        Some(name_start + name_len + extra_len + compressed_size)
+    } else {
+        None
+    };
+
+    Some((name, next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a ZIP archive of stored entries with a central directory
+    fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut directory = Vec::new();
+
+        for (name, data) in entries {
+            let offset = u32::try_from(archive.len()).unwrap();
+            let size = u32::try_from(data.len()).unwrap();
+            let name_len = u16::try_from(name.len()).unwrap();
+
+            archive.extend_from_slice(LOCAL_HEADER_SIGNATURE);
+            archive.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            archive.extend_from_slice(&size.to_le_bytes());
+            archive.extend_from_slice(&size.to_le_bytes());
+            archive.extend_from_slice(&name_len.to_le_bytes());
+            archive.extend_from_slice(&0u16.to_le_bytes());
+            archive.extend_from_slice(name.as_bytes());
+            archive.extend_from_slice(data);
+
+            directory.extend_from_slice(CENTRAL_HEADER_SIGNATURE);
+            directory.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            directory.extend_from_slice(&size.to_le_bytes());
+            directory.extend_from_slice(&size.to_le_bytes());
+            directory.extend_from_slice(&name_len.to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let directory_offset = u32::try_from(archive.len()).unwrap();
+        let directory_len = u32::try_from(directory.len()).unwrap();
+        let count = u16::try_from(entries.len()).unwrap();
+        archive.extend_from_slice(&directory);
+        archive.extend_from_slice(END_OF_DIRECTORY_SIGNATURE);
+        archive.extend_from_slice(&[0, 0, 0, 0]);
+        archive.extend_from_slice(&count.to_le_bytes());
+        archive.extend_from_slice(&count.to_le_bytes());
+        archive.extend_from_slice(&directory_len.to_le_bytes());
+        archive.extend_from_slice(&directory_offset.to_le_bytes());
+        archive.extend_from_slice(&0u16.to_le_bytes());
+        archive
+    }
+
+    #[test]
+    fn test_detect_docx() {
+        let docx = build_zip(&[
+            ("[Content_Types].xml", b"<Types/>"),
+            ("_rels/.rels", b"<Relationships/>"),
+            ("word/document.xml", b"<w:document/>"),
+        ]);
+
+        assert_eq!(ZipSubtype::detect(&docx), Some(ZipSubtype::Docx));
+        let m = detect_zip_subtype_match(&docx).unwrap();
+        assert_eq!(m.message, "Microsoft Word 2007+");
+        assert_eq!(m.offset, 0);
+        assert_eq!(m.mime_type.as_deref(), Some(ZipSubtype::Docx.mime_type()));
+    }
+
+    #[test]
+    fn test_detect_other_ooxml() {
+        let xlsx = build_zip(&[("[Content_Types].xml", b""), ("xl/workbook.xml", b"")]);
+        assert_eq!(ZipSubtype::detect(&xlsx), Some(ZipSubtype::Xlsx));
+
+        let pptx = build_zip(&[("[Content_Types].xml", b""), ("ppt/presentation.xml", b"")]);
+        assert_eq!(ZipSubtype::detect(&pptx), Some(ZipSubtype::Pptx));
+
+        let other = build_zip(&[("[Content_Types].xml", b"")]);
+        assert_eq!(ZipSubtype::detect(&other), Some(ZipSubtype::Ooxml));
+        assert_eq!(detect_zip_subtype_match(&other).unwrap().mime_type, None);
+    }
+
+    #[test]
+    fn test_detect_jar() {
+        let jar = build_zip(&[
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\r\n"),
+            ("com/example/Main.class", b"\xca\xfe\xba\xbe"),
+        ]);
+
+        assert_eq!(ZipSubtype::detect(&jar), Some(ZipSubtype::Jar));
+        assert_eq!(
+            detect_zip_subtype_match(&jar).unwrap().message,
+            "Java archive data (JAR)"
+        );
+    }
+
+    #[test]
+    fn test_detect_mimetype_entry() {
+        let odt = build_zip(&[
+            ("mimetype", b"application/vnd.oasis.opendocument.text"),
+            ("content.xml", b""),
+        ]);
+        assert_eq!(ZipSubtype::detect(&odt), Some(ZipSubtype::OpenDocumentText));
+
+        let epub = build_zip(&[("mimetype", b"application/epub+zip")]);
+        assert_eq!(ZipSubtype::detect(&epub), Some(ZipSubtype::Epub));
+    }
+
+    #[test]
+    fn test_detect_without_central_directory() {
+        // Only the start of the file is available, so the entries are found
+        // by walking the local headers
+        let docx = build_zip(&[
+            ("[Content_Types].xml", b"<Types/>"),
+            ("word/document.xml", b""),
+        ]);
+        let directory_start = docx
+            .windows(4)
+            .position(|window| window == CENTRAL_HEADER_SIGNATURE)
+            .unwrap();
+
+        assert_eq!(
+            ZipSubtype::detect(&docx[..directory_start]),
+            Some(ZipSubtype::Docx)
+        );
+    }
+
+    #[test]
+    fn test_detect_plain_zip_and_non_zip() {
+        let plain = build_zip(&[("readme.txt", b"hello")]);
+        assert_eq!(ZipSubtype::detect(&plain), None);
+
+        assert_eq!(ZipSubtype::detect(b""), None);
+        assert_eq!(ZipSubtype::detect(b"PK\x03\x04"), None);
+        assert_eq!(ZipSubtype::detect(b"\x7fELF"), None);
+    }
+
+    #[test]
+    fn test_detect_corrupt_directory_offsets() {
+        let mut docx = build_zip(&[("[Content_Types].xml", b""), ("word/document.xml", b"")]);
+        // Point the central directory past the end of the buffer
+        let len = docx.len();
+        docx[len - 6..len - 2].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        // Falls back to the local headers rather than failing
+        assert_eq!(ZipSubtype::detect(&docx), Some(ZipSubtype::Docx));
+    }
+}
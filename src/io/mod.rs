@@ -5,6 +5,7 @@
 
 use memmap2::{Mmap, MmapOptions};
 use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -127,6 +128,10 @@ pub enum IoError {
 /// which avoids loading the entire file into memory while providing fast
 /// random access to file data.
 ///
+/// Some filesystems (network mounts, `/proc`) refuse memory mapping even for
+/// regular files. In that case the file is read into an owned buffer instead;
+/// [`FileBuffer::is_memory_mapped`] reports which backing is in use.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -140,19 +145,46 @@ pub enum IoError {
 /// ```
 #[derive(Debug)]
 pub struct FileBuffer {
-    /// Memory-mapped file data
-    mmap: Mmap,
+    /// File contents, either mapped or read into memory
+    data: FileData,
+    /// Size of the whole file, which may exceed the loaded contents
+    file_size: FileSize,
     /// Path to the file for error reporting
     path: PathBuf,
 }
 
+/// Backing storage for a [`FileBuffer`]
+#[derive(Debug)]
+enum FileData {
+    /// Memory-mapped file contents
+    Mapped(Mmap),
+    /// File contents read into memory, because memory mapping failed or was not wanted
+    Owned(Vec<u8>),
+}
+
+impl FileData {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(bytes) => bytes,
+        }
+    }
+}
+
 impl FileBuffer {
     /// Maximum file size that can be processed (1 GB)
     ///
     /// This limit prevents memory exhaustion attacks and ensures reasonable
     /// processing times. Files larger than this are likely not suitable for
     /// magic rule evaluation and may indicate malicious input.
-    const MAX_FILE_SIZE: FileSize = 1024 * 1024 * 1024;
+    pub(crate) const MAX_FILE_SIZE: FileSize = 1024 * 1024 * 1024;
+
+    /// Number of bytes read from a device or FIFO when no length is given (7 MiB)
+    ///
+    /// Matches the default `bytes_max` of GNU `file`, so `/dev/zero` or a whole
+    /// disk is identified from its leading bytes instead of read up to the 1 GB
+    /// maximum file size.
+    pub const MAX_SPECIAL_FILE_READ: usize = 7 * 1024 * 1024;
 
     /// Maximum number of concurrent file mappings to prevent resource exhaustion
     /// TODO: Implement concurrent mapping tracking in future versions
@@ -167,6 +199,9 @@ impl FileBuffer {
 
     /// Creates a new memory-mapped file buffer
     ///
+    /// If the file cannot be memory-mapped, its contents are read into an
+    /// owned buffer instead.
+    ///
     /// # Arguments
     ///
     /// * `path` - Path to the file to be mapped
@@ -180,7 +215,7 @@ impl FileBuffer {
     ///
     /// This function will return an error if:
     /// - The file does not exist or cannot be opened
-    /// - The file cannot be memory-mapped
+    /// - The file can neither be memory-mapped nor read
     /// - The file is empty
     /// - The file is larger than the maximum allowed size
     /// - File metadata cannot be read
@@ -195,23 +230,221 @@ impl FileBuffer {
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn new(path: &Path) -> Result<Self, IoError> {
+        Self::open_with_limit(path, None, true)
+    }
+
+    /// Creates a file buffer holding at most the first `max_len` bytes of the file
+    ///
+    /// Only the leading window is mapped, which saves address space when
+    /// identifying very large files by their headers. The size of the whole
+    /// file remains available through [`FileBuffer::file_size`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `IoError::InvalidAccess` if `max_len` is 0, and otherwise the
+    /// same errors as [`FileBuffer::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libmagic_rs::io::FileBuffer;
+    /// use std::path::Path;
+    ///
+    /// let buffer = FileBuffer::new_with_max_len(Path::new("disk.img"), 4096)?;
+    /// assert!(buffer.len() <= 4096);
+    /// println!("Mapped {} of {} bytes", buffer.len(), buffer.file_size());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn new_with_max_len(path: &Path, max_len: usize) -> Result<Self, IoError> {
+        if max_len == 0 {
+            return Err(IoError::InvalidAccess {
+                offset: 0,
+                length: 0,
+            });
+        }
+        Self::open_with_limit(path, Some(max_len), true)
+    }
+
+    /// Creates a file buffer by reading the file into memory, never memory-mapping it
+    ///
+    /// Use this where `mmap` is forbidden, such as under some seccomp policies
+    /// and sandboxes. At most `max_len` bytes are read when it is given, and the
+    /// file is otherwise validated exactly as by [`FileBuffer::new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `IoError::InvalidAccess` if `max_len` is `Some(0)`, and otherwise
+    /// the same errors as [`FileBuffer::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libmagic_rs::io::FileBuffer;
+    /// use std::path::Path;
+    ///
+    /// let buffer = FileBuffer::new_unmapped(Path::new("example.bin"), None)?;
+    /// assert!(!buffer.is_memory_mapped());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn new_unmapped(path: &Path, max_len: Option<usize>) -> Result<Self, IoError> {
+        if max_len == Some(0) {
+            return Err(IoError::InvalidAccess {
+                offset: 0,
+                length: 0,
+            });
+        }
+        Self::open_with_limit(path, max_len, false)
+    }
+
+    /// Creates a file buffer that also accepts block devices, character devices and FIFOs
+    ///
+    /// Regular files are loaded as by [`FileBuffer::new`] or
+    /// [`FileBuffer::new_with_max_len`]. Special files, which [`FileBuffer::new`]
+    /// rejects, are read with a plain bounded `read` of at most `max_len` bytes
+    /// (or [`FileBuffer::MAX_SPECIAL_FILE_READ`] when `max_len` is `None`), since memory mapping
+    /// devices is unreliable. This mirrors `file -s` and is useful for
+    /// identifying disk images behind `/dev/sdX`. The read is always bounded, so
+    /// an endless FIFO or character device cannot exhaust memory; note that
+    /// opening a FIFO blocks until a writer connects.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IoError::InvalidAccess` if `max_len` is `Some(0)`,
+    /// `IoError::InvalidFileType` for directories and sockets, `IoError::EmptyFile`
+    /// if nothing could be read, and otherwise the same errors as
+    /// [`FileBuffer::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libmagic_rs::io::FileBuffer;
+    /// use std::path::Path;
+    ///
+    /// let buffer = FileBuffer::new_special(Path::new("/dev/sda"), Some(1024 * 1024))?;
+    /// assert!(!buffer.is_memory_mapped());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn new_special(path: &Path, max_len: Option<usize>) -> Result<Self, IoError> {
+        Self::open_special(path, max_len, true)
+    }
+
+    /// Loads a file as [`FileBuffer::new_special`] does, memory-mapping regular files only if `mmap` is set
+    pub(crate) fn open_special(
+        path: &Path,
+        max_len: Option<usize>,
+        mmap: bool,
+    ) -> Result<Self, IoError> {
+        if max_len == Some(0) {
+            return Err(IoError::InvalidAccess {
+                offset: 0,
+                length: 0,
+            });
+        }
+
+        let metadata = std::fs::metadata(path).map_err(|source| IoError::MetadataError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if !Self::is_readable_special_file(&metadata) {
+            return Self::open_with_limit(path, max_len, mmap);
+        }
+
+        let path_buf = path.to_path_buf();
+        let file = Self::open_file(path, &path_buf)?;
+        let max_len = max_len.unwrap_or(Self::MAX_SPECIAL_FILE_READ);
+        let len = Self::MAX_FILE_SIZE.min(FileSize::try_from(max_len).unwrap_or(FileSize::MAX));
+        let contents =
+            Self::read_file_contents(&file, len).map_err(|source| IoError::FileOpenError {
+                path: path_buf.clone(),
+                source,
+            })?;
+        if contents.is_empty() {
+            return Err(IoError::EmptyFile { path: path_buf });
+        }
+
+        Ok(Self {
+            file_size: contents.len() as FileSize,
+            data: FileData::Owned(contents),
+            path: path_buf,
+        })
+    }
+
+    /// Whether `metadata` describes a device or FIFO that can be read with a bounded read
+    fn is_readable_special_file(metadata: &std::fs::Metadata) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            let file_type = metadata.file_type();
+            file_type.is_block_device() || file_type.is_char_device() || file_type.is_fifo()
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = metadata;
+            false
+        }
+    }
+
+    /// Opens, validates and loads a file, limited to `max_len` bytes if given
+    ///
+    /// The file is memory-mapped when `mmap` is set, falling back to a plain
+    /// read if mapping fails, and always read into memory otherwise.
+    fn open_with_limit(path: &Path, max_len: Option<usize>, mmap: bool) -> Result<Self, IoError> {
         // TODO: Add additional error handling for edge cases:
         // - Handle symbolic links and their resolution
         // - Add validation for path length limits on different platforms
-        // - Handle special files (devices, pipes, etc.) gracefully
         // - Add retry logic for transient I/O errors
         let path_buf = path.to_path_buf();
 
+        // Check the file type first: opening a FIFO would block until a writer connects
+        let file_size = Self::validate_file_metadata(&path_buf)?;
         let file = Self::open_file(path, &path_buf)?;
-        Self::validate_file_metadata(&file, &path_buf)?;
-        let mmap = Self::create_memory_mapping(&file, &path_buf)?;
+        let len = max_len.map_or(file_size, |max_len| {
+            file_size.min(FileSize::try_from(max_len).unwrap_or(FileSize::MAX))
+        });
+        let data = if mmap {
+            match Self::create_memory_mapping(&file, &path_buf, len) {
+                Ok(mmap) => FileData::Mapped(mmap),
+                // Fall back to a plain read; report the mapping failure if that fails too
+                Err(mmap_error) => {
+                    log::debug!(
+                        "mmap of {} failed ({mmap_error}), reading instead",
+                        log_name(path)
+                    );
+                    FileData::Owned(Self::read_file_contents(&file, len).map_err(|_| mmap_error)?)
+                }
+            }
+        } else {
+            FileData::Owned(Self::read_file_contents(&file, len).map_err(|source| {
+                IoError::FileOpenError {
+                    path: path_buf.clone(),
+                    source,
+                }
+            })?)
+        };
 
+        log::debug!(
+            "loaded {len} of {file_size} bytes from {} ({})",
+            log_name(path),
+            if matches!(data, FileData::Mapped(_)) {
+                "mapped"
+            } else {
+                "read"
+            }
+        );
         Ok(Self {
-            mmap,
+            data,
+            file_size,
             path: path_buf,
         })
     }
 
+    /// Reads up to `len` bytes into memory when the file is not memory-mapped
+    fn read_file_contents(file: &File, len: FileSize) -> std::io::Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        file.take(len).read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
     /// Opens a file for reading with proper error handling
     fn open_file(path: &Path, path_buf: &Path) -> Result<File, IoError> {
         File::open(path).map_err(|source| IoError::FileOpenError {
@@ -221,10 +454,13 @@ fn open_file(path: &Path, path_buf: &Path) -> Result<File, IoError> {
     }
 
     /// Validates file metadata and ensures file is suitable for memory mapping
-    fn validate_file_metadata(_file: &File, path_buf: &Path) -> Result<(), IoError> {
-        // Resolve symlinks to get the actual target file
+    ///
+    /// Returns the size of the file in bytes.
+    fn validate_file_metadata(path_buf: &Path) -> Result<FileSize, IoError> {
+        // Resolve symlinks to get the actual target file; this runs before the
+        // file is opened, so a missing file is reported as failing to open it
         let canonical_path =
-            std::fs::canonicalize(path_buf).map_err(|source| IoError::MetadataError {
+            std::fs::canonicalize(path_buf).map_err(|source| IoError::FileOpenError {
                 path: path_buf.to_path_buf(),
                 source,
             })?;
@@ -276,7 +512,7 @@ fn validate_file_metadata(_file: &File, path_buf: &Path) -> Result<(), IoError>
             });
         }
 
-        Ok(())
+        Ok(file_size)
     }
 
     /// Detects special file types in a cross-platform manner
@@ -355,14 +591,19 @@ pub fn create_symlink<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
     /// - Avoids loading entire files into memory, reducing memory exhaustion attacks
     /// - Provides read-only access to file contents
     /// - Leverages OS-level memory protection mechanisms
-    fn create_memory_mapping(file: &File, path_buf: &Path) -> Result<Mmap, IoError> {
+    fn create_memory_mapping(file: &File, path_buf: &Path, len: FileSize) -> Result<Mmap, IoError> {
         // SAFETY: We use safe memory mapping through memmap2, which handles
         // the unsafe operations internally with proper error checking.
         // The memmap2 crate is a vetted dependency that provides safe abstractions
         // over unsafe memory mapping operations.
         #[allow(unsafe_code)]
         unsafe {
-            MmapOptions::new().map(file).map_err(|source| {
+            let len = usize::try_from(len).map_err(|_| IoError::FileTooLarge {
+                path: path_buf.to_path_buf(),
+                size: len,
+                max_size: Self::MAX_FILE_SIZE,
+            })?;
+            MmapOptions::new().len(len).map(file).map_err(|source| {
                 // Sanitize error message to avoid leaking sensitive path information
                 let sanitized_path = path_buf.file_name().map_or_else(
                     || "<unknown>".to_string(),
@@ -395,7 +636,30 @@ fn create_memory_mapping(file: &File, path_buf: &Path) -> Result<Mmap, IoError>
     /// ```
     #[must_use]
     pub fn as_slice(&self) -> &[u8] {
-        &self.mmap
+        self.data.as_slice()
+    }
+
+    /// Returns true if the contents are memory-mapped rather than read into memory
+    ///
+    /// This is false when memory mapping failed and the file was read with a
+    /// regular read instead, which callers may want to log, and for buffers
+    /// created with [`FileBuffer::new_unmapped`] or from special files.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libmagic_rs::io::FileBuffer;
+    /// use std::path::Path;
+    ///
+    /// let buffer = FileBuffer::new(Path::new("example.bin"))?;
+    /// if !buffer.is_memory_mapped() {
+    ///     eprintln!("{} was read without memory mapping", buffer.path().display());
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn is_memory_mapped(&self) -> bool {
+        matches!(self.data, FileData::Mapped(_))
     }
 
     /// Returns the path of the file
@@ -429,7 +693,16 @@ pub fn path(&self) -> &Path {
     /// ```
     #[must_use]
     pub fn len(&self) -> usize {
-        self.mmap.len()
+        self.as_slice().len()
+    }
+
+    /// Returns the size of the whole file in bytes
+    ///
+    /// This equals [`FileBuffer::len`] unless the buffer was created with
+    /// [`FileBuffer::new_with_max_len`] for a file larger than the limit.
+    #[must_use]
+    pub const fn file_size(&self) -> u64 {
+        self.file_size
     }
 
     /// Returns true if the file is empty
@@ -449,10 +722,18 @@ pub fn len(&self) -> usize {
     /// ```
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.mmap.is_empty()
+        self.as_slice().is_empty()
     }
 }
 
+/// The final component of `path`, so log records do not reveal directory layout
+fn log_name(path: &Path) -> std::borrow::Cow<'_, str> {
+    path.file_name()
+        .map_or(std::borrow::Cow::Borrowed("<unnamed>"), |name| {
+            name.to_string_lossy()
+        })
+}
+
 /// Safely reads bytes from a buffer with bounds checking
 ///
 /// This function provides safe access to buffer data with comprehensive
@@ -661,6 +942,125 @@ fn test_file_buffer_creation_success() {
         cleanup_temp_file(&temp_path);
     }
 
+    #[test]
+    fn test_file_buffer_regular_file_is_memory_mapped() {
+        let temp_path = create_temp_file(b"mapped");
+
+        let buffer = FileBuffer::new(&temp_path).expect("Failed to create FileBuffer");
+        assert!(buffer.is_memory_mapped());
+
+        cleanup_temp_file(&temp_path);
+    }
+
+    #[test]
+    fn test_file_buffer_new_unmapped_reads_contents() {
+        let content = b"\x7fELF read without mmap";
+        let temp_path = create_temp_file(content);
+
+        let buffer = FileBuffer::new_unmapped(&temp_path, None).expect("Failed to read file");
+        assert!(!buffer.is_memory_mapped());
+        assert_eq!(buffer.as_slice(), content);
+        assert_eq!(buffer.file_size(), content.len() as u64);
+
+        let window = FileBuffer::new_unmapped(&temp_path, Some(4)).expect("Failed to read window");
+        assert_eq!(window.as_slice(), b"\x7fELF");
+        assert!(matches!(
+            FileBuffer::new_unmapped(&temp_path, Some(0)),
+            Err(IoError::InvalidAccess { .. })
+        ));
+
+        cleanup_temp_file(&temp_path);
+    }
+
+    #[test]
+    fn test_file_buffer_owned_fallback_contents() {
+        let content = b"\x7fELF read without mmap";
+        let temp_path = create_temp_file(content);
+
+        let file = File::open(&temp_path).expect("Failed to open temp file");
+        let buffer = FileBuffer {
+            data: FileData::Owned(
+                FileBuffer::read_file_contents(&file, content.len() as u64)
+                    .expect("Failed to read temp file"),
+            ),
+            file_size: content.len() as u64,
+            path: temp_path.clone(),
+        };
+
+        assert!(!buffer.is_memory_mapped());
+        assert_eq!(buffer.as_slice(), content);
+        assert_eq!(buffer.len(), content.len());
+        assert!(!buffer.is_empty());
+
+        cleanup_temp_file(&temp_path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_buffer_new_special_reads_bounded_prefix() {
+        let device = Path::new("/dev/zero");
+        if device.exists() {
+            assert!(matches!(
+                FileBuffer::new(device),
+                Err(IoError::InvalidFileType { .. })
+            ));
+
+            let buffer = FileBuffer::new_special(device, Some(16)).expect("Failed to read device");
+            assert_eq!(buffer.as_slice(), &[0u8; 16]);
+            assert_eq!(buffer.file_size(), 16);
+            assert!(!buffer.is_memory_mapped());
+
+            // Without a length, only a prefix of an endless device is read
+            let buffer = FileBuffer::new_special(device, None).expect("Failed to read device");
+            assert_eq!(buffer.len(), FileBuffer::MAX_SPECIAL_FILE_READ);
+        }
+
+        // Regular files are loaded as usual, within the same bound
+        let temp_path = create_temp_file(b"ordinary file contents");
+        let buffer = FileBuffer::new_special(&temp_path, Some(8)).expect("Failed to read file");
+        assert_eq!(buffer.as_slice(), b"ordinary");
+        assert_eq!(buffer.file_size(), 22);
+        assert!(matches!(
+            FileBuffer::new_special(&temp_path, Some(0)),
+            Err(IoError::InvalidAccess { .. })
+        ));
+        cleanup_temp_file(&temp_path);
+
+        // Directories are still rejected
+        assert!(matches!(
+            FileBuffer::new_special(&std::env::temp_dir(), None),
+            Err(IoError::InvalidFileType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_file_buffer_max_len_maps_leading_window() {
+        let content: Vec<u8> = (0..2048u32).map(|i| (i % 251) as u8).collect();
+        let temp_path = create_temp_file(&content);
+
+        let buffer = FileBuffer::new_with_max_len(&temp_path, 512).expect("Failed to map window");
+        assert_eq!(buffer.len(), 512);
+        assert_eq!(buffer.as_slice(), &content[..512]);
+        assert_eq!(buffer.file_size(), 2048);
+
+        // A limit beyond the file size maps the whole file
+        let buffer = FileBuffer::new_with_max_len(&temp_path, 4096).expect("Failed to map file");
+        assert_eq!(buffer.len(), 2048);
+        assert_eq!(buffer.file_size(), 2048);
+
+        // The fallback read honors the same limit
+        let file = File::open(&temp_path).expect("Failed to open temp file");
+        let owned = FileBuffer::read_file_contents(&file, 512).expect("Failed to read window");
+        assert_eq!(owned, &content[..512]);
+
+        assert!(matches!(
+            FileBuffer::new_with_max_len(&temp_path, 0),
+            Err(IoError::InvalidAccess { .. })
+        ));
+
+        cleanup_temp_file(&temp_path);
+    }
+
     #[test]
     fn test_file_buffer_nonexistent_file() {
         let nonexistent_path = Path::new("/nonexistent/file.bin");
@@ -1226,6 +1626,29 @@ fn test_file_buffer_cross_platform_special_files() {
         std::fs::remove_dir(&temp_dir).unwrap();
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_file_buffer_fifo_rejected_before_opening() {
+        let fifo_path =
+            std::env::temp_dir().join(format!("test_fifo_unopened_{}", std::process::id()));
+        let _ = std::fs::remove_file(&fifo_path);
+        nix::unistd::mkfifo(&fifo_path, nix::sys::stat::Mode::S_IRWXU).unwrap();
+
+        // Without a writer, opening the FIFO would never return
+        for result in [
+            FileBuffer::new(&fifo_path),
+            FileBuffer::new_with_max_len(&fifo_path, 16),
+            FileBuffer::new_unmapped(&fifo_path, None),
+        ] {
+            assert!(
+                matches!(result, Err(IoError::InvalidFileType { ref file_type, .. }) if file_type == "FIFO/pipe"),
+                "{result:?}"
+            );
+        }
+
+        std::fs::remove_file(&fifo_path).unwrap();
+    }
+
     #[test]
     #[ignore = "FIFOs can cause hanging issues in CI environments"]
     fn test_file_buffer_fifo_rejection() {
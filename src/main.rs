@@ -3,80 +3,1186 @@
 //! This binary provides a CLI tool for file type identification using magic rules,
 //! serving as a drop-in replacement for the GNU `file` command.
 
-use clap::{Arg, Command};
-use libmagic_rs::{LibmagicError, MagicDatabase};
-use std::path::Path;
+use clap::builder::RangedU64ValueParser;
+use clap::{CommandFactory, Parser};
+use libmagic_rs::evaluator::encoding::mime_encoding;
+use libmagic_rs::output::text::{DEFAULT_SEPARATOR, format_raw_output, format_result};
+use libmagic_rs::output::{EvaluationMetadata, JSON_SCHEMA_VERSION};
+use libmagic_rs::parser::{self, compiled};
+use libmagic_rs::{EvaluationConfig, EvaluationResult, LibmagicError, MagicDatabase};
+use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::process;
 
+/// A pure-Rust implementation of libmagic for file type identification
+#[derive(Debug, Parser)]
+#[command(
+    name = "rmagic",
+    version,
+    author = "Rust Libmagic Contributors",
+    disable_help_flag = true
+)]
+struct Args {
+    /// File to analyze
+    #[arg(required_unless_present_any = ["stats", "compile"])]
+    file: Option<PathBuf>,
+
+    /// Output results in JSON format
+    #[arg(long)]
+    json: bool,
+
+    /// Output results in text format (default)
+    #[arg(long)]
+    text: bool,
+
+    /// Use custom magic file
+    #[arg(long, value_name = "FILE")]
+    magic_file: Option<String>,
+
+    /// Magic files listed in the `MAGIC` environment variable, separated by `:`
+    #[arg(skip)]
+    magic_env: Option<String>,
+
+    /// Do not prepend filenames to output lines
+    #[arg(short = 'b', long)]
+    brief: bool,
+
+    /// Use STR as the separator between the filename and the description
+    #[arg(short = 'F', long, value_name = "STR", default_value = DEFAULT_SEPARATOR)]
+    separator: String,
+
+    /// Read block devices, character devices and FIFOs like ordinary files
+    #[arg(short = 's', long)]
+    special_files: bool,
+
+    /// Follow symbolic links and identify their targets (the default)
+    #[arg(short = 'L', long, overrides_with = "no_dereference")]
+    dereference: bool,
+
+    /// Report symbolic links as `symbolic link to TARGET` instead of following them
+    #[arg(short = 'h', long, overrides_with = "dereference")]
+    no_dereference: bool,
+
+    /// Print help (`-h` is `--no-dereference`, as in GNU `file`)
+    #[arg(long, action = clap::ArgAction::Help)]
+    help: Option<bool>,
+
+    /// Read files into memory instead of memory-mapping them
+    #[arg(long)]
+    no_mmap: bool,
+
+    /// Give up on a file after MS milliseconds of rule evaluation
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..=300_000))]
+    timeout: Option<u64>,
+
+    /// Evaluate nested rules, `indirect` and `use` at most N levels deep
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=1000))]
+    max_depth: Option<u32>,
+
+    /// Read at most N bytes for a string value
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=1_048_576)
+    )]
+    max_string_length: Option<usize>,
+
+    /// With `--keep-going`, report at most N matches per file
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    max_matches: Option<usize>,
+
+    /// Identify every file below FILE when it is a directory, reporting unreadable files and continuing
+    #[arg(short = 'R', long)]
+    recursive: bool,
+
+    /// Keep going after the first match, printing each top-level match on its own line
+    #[arg(short = 'k', long)]
+    keep_going: bool,
+
+    /// Also print the magic file line of each matched rule
+    #[arg(long)]
+    verbose: bool,
+
+    /// Append the number of rules evaluated and matched and the evaluation time
+    #[arg(long)]
+    eval_stats: bool,
+
+    /// Print the MIME type instead of the description
+    #[arg(short = 'i', long, visible_alias = "mime")]
+    mime_type: bool,
+
+    /// With `--mime-type`, print the MIME types of all matches instead of the first
+    #[arg(long, requires = "mime_type")]
+    all: bool,
+
+    /// Print the text encoding, such as `us-ascii` or `utf-8`, or `binary`
+    #[arg(long, conflicts_with_all = ["json", "mime_type", "extension"])]
+    mime_encoding: bool,
+
+    /// Print the likely file extensions, separated by `/`, instead of the description
+    #[arg(long)]
+    extension: bool,
+
+    /// Print rule messages as written, without substituting matched values
+    #[arg(short = 'r', long)]
+    raw: bool,
+
+    /// Print statistics about the loaded magic database and exit
+    #[arg(long)]
+    stats: bool,
+
+    /// Compile the magic source file SOURCE into a compiled database and exit
+    #[arg(long, value_name = "SOURCE", requires = "output")]
+    compile: Option<String>,
+
+    /// Output path for the compiled database written by `--compile`
+    #[arg(short = 'o', long, value_name = "FILE")]
+    output: Option<String>,
+}
+
+/// Magic file used when neither `--magic-file` nor `MAGIC` is given
+const DEFAULT_MAGIC_FILE: &str = "magic.db";
+
+/// Environment variable listing the magic files to load, as for GNU `file`
+const MAGIC_ENV: &str = "MAGIC";
+
+/// Printed by `--extension` when no matching rule lists extensions
+const UNKNOWN_EXTENSION: &str = "???";
+
+/// MIME type printed by `--mime-type` when no matching rule has one
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// Bytes from the start of a file examined by `--mime-encoding`, as GNU `file` reads
+const ENCODING_SAMPLE_LEN: usize = 1_048_576;
+
+/// Exit code when evaluation exceeds the `--timeout` limit
+const TIMEOUT_EXIT_CODE: i32 = 5;
+
 fn main() {
-    let matches = Command::new("rmagic")
-        .version(env!("CARGO_PKG_VERSION"))
-        .author("Rust Libmagic Contributors")
-        .about("A pure-Rust implementation of libmagic for file type identification")
-        .arg(
-            Arg::new("file")
-                .help("File to analyze")
-                .required(true)
-                .index(1),
-        )
-        .arg(
-            Arg::new("json")
-                .long("json")
-                .help("Output results in JSON format")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("text")
-                .long("text")
-                .help("Output results in text format (default)")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("magic-file")
-                .long("magic-file")
-                .help("Use custom magic file")
-                .value_name("FILE"),
-        )
-        .get_matches();
+    let mut args = Args::parse();
+    args.magic_env = std::env::var(MAGIC_ENV).ok();
+    if let Some(e) = config_usage_error(&args) {
+        e.exit();
+    }
 
-    let file_path = matches.get_one::<String>("file").unwrap();
-    let json_output = matches.get_flag("json");
-    let _magic_file = matches.get_one::<String>("magic-file");
+    let result = if let Some(source) = &args.compile {
+        run_compile(source, args.output.as_deref().unwrap_or_default())
+    } else if args.stats {
+        run_stats(&args)
+    } else {
+        run_analysis(&args)
+    };
 
-    if let Err(e) = run_analysis(file_path, json_output) {
+    if let Err(e) = result {
         eprintln!("Error: {}", e);
-        process::exit(1);
+        process::exit(exit_code(&e));
+    }
+}
+
+/// A usage error for a combination of options the evaluation config rejects
+///
+/// Each option's own range is checked while parsing; this catches limits that
+/// are only unsafe together.
+fn config_usage_error(args: &Args) -> Option<clap::Error> {
+    let message = match evaluation_config(args).validate() {
+        Ok(()) => return None,
+        Err(LibmagicError::InvalidFormat(message)) => message,
+        Err(e) => e.to_string(),
+    };
+    Some(Args::command().error(clap::error::ErrorKind::ValueValidation, message))
+}
+
+/// The process exit code reported for `error`
+const fn exit_code(error: &LibmagicError) -> i32 {
+    match error {
+        LibmagicError::Timeout { .. } => TIMEOUT_EXIT_CODE,
+        _ => 1,
     }
 }
 
-fn run_analysis(file_path: &str, json_output: bool) -> Result<(), LibmagicError> {
+fn run_analysis(args: &Args) -> Result<(), LibmagicError> {
     // Verify file exists
-    let path = Path::new(file_path);
-    if !path.exists() {
+    let path = args.file.as_deref().ok_or_else(|| {
+        LibmagicError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "No file given",
+        ))
+    })?;
+    let file_path = &path.display().to_string();
+    // A link that is not followed is reported even when its target is missing
+    let exists = if args.no_dereference {
+        path.symlink_metadata().is_ok()
+    } else {
+        path.exists()
+    };
+    if !exists {
         return Err(LibmagicError::IoError(std::io::Error::new(
             std::io::ErrorKind::NotFound,
             format!("File not found: {}", file_path),
         )));
     }
 
-    // Load magic database (placeholder implementation)
-    let db = MagicDatabase::load_from_file("magic.db")?;
+    // Load magic database
+    let db = load_database(args)?;
+
+    if args.recursive && path.is_dir() {
+        let failures = scan_directory(&db, args, path, &mut |line| println!("{line}"));
+        if failures > 0 {
+            return Err(LibmagicError::IoError(std::io::Error::other(format!(
+                "{failures} file(s) under {file_path} could not be identified"
+            ))));
+        }
+        return Ok(());
+    }
+
+    // Evaluate file and output results
+    println!("{}", identify_file(&db, args, path, file_path)?);
 
-    // Evaluate file
+    Ok(())
+}
+
+/// The output line for the file at `path`, reported as `file`
+fn identify_file(
+    db: &MagicDatabase,
+    args: &Args,
+    path: &Path,
+    file: &str,
+) -> Result<String, LibmagicError> {
+    if args.mime_encoding {
+        // Neither an empty file nor a symbolic link that is not followed has content
+        let empty = std::fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.len() == 0);
+        if empty || (args.no_dereference && path.is_symlink()) {
+            return Ok(format_text(args, file, mime_encoding(&[])));
+        }
+        // Devices and FIFOs are only sampled with `-s`, as for identification
+        let sample = db.open_file(path, Some(ENCODING_SAMPLE_LEN))?;
+        return Ok(format_text(args, file, mime_encoding(sample.as_slice())));
+    }
     let result = db.evaluate_file(path)?;
+    Ok(format_output_for(args, file, &result))
+}
+
+/// Identify every file below `dir`, passing one output line per file to `emit`
+///
+/// Entries are visited in name order and symbolic links to directories are
+/// not descended into. A file or directory that cannot be read is reported as
+/// `path: ERROR (reason)` and the scan continues. Returns the number of
+/// entries that could not be identified.
+fn scan_directory(
+    db: &MagicDatabase,
+    args: &Args,
+    dir: &Path,
+    emit: &mut dyn FnMut(String),
+) -> usize {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            emit(format_error(args, dir, &LibmagicError::IoError(e)));
+            return 1;
+        }
+    };
+    let mut entries: Vec<_> = entries.filter_map(std::result::Result::ok).collect();
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+
+    let mut failures = 0;
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            failures += scan_directory(db, args, &path, emit);
+            continue;
+        }
+        match identify_file(db, args, &path, &path.display().to_string()) {
+            Ok(line) => emit(line),
+            Err(e) => {
+                emit(format_error(args, &path, &e));
+                failures += 1;
+            }
+        }
+    }
+    failures
+}
+
+/// The line reported for a file that could not be identified
+fn format_error(args: &Args, path: &Path, error: &LibmagicError) -> String {
+    // The I/O error that caused the failure may be wrapped several times
+    let kind = std::iter::successors(Some(error as &(dyn Error + 'static)), |&e| e.source())
+        .filter_map(|e| e.downcast_ref::<std::io::Error>())
+        .map(std::io::Error::kind)
+        .find(|kind| {
+            matches!(
+                kind,
+                std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::NotFound
+            )
+        });
+    let reason = match kind {
+        Some(std::io::ErrorKind::PermissionDenied) => "permission denied".to_string(),
+        Some(_) => "not found".to_string(),
+        None => error.to_string(),
+    };
+    format_result(
+        &path.display().to_string(),
+        &format!("ERROR ({reason})"),
+        &args.separator,
+    )
+}
+
+fn run_stats(args: &Args) -> Result<(), LibmagicError> {
+    let db = MagicDatabase::load_from_file(magic_file_path(args))?;
+    println!("{}", format_stats(&db));
+    Ok(())
+}
 
-    // Output results
-    if json_output {
+/// Parse `source` and write it as a compiled database to `output`
+///
+/// Nothing is written if the source fails to parse.
+fn run_compile(source: &str, output: &str) -> Result<(), LibmagicError> {
+    let rules = parser::parse_magic_file(source)?;
+    compiled::write_compiled(&rules, output)
+}
+
+/// Evaluation settings selected on the command line
+fn evaluation_config(args: &Args) -> EvaluationConfig {
+    let defaults = EvaluationConfig::default();
+    EvaluationConfig {
+        max_recursion_depth: args.max_depth.unwrap_or(defaults.max_recursion_depth),
+        max_string_length: args.max_string_length.unwrap_or(defaults.max_string_length),
+        max_matches: args.max_matches,
+        read_special_files: args.special_files,
+        use_mmap: !args.no_mmap,
+        stop_at_first_match: !args.keep_going,
+        follow_symlinks: !args.no_dereference,
+        timeout_ms: args.timeout,
+        ..defaults
+    }
+}
+
+/// The magic files to load: `--magic-file`, else those listed in `MAGIC`, else the default
+fn magic_file_paths(args: &Args) -> Vec<&str> {
+    if let Some(path) = args.magic_file.as_deref() {
+        return vec![path];
+    }
+    let listed: Vec<&str> = args
+        .magic_env
+        .as_deref()
+        .unwrap_or_default()
+        .split(':')
+        .filter(|path| !path.is_empty())
+        .collect();
+    if listed.is_empty() {
+        vec![DEFAULT_MAGIC_FILE]
+    } else {
+        listed
+    }
+}
+
+/// The first magic file to load, which `--stats` refers to
+fn magic_file_path(args: &Args) -> &str {
+    magic_file_paths(args)[0]
+}
+
+/// Load every selected magic file, earlier files taking precedence over later ones
+fn load_database(args: &Args) -> Result<MagicDatabase, LibmagicError> {
+    let config = evaluation_config(args);
+    let mut paths = magic_file_paths(args).into_iter();
+    let first = paths.next().unwrap_or(DEFAULT_MAGIC_FILE);
+    let first = MagicDatabase::load_from_file_with_config(first, config.clone())?;
+    paths.try_fold(first, |db, path| {
+        let next = MagicDatabase::load_from_file_with_config(path, config.clone())?;
+        Ok(db.with_fallback(next))
+    })
+}
+
+/// Render the rule counts of a loaded database for `--stats`
+fn format_stats(db: &MagicDatabase) -> String {
+    let stats = db.stats();
+    let source = db
+        .source_path()
+        .map_or_else(|| "(none)".to_string(), |path| path.display().to_string());
+
+    format!(
+        "Magic file: {source}\nTop-level rules: {}\nTotal rules: {}\nNamed subroutines: {}",
+        stats.top_level_rules, stats.total_rules, stats.named_subroutines
+    )
+}
+
+/// Render the evaluation result for `file` according to the selected output mode
+fn format_output_for(args: &Args, file: &str, result: &EvaluationResult) -> String {
+    if args.json {
         let json_result = serde_json::json!({
-            "filename": file_path,
-            "description": result.description,
+            "schema_version": JSON_SCHEMA_VERSION,
+            "filename": file,
+            "description": format_description(args, result),
             "mime_type": result.mime_type,
-            "confidence": result.confidence
+            "apple_type": result.apple_type,
+            "apple_creator": result.apple_creator,
+            "mime_types": result.mime_types(),
+            "extensions": result.extensions,
+            "confidence": result.confidence,
+            "examined_range": result.examined_range()
         });
-        println!("{}", serde_json::to_string_pretty(&json_result).unwrap());
+        serde_json::to_string_pretty(&json_result).unwrap()
     } else {
-        println!("{}: {}", file_path, result.description);
+        let text = if args.mime_type {
+            format_mime_types(args, result)
+        } else if args.extension {
+            format_extensions(result)
+        } else {
+            format_description(args, result)
+        };
+        let mut output = format_text(args, file, &text);
+        if args.eval_stats && !args.brief {
+            output.push(' ');
+            output.push_str(&format_eval_stats(&result.metadata));
+        }
+        if args.verbose {
+            // Line numbers refer to the magic file of the database that answered
+            let source = result.source_path.as_deref().map_or_else(
+                || "line ".to_string(),
+                |path| format!("{}:", path.display()),
+            );
+            for line in result.matches.iter().filter_map(|m| m.source_line) {
+                output.push_str(&format!("\nmatched rule at {source}{line}"));
+            }
+            if let Some((start, end)) = result.examined_range() {
+                output.push_str(&format!("\nexamined bytes {start}..{end}"));
+            }
+        }
+        output
     }
+}
 
-    Ok(())
+/// `text` for `file`, prefixed with its name unless `--brief` is given
+fn format_text(args: &Args, file: &str, text: &str) -> String {
+    if args.brief {
+        text.to_string()
+    } else {
+        format_result(file, text, &args.separator)
+    }
+}
+
+/// The description of a result, with its messages left unformatted for `--raw`
+fn format_description(args: &Args, result: &EvaluationResult) -> String {
+    if args.raw && !result.matches.is_empty() {
+        format_raw_output(&result.matches)
+    } else {
+        result.description.clone()
+    }
+}
+
+/// The MIME type shown by `--mime-type`, or every MIME type with `--all`
+fn format_mime_types(args: &Args, result: &EvaluationResult) -> String {
+    let mime_types = result.mime_types();
+    if mime_types.is_empty() {
+        DEFAULT_MIME_TYPE.to_string()
+    } else if args.all {
+        mime_types.join(", ")
+    } else {
+        mime_types[0].to_string()
+    }
+}
+
+/// The evaluation counts and time shown by `--eval-stats`
+fn format_eval_stats(metadata: &EvaluationMetadata) -> String {
+    format!(
+        "({} rules, {} matched, {:.1}ms)",
+        metadata.rules_evaluated, metadata.rules_matched, metadata.evaluation_time_ms
+    )
+}
+
+/// The extensions shown by `--extension`, like GNU `file --extension`
+fn format_extensions(result: &EvaluationResult) -> String {
+    if result.extensions.is_empty() {
+        UNKNOWN_EXTENSION.to_string()
+    } else {
+        result.extensions.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libmagic_rs::evaluator::{EvaluationContext, evaluate_rules};
+    use std::time::{Duration, Instant};
+
+    /// Render an evaluation result for the file named on the command line
+    fn format_output(args: &Args, result: &EvaluationResult) -> String {
+        let file = args.file.as_deref().unwrap_or_else(|| Path::new(""));
+        format_output_for(args, &file.display().to_string(), result)
+    }
+
+    fn sample_result() -> EvaluationResult {
+        EvaluationResult {
+            description: "ELF 64-bit LSB executable".to_string(),
+            mime_type: None,
+            apple_type: None,
+            apple_creator: None,
+            extensions: Vec::new(),
+            rules_loaded: true,
+            confidence: 1.0,
+            matches: Vec::new(),
+            source_path: None,
+            metadata: libmagic_rs::output::EvaluationMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_format_output_eval_stats() {
+        let mut result = sample_result();
+        result.metadata = EvaluationMetadata {
+            file_size: 64,
+            evaluation_time_ms: 2.34,
+            rules_evaluated: 42,
+            rules_matched: 1,
+        };
+
+        let args = Args::try_parse_from(["rmagic", "--eval-stats", "a.out"]).unwrap();
+        assert_eq!(
+            format_output(&args, &result),
+            "a.out: ELF 64-bit LSB executable (42 rules, 1 matched, 2.3ms)"
+        );
+
+        // Brief output is only the description
+        let args = Args::try_parse_from(["rmagic", "-b", "--eval-stats", "a.out"]).unwrap();
+        assert_eq!(format_output(&args, &result), "ELF 64-bit LSB executable");
+
+        // The counts come from the evaluation itself
+        let rules =
+            parser::parse_magic_string("0 string \\x7fELF ELF\n>4 byte 2 64-bit\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let result = db.evaluate_bytes(b"\x7fELF\x01").unwrap();
+        let args = Args::try_parse_from(["rmagic", "--eval-stats", "a.out"]).unwrap();
+        assert!(
+            format_output(&args, &result).starts_with("a.out: ELF (2 rules, 1 matched, "),
+            "{}",
+            format_output(&args, &result)
+        );
+    }
+
+    #[test]
+    fn test_args_default_separator() {
+        let args = Args::try_parse_from(["rmagic", "test.bin"]).unwrap();
+        assert_eq!(args.file, Some(PathBuf::from("test.bin")));
+        assert_eq!(args.separator, ": ");
+        assert!(!args.brief);
+        assert!(!args.json);
+    }
+
+    #[test]
+    fn test_args_custom_separator() {
+        let args = Args::try_parse_from(["rmagic", "--separator", "\t", "test.bin"]).unwrap();
+        assert_eq!(args.separator, "\t");
+
+        let args = Args::try_parse_from(["rmagic", "-F", " | ", "test.bin"]).unwrap();
+        assert_eq!(args.separator, " | ");
+    }
+
+    #[test]
+    fn test_args_separator_requires_value() {
+        assert!(Args::try_parse_from(["rmagic", "test.bin", "--separator"]).is_err());
+    }
+
+    #[test]
+    fn test_format_output_uses_separator() {
+        let args = Args::try_parse_from(["rmagic", "-F", "\t", "a:b.bin"]).unwrap();
+        assert_eq!(
+            format_output(&args, &sample_result()),
+            "a:b.bin\tELF 64-bit LSB executable"
+        );
+    }
+
+    #[test]
+    fn test_format_output_brief_ignores_separator() {
+        let args = Args::try_parse_from(["rmagic", "-b", "-F", "\t", "test.bin"]).unwrap();
+        assert_eq!(
+            format_output(&args, &sample_result()),
+            "ELF 64-bit LSB executable"
+        );
+    }
+
+    #[test]
+    fn test_args_special_files() {
+        let args = Args::try_parse_from(["rmagic", "test.bin"]).unwrap();
+        assert!(!evaluation_config(&args).read_special_files);
+
+        let args = Args::try_parse_from(["rmagic", "-s", "/dev/sda"]).unwrap();
+        assert!(evaluation_config(&args).read_special_files);
+        let args = Args::try_parse_from(["rmagic", "--special-files", "/dev/sda"]).unwrap();
+        assert!(args.special_files);
+    }
+
+    #[test]
+    fn test_magic_env_lists_magic_files_unless_overridden() {
+        let dir = std::env::temp_dir().join(format!("rmagic_magic_env_{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.magic");
+        let second = dir.join("second.magic");
+        std::fs::write(&first, "0 string ACME ACME firmware\n").unwrap();
+        std::fs::write(&second, "0 string ACME generic\n0 string PK ZIP\n").unwrap();
+        let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+        let mut args = Args::try_parse_from(["rmagic", "a.bin"]).unwrap();
+        assert_eq!(magic_file_paths(&args), [DEFAULT_MAGIC_FILE]);
+
+        args.magic_env = Some(format!("{first}::{second}"));
+        assert_eq!(magic_file_paths(&args), [first, second]);
+        let db = load_database(&args).unwrap();
+        assert_eq!(
+            db.evaluate_bytes(b"ACME").unwrap().description,
+            "ACME firmware"
+        );
+        assert_eq!(db.evaluate_bytes(b"PK\x03\x04").unwrap().description, "ZIP");
+
+        // An explicit --magic-file replaces the whole list
+        let mut args = Args::try_parse_from(["rmagic", "--magic-file", second, "a.bin"]).unwrap();
+        args.magic_env = Some(first.to_string());
+        assert_eq!(magic_file_paths(&args), [second]);
+        let db = load_database(&args).unwrap();
+        assert_eq!(db.evaluate_bytes(b"ACME").unwrap().description, "generic");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mime_encoding_reports_text_encoding() {
+        let dir = std::env::temp_dir().join(format!("rmagic_encoding_{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = MagicDatabase::from_rules(Vec::new(), EvaluationConfig::default());
+        let args = Args::try_parse_from(["rmagic", "--mime-encoding", "a.txt"]).unwrap();
+
+        for (name, contents, expected) in [
+            ("ascii.txt", b"hello, world\n".as_slice(), "us-ascii"),
+            ("utf8.txt", "gr\u{fc}\u{df}e\n".as_bytes(), "utf-8"),
+            ("binary.bin", b"\x7fELF\x02\x01\x01\x00\x00", "binary"),
+            ("empty.txt", b"", "binary"),
+        ] {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            assert_eq!(
+                identify_file(&db, &args, &path, name).unwrap(),
+                format!("{name}: {expected}")
+            );
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        #[cfg(unix)]
+        {
+            let fifo = std::env::temp_dir().join(format!("rmagic_encoding_fifo_{}", process::id()));
+            let _ = std::fs::remove_file(&fifo);
+            nix::unistd::mkfifo(&fifo, nix::sys::stat::Mode::S_IRWXU).unwrap();
+            // Neither is opened without `-s`, so this neither blocks nor reads forever
+            for special in [fifo.as_path(), Path::new("/dev/zero")] {
+                assert!(identify_file(&db, &args, special, "special").is_err());
+            }
+            std::fs::remove_file(&fifo).unwrap();
+
+            let args =
+                Args::try_parse_from(["rmagic", "--mime-encoding", "-s", "/dev/zero"]).unwrap();
+            let db = MagicDatabase::from_rules(Vec::new(), evaluation_config(&args));
+            let zero = Path::new("/dev/zero");
+            assert_eq!(
+                identify_file(&db, &args, zero, "/dev/zero").unwrap(),
+                "/dev/zero: binary"
+            );
+        }
+
+        assert!(Args::try_parse_from(["rmagic", "--mime-encoding", "--json", "a.txt"]).is_err());
+        assert!(Args::try_parse_from(["rmagic", "--mime-encoding", "-i", "a.txt"]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dereference_flags_control_symlink_following() {
+        let dir = std::env::temp_dir().join(format!("rmagic_dereference_{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.bin");
+        let link = dir.join("link.bin");
+        std::fs::write(&target, b"\x7fELF\x02").unwrap();
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let rules = parser::parse_magic_string("0 string \\x7fELF ELF\n").unwrap();
+
+        let identify = |flags: &[&str]| {
+            let args = Args::try_parse_from(
+                std::iter::once("rmagic")
+                    .chain(flags.iter().copied())
+                    .chain(["link.bin"]),
+            )
+            .unwrap();
+            let db = MagicDatabase::from_rules(rules.clone(), evaluation_config(&args));
+            identify_file(&db, &args, &link, "link.bin").unwrap()
+        };
+
+        assert_eq!(identify(&[]), "link.bin: ELF");
+        assert_eq!(identify(&["-L"]), "link.bin: ELF");
+        assert_eq!(
+            identify(&["-h"]),
+            format!("link.bin: symbolic link to {}", target.display())
+        );
+        assert_eq!(
+            identify(&["--no-dereference", "--mime-encoding"]),
+            "link.bin: binary"
+        );
+        // The last of the two flags wins
+        assert_eq!(identify(&["-h", "--dereference"]), "link.bin: ELF");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_args_help_is_long_only() {
+        let help = Args::try_parse_from(["rmagic", "--help"]).unwrap_err();
+        assert_eq!(help.kind(), clap::error::ErrorKind::DisplayHelp);
+    }
+
+    #[test]
+    fn test_args_no_mmap() {
+        let args = Args::try_parse_from(["rmagic", "test.bin"]).unwrap();
+        assert!(evaluation_config(&args).use_mmap);
+
+        let args = Args::try_parse_from(["rmagic", "--no-mmap", "test.bin"]).unwrap();
+        assert!(!evaluation_config(&args).use_mmap);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_directory_continues_past_unreadable_entries() {
+        let dir = std::env::temp_dir().join(format!("rmagic_scan_{}", process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.bin"), b"\x7fELF").unwrap();
+        // A dangling link stands in for an entry that cannot be opened
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("b.bin")).unwrap();
+        std::fs::write(dir.join("sub").join("c.bin"), b"PK\x03\x04").unwrap();
+
+        let rules = parser::parse_magic_string("0 string \\x7fELF ELF\n0 string PK ZIP\n").unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let args = Args::try_parse_from(["rmagic", "-R", dir.to_str().unwrap()]).unwrap();
+        assert!(args.recursive);
+
+        let mut lines = Vec::new();
+        let failures = scan_directory(&db, &args, &dir, &mut |line| lines.push(line));
+        let root = dir.display();
+        assert_eq!(
+            lines,
+            [
+                format!("{root}/a.bin: ELF"),
+                format!("{root}/b.bin: ERROR (not found)"),
+                format!("{root}/sub/c.bin: ZIP"),
+            ]
+        );
+        assert_eq!(failures, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_args_timeout() {
+        let args = Args::try_parse_from(["rmagic", "test.bin"]).unwrap();
+        assert_eq!(evaluation_config(&args).timeout_ms, None);
+
+        let args = Args::try_parse_from(["rmagic", "--timeout", "250", "test.bin"]).unwrap();
+        assert_eq!(evaluation_config(&args).timeout_ms, Some(250));
+        assert!(Args::try_parse_from(["rmagic", "--timeout", "soon", "test.bin"]).is_err());
+
+        // Out-of-range values are rejected before any file is read
+        for value in ["0", "300001"] {
+            let error =
+                Args::try_parse_from(["rmagic", "--timeout", value, "test.bin"]).unwrap_err();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        }
+    }
+
+    #[test]
+    fn test_args_resource_limits() {
+        let args = Args::try_parse_from(["rmagic", "test.bin"]).unwrap();
+        assert_eq!(evaluation_config(&args), EvaluationConfig::default());
+
+        let args = Args::try_parse_from(["rmagic", "--max-depth", "5", "test.bin"]).unwrap();
+        assert_eq!(evaluation_config(&args).max_recursion_depth, 5);
+        let args =
+            Args::try_parse_from(["rmagic", "--max-string-length", "64", "test.bin"]).unwrap();
+        assert_eq!(evaluation_config(&args).max_string_length, 64);
+        let args =
+            Args::try_parse_from(["rmagic", "-k", "--max-matches", "3", "test.bin"]).unwrap();
+        assert_eq!(evaluation_config(&args).max_matches, Some(3));
+
+        assert!(Args::try_parse_from(["rmagic", "--max-depth", "-1", "test.bin"]).is_err());
+    }
+
+    #[test]
+    fn test_args_resource_limits_are_validated() {
+        for (flag, value) in [
+            ("--max-depth", "0"),
+            ("--max-depth", "5000"),
+            ("--max-string-length", "0"),
+            ("--max-string-length", "2000000"),
+            ("--max-matches", "0"),
+        ] {
+            let error = Args::try_parse_from(["rmagic", flag, value, "test.bin"]).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                clap::error::ErrorKind::ValueValidation,
+                "{flag} {value}"
+            );
+            assert!(error.to_string().contains(flag), "{error}");
+        }
+
+        // Limits that are only unsafe together are reported without the magic file wording
+        let args = Args::try_parse_from([
+            "rmagic",
+            "--max-depth",
+            "200",
+            "--max-string-length",
+            "100000",
+            "test.bin",
+        ])
+        .unwrap();
+        let error = config_usage_error(&args).unwrap();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        assert!(!error.to_string().contains("magic file"), "{error}");
+
+        let args = Args::try_parse_from(["rmagic", "--max-depth", "5", "test.bin"]).unwrap();
+        assert!(config_usage_error(&args).is_none());
+    }
+
+    #[test]
+    fn test_expired_timeout_exits_with_timeout_code() {
+        let rules = parser::parse_magic_string("0 byte 0 zero\n").unwrap();
+        let args = Args::try_parse_from(["rmagic", "--timeout", "1", "big.bin"]).unwrap();
+        let mut context = EvaluationContext::new(evaluation_config(&args));
+        // The budget is already spent when evaluation starts
+        context.set_start_time(Instant::now() - Duration::from_millis(50));
+
+        let error = evaluate_rules(&rules, &[0], &mut context).unwrap_err();
+        assert!(matches!(error, LibmagicError::Timeout { timeout_ms: 1 }));
+        assert_eq!(exit_code(&error), TIMEOUT_EXIT_CODE);
+    }
+
+    #[test]
+    fn test_args_keep_going() {
+        let args = Args::try_parse_from(["rmagic", "test.bin"]).unwrap();
+        assert!(evaluation_config(&args).stop_at_first_match);
+
+        let args = Args::try_parse_from(["rmagic", "-k", "test.bin"]).unwrap();
+        assert!(!evaluation_config(&args).stop_at_first_match);
+        let args = Args::try_parse_from(["rmagic", "--keep-going", "test.bin"]).unwrap();
+        assert!(args.keep_going);
+    }
+
+    #[test]
+    fn test_args_stats_without_file() {
+        let args = Args::try_parse_from(["rmagic", "--stats"]).unwrap();
+        assert!(args.stats);
+        assert!(Args::try_parse_from(["rmagic"]).is_err());
+    }
+
+    #[test]
+    fn test_format_stats_counts_loaded_database() {
+        let path = std::env::temp_dir().join(format!("rmagic_stats_{}.magic", process::id()));
+        std::fs::write(
+            &path,
+            "\
+# Test database
+0 string \\x7fELF ELF
+>4 byte 1 32-bit
+>4 byte 2 64-bit
+>>5 byte 1 LSB
+0 string PK ZIP archive
+0 name header
+>0 byte 0 empty
+0 use header
+",
+        )
+        .unwrap();
+
+        let db = MagicDatabase::load_from_file(&path).unwrap();
+        let output = format_stats(&db);
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = format!(
+            "Magic file: {}\nTop-level rules: 3\nTotal rules: 8\nNamed subroutines: 1",
+            path.display()
+        );
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_args_compile_requires_output() {
+        let args =
+            Args::try_parse_from(["rmagic", "--compile", "magic.src", "-o", "magic.mgc"]).unwrap();
+        assert_eq!(args.compile.as_deref(), Some("magic.src"));
+        assert_eq!(args.output.as_deref(), Some("magic.mgc"));
+        assert!(Args::try_parse_from(["rmagic", "--compile", "magic.src"]).is_err());
+    }
+
+    #[test]
+    fn test_compile_and_load_compiled_database() {
+        let dir = std::env::temp_dir();
+        let source = dir.join(format!("rmagic_compile_{}.magic", process::id()));
+        let output = dir.join(format!("rmagic_compile_{}.mgc", process::id()));
+        std::fs::write(
+            &source,
+            "0 string \\x7fELF ELF\n>4 byte 1 32-bit\n>4 byte 2 64-bit\n",
+        )
+        .unwrap();
+
+        run_compile(source.to_str().unwrap(), output.to_str().unwrap()).unwrap();
+        let db = MagicDatabase::load_from_file(&output).unwrap();
+        let result = db.evaluate_bytes(b"\x7fELF\x02").unwrap();
+
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(result.description, "ELF 64-bit");
+    }
+
+    #[test]
+    fn test_compile_refuses_to_write_on_parse_error() {
+        let dir = std::env::temp_dir();
+        let source = dir.join(format!("rmagic_compile_bad_{}.magic", process::id()));
+        let output = dir.join(format!("rmagic_compile_bad_{}.mgc", process::id()));
+        std::fs::write(&source, "0 string PK ZIP\nnot a rule\n").unwrap();
+
+        let err = run_compile(source.to_str().unwrap(), output.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&source).unwrap();
+
+        match err {
+            LibmagicError::ParseError { line, .. } => assert_eq!(line, 2),
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_format_output_verbose_reports_rule_lines() {
+        let path = std::env::temp_dir().join(format!("rmagic_verbose_{}.magic", process::id()));
+        std::fs::write(
+            &path,
+            "# GIF\n0 string GIF8 GIF image data\n>4 byte 0x39 version 89a\n",
+        )
+        .unwrap();
+        let magic_file = path.to_str().unwrap();
+
+        let db = MagicDatabase::load_from_file(&path).unwrap();
+        let result = db.evaluate_bytes(b"GIF89a").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let args =
+            Args::try_parse_from(["rmagic", "--verbose", "--magic-file", magic_file, "a.gif"])
+                .unwrap();
+        assert_eq!(
+            format_output(&args, &result),
+            format!(
+                "a.gif: GIF image data version 89a\n\
+                 matched rule at {magic_file}:2\n\
+                 matched rule at {magic_file}:3\n\
+                 examined bytes 0..4"
+            )
+        );
+
+        let args = Args::try_parse_from(["rmagic", "--magic-file", magic_file, "a.gif"]).unwrap();
+        assert_eq!(
+            format_output(&args, &result),
+            "a.gif: GIF image data version 89a"
+        );
+    }
+
+    #[test]
+    fn test_format_output_verbose_names_the_answering_magic_file() {
+        let dir = std::env::temp_dir().join(format!("rmagic_verbose_env_{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.magic");
+        let second = dir.join("second.magic");
+        std::fs::write(&first, "0 string ACME ACME firmware\n").unwrap();
+        std::fs::write(&second, "# archives\n0 string PK ZIP\n").unwrap();
+
+        let mut args = Args::try_parse_from(["rmagic", "--verbose", "a.zip"]).unwrap();
+        args.magic_env = Some(format!("{}:{}", first.display(), second.display()));
+        let db = load_database(&args).unwrap();
+        let result = db.evaluate_bytes(b"PK\x03\x04").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Only the second file has a rule for ZIP archives
+        assert_eq!(result.source_path.as_deref(), Some(second.as_path()));
+        assert_eq!(
+            format_output(&args, &result),
+            format!(
+                "a.zip: ZIP\nmatched rule at {}:2\nexamined bytes 0..2",
+                second.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_output_escapes_hostile_filename() {
+        let name = "x\n\x1b[31mfake.bin: PNG image data";
+        let args = Args::try_parse_from(["rmagic", name]).unwrap();
+        assert_eq!(
+            format_output(&args, &sample_result()),
+            "x\\012\\033[31mfake.bin: PNG image data: ELF 64-bit LSB executable"
+        );
+
+        let args = Args::try_parse_from(["rmagic", "--json", name]).unwrap();
+        let output = format_output(&args, &sample_result());
+        assert!(!output.contains('\x1b'));
+        let parsed: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(parsed["filename"], name);
+    }
+
+    #[test]
+    fn test_format_output_json_ignores_separator() {
+        let args = Args::try_parse_from(["rmagic", "--json", "-F", "\t", "test.bin"]).unwrap();
+        let output = format_output(&args, &sample_result());
+        let parsed: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(parsed["schema_version"], 1);
+        assert_eq!(parsed["filename"], "test.bin");
+        assert_eq!(parsed["description"], "ELF 64-bit LSB executable");
+        assert_eq!(parsed["examined_range"], serde_json::Value::Null);
+        assert!(!output.contains("test.bin\t"));
+    }
+
+    /// A ZIP archive of empty stored entries, without a central directory
+    fn stored_zip(names: &[&str]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        for name in names {
+            zip.extend_from_slice(b"PK\x03\x04");
+            zip.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            zip.extend_from_slice(&[0; 8]);
+            zip.extend_from_slice(&u16::try_from(name.len()).unwrap().to_le_bytes());
+            zip.extend_from_slice(&[0, 0]);
+            zip.extend_from_slice(name.as_bytes());
+        }
+        zip
+    }
+
+    #[test]
+    fn test_mime_type_of_zip_subtypes() {
+        let dir = std::env::temp_dir().join(format!("rmagic_zip_mime_{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let docx = dir.join("a.docx");
+        let jar = dir.join("a.jar");
+        std::fs::write(
+            &docx,
+            stored_zip(&["[Content_Types].xml", "word/document.xml"]),
+        )
+        .unwrap();
+        std::fs::write(&jar, stored_zip(&["META-INF/MANIFEST.MF"])).unwrap();
+
+        let rules = parser::parse_magic_string(
+            "0 string PK\\x03\\x04 Zip archive data\n!:mime application/zip\n",
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["rmagic", "-i", "-b", "a.docx"]).unwrap();
+        let db = MagicDatabase::from_rules(rules, evaluation_config(&args));
+
+        assert_eq!(
+            identify_file(&db, &args, &docx, "a.docx").unwrap(),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        );
+        assert_eq!(
+            identify_file(&db, &args, &jar, "a.jar").unwrap(),
+            "application/java-archive"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_output_mime_types() {
+        let rules = parser::parse_magic_string(
+            "0 string %PDF PDF document\n!:mime application/pdf\n\
+             4 string PK ZIP archive\n!:mime application/zip\n",
+        )
+        .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+        let db = MagicDatabase::from_rules(rules, config);
+        let result = db.evaluate_bytes(b"%PDFPK").unwrap();
+
+        let args = Args::try_parse_from(["rmagic", "--mime-type", "a.pdf"]).unwrap();
+        assert_eq!(format_output(&args, &result), "a.pdf: application/pdf");
+        let args = Args::try_parse_from(["rmagic", "-i", "a.pdf"]).unwrap();
+        assert_eq!(format_output(&args, &result), "a.pdf: application/pdf");
+        let args = Args::try_parse_from(["rmagic", "--mime", "--all", "-b", "a.pdf"]).unwrap();
+        assert_eq!(
+            format_output(&args, &result),
+            "application/pdf, application/zip"
+        );
+        assert_eq!(
+            format_output(&args, &sample_result()),
+            "application/octet-stream"
+        );
+        assert!(Args::try_parse_from(["rmagic", "--all", "a.pdf"]).is_err());
+
+        let args = Args::try_parse_from(["rmagic", "--json", "a.pdf"]).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_output(&args, &result)).unwrap();
+        assert_eq!(
+            parsed["mime_types"],
+            serde_json::json!(["application/pdf", "application/zip"])
+        );
+    }
+
+    #[test]
+    fn test_format_output_extensions() {
+        let rules =
+            parser::parse_magic_string("0 beshort 0xffd8 JPEG image data\n!:ext jpg/jpeg\n")
+                .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let result = db.evaluate_bytes(b"\xff\xd8\xff").unwrap();
+
+        let args = Args::try_parse_from(["rmagic", "--extension", "photo"]).unwrap();
+        assert_eq!(format_output(&args, &result), "photo: jpg/jpeg");
+        let args = Args::try_parse_from(["rmagic", "--extension", "-b", "photo"]).unwrap();
+        assert_eq!(format_output(&args, &sample_result()), "???");
+
+        let args = Args::try_parse_from(["rmagic", "--json", "--extension", "photo"]).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_output(&args, &result)).unwrap();
+        assert_eq!(parsed["extensions"], serde_json::json!(["jpg", "jpeg"]));
+    }
+
+    #[test]
+    fn test_format_output_raw_keeps_placeholders() {
+        let rules =
+            parser::parse_magic_string("0 string GIF8 GIF image data\n>4 byte x version %d\n")
+                .unwrap();
+        let db = MagicDatabase::from_rules(rules, EvaluationConfig::default());
+        let result = db.evaluate_bytes(b"GIF89a").unwrap();
+
+        let args = Args::try_parse_from(["rmagic", "a.gif"]).unwrap();
+        assert_eq!(
+            format_output(&args, &result),
+            "a.gif: GIF image data version 57"
+        );
+        let args = Args::try_parse_from(["rmagic", "--raw", "a.gif"]).unwrap();
+        assert_eq!(
+            format_output(&args, &result),
+            "a.gif: GIF image data, version %d"
+        );
+        let args = Args::try_parse_from(["rmagic", "-r", "--json", "a.gif"]).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_output(&args, &result)).unwrap();
+        assert_eq!(parsed["description"], "GIF image data, version %d");
+    }
+
+    #[test]
+    fn test_format_output_json_includes_apple_codes() {
+        let args = Args::try_parse_from(["rmagic", "--json", "test.bin"]).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_output(&args, &sample_result())).unwrap();
+        assert!(parsed["apple_type"].is_null());
+
+        let result = EvaluationResult {
+            apple_type: Some("TEXT".to_string()),
+            apple_creator: Some("ttxt".to_string()),
+            ..sample_result()
+        };
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_output(&args, &result)).unwrap();
+        assert_eq!(parsed["apple_type"], "TEXT");
+        assert_eq!(parsed["apple_creator"], "ttxt");
+    }
 }
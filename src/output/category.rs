@@ -0,0 +1,252 @@
+//! Coarse file categories for triage
+//!
+//! A [`FileCategory`] groups detected file types into broad kinds such as
+//! images or archives. It is derived from the MIME type of a match when there
+//! is one, and from keywords in the description otherwise. Both mappings are
+//! tables, so supporting a new type is a one-line change.
+
+use crate::parser::ast::MagicRule;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Broad kind of a detected file type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FileCategory {
+    /// Raster or vector images
+    Image,
+    /// Audio recordings and music
+    Audio,
+    /// Video and animation containers
+    Video,
+    /// Archives and compressed data
+    Archive,
+    /// Executables, shared libraries and object files
+    Executable,
+    /// Office documents, PDFs and e-books
+    Document,
+    /// Plain text and text-based formats
+    Text,
+    /// Anything that fits none of the other categories
+    Other,
+}
+
+/// MIME type prefixes and their categories, checked in order
+///
+/// A prefix ending in `/` covers a whole top-level type; other entries cover
+/// one type and any parameters after it.
+const MIME_CATEGORIES: &[(&str, FileCategory)] = &[
+    ("image/", FileCategory::Image),
+    ("audio/", FileCategory::Audio),
+    ("video/", FileCategory::Video),
+    ("application/epub+zip", FileCategory::Document),
+    ("application/java-archive", FileCategory::Archive),
+    ("application/zip", FileCategory::Archive),
+    ("application/gzip", FileCategory::Archive),
+    ("application/x-gzip", FileCategory::Archive),
+    ("application/x-tar", FileCategory::Archive),
+    ("application/x-bzip2", FileCategory::Archive),
+    ("application/x-xz", FileCategory::Archive),
+    ("application/zstd", FileCategory::Archive),
+    ("application/x-7z-compressed", FileCategory::Archive),
+    ("application/vnd.rar", FileCategory::Archive),
+    ("application/x-rar", FileCategory::Archive),
+    ("application/x-executable", FileCategory::Executable),
+    ("application/x-pie-executable", FileCategory::Executable),
+    ("application/x-sharedlib", FileCategory::Executable),
+    ("application/x-object", FileCategory::Executable),
+    ("application/x-mach-binary", FileCategory::Executable),
+    ("application/x-dosexec", FileCategory::Executable),
+    (
+        "application/vnd.microsoft.portable-executable",
+        FileCategory::Executable,
+    ),
+    ("application/pdf", FileCategory::Document),
+    ("application/postscript", FileCategory::Document),
+    ("application/rtf", FileCategory::Document),
+    ("application/msword", FileCategory::Document),
+    ("application/vnd.ms-", FileCategory::Document),
+    (
+        "application/vnd.openxmlformats-officedocument.",
+        FileCategory::Document,
+    ),
+    (
+        "application/vnd.oasis.opendocument.",
+        FileCategory::Document,
+    ),
+    ("application/json", FileCategory::Text),
+    ("application/xml", FileCategory::Text),
+    ("application/javascript", FileCategory::Text),
+    ("text/", FileCategory::Text),
+];
+
+/// Description keywords and their categories, checked in order
+///
+/// Keywords are matched case-insensitively anywhere in the description, so
+/// more specific keywords come first (`executable` before `text` for scripts).
+const DESCRIPTION_CATEGORIES: &[(&str, FileCategory)] = &[
+    ("executable", FileCategory::Executable),
+    ("shared object", FileCategory::Executable),
+    ("archive", FileCategory::Archive),
+    ("compressed", FileCategory::Archive),
+    ("image", FileCategory::Image),
+    ("audio", FileCategory::Audio),
+    ("video", FileCategory::Video),
+    ("document", FileCategory::Document),
+    ("text", FileCategory::Text),
+];
+
+impl FileCategory {
+    /// The category of a MIME type, or `Other` if it is not in the table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::output::category::FileCategory;
+    ///
+    /// assert_eq!(FileCategory::from_mime_type("image/png"), FileCategory::Image);
+    /// assert_eq!(FileCategory::from_mime_type("text/plain; charset=us-ascii"), FileCategory::Text);
+    /// assert_eq!(FileCategory::from_mime_type("application/octet-stream"), FileCategory::Other);
+    /// ```
+    #[must_use]
+    pub fn from_mime_type(mime_type: &str) -> Self {
+        let mime_type = mime_type.trim().to_ascii_lowercase();
+        MIME_CATEGORIES
+            .iter()
+            .find(|(prefix, _)| {
+                mime_type.strip_prefix(prefix).is_some_and(|rest| {
+                    prefix.ends_with(['/', '.', '-']) || rest.is_empty() || rest.starts_with(';')
+                })
+            })
+            .map_or(Self::Other, |&(_, category)| category)
+    }
+
+    /// The category suggested by keywords in a description, or `Other` if none match
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::output::category::FileCategory;
+    ///
+    /// assert_eq!(FileCategory::from_description("PNG image data"), FileCategory::Image);
+    /// assert_eq!(FileCategory::from_description("ELF 64-bit LSB executable"), FileCategory::Executable);
+    /// assert_eq!(FileCategory::from_description("data"), FileCategory::Other);
+    /// ```
+    #[must_use]
+    pub fn from_description(description: &str) -> Self {
+        let description = description.to_lowercase();
+        DESCRIPTION_CATEGORIES
+            .iter()
+            .find(|(keyword, _)| description.contains(keyword))
+            .map_or(Self::Other, |&(_, category)| category)
+    }
+
+    /// The category of the file type a rule detects, from its MIME type
+    ///
+    /// The rule's own `!:mime` type is used when it has one with a known
+    /// category, and otherwise the first such type among its children, since
+    /// the MIME type is often given on the rule that identifies the variant.
+    /// A rule without any is `Other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::output::category::FileCategory;
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 string GIF8 GIF\n>4 byte 0x39 89a\n!:mime image/gif\n")?;
+    /// assert_eq!(FileCategory::of_rule(&rules[0]), FileCategory::Image);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn of_rule(rule: &MagicRule) -> Self {
+        rule.mime_type
+            .as_deref()
+            .map(Self::from_mime_type)
+            .filter(|&category| category != Self::Other)
+            .or_else(|| {
+                rule.children
+                    .iter()
+                    .map(Self::of_rule)
+                    .find(|&category| category != Self::Other)
+            })
+            .unwrap_or(Self::Other)
+    }
+}
+
+impl fmt::Display for FileCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Image => "image",
+            Self::Audio => "audio",
+            Self::Video => "video",
+            Self::Archive => "archive",
+            Self::Executable => "executable",
+            Self::Document => "document",
+            Self::Text => "text",
+            Self::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_mime_type_uses_table() {
+        assert_eq!(
+            FileCategory::from_mime_type("image/png"),
+            FileCategory::Image
+        );
+        assert_eq!(
+            FileCategory::from_mime_type("application/zip"),
+            FileCategory::Archive
+        );
+        assert_eq!(
+            FileCategory::from_mime_type("application/epub+zip"),
+            FileCategory::Document
+        );
+        assert_eq!(
+            FileCategory::from_mime_type(
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+            ),
+            FileCategory::Document
+        );
+        // A listed type does not cover longer names that merely start with it
+        assert_eq!(
+            FileCategory::from_mime_type("application/zipfoo"),
+            FileCategory::Other
+        );
+    }
+
+    #[test]
+    fn test_of_rule_uses_first_known_mime_type() {
+        let rules = crate::parser::parse_magic_string(
+            "0 string PK\\x03\\x04 Zip data\n!:mime application/octet-stream\n\
+             >30 string mimetype container\n\
+             >>38 string application/epub+zip EPUB\n!:mime application/epub+zip\n\
+             0 string RIFF RIFF data\n",
+        )
+        .unwrap();
+
+        assert_eq!(FileCategory::of_rule(&rules[0]), FileCategory::Document);
+        assert_eq!(FileCategory::of_rule(&rules[1]), FileCategory::Other);
+    }
+
+    #[test]
+    fn test_from_description_prefers_specific_keywords() {
+        assert_eq!(
+            FileCategory::from_description("POSIX shell script, ASCII text executable"),
+            FileCategory::Executable
+        );
+        assert_eq!(
+            FileCategory::from_description("Zip archive data"),
+            FileCategory::Archive
+        );
+        assert_eq!(
+            FileCategory::from_description("UTF-8 Unicode text"),
+            FileCategory::Text
+        );
+    }
+}
@@ -6,13 +6,22 @@
 //! The module follows a structured approach where evaluation results contain metadata
 //! about the evaluation process and a list of matches found during rule processing.
 
+pub mod category;
 pub mod text;
 
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::path::PathBuf;
 
 use crate::parser::ast::Value;
 
+/// Version of the JSON document written for each file by `rmagic --json`
+///
+/// Consumers should check it before reading the other fields. It is bumped
+/// whenever a field is removed, renamed or changes meaning; adding a field is
+/// not a breaking change and keeps the version.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
 /// Result of a single magic rule match
 ///
 /// Contains all information about a successful rule match, including the matched
@@ -32,6 +41,8 @@
 ///     rule_path: vec!["elf".to_string(), "elf64".to_string()],
 ///     confidence: 90,
 ///     mime_type: Some("application/x-executable".to_string()),
+///     source_line: Some(12),
+///     level: 0,
 /// };
 ///
 /// assert_eq!(result.message, "ELF 64-bit LSB executable");
@@ -46,6 +57,9 @@ pub struct MatchResult {
     pub offset: usize,
 
     /// Number of bytes that were examined for this match
+    ///
+    /// For integer types this is the width of the type read, such as 2 for a
+    /// `short`.
     pub length: usize,
 
     /// The actual value that was matched at the specified offset
@@ -68,6 +82,17 @@ pub struct MatchResult {
     /// When available, provides the standard MIME type corresponding
     /// to the detected file format.
     pub mime_type: Option<String>,
+
+    /// Line number of the matching rule in its magic source file
+    ///
+    /// `None` for built-in detections and for rules that were not parsed from
+    /// a source file.
+    #[serde(default)]
+    pub source_line: Option<usize>,
+
+    /// Nesting level of the matching rule, 0 for a top-level rule
+    #[serde(default)]
+    pub level: u32,
 }
 
 /// Complete evaluation result for a file
@@ -93,6 +118,8 @@ pub struct MatchResult {
 ///             rule_path: vec!["elf".to_string()],
 ///             confidence: 95,
 ///             mime_type: Some("application/x-executable".to_string()),
+///             source_line: None,
+///             level: 0,
 ///         }
 ///     ],
 ///     metadata: EvaluationMetadata {
@@ -132,12 +159,14 @@ pub struct EvaluationResult {
 ///
 /// Provides diagnostic information about how the evaluation was performed,
 /// including performance metrics and statistics about rule processing.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EvaluationMetadata {
     /// Size of the analyzed file in bytes
     pub file_size: u64,
 
-    /// Time taken for evaluation in milliseconds
+    /// Time taken for evaluation in fractional milliseconds
+    ///
+    /// Measured with a monotonic clock, so it is never negative.
     pub evaluation_time_ms: f64,
 
     /// Total number of rules that were evaluated
@@ -152,6 +181,10 @@ pub struct EvaluationMetadata {
 impl MatchResult {
     /// Create a new match result with basic information
     ///
+    /// The length is taken from the value, so integers are assumed to be
+    /// 8 bytes wide. Matches converted from the evaluator carry the exact
+    /// width of their type instead.
+    ///
     /// # Arguments
     ///
     /// * `message` - Human-readable description of the match
@@ -182,12 +215,14 @@ pub fn new(message: String, offset: usize, value: Value) -> Self {
             length: match &value {
                 Value::Bytes(bytes) => bytes.len(),
                 Value::String(s) => s.len(),
-                Value::Uint(_) | Value::Int(_) => std::mem::size_of::<u64>(),
+                Value::Uint(_) | Value::Int(_) | Value::OneOf(_) => std::mem::size_of::<u64>(),
             },
             value,
             rule_path: Vec::new(),
             confidence: 50, // Default moderate confidence
             mime_type: None,
+            source_line: None,
+            level: 0,
         }
     }
 
@@ -241,6 +276,8 @@ pub fn with_metadata(
             rule_path,
             confidence: confidence.min(100), // Clamp to valid range
             mime_type,
+            source_line: None,
+            level: 0,
         }
     }
 
@@ -321,6 +358,158 @@ pub fn add_rule_path(&mut self, rule_name: String) {
     pub fn set_mime_type(&mut self, mime_type: Option<String>) {
         self.mime_type = mime_type;
     }
+
+    /// Compare two matches by how well they identify the file, best first
+    ///
+    /// A match ranks ahead of another with higher confidence, then with a
+    /// longer match, then with a smaller offset. Matches that compare equal
+    /// keep their source order when sorted stably, so the ranking is the same
+    /// on every run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::output::MatchResult;
+    /// use libmagic_rs::parser::ast::Value;
+    /// use std::cmp::Ordering;
+    ///
+    /// let short = MatchResult::with_metadata("MZ".to_string(), 0, 2, Value::Uint(0), vec![], 50, None);
+    /// let long = MatchResult::with_metadata("PE".to_string(), 60, 4, Value::Uint(0), vec![], 50, None);
+    /// assert_eq!(long.rank_cmp(&short), Ordering::Less);
+    /// ```
+    #[must_use]
+    pub fn rank_cmp(&self, other: &Self) -> Ordering {
+        other
+            .confidence
+            .cmp(&self.confidence)
+            .then_with(|| other.length.cmp(&self.length))
+            .then_with(|| self.offset.cmp(&other.offset))
+    }
+
+    /// Qualitative reading of the confidence score
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::output::{ConfidenceBand, MatchResult};
+    /// use libmagic_rs::parser::ast::Value;
+    ///
+    /// let result = MatchResult::with_metadata("PNG".to_string(), 0, 8, Value::Uint(0), vec![], 90, None);
+    /// assert_eq!(result.confidence_band(), ConfidenceBand::High);
+    /// assert_eq!(result.confidence_band().to_string(), "high");
+    /// ```
+    #[must_use]
+    pub const fn confidence_band(&self) -> ConfidenceBand {
+        ConfidenceBand::from_confidence(self.confidence)
+    }
+}
+
+/// A value that differs between two evaluation results
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldChange<T> {
+    /// Value in the result the other was compared against
+    pub before: T,
+    /// Value in the other result
+    pub after: T,
+}
+
+/// Differences in classification between two results for the same file
+///
+/// Built by [`crate::EvaluationResult::diff`]; a field is `None` when both
+/// results agree on it. Serialize it to record which files a rule set change
+/// reclassified.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultDiff {
+    /// Change in the description
+    pub description: Option<FieldChange<String>>,
+    /// Change in the primary MIME type
+    pub mime_type: Option<FieldChange<Option<String>>>,
+    /// Change in the number of matches
+    pub match_count: Option<FieldChange<usize>>,
+}
+
+impl ResultDiff {
+    /// Whether the two results classify the file the same way
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.description.is_none() && self.mime_type.is_none() && self.match_count.is_none()
+    }
+}
+
+/// Lowest confidence score in the [`ConfidenceBand::Medium`] band
+pub const MEDIUM_CONFIDENCE_MIN: u8 = 40;
+
+/// Lowest confidence score in the [`ConfidenceBand::High`] band
+pub const HIGH_CONFIDENCE_MIN: u8 = 76;
+
+/// Coarse grouping of a 0-100 confidence score, for people reading results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfidenceBand {
+    /// Below [`MEDIUM_CONFIDENCE_MIN`]
+    Low,
+    /// From [`MEDIUM_CONFIDENCE_MIN`] up to, but not including, [`HIGH_CONFIDENCE_MIN`]
+    Medium,
+    /// [`HIGH_CONFIDENCE_MIN`] and above
+    High,
+}
+
+impl ConfidenceBand {
+    /// The band a confidence score falls in
+    #[must_use]
+    pub const fn from_confidence(confidence: u8) -> Self {
+        if confidence >= HIGH_CONFIDENCE_MIN {
+            Self::High
+        } else if confidence >= MEDIUM_CONFIDENCE_MIN {
+            Self::Medium
+        } else {
+            Self::Low
+        }
+    }
+}
+
+impl std::fmt::Display for ConfidenceBand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        })
+    }
+}
+
+impl From<crate::evaluator::MatchResult> for MatchResult {
+    /// Convert an evaluator match, keeping the source line of the rule
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::evaluator::evaluate_rules_with_config;
+    /// use libmagic_rs::output::MatchResult;
+    /// use libmagic_rs::parser::parse_magic_string;
+    /// use libmagic_rs::EvaluationConfig;
+    ///
+    /// let rules = parse_magic_string("# PDF\n0 string %PDF PDF document\n").unwrap();
+    /// let matches =
+    ///     evaluate_rules_with_config(&rules, b"%PDF-1.7", EvaluationConfig::default()).unwrap();
+    ///
+    /// let result = MatchResult::from(matches[0].clone());
+    /// assert_eq!(result.message, "PDF document");
+    /// assert_eq!(result.source_line, Some(2));
+    /// ```
+    fn from(m: crate::evaluator::MatchResult) -> Self {
+        Self {
+            message: m.message,
+            offset: m.offset,
+            length: m.length,
+            value: m.value,
+            rule_path: Vec::new(),
+            confidence: 50,
+            mime_type: m.mime_type,
+            source_line: m.source_line,
+            level: m.level,
+        }
+    }
 }
 
 impl EvaluationResult {
@@ -440,11 +629,11 @@ pub fn add_match(&mut self, match_result: MatchResult) {
         self.matches.push(match_result);
     }
 
-    /// Get the primary match (first match with highest confidence)
+    /// Get the primary match (the best match as ranked by [`MatchResult::rank_cmp`])
     ///
-    /// Returns the match that is most likely to represent the primary file type.
-    /// This is typically the first match, but if multiple matches exist, the one
-    /// with the highest confidence score is preferred.
+    /// Returns the match that is most likely to represent the primary file type:
+    /// the one with the highest confidence score, with ties going to the
+    /// longer match, then the smaller offset, then the earlier match.
     ///
     /// # Examples
     ///
@@ -487,9 +676,21 @@ pub fn add_match(&mut self, match_result: MatchResult) {
     /// ```
     #[must_use]
     pub fn primary_match(&self) -> Option<&MatchResult> {
-        self.matches
-            .iter()
-            .max_by_key(|match_result| match_result.confidence)
+        // `min_by` keeps the first of equally ranked matches
+        self.matches.iter().min_by(|a, b| a.rank_cmp(b))
+    }
+
+    /// All matches ordered from best to worst, as ranked by [`MatchResult::rank_cmp`]
+    ///
+    /// The first element is the [`EvaluationResult::primary_match`]. Matches
+    /// that rank equally keep their order in `matches`, so the same matches
+    /// are always ranked, and serialized, identically.
+    #[must_use]
+    pub fn ranked_matches(&self) -> Vec<&MatchResult> {
+        // The position breaks every remaining tie, making the order total
+        let mut ranked: Vec<(usize, &MatchResult)> = self.matches.iter().enumerate().collect();
+        ranked.sort_by(|(i, a), (j, b)| a.rank_cmp(b).then(i.cmp(j)));
+        ranked.into_iter().map(|(_, m)| m).collect()
     }
 
     /// Check if the evaluation was successful (no errors)
@@ -654,6 +855,28 @@ fn test_match_result_length_calculation() {
         assert_eq!(int_result.length, 8); // size_of::<u64>()
     }
 
+    #[test]
+    fn test_confidence_band_thresholds() {
+        for (confidence, band) in [
+            (0, ConfidenceBand::Low),
+            (39, ConfidenceBand::Low),
+            (40, ConfidenceBand::Medium),
+            (50, ConfidenceBand::Medium),
+            (75, ConfidenceBand::Medium),
+            (76, ConfidenceBand::High),
+            (100, ConfidenceBand::High),
+        ] {
+            let mut result = MatchResult::new("test".to_string(), 0, Value::Uint(0));
+            result.set_confidence(confidence);
+            assert_eq!(result.confidence_band(), band, "confidence {confidence}");
+        }
+        assert!(ConfidenceBand::Low < ConfidenceBand::High);
+        assert_eq!(
+            serde_json::to_string(&ConfidenceBand::Medium).unwrap(),
+            "\"medium\""
+        );
+    }
+
     #[test]
     fn test_match_result_set_confidence() {
         let mut result = MatchResult::new("Test".to_string(), 0, Value::Uint(0));
@@ -804,6 +1027,71 @@ fn test_evaluation_result_primary_match() {
         assert_eq!(primary.unwrap().confidence, 95);
     }
 
+    #[test]
+    fn test_evaluation_result_primary_match_breaks_ties() {
+        let metadata = EvaluationMetadata::new(2048, 1.0, 4, 4);
+        let candidate = |message: &str, offset, length| {
+            MatchResult::with_metadata(
+                message.to_string(),
+                offset,
+                length,
+                Value::Uint(0),
+                vec![],
+                70,
+                None,
+            )
+        };
+        let matches = vec![
+            candidate("short", 0, 2),
+            candidate("long late", 16, 8),
+            candidate("long early", 8, 8),
+            candidate("long early again", 8, 8),
+        ];
+
+        let result = EvaluationResult::new(PathBuf::from("tie.bin"), matches, metadata);
+
+        assert_eq!(result.primary_match().unwrap().message, "long early");
+        let ranked: Vec<&str> = result
+            .ranked_matches()
+            .iter()
+            .map(|m| m.message.as_str())
+            .collect();
+        assert_eq!(
+            ranked,
+            ["long early", "long early again", "long late", "short"]
+        );
+    }
+
+    #[test]
+    fn test_ranked_matches_serialize_identically_across_runs() {
+        use crate::EvaluationConfig;
+        use crate::evaluator::evaluate_rules_with_config;
+        use crate::parser::parse_magic_string;
+
+        let rules = parse_magic_string("0 string AB first\n0 byte 0x41 byte\n0 string AB second\n")
+            .unwrap();
+        let config = EvaluationConfig {
+            stop_at_first_match: false,
+            ..EvaluationConfig::default()
+        };
+        let ranked_json = || {
+            let matches = evaluate_rules_with_config(&rules, b"ABC", config.clone())
+                .unwrap()
+                .into_iter()
+                .map(MatchResult::from)
+                .collect();
+            let metadata = EvaluationMetadata::new(3, 0.0, 3, 3);
+            let result = EvaluationResult::new(PathBuf::from("ab.bin"), matches, metadata);
+            serde_json::to_string(&result.ranked_matches()).unwrap()
+        };
+
+        let first = ranked_json();
+        assert_eq!(first, ranked_json());
+        let first_pos = first.find("\"first\"").unwrap();
+        let second_pos = first.find("\"second\"").unwrap();
+        assert!(first_pos < second_pos, "{first}");
+    }
+
     #[test]
     fn test_evaluation_result_primary_match_empty() {
         let metadata = EvaluationMetadata::new(0, 0.0, 0, 0);
@@ -958,7 +1246,7 @@ fn test_complex_evaluation_result() {
         // Verify all matches have proper rule paths
         for match_result in &result.matches {
             assert!(!match_result.rule_path.is_empty());
-            assert!(match_result.rule_path[0] == "elf");
+            assert_eq!(match_result.rule_path[0], "elf");
         }
     }
 }
@@ -4,12 +4,249 @@
 //! text format compatible with the GNU `file` command output style.
 
 use crate::output::{EvaluationResult, MatchResult};
+use crate::parser::ast::Value;
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// Default separator placed between the filename and the description
+///
+/// Matches the GNU `file` command default of a colon followed by a space.
+pub const DEFAULT_SEPARATOR: &str = ": ";
+
+/// Separator placed between the descriptions of top-level matches in keep-going mode
+///
+/// Matches GNU `file -k`, which writes it as the octal escape `\012-`.
+pub const KEEP_GOING_SEPARATOR: &str = "\n- ";
+
+/// Substitute the matched value into a rule message
+///
+/// Messages may contain a printf-style conversion, as in magic(5), which is
+/// replaced with the value read from the file. The conversions `%d`, `%i`,
+/// `%u`, `%x`, `%X`, `%o`, `%c`, `%s` and `%%` are supported, with the `-`,
+/// `0` and `#` flags, a field width, and a precision. Length modifiers such
+/// as `l` or `ll` are accepted and ignored. Unknown conversions are kept as
+/// written.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::output::text::format_magic_message;
+/// use libmagic_rs::parser::ast::Value;
+///
+/// assert_eq!(format_magic_message("version %d", &Value::Uint(3)), "version 3");
+/// assert_eq!(format_magic_message("flags 0x%04x", &Value::Uint(0x1f)), "flags 0x001f");
+/// assert_eq!(
+///     format_magic_message("name \"%s\"", &Value::String("init".to_string())),
+///     "name \"init\""
+/// );
+/// ```
+#[must_use]
+pub fn format_magic_message(message: &str, value: &Value) -> String {
+    if !message.contains('%') {
+        return message.to_string();
+    }
+
+    let mut output = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+
+        let mut spec = String::from('%');
+        let mut left_align = false;
+        let mut zero_pad = false;
+        let mut alternate = false;
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => left_align = true,
+                '0' => zero_pad = true,
+                '#' => alternate = true,
+                '+' | ' ' => {}
+                _ => break,
+            }
+            spec.push(flag);
+            chars.next();
+        }
+
+        let mut width = 0usize;
+        while let Some(digit) = chars.peek().and_then(|d| d.to_digit(10)) {
+            width = width.saturating_mul(10).saturating_add(digit as usize);
+            spec.push(chars.next().unwrap_or_default());
+        }
+
+        let mut precision = None;
+        if chars.peek() == Some(&'.') {
+            spec.push(chars.next().unwrap_or_default());
+            let mut digits = 0usize;
+            while let Some(digit) = chars.peek().and_then(|d| d.to_digit(10)) {
+                digits = digits.saturating_mul(10).saturating_add(digit as usize);
+                spec.push(chars.next().unwrap_or_default());
+            }
+            precision = Some(digits);
+        }
+
+        while let Some(&modifier) = chars.peek() {
+            if !matches!(modifier, 'h' | 'l' | 'q' | 'j' | 'z' | 't') {
+                break;
+            }
+            spec.push(modifier);
+            chars.next();
+        }
+
+        let Some(conversion) = chars.next() else {
+            output.push_str(&spec);
+            break;
+        };
+
+        let (body, numeric) = match conversion {
+            '%' => {
+                output.push('%');
+                continue;
+            }
+            'd' | 'i' => (format_signed(value), true),
+            'u' => (format_unsigned(value, 10, false), true),
+            'x' => (format_unsigned(value, 16, false), true),
+            'X' => (format_unsigned(value, 16, true), true),
+            'o' => (format_unsigned(value, 8, false), true),
+            'c' => (format_char(value), false),
+            's' => (format_string(value, precision), false),
+            other => {
+                output.push_str(&spec);
+                output.push(other);
+                continue;
+            }
+        };
+
+        let body = if numeric {
+            apply_numeric_options(&body, conversion, precision, alternate)
+        } else {
+            body
+        };
+        output.push_str(&pad_field(&body, width, left_align, zero_pad && numeric));
+    }
+
+    output
+}
+
+/// Render a value for `%d`
+fn format_signed(value: &Value) -> String {
+    match value {
+        Value::Int(v) => v.to_string(),
+        Value::Uint(v) => v.to_string(),
+        Value::String(_) | Value::Bytes(_) | Value::OneOf(_) => format_string(value, None),
+    }
+}
+
+/// Render a value for `%u`, `%x`, `%X` and `%o`
+#[allow(clippy::cast_sign_loss)]
+fn format_unsigned(value: &Value, radix: u32, uppercase: bool) -> String {
+    let bits = match value {
+        Value::Uint(v) => *v,
+        Value::Int(v) => *v as u64,
+        Value::String(_) | Value::Bytes(_) | Value::OneOf(_) => {
+            return format_string(value, None);
+        }
+    };
+    match (radix, uppercase) {
+        (16, false) => format!("{bits:x}"),
+        (16, true) => format!("{bits:X}"),
+        (8, _) => format!("{bits:o}"),
+        _ => bits.to_string(),
+    }
+}
+
+/// Render a value for `%c`, using the low byte of integers as in C
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn format_char(value: &Value) -> String {
+    match value {
+        Value::Uint(v) => char::from(*v as u8).to_string(),
+        Value::Int(v) => char::from(*v as u8).to_string(),
+        Value::String(_) | Value::Bytes(_) | Value::OneOf(_) => format_string(value, Some(1)),
+    }
+}
+
+/// Render a value for `%s`, truncated to `precision` characters if given
+fn format_string(value: &Value, precision: Option<usize>) -> String {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Bytes(b) => String::from_utf8_lossy(b).into_owned(),
+        Value::Uint(v) => v.to_string(),
+        Value::Int(v) => v.to_string(),
+        Value::OneOf(members) => members
+            .iter()
+            .map(|member| format_string(member, None))
+            .collect::<Vec<_>>()
+            .join("|"),
+    };
+    match precision {
+        Some(limit) => text.chars().take(limit).collect(),
+        None => text,
+    }
+}
+
+/// Apply the precision and `#` flag to a rendered number
+fn apply_numeric_options(
+    body: &str,
+    conversion: char,
+    precision: Option<usize>,
+    alternate: bool,
+) -> String {
+    let (sign, digits) = body
+        .strip_prefix('-')
+        .map_or(("", body), |digits| ("-", digits));
+    let digits = match precision {
+        Some(min_digits) if digits.len() < min_digits => {
+            format!("{}{digits}", "0".repeat(min_digits - digits.len()))
+        }
+        _ => digits.to_string(),
+    };
+    let prefix = match conversion {
+        'x' if alternate && digits != "0" => "0x",
+        'X' if alternate && digits != "0" => "0X",
+        'o' if alternate && !digits.starts_with('0') => "0",
+        _ => "",
+    };
+    format!("{sign}{prefix}{digits}")
+}
+
+/// Pad a rendered field to `width` characters
+fn pad_field(body: &str, width: usize, left_align: bool, zero_pad: bool) -> String {
+    let len = body.chars().count();
+    if len >= width {
+        return body.to_string();
+    }
+    let fill = width - len;
+    if left_align {
+        format!("{body}{}", " ".repeat(fill))
+    } else if zero_pad {
+        // Zeros go after any sign or radix prefix
+        let sign_len = usize::from(body.starts_with('-'));
+        let rest = &body[sign_len..];
+        let prefix_len = if rest.starts_with("0x") || rest.starts_with("0X") {
+            sign_len + 2
+        } else {
+            sign_len
+        };
+        format!(
+            "{}{}{}",
+            &body[..prefix_len],
+            "0".repeat(fill),
+            &body[prefix_len..]
+        )
+    } else {
+        format!("{}{body}", " ".repeat(fill))
+    }
+}
 
 /// Format a single match result as text
 ///
 /// Converts a match result into a human-readable string format similar to
 /// the GNU `file` command output. The format includes the message from the
-/// matching rule.
+/// matching rule, with the matched value substituted for any printf-style
+/// conversion (see [`format_magic_message`]).
 ///
 /// # Arguments
 ///
@@ -36,14 +273,34 @@
 /// ```
 #[must_use]
 pub fn format_text_result(result: &MatchResult) -> String {
-    result.message.clone()
+    format_magic_message(
+        &result.message,
+        &narrow_to_length(&result.value, result.length),
+    )
+}
+
+/// Reduce a signed integer to the width it was read with, as libmagic prints it
+///
+/// A `byte` holding 0x90 is read as -112, but `%x` and `%d` show it as `90`
+/// and `144` rather than as a sign-extended 64-bit value.
+#[allow(clippy::cast_sign_loss)]
+fn narrow_to_length(value: &Value, length: usize) -> Cow<'_, Value> {
+    match (value, length) {
+        (Value::Int(v), 1 | 2 | 4) => {
+            let mask = u64::MAX >> (64 - 8 * length);
+            Cow::Owned(Value::Uint(*v as u64 & mask))
+        }
+        _ => Cow::Borrowed(value),
+    }
 }
 
 /// Format multiple match results as concatenated text
 ///
 /// Combines multiple match results into a single text string, with messages
-/// separated by commas and spaces. This follows the GNU `file` command convention
-/// of showing hierarchical matches in a single line.
+/// separated by a space. This follows the GNU `file` command convention of
+/// showing hierarchical matches in a single line. As in magic(5), a message
+/// starting with `\b` is appended without a separator: the `\b` is dropped and
+/// the rest of the message is kept as written, so `\b, x86-64` adds `, x86-64`.
 ///
 /// # Arguments
 ///
@@ -66,12 +323,12 @@ pub fn format_text_result(result: &MatchResult) -> String {
 ///         Value::Bytes(vec![0x7f, 0x45, 0x4c, 0x46])
 ///     ),
 ///     MatchResult::new(
-///         "x86-64".to_string(),
+///         "\\b, x86-64".to_string(),
 ///         18,
 ///         Value::Uint(0x3e)
 ///     ),
 ///     MatchResult::new(
-///         "dynamically linked".to_string(),
+///         "\\b, dynamically linked".to_string(),
 ///         16,
 ///         Value::Uint(0x02)
 ///     ),
@@ -79,6 +336,13 @@ pub fn format_text_result(result: &MatchResult) -> String {
 ///
 /// let formatted = format_text_output(&results);
 /// assert_eq!(formatted, "ELF 64-bit LSB executable, x86-64, dynamically linked");
+///
+/// let attached = vec![
+///     MatchResult::new("gzip compressed data".to_string(), 0, Value::Uint(0x1f)),
+///     MatchResult::new("\\b, was \"%s\"".to_string(), 10, Value::String("a.txt".to_string())),
+///     MatchResult::new("\\b (deflate)".to_string(), 2, Value::Uint(8)),
+/// ];
+/// assert_eq!(format_text_output(&attached), "gzip compressed data, was \"a.txt\" (deflate)");
 /// ```
 #[must_use]
 pub fn format_text_output(results: &[MatchResult]) -> String {
@@ -86,6 +350,80 @@ pub fn format_text_output(results: &[MatchResult]) -> String {
         return "data".to_string(); // Default fallback for unknown files
     }
 
+    let mut description = String::new();
+    for (i, result) in results.iter().enumerate() {
+        let text = format_text_result(result);
+        if let Some(attached) = text.strip_prefix("\\b") {
+            description.push_str(attached);
+        } else {
+            if i > 0 {
+                description.push(' ');
+            }
+            description.push_str(&text);
+        }
+    }
+    description
+}
+
+/// Format match results from keep-going evaluation, one line per top-level match
+///
+/// Each top-level match and the nested matches that follow it are formatted
+/// like [`format_text_output`], and the descriptions of successive top-level
+/// matches are joined with [`KEEP_GOING_SEPARATOR`], as GNU `file -k` does.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::output::{MatchResult, text::format_keep_going_output};
+/// use libmagic_rs::parser::ast::Value;
+///
+/// let mut version = MatchResult::new("version %d".to_string(), 4, Value::Uint(2));
+/// version.level = 1;
+/// let results = vec![
+///     MatchResult::new("ZIP archive".to_string(), 0, Value::Uint(0)),
+///     version,
+///     MatchResult::new("data".to_string(), 0, Value::Uint(0)),
+/// ];
+///
+/// assert_eq!(format_keep_going_output(&results), "ZIP archive version 2\n- data");
+/// ```
+#[must_use]
+pub fn format_keep_going_output(results: &[MatchResult]) -> String {
+    if results.is_empty() {
+        return "data".to_string();
+    }
+
+    results
+        .chunk_by(|_, next| next.level > 0)
+        .map(format_text_output)
+        .collect::<Vec<String>>()
+        .join(KEEP_GOING_SEPARATOR)
+}
+
+/// Format multiple match results with their messages exactly as written
+///
+/// Like [`format_text_output`], but printf-style conversions such as `%d` are
+/// left in place instead of being replaced with the matched value. Comparing
+/// the two helps tell a formatting problem from a matching problem when
+/// debugging a magic file.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::output::{MatchResult, text::{format_raw_output, format_text_output}};
+/// use libmagic_rs::parser::ast::Value;
+///
+/// let results = vec![MatchResult::new("version %d".to_string(), 4, Value::Uint(3))];
+///
+/// assert_eq!(format_raw_output(&results), "version %d");
+/// assert_eq!(format_text_output(&results), "version 3");
+/// ```
+#[must_use]
+pub fn format_raw_output(results: &[MatchResult]) -> String {
+    if results.is_empty() {
+        return "data".to_string();
+    }
+
     results
         .iter()
         .map(|result| result.message.as_str())
@@ -147,7 +485,75 @@ pub fn format_evaluation_result(evaluation: &EvaluationResult) -> String {
         format_text_output(&evaluation.matches)
     };
 
-    format!("{filename}: {description}")
+    format_result(filename, &description, DEFAULT_SEPARATOR)
+}
+
+/// Escape control characters in a filename so it is safe to print to a terminal
+///
+/// Each byte of a control character, such as a newline or the escape that
+/// starts an ANSI sequence, is written as a backslash and three octal digits,
+/// like GNU `file` does. A crafted name therefore cannot move the cursor,
+/// change colors or fake extra output lines. Other names are returned as is.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::output::text::escape_filename;
+///
+/// assert_eq!(escape_filename("photo.jpg"), "photo.jpg");
+/// assert_eq!(escape_filename("a\nb.txt"), "a\\012b.txt");
+/// assert_eq!(escape_filename("\x1b[31mred"), "\\033[31mred");
+/// ```
+#[must_use]
+pub fn escape_filename(filename: &str) -> Cow<'_, str> {
+    if !filename.chars().any(char::is_control) {
+        return Cow::Borrowed(filename);
+    }
+
+    let mut escaped = String::with_capacity(filename.len() + 8);
+    for c in filename.chars() {
+        if c.is_control() {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                let _ = write!(escaped, "\\{byte:03o}");
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Join a filename and description with a separator
+///
+/// This is the text-mode equivalent of GNU `file -F`: the separator replaces
+/// the default `": "` between the filename and the description, which makes
+/// the output easier to parse when filenames themselves contain colons.
+/// Control characters in the filename are escaped with [`escape_filename`].
+///
+/// # Arguments
+///
+/// * `filename` - The filename to display
+/// * `description` - The file type description
+/// * `separator` - The string placed between the filename and description
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::output::text::{DEFAULT_SEPARATOR, format_result};
+///
+/// assert_eq!(
+///     format_result("image.png", "PNG image data", DEFAULT_SEPARATOR),
+///     "image.png: PNG image data"
+/// );
+/// assert_eq!(
+///     format_result("a:b.png", "PNG image data", "\t"),
+///     "a:b.png\tPNG image data"
+/// );
+/// ```
+#[must_use]
+pub fn format_result(filename: &str, description: &str, separator: &str) -> String {
+    format!("{}{separator}{description}", escape_filename(filename))
 }
 
 #[cfg(test)]
@@ -211,7 +617,7 @@ fn test_format_text_output_multiple_results() {
         let formatted = format_text_output(&results);
         assert_eq!(
             formatted,
-            "ELF 64-bit LSB executable, x86-64, version 1 (SYSV), dynamically linked"
+            "ELF 64-bit LSB executable x86-64 version 1 (SYSV) dynamically linked"
         );
     }
 
@@ -247,7 +653,7 @@ fn test_format_text_output_with_confidence_variations() {
         ];
 
         let formatted = format_text_output(&results);
-        assert_eq!(formatted, "JPEG image data, JFIF standard 1.01");
+        assert_eq!(formatted, "JPEG image data JFIF standard 1.01");
     }
 
     #[test]
@@ -287,7 +693,7 @@ fn test_format_evaluation_result_with_multiple_matches() {
         let formatted = format_evaluation_result(&evaluation);
         assert_eq!(
             formatted,
-            "ls: ELF 64-bit LSB executable, x86-64, dynamically linked"
+            "ls: ELF 64-bit LSB executable x86-64 dynamically linked"
         );
     }
 
@@ -369,6 +775,26 @@ fn test_format_evaluation_result_edge_cases() {
         assert_eq!(formatted2, "unknown: data");
     }
 
+    #[test]
+    fn test_format_keep_going_output_separates_top_level_matches() {
+        let mut child = MatchResult::new("version %d".to_string(), 4, Value::Uint(1));
+        child.level = 1;
+        let results = vec![
+            MatchResult::new("primary".to_string(), 0, Value::Uint(0)),
+            child,
+            MatchResult::new("secondary".to_string(), 0, Value::Uint(0)),
+            MatchResult::new("tertiary".to_string(), 0, Value::Uint(0)),
+        ];
+
+        let output = format_keep_going_output(&results);
+        assert_eq!(
+            output.as_bytes(),
+            b"primary version 1\x0a- secondary\x0a- tertiary"
+        );
+        assert_eq!(format_keep_going_output(&[]), "data");
+        assert_eq!(format_keep_going_output(&results[2..3]), "secondary");
+    }
+
     #[test]
     fn test_format_text_output_preserves_message_order() {
         // Ensure that the order of messages is preserved in output
@@ -379,7 +805,7 @@ fn test_format_text_output_preserves_message_order() {
         ];
 
         let formatted = format_text_output(&results);
-        assert_eq!(formatted, "First, Second, Third");
+        assert_eq!(formatted, "First Second Third");
     }
 
     #[test]
@@ -389,6 +815,41 @@ fn test_format_text_result_handles_empty_message() {
         assert_eq!(formatted, "");
     }
 
+    #[test]
+    fn test_format_text_output_attaches_backspace_messages() {
+        let rules = crate::parser::parse_magic_string(
+            "0 string GIF8 GIF image data\n\
+             >4 string 9a \\b, version 89a\n\
+             >4 string 9a \\b (animated)\n\
+             >4 string 9a \\bx\n\
+             >4 string 9a   palette\n\
+             >4 string 9a \tglobal colors\n\
+             >4 string 9a sorted\n",
+        )
+        .unwrap();
+        let config = crate::EvaluationConfig {
+            stop_at_first_match: false,
+            ..crate::EvaluationConfig::default()
+        };
+        let matches =
+            crate::evaluator::evaluate_rules_with_config(&rules, b"GIF89a", config).unwrap();
+        let results: Vec<MatchResult> = matches.into_iter().map(MatchResult::from).collect();
+
+        // All whitespace before a message only separates it from the value
+        assert_eq!(results[4].message, "palette");
+        assert_eq!(results[5].message, "global colors");
+        // Messages are joined with one space, however they were aligned, and
+        // after `\b` the message's own leading space is kept and nothing is added
+        assert_eq!(
+            format_text_output(&results),
+            "GIF image data, version 89a (animated)x palette global colors sorted"
+        );
+        assert_eq!(
+            format_keep_going_output(&results),
+            format_text_output(&results)
+        );
+    }
+
     #[test]
     fn test_format_text_output_with_empty_messages() {
         let results = vec![
@@ -398,7 +859,7 @@ fn test_format_text_output_with_empty_messages() {
         ];
 
         let formatted = format_text_output(&results);
-        assert_eq!(formatted, "Valid message, , Another message");
+        assert_eq!(formatted, "Valid message  Another message");
     }
 
     #[test]
@@ -418,10 +879,7 @@ fn test_format_text_output_realistic_file_types() {
                 Value::String("1.4".to_string()),
             ),
         ];
-        assert_eq!(
-            format_text_output(&pdf_results),
-            "PDF document, version 1.4"
-        );
+        assert_eq!(format_text_output(&pdf_results), "PDF document version 1.4");
 
         // ZIP archive
         let zip_results = vec![
@@ -434,7 +892,7 @@ fn test_format_text_output_realistic_file_types() {
         ];
         assert_eq!(
             format_text_output(&zip_results),
-            "Zip archive data, at least v2.0 to extract"
+            "Zip archive data at least v2.0 to extract"
         );
 
         // JPEG image
@@ -445,16 +903,122 @@ fn test_format_text_output_realistic_file_types() {
                 Value::Bytes(vec![0xff, 0xd8]),
             ),
             MatchResult::new(
-                "JFIF standard 1.01".to_string(),
+                "\\b, JFIF standard 1.01".to_string(),
                 6,
                 Value::String("JFIF".to_string()),
             ),
-            MatchResult::new("resolution (DPI)".to_string(), 13, Value::Uint(1)),
-            MatchResult::new("density 72x72".to_string(), 14, Value::Uint(72)),
+            MatchResult::new("\\b, resolution (DPI)".to_string(), 13, Value::Uint(1)),
+            MatchResult::new("\\b, density 72x72".to_string(), 14, Value::Uint(72)),
         ];
         assert_eq!(
             format_text_output(&jpeg_results),
             "JPEG image data, JFIF standard 1.01, resolution (DPI), density 72x72"
         );
     }
+
+    #[test]
+    fn test_format_result_default_separator() {
+        assert_eq!(
+            format_result("test.bin", "data", DEFAULT_SEPARATOR),
+            "test.bin: data"
+        );
+    }
+
+    #[test]
+    fn test_format_result_custom_separator() {
+        assert_eq!(
+            format_result("archive:v2.zip", "Zip archive data", "\t"),
+            "archive:v2.zip\tZip archive data"
+        );
+        assert_eq!(
+            format_result("file.elf", "ELF 64-bit", " | "),
+            "file.elf | ELF 64-bit"
+        );
+        assert_eq!(format_result("file", "data", ""), "filedata");
+    }
+
+    #[test]
+    fn test_format_result_escapes_control_characters_in_filename() {
+        let output = format_result("evil\n\x1b[2Jname\u{85}.bin", "data", DEFAULT_SEPARATOR);
+        assert_eq!(output, "evil\\012\\033[2Jname\\302\\205.bin: data");
+        assert!(!output.chars().any(char::is_control));
+
+        // Printable non-ASCII names are left alone
+        assert_eq!(escape_filename("café.txt"), "café.txt");
+    }
+
+    #[test]
+    fn test_format_magic_message_conversions() {
+        let cases: [(&str, Value, &str); 12] = [
+            ("version %d", Value::Uint(3), "version 3"),
+            ("offset %i", Value::Int(-12), "offset -12"),
+            ("%u bytes", Value::Int(-1), "18446744073709551615 bytes"),
+            ("id 0x%x", Value::Uint(0xbeef), "id 0xbeef"),
+            ("id %#X", Value::Uint(0xbeef), "id 0XBEEF"),
+            ("mode %o", Value::Uint(0o755), "mode 755"),
+            ("[%5d]", Value::Uint(42), "[   42]"),
+            ("[%-5d]", Value::Uint(42), "[42   ]"),
+            ("[%05d]", Value::Int(-42), "[-0042]"),
+            ("%.2s", Value::String("abc".to_string()), "ab"),
+            ("%c", Value::Uint(u64::from(b'A')), "A"),
+            ("%ld%%", Value::Uint(99), "99%"),
+        ];
+
+        for (message, value, expected) in cases {
+            assert_eq!(
+                format_magic_message(message, &value),
+                expected,
+                "format of '{message}'"
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_magic_message_passthrough() {
+        let value = Value::Uint(1);
+        assert_eq!(
+            format_magic_message("no conversions", &value),
+            "no conversions"
+        );
+        assert_eq!(format_magic_message("odd %y here", &value), "odd %y here");
+        assert_eq!(format_magic_message("trailing %", &value), "trailing %");
+        assert_eq!(
+            format_magic_message("%s", &Value::Bytes(vec![b'o', b'k'])),
+            "ok"
+        );
+    }
+
+    #[test]
+    fn test_format_text_result_substitutes_value() {
+        let result = MatchResult::new("version %d".to_string(), 4, Value::Uint(2));
+        assert_eq!(format_text_result(&result), "version 2");
+    }
+
+    #[test]
+    fn test_format_text_result_uses_width_of_signed_values() {
+        let rules = crate::parser::parse_magic_string(
+            "0 byte x flags 0x%02x\n\
+             >0 byte x \\b, %d\n\
+             >1 leshort x \\b, id 0x%04x\n\
+             >1 leshort x \\b, %d\n\
+             >3 lelong x \\b, %u\n",
+        )
+        .unwrap();
+        let data = [0x90, 0x01, 0x80, 0xff, 0xff, 0xff, 0xff];
+        let matches = crate::evaluator::evaluate_rules_with_config(
+            &rules,
+            &data,
+            crate::EvaluationConfig {
+                stop_at_first_match: false,
+                ..crate::EvaluationConfig::default()
+            },
+        )
+        .unwrap();
+        let results: Vec<MatchResult> = matches.into_iter().map(MatchResult::from).collect();
+
+        assert_eq!(
+            format_text_output(&results),
+            "flags 0x90, 144, id 0x8001, 32769, 4294967295"
+        );
+    }
 }
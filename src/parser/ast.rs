@@ -4,9 +4,10 @@
 //! and their components, including offset specifications, type kinds, operators, and values.
 
 use serde::{Deserialize, Serialize};
+use std::fmt::Write;
 
 /// Offset specification for locating data in files
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum OffsetSpec {
     /// Absolute offset from file start
     ///
@@ -25,6 +26,12 @@ pub enum OffsetSpec {
     /// Reads a pointer value at `base_offset`, interprets it according to `pointer_type`
     /// and `endian`, then adds `adjustment` to get the final offset.
     ///
+    /// Absolute indirect offsets (`(0x3c.l)`) read the pointer from the start of
+    /// the file and the final offset is a file offset, even while evaluating an
+    /// `indirect` region or a `use` subroutine. Relative ones (`(&0x3c.l)`) read
+    /// the pointer at `base_offset` from the start of the current region, and the
+    /// final offset is measured from there too.
+    ///
     /// # Examples
     ///
     /// ```
@@ -35,6 +42,7 @@ pub enum OffsetSpec {
     ///     pointer_type: TypeKind::Long { endian: Endianness::Little, signed: false },
     ///     adjustment: 4,
     ///     endian: Endianness::Little,
+    ///     relative: false,
     /// };
     /// ```
     Indirect {
@@ -46,6 +54,9 @@ pub enum OffsetSpec {
         adjustment: i64,
         /// Endianness for pointer reading
         endian: Endianness,
+        /// Whether the offset is relative to the current region rather than the file
+        #[serde(default)]
+        relative: bool,
     },
 
     /// Relative offset from previous match position
@@ -69,13 +80,74 @@ pub enum OffsetSpec {
     /// let from_end = OffsetSpec::FromEnd(-16); // 16 bytes before end of file
     /// ```
     FromEnd(i64),
+
+    /// Value kept by the last matching rule with [`MagicRule::store`] set, plus an adjustment
+    ///
+    /// The sum is an offset from the start of the current region, like
+    /// [`OffsetSpec::Absolute`]. There is a single register for the whole
+    /// evaluation: each storing rule replaces the value, and evaluation fails
+    /// if nothing has been stored yet. This form has no magic(5) syntax.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::OffsetSpec;
+    ///
+    /// let after_body = OffsetSpec::Register(2); // 2 bytes past the stored value
+    /// ```
+    Register(i64),
+}
+
+impl OffsetSpec {
+    /// The same offset with an indirect pointer read in the opposite byte order
+    ///
+    /// Used for subroutines called with `use ^name`. Other offsets are
+    /// returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::{Endianness, OffsetSpec, TypeKind};
+    ///
+    /// let pointer = |endian| OffsetSpec::Indirect {
+    ///     base_offset: 0x20,
+    ///     pointer_type: TypeKind::Long { endian, signed: false },
+    ///     adjustment: 0,
+    ///     endian,
+    ///     relative: false,
+    /// };
+    /// assert_eq!(pointer(Endianness::Little).byte_swapped(), pointer(Endianness::Big));
+    /// assert_eq!(OffsetSpec::Absolute(4).byte_swapped(), OffsetSpec::Absolute(4));
+    /// ```
+    #[must_use]
+    pub fn byte_swapped(&self) -> Self {
+        match self {
+            Self::Indirect {
+                base_offset,
+                pointer_type,
+                adjustment,
+                endian,
+                relative,
+            } => Self::Indirect {
+                base_offset: *base_offset,
+                pointer_type: pointer_type.byte_swapped(),
+                adjustment: *adjustment,
+                endian: endian.swapped(),
+                relative: *relative,
+            },
+            other => other.clone(),
+        }
+    }
 }
 
 /// Data type specifications for interpreting bytes
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum TypeKind {
     /// Single byte
-    Byte,
+    Byte {
+        /// Whether value is signed
+        signed: bool,
+    },
     /// 16-bit integer
     Short {
         /// Byte order
@@ -90,15 +162,357 @@ pub enum TypeKind {
         /// Whether value is signed
         signed: bool,
     },
+    /// 64-bit integer
+    Quad {
+        /// Byte order
+        endian: Endianness,
+        /// Whether value is signed
+        signed: bool,
+    },
     /// String data
     String {
         /// Maximum length to read
         max_length: Option<usize>,
+        /// Modifier flags given after the type name (`string/wW`)
+        #[serde(default)]
+        flags: StringFlags,
     },
+    /// Pattern search over a range of offsets
+    ///
+    /// Unlike [`TypeKind::String`], which only matches at the resolved offset,
+    /// a search looks for the pattern at each of the `range` positions starting
+    /// at the resolved offset.
+    Search {
+        /// Number of starting positions to try
+        range: usize,
+    },
+    /// Re-run the whole rule database starting at the resolved offset
+    ///
+    /// Offsets of the rules evaluated this way are relative to the resolved
+    /// offset, which lets container formats identify their embedded members.
+    Indirect,
+    /// Match only if no earlier rule at the same level has matched
+    ///
+    /// Used as the last of a group of siblings to describe values none of them
+    /// recognised. Reads nothing, so the rule has no value column.
+    Default,
+    /// Forget earlier matches at the same level, so a later `default` can match
+    ///
+    /// Always matches and reads nothing, so the rule has no value column.
+    Clear,
+    /// Define a named subroutine whose children can be invoked with `use`
+    ///
+    /// Named rules are never evaluated directly.
+    Name {
+        /// Subroutine name
+        name: String,
+    },
+    /// Invoke a named subroutine at the resolved offset
+    ///
+    /// Offsets inside the subroutine are relative to the resolved offset.
+    Use {
+        /// Name of the subroutine to invoke
+        name: String,
+        /// Flip the byte order of every integer read inside the subroutine (`use ^name`)
+        #[serde(default)]
+        byte_swap: bool,
+    },
+    /// Length of the data being evaluated, compared with the rule value
+    ///
+    /// This is the size of the whole file, or the data from the region origin
+    /// inside an `indirect` or `use` rule. The offset column is not used, so
+    /// rules conventionally write `0 filesize 512`.
+    FileSize,
+    /// Checksum of a byte range, compared with the rule value
+    ///
+    /// The range is measured from the resolved offset, so `0 crc32/0,16` covers
+    /// the first 16 bytes of the data. Written `crc32/START,END` or
+    /// `adler32/START,END`, with `END` exclusive.
+    Checksum {
+        /// Checksum algorithm to compute
+        algo: ChecksumAlgo,
+        /// Start and exclusive end of the covered bytes, relative to the resolved offset
+        range: (usize, usize),
+    },
+    /// Custom type read by a reader registered at runtime
+    ///
+    /// Type names starting with `x-` are not part of magic(5); their values are
+    /// read by the [`TypeReader`](crate::evaluator::extension::TypeReader)
+    /// registered under the same name.
+    Extension {
+        /// Full type name, including the `x-` prefix
+        name: String,
+    },
+}
+
+impl TypeKind {
+    /// Returns `true` for the fixed-width integer types
+    ///
+    /// Only integer types can be used as the pointer type of an indirect offset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::{Endianness, StringFlags, TypeKind};
+    ///
+    /// assert!(TypeKind::Byte { signed: false }.is_integer());
+    /// assert!(TypeKind::Quad { endian: Endianness::Big, signed: false }.is_integer());
+    /// let string = TypeKind::String { max_length: None, flags: StringFlags::default() };
+    /// assert!(!string.is_integer());
+    /// ```
+    #[must_use]
+    pub const fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Self::Byte { .. } | Self::Short { .. } | Self::Long { .. } | Self::Quad { .. }
+        )
+    }
+
+    /// Returns `true` if rules of this type have a value column
+    ///
+    /// `indirect`, `default`, and `clear` compare nothing, so a rule line may
+    /// go straight from the type to the message, or carry an `x` placeholder.
+    /// `name` and `use` take a subroutine name in the value column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::TypeKind;
+    ///
+    /// assert!(TypeKind::Byte { signed: false }.requires_value());
+    /// assert!(!TypeKind::Default.requires_value());
+    /// ```
+    #[must_use]
+    pub const fn requires_value(&self) -> bool {
+        !matches!(self, Self::Indirect | Self::Default | Self::Clear)
+    }
+
+    /// Number of bytes read for fixed-width types
+    ///
+    /// Returns `None` for string and search types, whose width depends on the data
+    /// or the pattern being matched, and for the `indirect`, `default`, `clear`,
+    /// `name`, and `use` control types, which do not read a value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::{Endianness, TypeKind};
+    ///
+    /// assert_eq!(TypeKind::Byte { signed: false }.fixed_size(), Some(1));
+    /// assert_eq!(TypeKind::Long { endian: Endianness::Little, signed: false }.fixed_size(), Some(4));
+    /// assert_eq!(TypeKind::Search { range: 64 }.fixed_size(), None);
+    /// ```
+    #[must_use]
+    pub const fn fixed_size(&self) -> Option<usize> {
+        match self {
+            Self::Byte { .. } => Some(1),
+            Self::Short { .. } => Some(2),
+            Self::Long { .. } => Some(4),
+            Self::Quad { .. } => Some(8),
+            Self::String { .. }
+            | Self::Search { .. }
+            | Self::Indirect
+            | Self::Default
+            | Self::Clear
+            | Self::Name { .. }
+            | Self::Use { .. }
+            | Self::FileSize
+            | Self::Checksum { .. }
+            | Self::Extension { .. } => None,
+        }
+    }
+
+    /// The same type read with the opposite byte order
+    ///
+    /// Used for the integer reads inside a `use ^name` subroutine. Types
+    /// without a byte order are returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::{Endianness, TypeKind};
+    ///
+    /// let little = TypeKind::Short { endian: Endianness::Little, signed: false };
+    /// let big = TypeKind::Short { endian: Endianness::Big, signed: false };
+    /// assert_eq!(little.byte_swapped(), big);
+    ///
+    /// let byte = TypeKind::Byte { signed: true };
+    /// assert_eq!(byte.byte_swapped(), byte);
+    /// ```
+    #[must_use]
+    pub fn byte_swapped(&self) -> Self {
+        match self {
+            Self::Short { endian, signed } => Self::Short {
+                endian: endian.swapped(),
+                signed: *signed,
+            },
+            Self::Long { endian, signed } => Self::Long {
+                endian: endian.swapped(),
+                signed: *signed,
+            },
+            Self::Quad { endian, signed } => Self::Quad {
+                endian: endian.swapped(),
+                signed: *signed,
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// The same type with a `Native` byte order replaced by `native`
+    ///
+    /// Used when [`EvaluationConfig::native_endianness_override`](crate::EvaluationConfig::native_endianness_override)
+    /// is set. Types with an explicit byte order are returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::{Endianness, TypeKind};
+    ///
+    /// let native = TypeKind::Long { endian: Endianness::Native, signed: false };
+    /// let big = TypeKind::Long { endian: Endianness::Big, signed: false };
+    /// assert_eq!(native.with_native_endianness(Endianness::Big), big);
+    ///
+    /// let little = TypeKind::Long { endian: Endianness::Little, signed: false };
+    /// assert_eq!(little.with_native_endianness(Endianness::Big), little);
+    /// ```
+    #[must_use]
+    pub fn with_native_endianness(&self, native: Endianness) -> Self {
+        let resolve = |endian: Endianness| match endian {
+            Endianness::Native => native,
+            explicit => explicit,
+        };
+        match self {
+            Self::Short { endian, signed } => Self::Short {
+                endian: resolve(*endian),
+                signed: *signed,
+            },
+            Self::Long { endian, signed } => Self::Long {
+                endian: resolve(*endian),
+                signed: *signed,
+            },
+            Self::Quad { endian, signed } => Self::Quad {
+                endian: resolve(*endian),
+                signed: *signed,
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+/// Modifier flags of a `string` type, written after a slash (`string/w`)
+///
+/// The `t` and `f` letters of magic(5) are not supported; the parser accepts
+/// them and warns that they are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::ast::StringFlags;
+///
+/// let flags = StringFlags::from_letters("wct");
+/// assert!(flags.optional_blanks);
+/// assert!(!flags.compact_whitespace);
+/// assert!(flags.ignore_lower_case);
+/// assert_eq!(flags.to_string(), "wc");
+/// ```
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[allow(clippy::struct_excessive_bools)] // Independent modifiers, as in magic(5)
+pub struct StringFlags {
+    /// `W`: runs of whitespace in the pattern and the data compare as a single
+    /// blank, so the data needs at least one blank where the pattern has one
+    pub compact_whitespace: bool,
+    /// `w`: each run of blanks in the pattern matches zero or more blanks in the data
+    pub optional_blanks: bool,
+    /// `T`: leading and trailing whitespace is removed from the string read
+    #[serde(default)]
+    pub trim: bool,
+    /// `b`: the string read is kept as raw bytes instead of being decoded as text
+    #[serde(default)]
+    pub binary: bool,
+    /// `c`: lower case letters in the pattern also match upper case in the data
+    #[serde(default)]
+    pub ignore_lower_case: bool,
+    /// `C`: upper case letters in the pattern also match lower case in the data
+    #[serde(default)]
+    pub ignore_upper_case: bool,
+}
+
+impl StringFlags {
+    /// Collect the supported flags from the letters after a `string/`
+    #[must_use]
+    pub fn from_letters(letters: &str) -> Self {
+        let mut flags = Self::default();
+        for letter in letters.chars() {
+            match letter {
+                'w' => flags.optional_blanks = true,
+                'W' => flags.compact_whitespace = true,
+                'T' => flags.trim = true,
+                'b' => flags.binary = true,
+                'c' => flags.ignore_lower_case = true,
+                'C' => flags.ignore_upper_case = true,
+                _ => {}
+            }
+        }
+        flags
+    }
+
+    /// Whether `letter` is a `string` flag in magic(5), even if it is not acted on
+    #[must_use]
+    pub const fn is_known_letter(letter: char) -> bool {
+        Self::is_supported_letter(letter) || matches!(letter, 't' | 'f')
+    }
+
+    /// Whether `letter` is a `string` flag that [`StringFlags::from_letters`] acts on
+    #[must_use]
+    pub const fn is_supported_letter(letter: char) -> bool {
+        matches!(letter, 'w' | 'W' | 'T' | 'b' | 'c' | 'C')
+    }
+
+    /// Returns `true` if any flag changes how whitespace is compared
+    #[must_use]
+    pub const fn affects_whitespace(&self) -> bool {
+        self.compact_whitespace || self.optional_blanks
+    }
+
+    /// Returns `true` if any flag lets letters match in the other case
+    #[must_use]
+    pub const fn affects_case(&self) -> bool {
+        self.ignore_lower_case || self.ignore_upper_case
+    }
+
+    /// Returns `true` if the data can match the pattern without being equal to it
+    #[must_use]
+    pub const fn relaxes_comparison(&self) -> bool {
+        self.affects_whitespace() || self.affects_case()
+    }
+}
+
+impl std::fmt::Display for StringFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.optional_blanks {
+            f.write_str("w")?;
+        }
+        if self.compact_whitespace {
+            f.write_str("W")?;
+        }
+        if self.trim {
+            f.write_str("T")?;
+        }
+        if self.binary {
+            f.write_str("b")?;
+        }
+        if self.ignore_lower_case {
+            f.write_str("c")?;
+        }
+        if self.ignore_upper_case {
+            f.write_str("C")?;
+        }
+        Ok(())
+    }
 }
 
 /// Comparison and bitwise operators
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Operator {
     /// Equality comparison
     Equal,
@@ -106,10 +520,20 @@ pub enum Operator {
     NotEqual,
     /// Bitwise AND operation
     BitwiseAnd,
+    /// Less-than comparison (`<`)
+    LessThan,
+    /// Greater-than comparison (`>`)
+    GreaterThan,
+    /// Matches when any bit set in the operand is clear in the value (`^`)
+    BitwiseXor,
+    /// Equality with the bitwise complement of the operand (`~`)
+    BitwiseNot,
+    /// Matches any value (`x`); the value is still read for display
+    Always,
 }
 
 /// Value types for rule matching
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Value {
     /// Unsigned integer value
     Uint(u64),
@@ -119,10 +543,80 @@ pub enum Value {
     Bytes(Vec<u8>),
     /// String value
     String(String),
+    /// Any one of several values, written `1|2|3`; `=` matches a member
+    OneOf(Vec<Value>),
+}
+
+impl Value {
+    /// The value as an unsigned integer, if it is an integer that fits
+    ///
+    /// Negative `Int` values, byte sequences and strings return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::Value;
+    ///
+    /// assert_eq!(Value::Uint(42).as_u64(), Some(42));
+    /// assert_eq!(Value::Int(42).as_u64(), Some(42));
+    /// assert_eq!(Value::Int(-1).as_u64(), None);
+    /// assert_eq!(Value::String("42".to_string()).as_u64(), None);
+    /// ```
+    #[must_use]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Uint(value) => Some(*value),
+            Self::Int(value) => u64::try_from(*value).ok(),
+            Self::Bytes(_) | Self::String(_) | Self::OneOf(_) => None,
+        }
+    }
+
+    /// The value as a signed integer, if it is an integer that fits
+    ///
+    /// `Uint` values above `i64::MAX`, byte sequences and strings return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::Value;
+    ///
+    /// assert_eq!(Value::Int(-1).as_i64(), Some(-1));
+    /// assert_eq!(Value::Uint(42).as_i64(), Some(42));
+    /// assert_eq!(Value::Uint(u64::MAX).as_i64(), None);
+    /// assert_eq!(Value::Bytes(vec![42]).as_i64(), None);
+    /// ```
+    #[must_use]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Uint(value) => i64::try_from(*value).ok(),
+            Self::Int(value) => Some(*value),
+            Self::Bytes(_) | Self::String(_) | Self::OneOf(_) => None,
+        }
+    }
+}
+
+/// Checksum algorithm of a [`TypeKind::Checksum`] rule
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgo {
+    /// CRC-32 with the IEEE polynomial, as used by ZIP, gzip and PNG
+    Crc32,
+    /// Adler-32, as used by zlib
+    Adler32,
+}
+
+impl ChecksumAlgo {
+    /// The type keyword for this algorithm in a magic file
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Crc32 => "crc32",
+            Self::Adler32 => "adler32",
+        }
+    }
 }
 
 /// Endianness specification for multi-byte values
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Endianness {
     /// Little-endian byte order (least significant byte first)
     Little,
@@ -132,6 +626,26 @@ pub enum Endianness {
     Native,
 }
 
+impl Endianness {
+    /// The opposite byte order
+    ///
+    /// `Native` swaps to the byte order the target architecture does not use.
+    #[must_use]
+    pub const fn swapped(self) -> Self {
+        match self {
+            Self::Little => Self::Big,
+            Self::Big => Self::Little,
+            Self::Native => {
+                if cfg!(target_endian = "little") {
+                    Self::Big
+                } else {
+                    Self::Little
+                }
+            }
+        }
+    }
+}
+
 /// Magic rule representation in the AST
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MagicRule {
@@ -149,6 +663,287 @@ pub struct MagicRule {
     pub children: Vec<MagicRule>,
     /// Indentation level for hierarchical rules
     pub level: u32,
+    /// Line number of this rule in its magic source file, if parsed from one
+    #[serde(default)]
+    pub source_line: Option<usize>,
+    /// Four-character Apple file type from a `!:apple` directive
+    #[serde(default)]
+    pub apple_type: Option<String>,
+    /// Four-character Apple creator code from a `!:apple` directive
+    #[serde(default)]
+    pub apple_creator: Option<String>,
+    /// Bits of an integer value kept before comparison (`byte&0xf0`)
+    #[serde(default)]
+    pub mask: Option<u64>,
+    /// MIME type from a `!:mime` directive
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// Likely file extensions from a `!:ext` directive, such as `jpg/jpeg`
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Match when the rule's test fails instead of when it succeeds
+    ///
+    /// The value is still read, so a negated rule whose data is out of bounds
+    /// does not match either. There is no magic(5) syntax for this; it is set
+    /// on rules built in code to express "X, but not Y" checks.
+    #[serde(default)]
+    pub negate: bool,
+    /// Keep the matched integer value in the evaluation's register
+    ///
+    /// Later rules can then use an [`OffsetSpec::Register`] offset to seek by
+    /// it. Like `negate`, this has no magic(5) syntax.
+    #[serde(default)]
+    pub store: bool,
+}
+
+impl MagicRule {
+    /// Deepest nesting rendered by [`MagicRule::to_tree_string`]
+    ///
+    /// Parsed rules never nest this deeply; the limit only guards against
+    /// hand-built trees overflowing the stack.
+    pub const MAX_TREE_DEPTH: usize = 64;
+
+    /// Longest string or byte-sequence value shown in full by [`MagicRule::to_tree_string`]
+    pub const TREE_VALUE_LIMIT: usize = 16;
+
+    /// Create a top-level rule with no children, mask or directive metadata
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::ast::{MagicRule, OffsetSpec, Operator, StringFlags, TypeKind, Value};
+    ///
+    /// let version = MagicRule::new(
+    ///     OffsetSpec::Absolute(4),
+    ///     TypeKind::Byte { signed: false },
+    ///     Operator::Equal,
+    ///     Value::Uint(20),
+    ///     "v2.0",
+    /// )
+    /// .with_level(1);
+    /// let zip = MagicRule::new(
+    ///     OffsetSpec::Absolute(0),
+    ///     TypeKind::String { max_length: None, flags: StringFlags::default() },
+    ///     Operator::Equal,
+    ///     Value::String("PK".to_string()),
+    ///     "ZIP archive",
+    /// )
+    /// .with_children(vec![version]);
+    ///
+    /// assert_eq!(zip.level, 0);
+    /// assert_eq!(zip.children[0].level, 1);
+    /// assert!(zip.mask.is_none());
+    /// ```
+    #[must_use]
+    pub fn new(
+        offset: OffsetSpec,
+        typ: TypeKind,
+        op: Operator,
+        value: Value,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            offset,
+            typ,
+            op,
+            value,
+            message: message.into(),
+            children: Vec::new(),
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        }
+    }
+
+    /// Replace the child rules evaluated when this rule matches
+    #[must_use]
+    pub fn with_children(mut self, children: Vec<Self>) -> Self {
+        self.children = children;
+        self
+    }
+
+    /// Match when the rule's test fails, see [`MagicRule::negate`]
+    #[must_use]
+    pub const fn negated(mut self) -> Self {
+        self.negate = true;
+        self
+    }
+
+    /// Keep the matched value in the register, see [`MagicRule::store`]
+    #[must_use]
+    pub const fn stored(mut self) -> Self {
+        self.store = true;
+        self
+    }
+
+    /// Set the nesting level, 0 for a top-level rule
+    #[must_use]
+    pub const fn with_level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Render the rule and its children as an indented tree, one rule per line
+    ///
+    /// Each rule is shown in magic-like syntax (`offset type op value message`)
+    /// and children are indented by two spaces per level. String and byte values
+    /// longer than [`MagicRule::TREE_VALUE_LIMIT`] are truncated with `...`, and
+    /// rules nested deeper than [`MagicRule::MAX_TREE_DEPTH`] are elided.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmagic_rs::parser::parse_magic_string;
+    ///
+    /// let rules = parse_magic_string("0 string PK ZIP archive\n>4 byte 20 v2.0\n").unwrap();
+    /// assert_eq!(
+    ///     rules[0].to_tree_string(),
+    ///     "0 string = \"PK\" ZIP archive\n  4 byte = 20 v2.0\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_tree_string(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    /// Append this rule at `depth` and its children below it to `out`
+    fn write_tree(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        if depth >= Self::MAX_TREE_DEPTH {
+            let _ = writeln!(out, "{indent}... (nesting limit reached)");
+            return;
+        }
+
+        let mut line = format!(
+            "{indent}{} {}",
+            format_tree_offset(&self.offset),
+            super::codegen::format_masked_type(self)
+        );
+        match (&self.typ, &self.op) {
+            (TypeKind::Name { name }, _) => {
+                line.push(' ');
+                line.push_str(name);
+            }
+            (TypeKind::Use { name, byte_swap }, _) => {
+                line.push_str(if *byte_swap { " ^" } else { " " });
+                line.push_str(name);
+            }
+            (TypeKind::Indirect, _) | (_, Operator::Always) => line.push_str(" x"),
+            (_, op) => {
+                let _ = write!(
+                    line,
+                    " {} {}",
+                    format_tree_operator(op),
+                    format_tree_value(&self.value)
+                );
+            }
+        }
+        if !self.message.is_empty() {
+            line.push(' ');
+            line.push_str(&self.message);
+        }
+        out.push_str(&line);
+        out.push('\n');
+
+        for child in &self.children {
+            child.write_tree(out, depth + 1);
+        }
+    }
+}
+
+/// Render an offset in magic(5) syntax for [`MagicRule::to_tree_string`]
+fn format_tree_offset(offset: &OffsetSpec) -> String {
+    match offset {
+        OffsetSpec::Absolute(value) | OffsetSpec::FromEnd(value) => value.to_string(),
+        OffsetSpec::Relative(value) => format!("&{value}"),
+        OffsetSpec::Register(value) => super::codegen::format_register_offset(*value),
+        OffsetSpec::Indirect {
+            base_offset,
+            pointer_type,
+            adjustment,
+            relative,
+            ..
+        } => {
+            let (size, endian) = match pointer_type {
+                TypeKind::Short { endian, .. } => ('s', *endian),
+                TypeKind::Long { endian, .. } => ('l', *endian),
+                TypeKind::Quad { endian, .. } => ('q', *endian),
+                _ => ('b', Endianness::Little),
+            };
+            let size = if endian == Endianness::Big {
+                size.to_ascii_uppercase()
+            } else {
+                size
+            };
+            let relative = if *relative { "&" } else { "" };
+            let adjustment = match adjustment {
+                0 => String::new(),
+                a if *a > 0 => format!("+{a}"),
+                a => a.to_string(),
+            };
+            format!("({relative}{base_offset}.{size}{adjustment})")
+        }
+    }
+}
+
+/// Render an operator symbol for [`MagicRule::to_tree_string`]
+const fn format_tree_operator(op: &Operator) -> &'static str {
+    match op {
+        Operator::Equal => "=",
+        Operator::NotEqual => "!=",
+        Operator::BitwiseAnd => "&",
+        Operator::LessThan => "<",
+        Operator::GreaterThan => ">",
+        Operator::BitwiseXor => "^",
+        Operator::BitwiseNot => "~",
+        Operator::Always => "x",
+    }
+}
+
+/// Render a value for [`MagicRule::to_tree_string`], truncating long ones
+fn format_tree_value(value: &Value) -> String {
+    let limit = MagicRule::TREE_VALUE_LIMIT;
+    match value {
+        Value::Uint(v) => format!("{v:#x}"),
+        Value::Int(v) => v.to_string(),
+        Value::Bytes(bytes) => {
+            let shown = bytes
+                .iter()
+                .take(limit)
+                .fold(String::new(), |mut shown, b| {
+                    let _ = write!(shown, "\\x{b:02x}");
+                    shown
+                });
+            let more = if bytes.len() > limit { "..." } else { "" };
+            format!("\"{shown}{more}\"")
+        }
+        Value::String(text) => {
+            let shown: String = text
+                .chars()
+                .take(limit)
+                .flat_map(char::escape_default)
+                .collect();
+            let more = if text.chars().count() > limit {
+                "..."
+            } else {
+                ""
+            };
+            format!("\"{shown}{more}\"")
+        }
+        Value::OneOf(members) => members
+            .iter()
+            .map(format_tree_value)
+            .collect::<Vec<_>>()
+            .join("|"),
+    }
 }
 
 // TODO: Add validation methods for MagicRule:
@@ -183,6 +978,7 @@ fn test_offset_spec_indirect() {
             },
             adjustment: 4,
             endian: Endianness::Little,
+            relative: false,
         };
 
         match indirect {
@@ -236,6 +1032,7 @@ fn test_offset_spec_clone() {
             },
             adjustment: -2,
             endian: Endianness::Big,
+            relative: false,
         };
 
         let cloned = original.clone();
@@ -263,6 +1060,7 @@ fn test_offset_spec_indirect_serialization() {
             },
             adjustment: 12,
             endian: Endianness::Native,
+            relative: false,
         };
 
         // Test JSON serialization for complex variant
@@ -274,14 +1072,15 @@ fn test_offset_spec_indirect_serialization() {
 
     #[test]
     fn test_all_offset_spec_variants() {
-        let variants = vec![
+        let variants = [
             OffsetSpec::Absolute(0),
             OffsetSpec::Absolute(-100),
             OffsetSpec::Indirect {
                 base_offset: 0x20,
-                pointer_type: TypeKind::Byte,
+                pointer_type: TypeKind::Byte { signed: false },
                 adjustment: 0,
                 endian: Endianness::Little,
+                relative: false,
             },
             OffsetSpec::Relative(50),
             OffsetSpec::Relative(-25),
@@ -315,6 +1114,7 @@ fn test_endianness_variants() {
                 },
                 adjustment: 0,
                 endian,
+                relative: false,
             };
 
             // Verify the endianness is preserved
@@ -398,6 +1198,29 @@ fn test_value_comparison() {
         assert_ne!(bytes_val, string_val);
     }
 
+    #[test]
+    fn test_value_as_u64() {
+        assert_eq!(Value::Uint(0).as_u64(), Some(0));
+        assert_eq!(Value::Uint(u64::MAX).as_u64(), Some(u64::MAX));
+        assert_eq!(Value::Int(42).as_u64(), Some(42));
+        assert_eq!(Value::Int(i64::MAX).as_u64(), Some(i64::MAX as u64));
+        assert_eq!(Value::Int(-1).as_u64(), None);
+        assert_eq!(Value::Int(i64::MIN).as_u64(), None);
+        assert_eq!(Value::Bytes(vec![42]).as_u64(), None);
+        assert_eq!(Value::String("42".to_string()).as_u64(), None);
+    }
+
+    #[test]
+    fn test_value_as_i64() {
+        assert_eq!(Value::Int(-42).as_i64(), Some(-42));
+        assert_eq!(Value::Int(i64::MIN).as_i64(), Some(i64::MIN));
+        assert_eq!(Value::Uint(42).as_i64(), Some(42));
+        assert_eq!(Value::Uint(i64::MAX as u64).as_i64(), Some(i64::MAX));
+        assert_eq!(Value::Uint(i64::MAX as u64 + 1).as_i64(), None);
+        assert_eq!(Value::Bytes(vec![42]).as_i64(), None);
+        assert_eq!(Value::String("42".to_string()).as_i64(), None);
+    }
+
     #[test]
     fn test_value_debug() {
         let uint_val = Value::Uint(123);
@@ -477,8 +1300,8 @@ fn test_value_serialization_edge_cases() {
     // TypeKind tests
     #[test]
     fn test_type_kind_byte() {
-        let byte_type = TypeKind::Byte;
-        assert_eq!(byte_type, TypeKind::Byte);
+        let byte_type = TypeKind::Byte { signed: false };
+        assert_eq!(byte_type, TypeKind::Byte { signed: false });
     }
 
     #[test]
@@ -514,9 +1337,13 @@ fn test_type_kind_long() {
 
     #[test]
     fn test_type_kind_string() {
-        let unlimited_string = TypeKind::String { max_length: None };
+        let unlimited_string = TypeKind::String {
+            max_length: None,
+            flags: StringFlags::default(),
+        };
         let limited_string = TypeKind::String {
             max_length: Some(256),
+            flags: StringFlags::default(),
         };
 
         assert_ne!(unlimited_string, limited_string);
@@ -526,7 +1353,7 @@ fn test_type_kind_string() {
     #[test]
     fn test_type_kind_serialization() {
         let types = vec![
-            TypeKind::Byte,
+            TypeKind::Byte { signed: false },
             TypeKind::Short {
                 endian: Endianness::Little,
                 signed: false,
@@ -535,9 +1362,13 @@ fn test_type_kind_serialization() {
                 endian: Endianness::Big,
                 signed: true,
             },
-            TypeKind::String { max_length: None },
+            TypeKind::String {
+                max_length: None,
+                flags: StringFlags::default(),
+            },
             TypeKind::String {
                 max_length: Some(128),
+                flags: StringFlags::default(),
             },
         ];
 
@@ -567,7 +1398,16 @@ fn test_operator_variants() {
 
     #[test]
     fn test_operator_serialization() {
-        let operators = vec![Operator::Equal, Operator::NotEqual, Operator::BitwiseAnd];
+        let operators = vec![
+            Operator::Equal,
+            Operator::NotEqual,
+            Operator::BitwiseAnd,
+            Operator::LessThan,
+            Operator::GreaterThan,
+            Operator::BitwiseXor,
+            Operator::BitwiseNot,
+            Operator::Always,
+        ];
 
         for op in operators {
             let json = serde_json::to_string(&op).expect("Failed to serialize Operator");
@@ -582,12 +1422,20 @@ fn test_operator_serialization() {
     fn test_magic_rule_creation() {
         let rule = MagicRule {
             offset: OffsetSpec::Absolute(0),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(0x7f),
             message: "ELF magic".to_string(),
             children: vec![],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         assert_eq!(rule.message, "ELF magic");
@@ -599,12 +1447,20 @@ fn test_magic_rule_creation() {
     fn test_magic_rule_with_children() {
         let child_rule = MagicRule {
             offset: OffsetSpec::Absolute(4),
-            typ: TypeKind::Byte,
+            typ: TypeKind::Byte { signed: false },
             op: Operator::Equal,
             value: Value::Uint(1),
             message: "32-bit".to_string(),
             children: vec![],
             level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let parent_rule = MagicRule {
@@ -618,6 +1474,14 @@ fn test_magic_rule_with_children() {
             message: "ELF executable".to_string(),
             children: vec![child_rule],
             level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         assert_eq!(parent_rule.children.len(), 1);
@@ -625,6 +1489,130 @@ fn test_magic_rule_with_children() {
         assert_eq!(parent_rule.children[0].message, "32-bit");
     }
 
+    #[test]
+    fn test_magic_rule_to_tree_string() {
+        let mut child = MagicRule {
+            offset: OffsetSpec::Indirect {
+                base_offset: 0x3c,
+                pointer_type: TypeKind::Long {
+                    endian: Endianness::Little,
+                    signed: false,
+                },
+                adjustment: 4,
+                endian: Endianness::Little,
+                relative: false,
+            },
+            typ: TypeKind::Short {
+                endian: Endianness::Big,
+                signed: true,
+            },
+            op: Operator::LessThan,
+            value: Value::Int(-2),
+            message: "header".to_string(),
+            children: vec![],
+            level: 1,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        child.children.push(MagicRule {
+            offset: OffsetSpec::Absolute(8),
+            typ: TypeKind::Byte { signed: true },
+            op: Operator::Always,
+            value: Value::Uint(0),
+            message: "version %d".to_string(),
+            children: vec![],
+            level: 2,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        });
+        let rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::String {
+                max_length: None,
+                flags: StringFlags::default(),
+            },
+            op: Operator::Equal,
+            value: Value::Bytes((0u8..20).collect()),
+            message: "long magic".to_string(),
+            children: vec![child],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+
+        assert_eq!(
+            rule.to_tree_string(),
+            "0 string = \"\\x00\\x01\\x02\\x03\\x04\\x05\\x06\\x07\\x08\\x09\\x0a\\x0b\\x0c\\x0d\\x0e\\x0f...\" long magic\n\
+             \x20 (60.l+4) beshort < -2 header\n\
+             \x20   8 byte x version %d\n"
+        );
+    }
+
+    #[test]
+    fn test_magic_rule_to_tree_string_depth_limit() {
+        let mut rule = MagicRule {
+            offset: OffsetSpec::Absolute(0),
+            typ: TypeKind::String {
+                max_length: None,
+                flags: StringFlags::default(),
+            },
+            op: Operator::Equal,
+            value: Value::String("a string value longer than the limit".to_string()),
+            message: String::new(),
+            children: vec![],
+            level: 0,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        };
+        for _ in 0..MagicRule::MAX_TREE_DEPTH + 5 {
+            let parent = MagicRule {
+                children: vec![rule],
+                ..rule_template()
+            };
+            rule = parent;
+        }
+
+        let tree = rule.to_tree_string();
+        let lines: Vec<&str> = tree.lines().collect();
+        assert_eq!(lines.len(), MagicRule::MAX_TREE_DEPTH + 1);
+        assert_eq!(lines.last().unwrap().trim(), "... (nesting limit reached)");
+        assert!(!tree.contains("a string value longer"));
+    }
+
+    fn rule_template() -> MagicRule {
+        MagicRule::new(
+            OffsetSpec::Absolute(0),
+            TypeKind::Byte { signed: false },
+            Operator::Equal,
+            Value::Uint(1),
+            "level",
+        )
+    }
+
     #[test]
     fn test_magic_rule_serialization() {
         let rule = MagicRule {
@@ -638,6 +1626,14 @@ fn test_magic_rule_serialization() {
             message: "Non-zero short value".to_string(),
             children: vec![],
             level: 2,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask: None,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
         };
 
         let json = serde_json::to_string(&rule).expect("Failed to serialize MagicRule");
@@ -0,0 +1,379 @@
+//! Render a rule hierarchy back into magic(5) source text
+//!
+//! This is the inverse of [`parse_magic_string`](super::parse_magic_string):
+//! parsing the generated text yields the same rules, so rule sets can be
+//! edited or built programmatically and written out as ordinary magic files.
+
+use crate::parser::ast::{Endianness, MagicRule, OffsetSpec, Operator, TypeKind, Value};
+use std::fmt::Write;
+
+/// Render rules as magic(5) text, one rule per line
+///
+/// Children follow their parent with one more `>` marker per nesting level,
+/// and columns are separated by tabs. Parsing the output with
+/// [`parse_magic_string`](super::parse_magic_string) produces rules equal to
+/// the input, apart from their source line numbers.
+///
+/// A few forms have no magic(5) syntax the parser reads back: relative offsets
+/// are written as `&N`, from-end offsets as the equivalent negative offset, and
+/// a string `max_length` is dropped, and register offsets are written as
+/// `(reg+N)`; the `negate` and `store` flags are not written. Leading and
+/// trailing whitespace in messages is not preserved. Apple type and creator codes, MIME types and
+/// extensions are written as `!:apple`, `!:mime` and `!:ext` directives after
+/// their rule; the AST does not keep other `!:` directives, so they are not
+/// written.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::{parse_magic_string, to_magic_text};
+///
+/// let rules = parse_magic_string("0 string PK\\x03\\x04 ZIP archive\n>4 leshort >19 v2.0+\n")?;
+/// assert_eq!(
+///     to_magic_text(&rules),
+///     "0\tstring\tPK\\x03\\x04\tZIP archive\n>4\tleshort\t>19\tv2.0+\n"
+/// );
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[must_use]
+pub fn to_magic_text(rules: &[MagicRule]) -> String {
+    let mut out = String::new();
+    for rule in rules {
+        write_rule(&mut out, rule, 0);
+    }
+    out
+}
+
+/// Append `rule` at nesting `depth` and its children below it to `out`
+fn write_rule(out: &mut String, rule: &MagicRule, depth: usize) {
+    let _ = write!(
+        out,
+        "{}{}\t{}\t{}",
+        ">".repeat(depth),
+        format_offset(&rule.offset),
+        format_masked_type(rule),
+        format_test(rule)
+    );
+    if !rule.message.is_empty() {
+        out.push('\t');
+        out.push_str(&rule.message);
+    }
+    out.push('\n');
+    if let (Some(apple_type), Some(apple_creator)) = (&rule.apple_type, &rule.apple_creator) {
+        let _ = writeln!(out, "!:apple\t{apple_type}{apple_creator}");
+    }
+    if let Some(mime_type) = &rule.mime_type {
+        let _ = writeln!(out, "!:mime\t{mime_type}");
+    }
+    if !rule.extensions.is_empty() {
+        let _ = writeln!(out, "!:ext\t{}", rule.extensions.join("/"));
+    }
+
+    for child in &rule.children {
+        write_rule(out, child, depth + 1);
+    }
+}
+
+/// Render an offset column
+fn format_offset(offset: &OffsetSpec) -> String {
+    match offset {
+        OffsetSpec::Absolute(value) | OffsetSpec::FromEnd(value) => value.to_string(),
+        OffsetSpec::Relative(value) => format!("&{value}"),
+        OffsetSpec::Register(value) => format_register_offset(*value),
+        OffsetSpec::Indirect {
+            base_offset,
+            pointer_type,
+            adjustment,
+            relative,
+            ..
+        } => {
+            let (size, endian) = match pointer_type {
+                TypeKind::Short { endian, .. } => ('h', *endian),
+                TypeKind::Long { endian, .. } => ('l', *endian),
+                TypeKind::Quad { endian, .. } => ('q', *endian),
+                _ => ('b', Endianness::Little),
+            };
+            let size = if endian == Endianness::Big {
+                size.to_ascii_uppercase()
+            } else {
+                size
+            };
+            let relative = if *relative { "&" } else { "" };
+            let adjustment = if *adjustment == 0 {
+                String::new()
+            } else {
+                format!("{adjustment:+}")
+            };
+            format!("({relative}{base_offset}.{size}{adjustment})")
+        }
+    }
+}
+
+/// Render a register offset, which has no magic(5) syntax, as `(reg+N)`
+pub(crate) fn format_register_offset(adjustment: i64) -> String {
+    format!("(reg{adjustment:+})")
+}
+
+/// Render a rule's type keyword followed by its `&mask`, if any
+pub(crate) fn format_masked_type(rule: &MagicRule) -> String {
+    let typ = format_type(&rule.typ);
+    match rule.mask {
+        Some(mask) => format!("{typ}&{}", format_uint(mask)),
+        None => typ,
+    }
+}
+
+/// Render a type keyword, including any `/flags` or `/range` suffix
+pub(crate) fn format_type(typ: &TypeKind) -> String {
+    let integer = |name: &str, endian: Endianness, signed: bool| {
+        let sign = if signed { "" } else { "u" };
+        let endian = match endian {
+            Endianness::Little => "le",
+            Endianness::Big => "be",
+            Endianness::Native => "",
+        };
+        format!("{sign}{endian}{name}")
+    };
+    match typ {
+        TypeKind::Byte { signed } => format!("{}byte", if *signed { "" } else { "u" }),
+        TypeKind::Short { endian, signed } => integer("short", *endian, *signed),
+        TypeKind::Long { endian, signed } => integer("long", *endian, *signed),
+        TypeKind::Quad { endian, signed } => integer("quad", *endian, *signed),
+        TypeKind::String { flags, .. } => {
+            let flags = flags.to_string();
+            if flags.is_empty() {
+                "string".to_string()
+            } else {
+                format!("string/{flags}")
+            }
+        }
+        TypeKind::Search { range } => format!("search/{range}"),
+        TypeKind::Indirect => "indirect".to_string(),
+        TypeKind::Default => "default".to_string(),
+        TypeKind::Clear => "clear".to_string(),
+        TypeKind::Name { .. } => "name".to_string(),
+        TypeKind::Use { .. } => "use".to_string(),
+        TypeKind::FileSize => "filesize".to_string(),
+        TypeKind::Checksum {
+            algo,
+            range: (start, end),
+        } => format!("{}/{start},{end}", algo.name()),
+        TypeKind::Extension { name } => name.clone(),
+    }
+}
+
+/// Render the operator and value column
+fn format_test(rule: &MagicRule) -> String {
+    match (&rule.typ, &rule.op) {
+        (TypeKind::Name { name }, _) => name.clone(),
+        (TypeKind::Use { name, byte_swap }, _) => {
+            format!("{}{name}", if *byte_swap { "^" } else { "" })
+        }
+        (TypeKind::Indirect, _) | (_, Operator::Always) => "x".to_string(),
+        (typ, op) => format!("{}{}", format_operator(op), format_value(&rule.value, typ)),
+    }
+}
+
+/// Render a rule value so that it parses back to the same value for `typ`
+fn format_value(value: &Value, typ: &TypeKind) -> String {
+    match value {
+        Value::Uint(v) => format_uint(*v),
+        Value::Int(v) => v.to_string(),
+        Value::String(text) => escape_string(text, typ),
+        Value::Bytes(bytes) => escape_bytes(bytes),
+        Value::OneOf(members) => members
+            .iter()
+            .map(|member| format_value(member, typ))
+            .collect::<Vec<_>>()
+            .join("|"),
+    }
+}
+
+/// Render an unsigned value so that it parses back to the same number
+///
+/// Values above `i64::MAX` only come from negative literals on unsigned
+/// 64-bit types, so they are written as that negative literal.
+fn format_uint(value: u64) -> String {
+    i64::try_from(value).map_or_else(
+        |_| {
+            #[allow(clippy::cast_possible_wrap)]
+            let literal = value as i64;
+            literal.to_string()
+        },
+        |_| format!("{value:#x}"),
+    )
+}
+
+/// Render the operator prefix of a value; equality is implied and omitted
+const fn format_operator(op: &Operator) -> &'static str {
+    match op {
+        Operator::Equal | Operator::Always => "",
+        Operator::NotEqual => "!",
+        Operator::BitwiseAnd => "&",
+        Operator::LessThan => "<",
+        Operator::GreaterThan => ">",
+        Operator::BitwiseXor => "^",
+        Operator::BitwiseNot => "~",
+    }
+}
+
+/// Escape a string value as an unquoted magic(5) string
+///
+/// The first character is escaped when it would otherwise be read as an
+/// operator, as the `x` wildcard, or as a number for an extension type.
+fn escape_string(text: &str, typ: &TypeKind) -> String {
+    if text.is_empty() {
+        return "\"\"".to_string();
+    }
+    if text == "x" {
+        return "\\x78".to_string();
+    }
+
+    let numeric_start = matches!(typ, TypeKind::Extension { .. })
+        && text.starts_with(|c: char| c.is_ascii_digit() || c == '-');
+    let mut out = String::new();
+    for (index, c) in text.chars().enumerate() {
+        match c {
+            c if index == 0 && numeric_start => push_hex_bytes(&mut out, c),
+            '=' | '!' | '<' | '>' | '&' | '^' | '~' | '"' if index == 0 => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\\' => out.push_str("\\\\"),
+            ' ' => out.push_str("\\ "),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_whitespace() || c.is_control() => push_hex_bytes(&mut out, c),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape a byte-sequence value, keeping printable ASCII readable
+fn escape_bytes(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "\"\"".to_string();
+    }
+
+    let mut out = String::new();
+    for (index, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'=' | b'!' | b'<' | b'>' | b'&' | b'^' | b'~' | b'"' | b'x' if index == 0 => {
+                let _ = write!(out, "\\x{byte:02x}");
+            }
+            b'\\' => out.push_str("\\\\"),
+            byte if byte.is_ascii_graphic() => out.push(char::from(byte)),
+            byte => {
+                let _ = write!(out, "\\x{byte:02x}");
+            }
+        }
+    }
+    out
+}
+
+/// Append the UTF-8 bytes of `c` as `\xNN` escapes
+fn push_hex_bytes(out: &mut String, c: char) {
+    let mut buf = [0u8; 4];
+    for byte in c.encode_utf8(&mut buf).bytes() {
+        let _ = write!(out, "\\x{byte:02x}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_magic_string;
+
+    /// Debug form of rules without their source lines, which codegen does not keep
+    fn normalized(mut rules: Vec<MagicRule>) -> String {
+        fn clear_lines(rules: &mut [MagicRule]) {
+            for rule in rules {
+                rule.source_line = None;
+                clear_lines(&mut rule.children);
+            }
+        }
+        clear_lines(&mut rules);
+        format!("{rules:#?}")
+    }
+
+    fn assert_round_trip(source: &str) {
+        let rules = parse_magic_string(source).unwrap();
+        let text = to_magic_text(&rules);
+        let reparsed = parse_magic_string(&text)
+            .unwrap_or_else(|e| panic!("generated text does not parse: {e}\n{text}"));
+        assert_eq!(normalized(reparsed), normalized(rules), "{text}");
+    }
+
+    #[test]
+    fn test_round_trip_preserves_ast() {
+        assert_round_trip(
+            "# ELF\n\
+             0 string \\x7fELF ELF\n\
+             >4 byte 1 32-bit\n\
+             >4 byte 2 64-bit\n\
+             >>(0x18.l+4) ulelong !0 entry %#x\n\
+             >>(&0x20.Q-8) beshort <-2 negative\n\
+             >5 byte x \\b, data %d\n\
+             0 belong &0xf0 masked\n\
+             0 byte&0xf0 0x40 high nibble\n\
+             0 ubeshort&-16 <0x100 low\n\
+             0 uquad -1 all ones\n\
+             0 lelong ~0x10 complement\n\
+             0 byte ^0x80 clear\n\
+             -4 string/wW TRAILER\\ \\ end\n\
+             0 string \\=\\x00\\xff\\\\path\\tab binary\n\
+             0 string x\\ y starts with x\n\
+             0 string \\x78 just x\n\
+             0 string \"\" empty\n\
+             0 search/4096 !%PDF not a pdf\n\
+             0 string >\\<html html-ish\n\
+             0 string caf\\xc3\\xa9 unicode\n\
+             0 indirect x\n\
+             0 name part\n\
+             >0 byte 1 one\n\
+             0 use part\n\
+             8 use ^part\n\
+             0 filesize >512 large\n\
+             0 x-custom 42 number\n\
+             0 x-custom \\x31abc text\n\
+             0 belong 0x00051600 AppleSingle\n\
+             !:apple ????????\n\
+             >8 string TEXT text\n\
+             !:apple TEXTttxt\n\
+             !:mime text/plain\n\
+             !:ext txt/text\n",
+        );
+    }
+
+    #[test]
+    fn test_to_magic_text_writes_levels_and_columns() {
+        let rules =
+            parse_magic_string("0 string PK ZIP\n>4 byte 20 v2.0\n>>8 use ^sub\n0 name sub\n")
+                .unwrap();
+        assert_eq!(
+            to_magic_text(&rules),
+            "0\tstring\tPK\tZIP\n\
+             >4\tbyte\t20\tv2.0\n\
+             >>8\tuse\t^sub\n\
+             0\tname\tsub\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_string_protects_leading_characters() {
+        let string = TypeKind::String {
+            max_length: None,
+            flags: crate::parser::ast::StringFlags::default(),
+        };
+        assert_eq!(escape_string("=a b", &string), "\\=a\\ b");
+        assert_eq!(escape_string("x", &string), "\\x78");
+        assert_eq!(escape_string("1.0", &string), "1.0");
+        let extension = TypeKind::Extension {
+            name: "x-text".to_string(),
+        };
+        assert_eq!(escape_string("1.0", &extension), "\\x31.0");
+        assert_eq!(escape_bytes(&[b'x', 0, b'\\']), "\\x78\\x00\\\\");
+    }
+}
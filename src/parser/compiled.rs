@@ -0,0 +1,139 @@
+//! Compiled magic database format
+//!
+//! A compiled database stores already-parsed rules so they can be loaded
+//! without re-parsing the magic source, like the `.mgc` files produced by
+//! GNU `file -C`. The format is a fixed header (the [`COMPILED_MAGIC`]
+//! signature followed by a little-endian `u32` format version) and the
+//! serialized rule tree.
+
+use crate::parser::ast::MagicRule;
+use crate::{LibmagicError, Result};
+use std::path::Path;
+
+/// Signature at the start of every compiled database
+pub const COMPILED_MAGIC: &[u8] = b"RMAGIC\0\x01";
+
+/// Version of the compiled format written by this library
+pub const COMPILED_VERSION: u32 = 1;
+
+/// Length of the compiled database header
+const HEADER_LEN: usize = COMPILED_MAGIC.len() + 4;
+
+/// Check whether `data` starts with the compiled database signature
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::compiled::{is_compiled, serialize_rules};
+///
+/// let compiled = serialize_rules(&[]).unwrap();
+/// assert!(is_compiled(&compiled));
+/// assert!(!is_compiled(b"0 string PK ZIP archive"));
+/// ```
+#[must_use]
+pub fn is_compiled(data: &[u8]) -> bool {
+    data.starts_with(COMPILED_MAGIC)
+}
+
+/// Serialize rules into the compiled database format
+///
+/// # Errors
+///
+/// Returns `LibmagicError::InvalidFormat` if the rules cannot be serialized.
+pub fn serialize_rules(rules: &[MagicRule]) -> Result<Vec<u8>> {
+    let body = serde_json::to_vec(rules).map_err(|e| {
+        LibmagicError::InvalidFormat(format!("Failed to serialize compiled database: {e}"))
+    })?;
+
+    let mut data = Vec::with_capacity(HEADER_LEN + body.len());
+    data.extend_from_slice(COMPILED_MAGIC);
+    data.extend_from_slice(&COMPILED_VERSION.to_le_bytes());
+    data.extend_from_slice(&body);
+    Ok(data)
+}
+
+/// Deserialize rules from the compiled database format
+///
+/// # Errors
+///
+/// Returns `LibmagicError::InvalidFormat` if `data` is not a compiled database,
+/// was written by an unsupported format version, or is corrupt.
+pub fn deserialize_rules(data: &[u8]) -> Result<Vec<MagicRule>> {
+    if !is_compiled(data) || data.len() < HEADER_LEN {
+        return Err(LibmagicError::InvalidFormat(
+            "Not a compiled magic database".to_string(),
+        ));
+    }
+
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&data[COMPILED_MAGIC.len()..HEADER_LEN]);
+    let version = u32::from_le_bytes(version);
+    if version != COMPILED_VERSION {
+        return Err(LibmagicError::InvalidFormat(format!(
+            "Unsupported compiled magic database version {version} (expected {COMPILED_VERSION})"
+        )));
+    }
+
+    serde_json::from_slice(&data[HEADER_LEN..])
+        .map_err(|e| LibmagicError::InvalidFormat(format!("Corrupt compiled magic database: {e}")))
+}
+
+/// Write rules to `path` in the compiled database format
+///
+/// # Errors
+///
+/// Returns `LibmagicError::IoError` if the file cannot be written.
+pub fn write_compiled<P: AsRef<Path>>(rules: &[MagicRule], path: P) -> Result<()> {
+    let data = serialize_rules(rules)?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_magic_string;
+
+    #[test]
+    fn test_compiled_round_trip() {
+        let rules = parse_magic_string(
+            "0 string \\x7fELF ELF\n>4 byte 2 64-bit\n0 name sub\n>0 beshort x value %d\n",
+        )
+        .unwrap();
+
+        let data = serialize_rules(&rules).unwrap();
+        assert!(data.starts_with(COMPILED_MAGIC));
+
+        let loaded = deserialize_rules(&data).unwrap();
+        assert_eq!(loaded.len(), rules.len());
+        for (loaded, original) in loaded.iter().zip(&rules) {
+            assert_eq!(loaded.typ, original.typ);
+            assert_eq!(loaded.value, original.value);
+            assert_eq!(loaded.message, original.message);
+            assert_eq!(loaded.children.len(), original.children.len());
+        }
+    }
+
+    #[test]
+    fn test_deserialize_rejects_bad_input() {
+        assert!(matches!(
+            deserialize_rules(b"0 string PK ZIP"),
+            Err(LibmagicError::InvalidFormat(_))
+        ));
+
+        let mut data = serialize_rules(&[]).unwrap();
+        data[COMPILED_MAGIC.len()] = 99;
+        match deserialize_rules(&data).unwrap_err() {
+            LibmagicError::InvalidFormat(message) => assert!(message.contains("version 99")),
+            other => panic!("Expected InvalidFormat, got {other:?}"),
+        }
+
+        let mut data = serialize_rules(&[]).unwrap();
+        data.truncate(HEADER_LEN);
+        data.extend_from_slice(b"{not json");
+        assert!(matches!(
+            deserialize_rules(&data),
+            Err(LibmagicError::InvalidFormat(_))
+        ));
+    }
+}
@@ -11,10 +11,12 @@
     combinator::{map, opt, recognize},
     error::Error as NomError,
     multi::many0,
-    sequence::pair,
+    sequence::{pair, preceded},
 };
 
-use crate::parser::ast::{OffsetSpec, Operator, Value};
+use crate::parser::ast::{
+    ChecksumAlgo, Endianness, MagicRule, OffsetSpec, Operator, StringFlags, TypeKind, Value,
+};
 
 /// Parse a decimal number with overflow protection
 fn parse_decimal_number(input: &str) -> IResult<&str, i64> {
@@ -100,21 +102,42 @@ pub fn parse_number(input: &str) -> IResult<&str, i64> {
     Ok((input, result))
 }
 
-/// Parse an offset specification for absolute offsets
+/// Parse an offset specification
 ///
-/// Supports decimal and hexadecimal formats, both positive and negative.
+/// Supports absolute offsets in decimal and hexadecimal formats, both positive and
+/// negative, relative offsets of the form `&adjustment`, and indirect offsets of
+/// the form `(base.type+adjustment)`. A `&` inside the parentheses, as in
+/// `(&4.l)`, makes an indirect offset relative instead.
 ///
 /// # Examples
 ///
 /// ```
 /// use libmagic_rs::parser::grammar::parse_offset;
-/// use libmagic_rs::parser::ast::OffsetSpec;
+/// use libmagic_rs::parser::ast::{Endianness, OffsetSpec, TypeKind};
 ///
 /// assert_eq!(parse_offset("0"), Ok(("", OffsetSpec::Absolute(0))));
 /// assert_eq!(parse_offset("123"), Ok(("", OffsetSpec::Absolute(123))));
 /// assert_eq!(parse_offset("0x10"), Ok(("", OffsetSpec::Absolute(16))));
 /// assert_eq!(parse_offset("-4"), Ok(("", OffsetSpec::Absolute(-4))));
 /// assert_eq!(parse_offset("-0xFF"), Ok(("", OffsetSpec::Absolute(-255))));
+///
+/// // `&` measures the offset from the end of the parent rule's match
+/// assert_eq!(parse_offset("&4"), Ok(("", OffsetSpec::Relative(4))));
+/// assert_eq!(parse_offset("&-2"), Ok(("", OffsetSpec::Relative(-2))));
+///
+/// assert_eq!(
+///     parse_offset("(0x3c.l+4)"),
+///     Ok((
+///         "",
+///         OffsetSpec::Indirect {
+///             base_offset: 0x3c,
+///             pointer_type: TypeKind::Long { endian: Endianness::Little, signed: false },
+///             adjustment: 4,
+///             endian: Endianness::Little,
+///             relative: false,
+///         }
+///     ))
+/// );
 /// ```
 ///
 /// # Errors
@@ -123,20 +146,125 @@ pub fn parse_number(input: &str) -> IResult<&str, i64> {
 /// - The input contains invalid number format (propagated from `parse_number`)
 /// - Input is empty or contains no parseable offset value
 /// - The offset value cannot be represented as a valid `i64`
+///
+/// Returns a `nom::Err::Failure` if an indirect offset names an unknown pointer
+/// type, since such an offset could never be evaluated.
 pub fn parse_offset(input: &str) -> IResult<&str, OffsetSpec> {
     let (input, _) = multispace0(input)?;
-    let (input, offset_value) = parse_number(input)?;
+    let (input, offset) = if input.starts_with('(') {
+        parse_indirect_offset(input)?
+    } else if let Some(rest) = input.strip_prefix('&') {
+        let (input, offset_value) = parse_number(rest)?;
+        (input, OffsetSpec::Relative(offset_value))
+    } else {
+        let (input, offset_value) = parse_number(input)?;
+        (input, OffsetSpec::Absolute(offset_value))
+    };
     let (input, _) = multispace0(input)?;
 
-    Ok((input, OffsetSpec::Absolute(offset_value)))
+    Ok((input, offset))
+}
+
+/// Parse an indirect offset of the form `([&]base[.type][+-adjustment])`
+///
+/// The pointer type defaults to a little-endian long when omitted, matching
+/// libmagic. A leading `&` makes the offset relative to the current region.
+fn parse_indirect_offset(input: &str) -> IResult<&str, OffsetSpec> {
+    let (input, _) = char('(').parse(input)?;
+    let (input, relative) = opt(char('&')).parse(input)?;
+    let (input, base_offset) = parse_number(input)?;
+    let (input, pointer_type) = opt(parse_pointer_type).parse(input)?;
+    let pointer_type = pointer_type.unwrap_or(TypeKind::Long {
+        endian: Endianness::Little,
+        signed: false,
+    });
+    let (input, adjustment) = opt(parse_offset_adjustment).parse(input)?;
+    let (input, _) = char(')').parse(input)?;
+
+    let endian = match pointer_type {
+        TypeKind::Short { endian, .. }
+        | TypeKind::Long { endian, .. }
+        | TypeKind::Quad { endian, .. } => endian,
+        _ => Endianness::Little,
+    };
+
+    Ok((
+        input,
+        OffsetSpec::Indirect {
+            base_offset,
+            pointer_type,
+            adjustment: adjustment.unwrap_or(0),
+            endian,
+            relative: relative.is_some(),
+        },
+    ))
+}
+
+/// Parse the `.type` suffix of an indirect offset
+///
+/// Lowercase letters select little-endian and uppercase letters big-endian reads.
+/// As in magic(5), `.h` and `.s` both read a short. Any other letter is rejected
+/// with a `nom::Err::Failure` so the caller does not try other alternatives.
+fn parse_pointer_type(input: &str) -> IResult<&str, TypeKind> {
+    let (rest, _) = char('.').parse(input)?;
+    let (after, type_char) = nom::character::complete::anychar(rest)?;
+
+    let endian = if type_char.is_ascii_uppercase() {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    };
+
+    let pointer_type = match type_char.to_ascii_lowercase() {
+        'b' | 'c' => TypeKind::Byte { signed: false },
+        'h' | 's' => TypeKind::Short {
+            endian,
+            signed: false,
+        },
+        'l' => TypeKind::Long {
+            endian,
+            signed: false,
+        },
+        'q' => TypeKind::Quad {
+            endian,
+            signed: false,
+        },
+        _ => {
+            return Err(nom::Err::Failure(NomError::new(
+                rest,
+                nom::error::ErrorKind::OneOf,
+            )));
+        }
+    };
+
+    Ok((after, pointer_type))
+}
+
+/// Parse the `+N` or `-N` adjustment of an indirect offset
+fn parse_offset_adjustment(input: &str) -> IResult<&str, i64> {
+    let (input, sign) = one_of("+-").parse(input)?;
+    let (input, value) = parse_number(input)?;
+
+    let value = if sign == '-' {
+        value
+            .checked_neg()
+            .ok_or_else(|| nom::Err::Error(NomError::new(input, nom::error::ErrorKind::MapRes)))?
+    } else {
+        value
+    };
+
+    Ok((input, value))
 }
 
 /// Parse comparison operators for magic rules
 ///
 /// Supports both symbolic and text representations of operators:
 /// - `=` or `==` for equality
-/// - `!=` or `<>` for inequality
+/// - `!=`, `<>` or `!` for inequality
 /// - `&` for bitwise AND
+/// - `<` and `>` for ordering comparisons
+/// - `^` to test that any of the operand's bits are clear
+/// - `~` to compare against the operand's bitwise complement
 ///
 /// # Examples
 ///
@@ -149,6 +277,8 @@ pub fn parse_offset(input: &str) -> IResult<&str, OffsetSpec> {
 /// assert_eq!(parse_operator("!="), Ok(("", Operator::NotEqual)));
 /// assert_eq!(parse_operator("<>"), Ok(("", Operator::NotEqual)));
 /// assert_eq!(parse_operator("&"), Ok(("", Operator::BitwiseAnd)));
+/// assert_eq!(parse_operator("!0x10"), Ok(("0x10", Operator::NotEqual)));
+/// assert_eq!(parse_operator("<10"), Ok(("10", Operator::LessThan)));
 /// ```
 ///
 /// # Errors
@@ -156,7 +286,7 @@ pub fn parse_offset(input: &str) -> IResult<&str, OffsetSpec> {
 /// Returns a nom parsing error if:
 /// - Input does not start with a recognized operator symbol
 /// - Input is empty or contains no valid operator
-/// - Operator syntax is incomplete (e.g., just `!` without `=`)
+/// - An operator symbol is doubled (e.g., `===` or `&&`)
 pub fn parse_operator(input: &str) -> IResult<&str, Operator> {
     let (input, _) = multispace0(input)?;
 
@@ -207,6 +337,21 @@ pub fn parse_operator(input: &str) -> IResult<&str, Operator> {
         return Ok((remaining, Operator::BitwiseAnd));
     }
 
+    // Single-character operators from the magic(5) value column; the two-character
+    // forms above have already been tried
+    let single = match input.chars().next() {
+        Some('!') => Some(Operator::NotEqual),
+        Some('<') => Some(Operator::LessThan),
+        Some('>') => Some(Operator::GreaterThan),
+        Some('^') => Some(Operator::BitwiseXor),
+        Some('~') => Some(Operator::BitwiseNot),
+        _ => None,
+    };
+    if let Some(operator) = single {
+        let (remaining, _) = multispace0(&input[1..])?;
+        return Ok((remaining, operator));
+    }
+
     // If no operator matches, return an error
     Err(nom::Err::Error(nom::error::Error::new(
         input,
@@ -228,15 +373,26 @@ fn parse_hex_byte_with_prefix(input: &str) -> IResult<&str, u8> {
 }
 
 /// Parse a hex byte sequence starting with \x prefix
+///
+/// Consecutive escapes form one sequence, which must end at whitespace or at
+/// the end of the input. A run followed by anything else, such as `\x7fELF`
+/// or an incomplete `\x7`, is not a byte literal.
 fn parse_hex_bytes_with_prefix(input: &str) -> IResult<&str, Vec<u8>> {
-    if input.starts_with("\\x") {
-        many0(parse_hex_byte_with_prefix).parse(input)
-    } else {
-        Err(nom::Err::Error(NomError::new(
+    if !input.starts_with("\\x") {
+        return Err(nom::Err::Error(NomError::new(
             input,
             nom::error::ErrorKind::Tag,
-        )))
+        )));
     }
+
+    let (rest, bytes) = many0(parse_hex_byte_with_prefix).parse(input)?;
+    if rest.chars().next().is_some_and(|c| !c.is_whitespace()) {
+        return Err(nom::Err::Error(NomError::new(
+            rest,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+    Ok((rest, bytes))
 }
 
 /// Parse a hex byte sequence without prefix (only if it looks like pure hex bytes)
@@ -443,6 +599,561 @@ pub fn parse_value(input: &str) -> IResult<&str, Value> {
     Ok((input, value))
 }
 
+/// Parse a type keyword from a magic rule
+///
+/// Supports the integer types `byte`, `short`, `long`, and `quad` with optional
+/// `le`/`be` endianness prefixes (native endianness otherwise) and an optional
+/// `u` prefix for unsigned comparisons (every integer type is signed without
+/// it), plus `string`, `search/N`, `indirect`, `name`, `use`, and `filesize`.
+/// Of the string flags after a `/`, `w`, `W`, `T` and `b` are kept and the
+/// others ignored. Names starting with `x-` are extension types, read by a
+/// registered custom reader.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::grammar::parse_type;
+/// use libmagic_rs::parser::ast::{Endianness, TypeKind};
+///
+/// assert_eq!(parse_type("byte"), Ok(("", TypeKind::Byte { signed: true })));
+/// assert_eq!(parse_type("ubyte"), Ok(("", TypeKind::Byte { signed: false })));
+/// assert_eq!(
+///     parse_type("ubelong"),
+///     Ok(("", TypeKind::Long { endian: Endianness::Big, signed: false }))
+/// );
+/// assert_eq!(
+///     parse_type("leshort"),
+///     Ok(("", TypeKind::Short { endian: Endianness::Little, signed: true }))
+/// );
+/// assert_eq!(parse_type("search/256"), Ok(("", TypeKind::Search { range: 256 })));
+/// assert_eq!(
+///     parse_type("x-mytype"),
+///     Ok(("", TypeKind::Extension { name: "x-mytype".to_string() }))
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Returns a nom parsing error if the input does not start with a known type
+/// keyword, or if `search` is missing its range.
+pub fn parse_type(input: &str) -> IResult<&str, TypeKind> {
+    if let Ok((rest, name)) = recognize(pair(
+        tag::<&str, &str, NomError<&str>>("x-"),
+        nom::bytes::complete::take_while1(|c: char| {
+            c.is_ascii_alphanumeric() || c == '-' || c == '_'
+        }),
+    ))
+    .parse(input)
+    {
+        return Ok((
+            rest,
+            TypeKind::Extension {
+                name: name.to_string(),
+            },
+        ));
+    }
+
+    let (rest, keyword) =
+        nom::bytes::complete::take_while1(|c: char| c.is_ascii_alphanumeric()).parse(input)?;
+
+    match keyword {
+        "string" => {
+            let (rest, letters) = parse_type_flags(rest)?;
+            return Ok((
+                rest,
+                TypeKind::String {
+                    max_length: None,
+                    flags: StringFlags::from_letters(letters.unwrap_or_default()),
+                },
+            ));
+        }
+        "search" => {
+            let (rest, _) = char('/').parse(rest)?;
+            let (rest, range) = parse_decimal_number(rest)?;
+            let range = usize::try_from(range)
+                .map_err(|_| nom::Err::Error(NomError::new(rest, nom::error::ErrorKind::MapRes)))?;
+            let (rest, _) = parse_type_flags(rest)?;
+            return Ok((rest, TypeKind::Search { range }));
+        }
+        "indirect" => return Ok((rest, TypeKind::Indirect)),
+        "default" => return Ok((rest, TypeKind::Default)),
+        "clear" => return Ok((rest, TypeKind::Clear)),
+        "filesize" => return Ok((rest, TypeKind::FileSize)),
+        "crc32" => return parse_checksum_range(rest, ChecksumAlgo::Crc32),
+        "adler32" => return parse_checksum_range(rest, ChecksumAlgo::Adler32),
+        "name" => {
+            return Ok((
+                rest,
+                TypeKind::Name {
+                    name: String::new(),
+                },
+            ));
+        }
+        "use" => {
+            return Ok((
+                rest,
+                TypeKind::Use {
+                    name: String::new(),
+                    byte_swap: false,
+                },
+            ));
+        }
+        _ => {}
+    }
+
+    let (signed, name) = keyword
+        .strip_prefix('u')
+        .map_or((true, keyword), |name| (false, name));
+    let (endian, base) = if let Some(base) = name.strip_prefix("le") {
+        (Endianness::Little, base)
+    } else if let Some(base) = name.strip_prefix("be") {
+        (Endianness::Big, base)
+    } else {
+        (Endianness::Native, name)
+    };
+
+    let typ = match (base, endian) {
+        ("byte", Endianness::Native) => TypeKind::Byte { signed },
+        ("short", _) => TypeKind::Short { endian, signed },
+        ("long", _) => TypeKind::Long { endian, signed },
+        ("quad", _) => TypeKind::Quad { endian, signed },
+        _ => {
+            return Err(nom::Err::Error(NomError::new(
+                input,
+                nom::error::ErrorKind::Tag,
+            )));
+        }
+    };
+
+    Ok((rest, typ))
+}
+
+/// The type name of a rule line, if it is not a type the parser supports
+///
+/// Used to report a rule that failed to parse because of its type, rather
+/// than with a generic parse error. Returns `None` when the type is known,
+/// including when it is known but malformed (such as `search` without a
+/// range), or when the line fails to parse before its type.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::grammar::unsupported_type_name;
+///
+/// assert_eq!(unsupported_type_name(">4 bogustype 1 hello"), Some("bogustype"));
+/// assert_eq!(unsupported_type_name("0 belong 1 hello"), None);
+/// assert_eq!(unsupported_type_name("0 search 1 hello"), None);
+/// ```
+#[must_use]
+pub fn unsupported_type_name(line: &str) -> Option<&str> {
+    let (input, _) = many0(char::<&str, NomError<&str>>('>')).parse(line).ok()?;
+    let (input, _) = parse_offset(input).ok()?;
+    let (_, name) = nom::bytes::complete::take_while1::<_, _, NomError<&str>>(|c: char| {
+        c.is_ascii_alphanumeric() || c == '-' || c == '_'
+    })
+    .parse(input)
+    .ok()?;
+
+    // Known types that are malformed fail after their keyword
+    match parse_type(input) {
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) if e.input == input => Some(name),
+        _ => None,
+    }
+}
+
+/// The flag letters after `string/` on a rule line, if it has any
+///
+/// Lets the parser warn about letters that [`StringFlags::from_letters`]
+/// drops. Returns `None` for other types and lines that fail to parse before
+/// their type.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::grammar::string_flag_letters;
+///
+/// assert_eq!(string_flag_letters(">8 string/Tb x %s"), Some("Tb"));
+/// assert_eq!(string_flag_letters("0 string PK zip"), None);
+/// ```
+#[must_use]
+pub fn string_flag_letters(line: &str) -> Option<&str> {
+    let (input, _) = many0(char::<&str, NomError<&str>>('>')).parse(line).ok()?;
+    let (input, _) = parse_offset(input).ok()?;
+    let input = input.strip_prefix("string/")?;
+    let end = input
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(input.len());
+    Some(&input[..end])
+}
+
+/// Apply the `/r` flag of an `indirect` type, if present, to the rule's offset
+///
+/// `indirect/r` measures a plain offset from the end of the previous match, so
+/// `>0 indirect/r` re-runs the database at the current position. It becomes the
+/// same relative offset as `&N`; other offsets are left as written.
+fn parse_indirect_relative_flag<'a>(
+    input: &'a str,
+    typ: &TypeKind,
+    offset: OffsetSpec,
+) -> (&'a str, OffsetSpec) {
+    match (typ, input.strip_prefix("/r")) {
+        (TypeKind::Indirect, Some(rest)) => match offset {
+            OffsetSpec::Absolute(delta) => (rest, OffsetSpec::Relative(delta)),
+            other => (rest, other),
+        },
+        _ => (input, offset),
+    }
+}
+
+/// Parse the `/START,END` byte range following a checksum type
+///
+/// A range that ends before it starts is a `nom::Err::Failure`.
+fn parse_checksum_range(input: &str, algo: ChecksumAlgo) -> IResult<&str, TypeKind> {
+    let (input, _) = char('/').parse(input)?;
+    let (input, start) = parse_decimal_number(input)?;
+    let (input, _) = char(',').parse(input)?;
+    let (rest, end) = parse_decimal_number(input)?;
+    let to_usize = |n: i64| {
+        usize::try_from(n)
+            .map_err(|_| nom::Err::Failure(NomError::new(input, nom::error::ErrorKind::MapRes)))
+    };
+    let (start, end) = (to_usize(start)?, to_usize(end)?);
+    if end < start {
+        return Err(nom::Err::Failure(NomError::new(
+            input,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+
+    Ok((
+        rest,
+        TypeKind::Checksum {
+            algo,
+            range: (start, end),
+        },
+    ))
+}
+
+/// Skip `/flags` modifiers following a string-like type
+fn parse_type_flags(input: &str) -> IResult<&str, Option<&str>> {
+    opt(nom::sequence::preceded(
+        char('/'),
+        nom::bytes::complete::take_while1(|c: char| c.is_ascii_alphanumeric()),
+    ))
+    .parse(input)
+}
+
+/// Parse an unquoted or quoted string value into raw bytes
+///
+/// Unquoted values end at the first unescaped whitespace. Escapes produce raw
+/// bytes: `\xNN`, octal `\NNN`, the C escapes `\n`, `\r`, `\t`, `\a`, `\b`, `\f`,
+/// `\v`, `\0`, and any other escaped character (such as `\ ` or `\\`) stands
+/// for itself.
+fn parse_string_bytes(input: &str) -> IResult<&str, Vec<u8>> {
+    let quoted = input.starts_with('"');
+    let mut chars = input.char_indices().skip(usize::from(quoted)).peekable();
+    let mut bytes = Vec::new();
+    let mut end = input.len();
+    let mut closed = !quoted;
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' if quoted => {
+                end = index + 1;
+                closed = true;
+                break;
+            }
+            c if !quoted && c.is_whitespace() => {
+                end = index;
+                break;
+            }
+            '\\' => {
+                let Some((_, escaped)) = chars.next() else {
+                    return Err(nom::Err::Error(NomError::new(
+                        &input[index..],
+                        nom::error::ErrorKind::Escaped,
+                    )));
+                };
+                match escaped {
+                    'x' => {
+                        let mut value = 0u8;
+                        let mut digits = 0;
+                        while digits < 2 {
+                            match chars.peek().and_then(|&(_, d)| d.to_digit(16)) {
+                                Some(digit) => {
+                                    value = value * 16 + u8::try_from(digit).unwrap_or(0);
+                                    digits += 1;
+                                    chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        if digits == 0 {
+                            bytes.push(b'x');
+                        } else {
+                            bytes.push(value);
+                        }
+                    }
+                    '0'..='7' => {
+                        let mut value = escaped.to_digit(8).unwrap_or(0);
+                        for _ in 0..2 {
+                            match chars.peek().and_then(|&(_, d)| d.to_digit(8)) {
+                                Some(digit) => {
+                                    value = value * 8 + digit;
+                                    chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        bytes.push(u8::try_from(value & 0xff).unwrap_or(0));
+                    }
+                    'n' => bytes.push(b'\n'),
+                    'r' => bytes.push(b'\r'),
+                    't' => bytes.push(b'\t'),
+                    'a' => bytes.push(0x07),
+                    'b' => bytes.push(0x08),
+                    'f' => bytes.push(0x0c),
+                    'v' => bytes.push(0x0b),
+                    other => {
+                        let mut buf = [0u8; 4];
+                        bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+                    }
+                }
+            }
+            other => {
+                let mut buf = [0u8; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+
+    if !closed || end == 0 {
+        return Err(nom::Err::Error(NomError::new(
+            input,
+            nom::error::ErrorKind::Char,
+        )));
+    }
+
+    Ok((&input[end..], bytes))
+}
+
+/// Parse the value of a `string` or `search` rule
+///
+/// The value is returned as `Value::String` when it is valid UTF-8 and as
+/// `Value::Bytes` otherwise.
+fn parse_string_value(input: &str) -> IResult<&str, Value> {
+    let (input, bytes) = parse_string_bytes(input)?;
+    let value =
+        String::from_utf8(bytes).map_or_else(|e| Value::Bytes(e.into_bytes()), Value::String);
+    Ok((input, value))
+}
+
+/// Recognize the `x` value that matches anything, returning the input after it
+fn parse_any_value(input: &str) -> Option<&str> {
+    input
+        .strip_prefix('x')
+        .filter(|rest| rest.chars().next().is_none_or(char::is_whitespace))
+}
+
+/// Convert a parsed numeric value to the signedness of the rule's type
+///
+/// Signed types compare against sign-extended values, so an unsigned literal
+/// such as `0xffff` for a signed `short` becomes `-1`. Literals that do not fit
+/// in the type's width are left unchanged and simply never match.
+fn coerce_numeric_value(value: Value, typ: &TypeKind) -> Value {
+    let (bits, signed) = match typ {
+        TypeKind::Byte { signed } => (8, *signed),
+        TypeKind::Short { signed, .. } => (16, *signed),
+        TypeKind::Long { signed, .. } => (32, *signed),
+        TypeKind::Quad { signed, .. } => (64, *signed),
+        _ => return value,
+    };
+
+    match value {
+        Value::Uint(v) if signed && (bits == 64 || v >> bits == 0) => {
+            let shift = 64 - bits;
+            #[allow(clippy::cast_possible_wrap)]
+            Value::Int(((v << shift) as i64) >> shift)
+        }
+        Value::Int(v) if !signed && v < 0 => {
+            #[allow(clippy::cast_sign_loss)]
+            let unsigned = v as u64;
+            Value::Uint(if bits == 64 {
+                unsigned
+            } else {
+                unsigned & ((1u64 << bits) - 1)
+            })
+        }
+        other => other,
+    }
+}
+
+/// Parse the operator and numeric value of a rule of type `typ`
+///
+/// `=1|2|3` and `!1|2|3` test membership in a list of alternatives.
+fn parse_numeric_test<'a>(input: &'a str, typ: &TypeKind) -> IResult<&'a str, (Operator, Value)> {
+    let (input, op) = opt(parse_operator).parse(input)?;
+    let op = op.unwrap_or(Operator::Equal);
+    let (input, number) = parse_number(input)?;
+    let literal = |number: i64| {
+        let value = if number >= 0 {
+            Value::Uint(number.unsigned_abs())
+        } else {
+            Value::Int(number)
+        };
+        coerce_numeric_value(value, typ)
+    };
+    let (input, alternatives) = if matches!(op, Operator::Equal | Operator::NotEqual) {
+        many0(preceded(char('|'), parse_number)).parse(input)?
+    } else {
+        (input, Vec::new())
+    };
+    let value = if alternatives.is_empty() {
+        literal(number)
+    } else {
+        Value::OneOf(
+            std::iter::once(number)
+                .chain(alternatives)
+                .map(literal)
+                .collect(),
+        )
+    };
+    Ok((input, (op, value)))
+}
+
+/// Parse a complete magic rule line
+///
+/// A rule line has the form `[>...]offset type [operator]value [message]`, where
+/// the number of leading `>` characters is the rule's nesting level. An integer
+/// type may carry a mask (`byte&0xf0`) that is applied to the value read before
+/// it is compared. `name` and `use` take a subroutine name in place of the
+/// value, and types without a value column (see [`TypeKind::requires_value`])
+/// go straight to the message, though an `x` placeholder is accepted. A
+/// value of `x` on any other type matches anything and produces
+/// [`Operator::Always`]. The message is the remainder of the line with trailing
+/// whitespace removed.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::grammar::parse_rule_line;
+/// use libmagic_rs::parser::ast::{OffsetSpec, Operator, StringFlags, TypeKind, Value};
+///
+/// let (_, rule) = parse_rule_line(r"0 string \x7fELF ELF").unwrap();
+/// assert_eq!(rule.offset, OffsetSpec::Absolute(0));
+/// assert_eq!(
+///     rule.typ,
+///     TypeKind::String { max_length: None, flags: StringFlags::default() }
+/// );
+/// assert_eq!(rule.value, Value::String("\x7fELF".to_string()));
+/// assert_eq!(rule.message, "ELF");
+///
+/// let (_, child) = parse_rule_line(">4 byte 2 64-bit").unwrap();
+/// assert_eq!(child.level, 1);
+/// assert_eq!(child.op, Operator::Equal);
+/// assert_eq!(child.value, Value::Int(2));
+/// ```
+///
+/// # Errors
+///
+/// Returns a nom parsing error if any column of the rule is malformed.
+pub fn parse_rule_line(input: &str) -> IResult<&str, MagicRule> {
+    let (input, levels) = many0(char('>')).parse(input)?;
+    let level = u32::try_from(levels.len())
+        .map_err(|_| nom::Err::Failure(NomError::new(input, nom::error::ErrorKind::TooLarge)))?;
+
+    let (input, offset) = parse_offset(input)?;
+    let (input, mut typ) = parse_type(input)?;
+    let (input, offset) = parse_indirect_relative_flag(input, &typ, offset);
+    let (input, mask) = if typ.is_integer() {
+        opt(parse_type_mask).parse(input)?
+    } else {
+        (input, None)
+    };
+    let (input, _) = nom::character::complete::space0(input)?;
+
+    let (input, op, value) = match (&mut typ, parse_any_value(input)) {
+        (TypeKind::Name { name }, _) => {
+            let (input, identifier) =
+                nom::bytes::complete::take_till1(char::is_whitespace).parse(input)?;
+            identifier.clone_into(name);
+            (
+                input,
+                Operator::Equal,
+                Value::String(identifier.to_string()),
+            )
+        }
+        (TypeKind::Use { name, byte_swap }, _) => {
+            // `use ^name` runs the subroutine with the byte order flipped
+            let (input, swap) = opt(char('^')).parse(input)?;
+            *byte_swap = swap.is_some();
+            let (input, identifier) =
+                nom::bytes::complete::take_till1(char::is_whitespace).parse(input)?;
+            identifier.clone_into(name);
+            (
+                input,
+                Operator::Equal,
+                Value::String(identifier.to_string()),
+            )
+        }
+        // Value-less types commonly carry an `x` placeholder before the message
+        (TypeKind::Indirect, rest) => (rest.unwrap_or(input), Operator::Equal, Value::Uint(0)),
+        (typ, rest) if !typ.requires_value() => {
+            (rest.unwrap_or(input), Operator::Always, Value::Uint(0))
+        }
+        (_, Some(rest)) => (rest, Operator::Always, Value::Uint(0)),
+        (TypeKind::String { .. } | TypeKind::Search { .. }, None) => {
+            let (input, op) = opt(parse_operator).parse(input)?;
+            let (input, value) = parse_string_value(input)?;
+            (input, op.unwrap_or(Operator::Equal), value)
+        }
+        // Extension readers may produce numbers or strings
+        (TypeKind::Extension { .. }, None) => {
+            let (input, op) = opt(parse_operator).parse(input)?;
+            let (input, value) = alt((parse_numeric_value, parse_string_value)).parse(input)?;
+            (input, op.unwrap_or(Operator::Equal), value)
+        }
+        (_, None) => {
+            let (input, (op, value)) = parse_numeric_test(input, &typ)?;
+            (input, op, value)
+        }
+    };
+
+    // All whitespace before the message separates it from the value, as in magic(5)
+    let (input, _) = nom::character::complete::space0(input)?;
+    let message = input.trim_end().to_string();
+
+    Ok((
+        "",
+        MagicRule {
+            offset,
+            typ,
+            op,
+            value,
+            message,
+            children: vec![],
+            level,
+            source_line: None,
+            apple_type: None,
+            apple_creator: None,
+            mask,
+            mime_type: None,
+            extensions: Vec::new(),
+            negate: false,
+            store: false,
+        },
+    ))
+}
+
+/// Parse the `&mask` written directly after an integer type (`byte&0xf0`)
+fn parse_type_mask(input: &str) -> IResult<&str, u64> {
+    let (input, _) = char('&').parse(input)?;
+    let (input, mask) = parse_number(input)?;
+    // Negative masks keep their two's complement bits, like libmagic
+    #[allow(clippy::cast_sign_loss)]
+    Ok((input, mask as u64))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -710,6 +1421,215 @@ fn test_parse_offset_boundary_values() {
         );
     }
 
+    #[test]
+    fn test_parse_offset_indirect_valid_pointer_types() {
+        assert_eq!(
+            parse_offset("(0x3c.l)"),
+            Ok((
+                "",
+                OffsetSpec::Indirect {
+                    base_offset: 0x3c,
+                    pointer_type: TypeKind::Long {
+                        endian: Endianness::Little,
+                        signed: false,
+                    },
+                    adjustment: 0,
+                    endian: Endianness::Little,
+                    relative: false,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_offset("(4.b+1)"),
+            Ok((
+                "",
+                OffsetSpec::Indirect {
+                    base_offset: 4,
+                    pointer_type: TypeKind::Byte { signed: false },
+                    adjustment: 1,
+                    endian: Endianness::Little,
+                    relative: false,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_offset("(0x10.Q-8)"),
+            Ok((
+                "",
+                OffsetSpec::Indirect {
+                    base_offset: 0x10,
+                    pointer_type: TypeKind::Quad {
+                        endian: Endianness::Big,
+                        signed: false,
+                    },
+                    adjustment: -8,
+                    endian: Endianness::Big,
+                    relative: false,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_offset("(2.H)"),
+            Ok((
+                "",
+                OffsetSpec::Indirect {
+                    base_offset: 2,
+                    pointer_type: TypeKind::Short {
+                        endian: Endianness::Big,
+                        signed: false,
+                    },
+                    adjustment: 0,
+                    endian: Endianness::Big,
+                    relative: false,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_offset_indirect_default_pointer_type() {
+        assert_eq!(
+            parse_offset("(0x3c) long"),
+            Ok((
+                "long",
+                OffsetSpec::Indirect {
+                    base_offset: 0x3c,
+                    pointer_type: TypeKind::Long {
+                        endian: Endianness::Little,
+                        signed: false,
+                    },
+                    adjustment: 0,
+                    endian: Endianness::Little,
+                    relative: false,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_offset_indirect_relative() {
+        assert_eq!(
+            parse_offset("(&0x8.b+2)"),
+            Ok((
+                "",
+                OffsetSpec::Indirect {
+                    base_offset: 8,
+                    pointer_type: TypeKind::Byte { signed: false },
+                    adjustment: 2,
+                    endian: Endianness::Little,
+                    relative: true,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_line_value_list() {
+        let (_, rule) = parse_rule_line("0 ubyte 1|2|0x10 allowed").unwrap();
+        assert_eq!(rule.op, Operator::Equal);
+        assert_eq!(
+            rule.value,
+            Value::OneOf(vec![Value::Uint(1), Value::Uint(2), Value::Uint(16)])
+        );
+        assert_eq!(rule.message, "allowed");
+
+        // Members are coerced to the type's signedness like single values
+        let (_, rule) = parse_rule_line("0 byte !1|0xff other").unwrap();
+        assert_eq!(rule.op, Operator::NotEqual);
+        assert_eq!(
+            rule.value,
+            Value::OneOf(vec![Value::Int(1), Value::Int(-1)])
+        );
+
+        // Other operators take a single value
+        let (_, rule) = parse_rule_line("0 ubyte <5 small").unwrap();
+        assert_eq!(rule.value, Value::Uint(5));
+    }
+
+    #[test]
+    fn test_parse_offset_distinguishes_relative_forms() {
+        let offset_of = |line: &str| parse_rule_line(line).unwrap().1.offset;
+
+        assert_eq!(offset_of(">&4 byte 1 relative"), OffsetSpec::Relative(4));
+        assert_eq!(
+            offset_of(">(&4.l) byte 1 relative indirect"),
+            OffsetSpec::Indirect {
+                base_offset: 4,
+                pointer_type: TypeKind::Long {
+                    endian: Endianness::Little,
+                    signed: false,
+                },
+                adjustment: 0,
+                endian: Endianness::Little,
+                relative: true,
+            }
+        );
+        assert_eq!(offset_of(">4 byte 1 absolute"), OffsetSpec::Absolute(4));
+
+        assert_eq!(parse_offset("&0x10"), Ok(("", OffsetSpec::Relative(16))));
+        assert!(parse_offset("&").is_err());
+    }
+
+    #[test]
+    fn test_parse_offset_indirect_short_pointer_letters() {
+        // `.s` is a short, like `.h`, not a string
+        assert_eq!(
+            parse_offset("(0x10.s)"),
+            Ok((
+                "",
+                OffsetSpec::Indirect {
+                    base_offset: 16,
+                    pointer_type: TypeKind::Short {
+                        endian: Endianness::Little,
+                        signed: false,
+                    },
+                    adjustment: 0,
+                    endian: Endianness::Little,
+                    relative: false,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_offset("(0x10.S+4)"),
+            Ok((
+                "",
+                OffsetSpec::Indirect {
+                    base_offset: 16,
+                    pointer_type: TypeKind::Short {
+                        endian: Endianness::Big,
+                        signed: false,
+                    },
+                    adjustment: 4,
+                    endian: Endianness::Big,
+                    relative: false,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_offset_indirect_rejects_unknown_pointer_type() {
+        assert!(matches!(
+            parse_offset("(0x10.z)"),
+            Err(nom::Err::Failure(NomError {
+                code: nom::error::ErrorKind::OneOf,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            parse_offset("(0x10.Z+4)"),
+            Err(nom::Err::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_offset_indirect_malformed() {
+        assert!(parse_offset("(0x10.l").is_err());
+        assert!(parse_offset("(.l)").is_err());
+        assert!(parse_offset("()").is_err());
+        assert!(parse_offset("(0x10.l+)").is_err());
+    }
+
     // Operator parsing tests
     #[test]
     fn test_parse_operator_equality() {
@@ -794,9 +1714,6 @@ fn test_parse_operator_invalid_input() {
         assert!(parse_operator("").is_err());
         assert!(parse_operator("abc").is_err());
         assert!(parse_operator("123").is_err());
-        assert!(parse_operator(">").is_err());
-        assert!(parse_operator("<").is_err());
-        assert!(parse_operator("!").is_err());
         assert!(parse_operator("===").is_err()); // Too many equals
         assert!(parse_operator("&&").is_err()); // Double ampersand not supported
     }
@@ -853,6 +1770,11 @@ fn test_parse_operator_all_variants() {
             ("!=", Operator::NotEqual),
             ("<>", Operator::NotEqual),
             ("&", Operator::BitwiseAnd),
+            ("!", Operator::NotEqual),
+            ("<", Operator::LessThan),
+            (">", Operator::GreaterThan),
+            ("^", Operator::BitwiseXor),
+            ("~", Operator::BitwiseNot),
         ];
 
         for (input, expected) in test_cases {
@@ -1167,6 +2089,21 @@ fn test_parse_value_hex_byte_sequences() {
         assert_eq!(parse_value("\\xCd"), Ok(("", Value::Bytes(vec![0xcd]))));
     }
 
+    #[test]
+    fn test_parse_value_hex_escape_run_ends_at_whitespace() {
+        assert_eq!(
+            parse_value("\\x7f\\x45\\x4c\\x46\tELF executable"),
+            Ok((
+                "\tELF executable",
+                Value::Bytes(vec![0x7f, 0x45, 0x4c, 0x46])
+            ))
+        );
+
+        // Text or a truncated escape directly after the run is not part of a byte literal
+        assert!(parse_value("\\x7fELF").is_err());
+        assert!(parse_value("\\x7f\\x4").is_err());
+    }
+
     #[test]
     fn test_parse_value_with_whitespace() {
         // All value types with whitespace - trailing whitespace is consumed by individual parsers
@@ -1343,4 +2280,296 @@ fn test_parse_operator_whitespace_handling() {
             }
         }
     }
+
+    #[test]
+    fn test_parse_rule_line_value_operator_prefixes() {
+        let cases = [
+            ("0 byte =0x10", Operator::Equal),
+            ("0 byte 0x10", Operator::Equal),
+            ("0 byte !0x10", Operator::NotEqual),
+            ("0 byte &0x10", Operator::BitwiseAnd),
+            ("0 byte <0x10", Operator::LessThan),
+            ("0 byte >0x10", Operator::GreaterThan),
+            ("0 byte ^0x10", Operator::BitwiseXor),
+            ("0 byte ~0x10", Operator::BitwiseNot),
+        ];
+
+        for (line, expected) in cases {
+            let (_, rule) = parse_rule_line(line).unwrap();
+            assert_eq!(rule.op, expected, "operator for '{line}'");
+            assert_eq!(rule.value, Value::Int(0x10), "value for '{line}'");
+        }
+
+        let (_, rule) = parse_rule_line("0 string >\\0 text").unwrap();
+        assert_eq!(rule.op, Operator::GreaterThan);
+        assert_eq!(rule.value, Value::String("\0".to_string()));
+        assert_eq!(rule.message, "text");
+    }
+
+    #[test]
+    fn test_parse_rule_line_type_mask() {
+        let (_, rule) = parse_rule_line("0 byte&0xf0 0x40 high nibble").unwrap();
+        assert_eq!(rule.typ, TypeKind::Byte { signed: true });
+        assert_eq!(rule.mask, Some(0xf0));
+        assert_eq!(rule.op, Operator::Equal);
+        assert_eq!(rule.value, Value::Int(0x40));
+        assert_eq!(rule.message, "high nibble");
+
+        // `&` after whitespace is the operator, not a mask
+        let (_, rule) = parse_rule_line("0 byte &0x0f flags").unwrap();
+        assert_eq!(rule.mask, None);
+        assert_eq!(rule.op, Operator::BitwiseAnd);
+
+        let (_, rule) = parse_rule_line("4 ubelong&0xffff0000 >0x10000 big").unwrap();
+        assert_eq!(rule.mask, Some(0xffff_0000));
+        assert_eq!(rule.op, Operator::GreaterThan);
+
+        // Only integer types take a mask
+        let (_, rule) = parse_rule_line("0 string&x y").unwrap();
+        assert_eq!(rule.mask, None);
+    }
+
+    #[test]
+    fn test_parse_rule_line_any_value() {
+        let (_, rule) = parse_rule_line(">4 byte x version %d").unwrap();
+        assert_eq!(rule.op, Operator::Always);
+        assert_eq!(rule.message, "version %d");
+
+        let (_, rule) = parse_rule_line("0 string x name %s").unwrap();
+        assert_eq!(rule.op, Operator::Always);
+        assert_eq!(rule.message, "name %s");
+
+        // An `x` that starts a longer token is a literal string value
+        let (_, rule) = parse_rule_line("0 string xml XML").unwrap();
+        assert_eq!(rule.op, Operator::Equal);
+        assert_eq!(rule.value, Value::String("xml".to_string()));
+    }
+
+    #[test]
+    fn test_parse_type_checksum_range() {
+        assert_eq!(
+            parse_type("crc32/0,9"),
+            Ok((
+                "",
+                TypeKind::Checksum {
+                    algo: ChecksumAlgo::Crc32,
+                    range: (0, 9)
+                }
+            ))
+        );
+        assert_eq!(
+            parse_type("adler32/4,4 x"),
+            Ok((
+                " x",
+                TypeKind::Checksum {
+                    algo: ChecksumAlgo::Adler32,
+                    range: (4, 4)
+                }
+            ))
+        );
+
+        // A reversed range is rejected outright, a missing one is not a checksum
+        assert!(matches!(parse_type("crc32/9,0"), Err(nom::Err::Failure(_))));
+        assert!(parse_type("crc32").is_err());
+        assert!(parse_type("crc32/9").is_err());
+    }
+
+    #[test]
+    fn test_parse_type_integer_signedness() {
+        for base in ["byte", "short", "long", "quad"] {
+            let prefixes: &[&str] = if base == "byte" {
+                &[""]
+            } else {
+                &["", "le", "be"]
+            };
+            for endian in prefixes {
+                for (sign, signed) in [("", true), ("u", false)] {
+                    let name = format!("{sign}{endian}{base}");
+                    let (rest, typ) = parse_type(&name).unwrap();
+                    assert_eq!(rest, "", "{name}");
+                    let actual = match typ {
+                        TypeKind::Byte { signed }
+                        | TypeKind::Short { signed, .. }
+                        | TypeKind::Long { signed, .. }
+                        | TypeKind::Quad { signed, .. } => signed,
+                        other => panic!("{name} parsed as {other:?}"),
+                    };
+                    assert_eq!(actual, signed, "{name}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_rule_line_byte_value_follows_signedness() {
+        let (_, rule) = parse_rule_line("0 byte 0xff minus one").unwrap();
+        assert_eq!(rule.value, Value::Int(-1));
+        let (_, rule) = parse_rule_line("0 ubyte 0xff all ones").unwrap();
+        assert_eq!(rule.value, Value::Uint(0xff));
+        let (_, rule) = parse_rule_line("0 ubyte -1 all ones").unwrap();
+        assert_eq!(rule.value, Value::Uint(0xff));
+    }
+
+    #[test]
+    fn test_parse_type_keywords() {
+        assert_eq!(
+            parse_type("byte"),
+            Ok(("", TypeKind::Byte { signed: true }))
+        );
+        assert_eq!(
+            parse_type("short"),
+            Ok((
+                "",
+                TypeKind::Short {
+                    endian: Endianness::Native,
+                    signed: true
+                }
+            ))
+        );
+        assert_eq!(
+            parse_type("ulequad"),
+            Ok((
+                "",
+                TypeKind::Quad {
+                    endian: Endianness::Little,
+                    signed: false
+                }
+            ))
+        );
+        assert_eq!(
+            parse_type("string/c rest"),
+            Ok((
+                " rest",
+                TypeKind::String {
+                    max_length: None,
+                    flags: StringFlags::from_letters("c"),
+                }
+            ))
+        );
+        assert_eq!(
+            parse_type("search/64/b"),
+            Ok(("", TypeKind::Search { range: 64 }))
+        );
+        assert_eq!(parse_type("indirect"), Ok(("", TypeKind::Indirect)));
+        assert_eq!(parse_type("filesize"), Ok(("", TypeKind::FileSize)));
+        assert_eq!(
+            parse_type("string/wWc"),
+            Ok((
+                "",
+                TypeKind::String {
+                    max_length: None,
+                    flags: StringFlags {
+                        compact_whitespace: true,
+                        optional_blanks: true,
+                        ignore_lower_case: true,
+                        ..StringFlags::default()
+                    },
+                }
+            ))
+        );
+        assert_eq!(
+            parse_type("x-my_type2 rest"),
+            Ok((
+                " rest",
+                TypeKind::Extension {
+                    name: "x-my_type2".to_string()
+                }
+            ))
+        );
+        assert!(parse_type("x-").is_err());
+        assert!(parse_type("search").is_err());
+        assert!(parse_type("lebyte").is_err());
+        assert!(parse_type("bogus").is_err());
+    }
+
+    #[test]
+    fn test_parse_rule_line_string_escapes() {
+        let (_, rule) = parse_rule_line(r#"0 string "GIF8\x37a" GIF image"#).unwrap();
+        assert_eq!(rule.value, Value::String("GIF87a".to_string()));
+        assert_eq!(rule.message, "GIF image");
+
+        let (_, rule) = parse_rule_line(r"0 string \xff\xd8 JPEG").unwrap();
+        assert_eq!(rule.value, Value::Bytes(vec![0xff, 0xd8]));
+
+        let (_, rule) = parse_rule_line(r"0 string \xca\xfe\xba\xbe Mach-O universal").unwrap();
+        assert_eq!(rule.value, Value::Bytes(vec![0xca, 0xfe, 0xba, 0xbe]));
+        assert_eq!(rule.message, "Mach-O universal");
+
+        let (_, rule) = parse_rule_line(r"0 string a\ b spaced").unwrap();
+        assert_eq!(rule.value, Value::String("a b".to_string()));
+        assert_eq!(rule.message, "spaced");
+    }
+
+    #[test]
+    fn test_parse_rule_line_numeric() {
+        let (_, rule) = parse_rule_line(">>8 beshort !=0xffff not all ones").unwrap();
+        assert_eq!(rule.level, 2);
+        assert_eq!(rule.op, Operator::NotEqual);
+        assert_eq!(rule.value, Value::Int(-1));
+        assert_eq!(rule.message, "not all ones");
+
+        let (_, rule) = parse_rule_line("0 ulong -1").unwrap();
+        assert_eq!(rule.value, Value::Uint(0xffff_ffff));
+        assert_eq!(rule.message, "");
+    }
+
+    #[test]
+    fn test_parse_rule_line_subroutines() {
+        let (_, rule) = parse_rule_line("0 name elf-header").unwrap();
+        assert_eq!(
+            rule.typ,
+            TypeKind::Name {
+                name: "elf-header".to_string()
+            }
+        );
+
+        let (_, rule) = parse_rule_line(">16 use elf-header ELF header").unwrap();
+        assert_eq!(rule.value, Value::String("elf-header".to_string()));
+        assert_eq!(rule.message, "ELF header");
+
+        let (_, rule) = parse_rule_line(">16 use ^elf-header swapped").unwrap();
+        assert_eq!(
+            rule.typ,
+            TypeKind::Use {
+                name: "elf-header".to_string(),
+                byte_swap: true,
+            }
+        );
+        assert_eq!(rule.message, "swapped");
+
+        let (_, rule) = parse_rule_line("0 indirect x").unwrap();
+        assert_eq!(rule.typ, TypeKind::Indirect);
+        assert_eq!(rule.message, "");
+
+        let (_, rule) = parse_rule_line(">>4 indirect/r x embedded").unwrap();
+        assert_eq!(rule.typ, TypeKind::Indirect);
+        assert_eq!(rule.offset, OffsetSpec::Relative(4));
+        assert_eq!(rule.message, "embedded");
+    }
+
+    #[test]
+    fn test_parse_rule_line_without_value_column() {
+        let (_, rule) = parse_rule_line("0 name zip-header").unwrap();
+        assert_eq!(
+            rule.typ,
+            TypeKind::Name {
+                name: "zip-header".to_string()
+            }
+        );
+        assert_eq!(rule.message, "");
+
+        // The message must not be taken for a value
+        let (_, rule) = parse_rule_line(">4 default unknown version").unwrap();
+        assert_eq!(rule.typ, TypeKind::Default);
+        assert_eq!(rule.op, Operator::Always);
+        assert_eq!(rule.message, "unknown version");
+
+        let (_, rule) = parse_rule_line(">4 default x unknown version").unwrap();
+        assert_eq!(rule.typ, TypeKind::Default);
+        assert_eq!(rule.message, "unknown version");
+
+        let (_, rule) = parse_rule_line(">4 clear").unwrap();
+        assert_eq!(rule.typ, TypeKind::Clear);
+        assert_eq!(rule.message, "");
+    }
 }
@@ -0,0 +1,203 @@
+//! Optional checks for suspicious rules in a loaded magic database
+//!
+//! These checks never reject a database; they report rules that are valid but
+//! probably not what the author intended, such as a top-level signature that
+//! can never be reported because an earlier rule tests exactly the same bytes.
+
+use crate::parser::ast::{MagicRule, OffsetSpec, Operator, TypeKind, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Which lint checks to run when loading a database
+///
+/// All checks are off by default, so linting is opt-in.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::parser::lint::LintOptions;
+///
+/// assert!(!LintOptions::default().shadowed_rules);
+/// assert!(LintOptions::all().shadowed_rules);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LintOptions {
+    /// Flag top-level rules whose offset, type, operator and value repeat an earlier rule
+    pub shadowed_rules: bool,
+}
+
+impl LintOptions {
+    /// Enable every lint check
+    #[must_use]
+    pub const fn all() -> Self {
+        Self {
+            shadowed_rules: true,
+        }
+    }
+}
+
+/// A suspicious rule found by a lint check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintWarning {
+    /// A top-level rule tests the same signature as an earlier top-level rule
+    ///
+    /// The earlier rule matches whenever the later one would, so the later
+    /// rule's message is never reported on its own.
+    ShadowedRule {
+        /// Source line of the shadowed rule, if known
+        line: Option<usize>,
+        /// Message of the shadowed rule
+        message: String,
+        /// Source line of the earlier rule with the same signature, if known
+        shadowed_by_line: Option<usize>,
+        /// Message of the earlier rule with the same signature
+        shadowed_by_message: String,
+    },
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |line: &Option<usize>| line.map_or_else(|| "?".to_string(), |l| l.to_string());
+        match self {
+            Self::ShadowedRule {
+                line: shadowed,
+                message,
+                shadowed_by_line,
+                shadowed_by_message,
+            } => write!(
+                f,
+                "line {}: shadowed rule '{message}' has the same signature as line {} ('{shadowed_by_message}')",
+                line(shadowed),
+                line(shadowed_by_line),
+            ),
+        }
+    }
+}
+
+/// Run the lint checks enabled in `options` over a parsed rule set
+///
+/// # Examples
+///
+/// ```rust
+/// use libmagic_rs::parser::lint::{check_rules, LintOptions};
+/// use libmagic_rs::parser::parse_magic_string;
+///
+/// let rules = parse_magic_string("0 string PK ZIP\n0 string PK archive\n")?;
+/// assert!(check_rules(&rules, LintOptions::default()).is_empty());
+///
+/// let warnings = check_rules(&rules, LintOptions::all());
+/// assert_eq!(warnings.len(), 1);
+/// assert!(warnings[0].to_string().contains("shadowed rule 'archive'"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[must_use]
+pub fn check_rules(rules: &[MagicRule], options: LintOptions) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    if options.shadowed_rules {
+        warnings.extend(find_shadowed_rules(rules));
+    }
+    warnings
+}
+
+/// The parts of a rule that decide which bytes it tests and how
+#[derive(PartialEq, Eq, Hash)]
+struct RuleSignature<'a> {
+    offset: &'a OffsetSpec,
+    typ: &'a TypeKind,
+    mask: Option<u64>,
+    op: &'a Operator,
+    value: &'a Value,
+    negate: bool,
+}
+
+impl<'a> RuleSignature<'a> {
+    const fn of(rule: &'a MagicRule) -> Self {
+        Self {
+            offset: &rule.offset,
+            typ: &rule.typ,
+            mask: rule.mask,
+            op: &rule.op,
+            value: &rule.value,
+            negate: rule.negate,
+        }
+    }
+}
+
+/// Find top-level rules whose offset, type, mask, operator and value repeat an earlier rule
+///
+/// Named subroutine definitions are not signatures and are skipped.
+#[must_use]
+pub fn find_shadowed_rules(rules: &[MagicRule]) -> Vec<LintWarning> {
+    let mut seen: HashMap<RuleSignature<'_>, &MagicRule> = HashMap::new();
+    let mut warnings = Vec::new();
+
+    for rule in rules {
+        if matches!(rule.typ, TypeKind::Name { .. }) {
+            continue;
+        }
+
+        let signature = RuleSignature::of(rule);
+        match seen.get(&signature) {
+            Some(first) => warnings.push(LintWarning::ShadowedRule {
+                line: rule.source_line,
+                message: rule.message.clone(),
+                shadowed_by_line: first.source_line,
+                shadowed_by_message: first.message.clone(),
+            }),
+            None => {
+                seen.insert(signature, rule);
+            }
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_magic_string;
+
+    #[test]
+    fn test_find_shadowed_rules_reports_both_lines() {
+        let rules = parse_magic_string(
+            "0 string \\x7fELF ELF\n\
+             0 string PK ZIP\n\
+             # duplicate signature\n\
+             0 string \\x7fELF executable\n",
+        )
+        .unwrap();
+
+        let warnings = find_shadowed_rules(&rules);
+        assert_eq!(
+            warnings,
+            vec![LintWarning::ShadowedRule {
+                line: Some(4),
+                message: "executable".to_string(),
+                shadowed_by_line: Some(1),
+                shadowed_by_message: "ELF".to_string(),
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "line 4: shadowed rule 'executable' has the same signature as line 1 ('ELF')"
+        );
+    }
+
+    #[test]
+    fn test_find_shadowed_rules_ignores_distinct_signatures() {
+        // Same offset and value with a different type, operator or value is not shadowed
+        let rules = parse_magic_string(
+            "0 byte 1 one\n\
+             0 short 1 short one\n\
+             0 byte >1 more\n\
+             0 byte 2 two\n\
+             >1 byte 1 child one\n\
+             0 name sub\n\
+             >0 byte 1 sub one\n",
+        )
+        .unwrap();
+
+        assert!(find_shadowed_rules(&rules).is_empty());
+    }
+}
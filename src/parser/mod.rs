@@ -4,10 +4,954 @@
 //! that can be evaluated against file buffers for type identification.
 
 pub mod ast;
+pub mod codegen;
+pub mod compiled;
 pub mod grammar;
+pub mod lint;
 
 // Re-export AST types for convenience
 pub use ast::{Endianness, MagicRule, OffsetSpec, Operator, TypeKind, Value};
 
 // Re-export parser functions for convenience
-pub use grammar::{parse_number, parse_offset};
+pub use codegen::to_magic_text;
+pub use grammar::{parse_number, parse_offset, parse_rule_line, parse_type};
+
+use crate::{LibmagicError, Result};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+
+/// `!:` directives from magic(5) that are recognized but not acted on yet
+///
+/// These are skipped without a warning, even in strict mode.
+pub const KNOWN_UNIMPLEMENTED_DIRECTIVES: &[&str] = &["strength"];
+
+/// Options controlling how magic source text is parsed
+///
+/// The default is lenient, which suits loading system databases that may use
+/// directives this library does not know. The structural limits bound the
+/// memory a crafted magic file can make the parser allocate; the defaults are
+/// far above what real databases need.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::ParseOptions;
+///
+/// let options = ParseOptions::default();
+/// assert!(!options.strict_directives);
+/// assert_eq!(options.max_children_per_rule, 10_000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Reject unknown `!:` directives with a parse error instead of a warning
+    pub strict_directives: bool,
+    /// Most children any one rule may have
+    pub max_children_per_rule: usize,
+    /// Most rules, at any level, the whole source may contain
+    pub max_rules: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict_directives: false,
+            max_children_per_rule: 10_000,
+            max_rules: 1_000_000,
+        }
+    }
+}
+
+/// A problem found while parsing that did not stop the parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A `!:` directive that is neither supported nor a known magic(5) directive
+    UnknownDirective {
+        /// 1-based line number of the directive
+        line: usize,
+        /// Directive name, without the `!:` prefix
+        directive: String,
+    },
+    /// A `string/` flag letter that magic(5) does not define
+    UnknownStringFlag {
+        /// 1-based line number of the rule
+        line: usize,
+        /// The flag letter
+        flag: char,
+    },
+    /// A `string/` flag letter of magic(5) that this crate does not act on
+    IgnoredStringFlag {
+        /// 1-based line number of the rule
+        line: usize,
+        /// The flag letter
+        flag: char,
+    },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownDirective { line, directive } => {
+                write!(f, "line {line}: unknown directive '!:{directive}' ignored")
+            }
+            Self::UnknownStringFlag { line, flag } => {
+                write!(f, "line {line}: unknown string flag '{flag}' ignored")
+            }
+            Self::IgnoredStringFlag { line, flag } => {
+                write!(f, "line {line}: unsupported string flag '{flag}' ignored")
+            }
+        }
+    }
+}
+
+/// Parse the contents of a magic file into a rule hierarchy
+///
+/// Blank lines and `#` comments are skipped. A rule line ending in an
+/// unescaped backslash continues on the next line, so a long message can span
+/// several lines. Each rule is attached as a child of the closest preceding
+/// rule one level above it. A `!:apple` directive sets the Apple type and
+/// creator codes of the rule before it, `!:mime` sets its MIME type to the rest
+/// of the line, parameters included, and `!:ext` its likely file extensions;
+/// other `!:` directives are skipped.
+///
+/// This uses the default, lenient [`ParseOptions`]; use
+/// [`parse_magic_string_with_options`] to reject unknown directives or to see
+/// the warnings about them.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::parse_magic_string;
+///
+/// let rules = parse_magic_string("0 string PK ZIP\n>4 byte 20 v2.0\n").unwrap();
+/// assert_eq!(rules.len(), 1);
+/// assert_eq!(rules[0].children.len(), 1);
+/// ```
+///
+/// # Errors
+///
+/// Returns `LibmagicError::ParseError` with the 1-based line number if a rule
+/// line is malformed, a continuation rule has no parent, an `!:apple`,
+/// `!:mime` or `!:ext` directive has no preceding rule, a `!:apple` payload is
+/// not 8 characters, or a `!:mime` or `!:ext` payload is empty.
+pub fn parse_magic_string(input: &str) -> Result<Vec<MagicRule>> {
+    parse_magic_string_with_options(input, ParseOptions::default()).map(|(rules, _)| rules)
+}
+
+/// Parse the contents of a magic file with explicit options
+///
+/// Returns the rules together with warnings about lines that were skipped.
+/// An unknown `!:` directive is a warning unless `options.strict_directives`
+/// is set, in which case it is a parse error.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::{parse_magic_string_with_options, ParseOptions};
+///
+/// let source = "0 string %PDF PDF document\n!:mimetype application/pdf\n";
+/// let (rules, warnings) = parse_magic_string_with_options(source, ParseOptions::default())?;
+/// assert_eq!(rules.len(), 1);
+/// assert_eq!(warnings[0].to_string(), "line 2: unknown directive '!:mimetype' ignored");
+///
+/// let strict = ParseOptions { strict_directives: true, ..ParseOptions::default() };
+/// assert!(parse_magic_string_with_options(source, strict).is_err());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns the same errors as [`parse_magic_string`], and a
+/// `LibmagicError::ParseError` for an unknown directive in strict mode or a
+/// source that exceeds one of the structural limits in `options`.
+pub fn parse_magic_string_with_options(
+    input: &str,
+    options: ParseOptions,
+) -> Result<(Vec<MagicRule>, Vec<ParseWarning>)> {
+    let mut builder = RuleTreeBuilder::new(options);
+    let mut roots = Vec::new();
+    let lines = input.lines().map(|line| Ok(Cow::Borrowed(line)));
+    for logical in logical_lines(lines) {
+        let (line_number, line) = logical?;
+        roots.extend(builder.push_line(line_number, &line)?);
+    }
+    roots.extend(builder.finish());
+    Ok((roots, builder.warnings))
+}
+
+/// Parse magic source from a reader, yielding one top-level rule at a time
+///
+/// A top-level rule is yielded with all of its children attached, as soon as
+/// the next top-level rule or the end of the input shows that it is complete,
+/// so a large database never has to be held in memory as text or as a whole
+/// rule set. Collecting the iterator gives the same rules as
+/// [`parse_magic_string`], which uses the same default, lenient options.
+///
+/// Lines that are not valid UTF-8 are decoded lossily, so a stray byte from
+/// another encoding, typically in a comment, does not stop the whole source
+/// from loading. Iteration stops after the first error.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::parse_rules_iter;
+///
+/// let source = "0 string PK ZIP\n>4 byte 20 v2.0\n0 string %PDF PDF\n";
+/// let mut rules = parse_rules_iter(source.as_bytes());
+///
+/// let zip = rules.next().unwrap()?;
+/// assert_eq!(zip.message, "ZIP");
+/// assert_eq!(zip.children.len(), 1);
+/// assert_eq!(rules.next().unwrap()?.message, "PDF");
+/// assert!(rules.next().is_none());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Yields `LibmagicError::IoError` if the reader fails, and the same parse
+/// errors as [`parse_magic_string`].
+pub fn parse_rules_iter<R: BufRead>(reader: R) -> impl Iterator<Item = Result<MagicRule>> {
+    parse_rules_iter_with_options(reader, ParseOptions::default())
+}
+
+/// Parse magic source from a reader as [`parse_rules_iter`] does, with explicit options
+///
+/// Warnings are not collected; use [`parse_magic_string_with_options`] to
+/// see them.
+///
+/// # Examples
+///
+/// ```
+/// use libmagic_rs::parser::{parse_rules_iter_with_options, ParseOptions};
+///
+/// let options = ParseOptions { max_rules: 2, ..ParseOptions::default() };
+/// let source = "0 string PK ZIP\n>4 byte 20 v2.0\n0 string %PDF PDF\n";
+/// let rules: Result<Vec<_>, _> = parse_rules_iter_with_options(source.as_bytes(), options).collect();
+/// assert!(rules.is_err());
+/// ```
+///
+/// # Errors
+///
+/// Yields the same errors as [`parse_rules_iter`], and a
+/// `LibmagicError::ParseError` for a source that exceeds one of the limits in
+/// `options` or an unknown directive in strict mode.
+pub fn parse_rules_iter_with_options<R: BufRead>(
+    reader: R,
+    options: ParseOptions,
+) -> impl Iterator<Item = Result<MagicRule>> {
+    let lines = reader.split(b'\n').map(|line| {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(Cow::Owned(match String::from_utf8(line) {
+            Ok(text) => text,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        }))
+    });
+    let mut lines = logical_lines(lines);
+    let mut builder = RuleTreeBuilder::new(options);
+    let mut done = false;
+
+    std::iter::from_fn(move || {
+        while !done {
+            let Some(logical) = lines.next() else {
+                done = true;
+                return builder.finish().map(Ok);
+            };
+            let parsed =
+                logical.and_then(|(line_number, line)| builder.push_line(line_number, &line));
+            match parsed {
+                Ok(Some(rule)) => return Some(Ok(rule)),
+                Ok(None) => {}
+                Err(e) => {
+                    done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    })
+}
+
+/// Builds the rule hierarchy one logical line at a time
+struct RuleTreeBuilder {
+    options: ParseOptions,
+    /// Chain of open rules, one per level, from the current root downwards
+    stack: Vec<MagicRule>,
+    warnings: Vec<ParseWarning>,
+    /// Rules parsed so far, at any level
+    rule_count: usize,
+}
+
+impl RuleTreeBuilder {
+    const fn new(options: ParseOptions) -> Self {
+        Self {
+            options,
+            stack: Vec::new(),
+            warnings: Vec::new(),
+            rule_count: 0,
+        }
+    }
+
+    /// Add one logical line, returning the previous top-level rule if this line completes it
+    fn push_line(&mut self, line_number: usize, line: &str) -> Result<Option<MagicRule>> {
+        let trimmed = line.trim();
+        if let Some(directive) = trimmed.strip_prefix("!:") {
+            self.push_directive(line_number, directive)?;
+            return Ok(None);
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (_, mut rule) = parse_rule_line(trimmed).map_err(|e| LibmagicError::ParseError {
+            line: line_number,
+            message: match grammar::unsupported_type_name(trimmed) {
+                Some(name) => format!("Unsupported type '{name}' in magic rule '{trimmed}'"),
+                None => format!("Invalid magic rule '{trimmed}': {e}"),
+            },
+        })?;
+
+        let level = rule.level as usize;
+        if level > self.stack.len() {
+            return Err(LibmagicError::ParseError {
+                line: line_number,
+                message: format!("Rule at level {level} has no parent rule"),
+            });
+        }
+
+        rule.source_line = Some(line_number);
+        self.rule_count += 1;
+        if self.rule_count > self.options.max_rules {
+            return Err(LibmagicError::ParseError {
+                line: line_number,
+                message: format!("More than {} rules", self.options.max_rules),
+            });
+        }
+        let letters = grammar::string_flag_letters(trimmed).unwrap_or_default();
+        for flag in letters
+            .chars()
+            .filter(|&c| !ast::StringFlags::is_supported_letter(c))
+        {
+            self.warnings
+                .push(if ast::StringFlags::is_known_letter(flag) {
+                    ParseWarning::IgnoredStringFlag {
+                        line: line_number,
+                        flag,
+                    }
+                } else {
+                    ParseWarning::UnknownStringFlag {
+                        line: line_number,
+                        flag,
+                    }
+                });
+        }
+        let completed = close_levels(&mut self.stack, level);
+        let max_children = self.options.max_children_per_rule;
+        if self
+            .stack
+            .last()
+            .is_some_and(|parent| parent.children.len() >= max_children)
+        {
+            return Err(LibmagicError::ParseError {
+                line: line_number,
+                message: format!("Rule has more than {max_children} children"),
+            });
+        }
+        self.stack.push(rule);
+        Ok(completed)
+    }
+
+    /// Apply or skip the `!:` directive on `line_number`, given without its prefix
+    fn push_directive(&mut self, line_number: usize, directive: &str) -> Result<()> {
+        let (name, payload) = directive
+            .split_once(char::is_whitespace)
+            .unwrap_or((directive, ""));
+        if KNOWN_UNIMPLEMENTED_DIRECTIVES.contains(&name) {
+            return Ok(());
+        }
+        if !matches!(name, "apple" | "mime" | "ext") {
+            if self.options.strict_directives {
+                return Err(LibmagicError::ParseError {
+                    line: line_number,
+                    message: format!("Unknown directive '!:{name}'"),
+                });
+            }
+            self.warnings.push(ParseWarning::UnknownDirective {
+                line: line_number,
+                directive: name.to_string(),
+            });
+            return Ok(());
+        }
+
+        let rule = self
+            .stack
+            .last_mut()
+            .ok_or_else(|| LibmagicError::ParseError {
+                line: line_number,
+                message: format!("!:{name} directive has no preceding rule"),
+            })?;
+        apply_directive(rule, name, payload.trim()).map_err(|message| LibmagicError::ParseError {
+            line: line_number,
+            message,
+        })
+    }
+
+    /// Close every open rule, returning the last top-level rule if there is one
+    fn finish(&mut self) -> Option<MagicRule> {
+        close_levels(&mut self.stack, 0)
+    }
+}
+
+/// Iterate over physical `lines`, joining rule lines continued with a trailing `\`
+///
+/// Each item is the 1-based number of the first physical line and the joined
+/// text, without the continuation backslashes. A read error is passed through.
+fn logical_lines<'a>(
+    mut lines: impl Iterator<Item = Result<Cow<'a, str>>>,
+) -> impl Iterator<Item = Result<(usize, Cow<'a, str>)>> {
+    let mut line_number = 0;
+    std::iter::from_fn(move || {
+        let first = match lines.next()? {
+            Ok(first) => first,
+            Err(e) => return Some(Err(e)),
+        };
+        line_number += 1;
+        let start = line_number;
+        let Some(head_len) = continuation(&first).map(str::len) else {
+            return Some(Ok((start, first)));
+        };
+
+        let mut joined = first[..head_len].to_string();
+        for next in lines.by_ref() {
+            let next = match next {
+                Ok(next) => next,
+                Err(e) => return Some(Err(e)),
+            };
+            line_number += 1;
+            if let Some(head) = continuation(&next) {
+                joined.push_str(head);
+            } else {
+                joined.push_str(&next);
+                break;
+            }
+        }
+        Some(Ok((start, Cow::Owned(joined))))
+    })
+}
+
+/// The text before the continuation backslash, if `line` is a continued rule line
+///
+/// A line ending in `\\` ends with an escaped backslash, such as the last
+/// character of a string value, and is not continued. Comments and directives
+/// are never continued.
+fn continuation(line: &str) -> Option<&str> {
+    let trimmed = line.trim_end();
+    let start = trimmed.trim_start();
+    if start.starts_with('#') || start.starts_with("!:") {
+        return None;
+    }
+    let backslashes = trimmed.bytes().rev().take_while(|&b| b == b'\\').count();
+    (backslashes % 2 == 1).then(|| &trimmed[..trimmed.len() - 1])
+}
+
+/// Set the fields of `rule` that the `!:apple`, `!:mime` or `!:ext` directive `name` carries
+fn apply_directive(
+    rule: &mut MagicRule,
+    name: &str,
+    payload: &str,
+) -> std::result::Result<(), String> {
+    match name {
+        "apple" => {
+            let (apple_type, apple_creator) = parse_apple_payload(payload).ok_or_else(|| {
+                format!(
+                    "!:apple payload '{payload}' must be a 4-character type followed by a 4-character creator"
+                )
+            })?;
+            rule.apple_type = Some(apple_type);
+            rule.apple_creator = Some(apple_creator);
+        }
+        "mime" => {
+            if payload.is_empty() {
+                return Err("!:mime directive has no MIME type".to_string());
+            }
+            rule.mime_type = Some(payload.to_string());
+        }
+        _ => {
+            let extensions: Vec<String> = payload
+                .split('/')
+                .map(str::trim)
+                .filter(|ext| !ext.is_empty())
+                .map(str::to_string)
+                .collect();
+            if extensions.is_empty() {
+                return Err(format!("!:{name} directive has no extensions"));
+            }
+            rule.extensions = extensions;
+        }
+    }
+    Ok(())
+}
+
+/// Split an `!:apple` payload into its type and creator codes
+///
+/// The payload must be exactly 8 characters, such as `TEXTttxt`.
+fn parse_apple_payload(payload: &str) -> Option<(String, String)> {
+    let payload = payload.trim();
+    if payload.chars().count() != 8 || payload.contains(char::is_whitespace) {
+        return None;
+    }
+    let split = payload.char_indices().nth(4).map(|(index, _)| index)?;
+    let (apple_type, apple_creator) = payload.split_at(split);
+    Some((apple_type.to_string(), apple_creator.to_string()))
+}
+
+/// Pop open rules until only `depth` remain, attaching each to its parent
+///
+/// Returns the top-level rule if it was closed as well.
+fn close_levels(stack: &mut Vec<MagicRule>, depth: usize) -> Option<MagicRule> {
+    while stack.len() > depth {
+        let rule = stack.pop()?;
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(rule),
+            None => return Some(rule),
+        }
+    }
+    None
+}
+
+/// Read and parse a magic file from disk
+///
+/// # Errors
+///
+/// Returns `LibmagicError::IoError` if the file cannot be read, and
+/// `LibmagicError::ParseError` if its contents are not valid magic rules.
+pub fn parse_magic_file<P: AsRef<Path>>(path: P) -> Result<Vec<MagicRule>> {
+    let file = std::fs::File::open(path.as_ref())?;
+    parse_rules_iter(std::io::BufReader::new(file)).collect()
+}
+
+/// Load rules from a magic source file or a compiled database
+///
+/// Files starting with the compiled database signature are deserialized
+/// directly; anything else is parsed as magic source.
+///
+/// # Errors
+///
+/// Returns `LibmagicError::IoError` if the file cannot be read,
+/// `LibmagicError::InvalidFormat` if a compiled database is corrupt, and
+/// `LibmagicError::ParseError` if magic source is malformed.
+pub fn load_magic_file<P: AsRef<Path>>(path: P) -> Result<Vec<MagicRule>> {
+    let data = std::fs::read(path.as_ref())?;
+    if compiled::is_compiled(&data) {
+        return compiled::deserialize_rules(&data);
+    }
+
+    parse_rules_iter(data.as_slice()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_magic_string_hierarchy() {
+        let input = "\
+# ELF files
+0 string \\x7fELF ELF
+>4 byte 1 32-bit
+>4 byte 2 64-bit
+>>5 byte 1 LSB
+
+0 string PK ZIP archive
+";
+        let rules = parse_magic_string(input).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].message, "ELF");
+        assert_eq!(rules[0].children.len(), 2);
+        assert_eq!(rules[0].children[1].message, "64-bit");
+        assert_eq!(rules[0].children[1].children.len(), 1);
+        assert_eq!(rules[0].children[1].children[0].level, 2);
+        assert_eq!(rules[1].message, "ZIP archive");
+        assert!(rules[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_parse_magic_string_records_source_lines() {
+        let input = "\
+# ELF files
+0 string \\x7fELF ELF
+
+>4 byte 2 64-bit
+0 string PK ZIP archive
+";
+        let rules = parse_magic_string(input).unwrap();
+        assert_eq!(rules[0].source_line, Some(2));
+        assert_eq!(rules[0].children[0].source_line, Some(4));
+        assert_eq!(rules[1].source_line, Some(5));
+    }
+
+    #[test]
+    fn test_parse_magic_string_skips_directives() {
+        let rules =
+            parse_magic_string("0 string %PDF PDF document\n!:mime application/pdf\n").unwrap();
+        assert_eq!(rules.len(), 1);
+        assert!(rules[0].children.is_empty());
+    }
+
+    #[test]
+    fn test_parse_magic_string_joins_continued_lines() {
+        let rules = parse_magic_string(
+            "0 string PK ZIP archive with a \\\n\
+             long message\n\
+             >4 byte 20 v2.0\n",
+        )
+        .unwrap();
+        assert_eq!(rules[0].message, "ZIP archive with a long message");
+        assert_eq!(rules[0].source_line, Some(1));
+        assert_eq!(rules[0].children[0].source_line, Some(3));
+
+        // An escaped backslash at the end of a value does not continue the line
+        let rules = parse_magic_string("0 string end\\\\\n>4 byte 1 child\n").unwrap();
+        assert_eq!(rules[0].value, Value::String("end\\".to_string()));
+        assert_eq!(rules[0].children.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_magic_string_apple_directive() {
+        let rules = parse_magic_string(
+            "0 belong 0x00051600 AppleSingle encoded Macintosh file\n\
+             >8 string TEXT text\n\
+             !:apple\tTEXTttxt\n\
+             !:mime application/applefile\n",
+        )
+        .unwrap();
+        assert_eq!(rules[0].apple_type, None);
+        let child = &rules[0].children[0];
+        assert_eq!(child.apple_type.as_deref(), Some("TEXT"));
+        assert_eq!(child.apple_creator.as_deref(), Some("ttxt"));
+    }
+
+    #[test]
+    fn test_parse_magic_string_mime_directive_keeps_parameters() {
+        let rules =
+            parse_magic_string("0 string #! script text\n!:mime text/plain; charset=utf-8 \n")
+                .unwrap();
+        assert_eq!(
+            rules[0].mime_type.as_deref(),
+            Some("text/plain; charset=utf-8")
+        );
+    }
+
+    #[test]
+    fn test_parse_magic_string_mime_directive() {
+        let rules = parse_magic_string(
+            "0 string PK ZIP archive
+             !:mime application/zip
+             >30 string mimetype OpenDocument
+             !:mime  application/vnd.oasis.opendocument 
+",
+        )
+        .unwrap();
+        assert_eq!(rules[0].mime_type.as_deref(), Some("application/zip"));
+        assert_eq!(
+            rules[0].children[0].mime_type.as_deref(),
+            Some("application/vnd.oasis.opendocument")
+        );
+
+        for (input, expected) in [
+            (
+                "!:mime text/plain
+",
+                "no preceding rule",
+            ),
+            (
+                "0 byte 1 one
+!:mime
+",
+                "no MIME type",
+            ),
+        ] {
+            match parse_magic_string(input).unwrap_err() {
+                LibmagicError::ParseError { message, .. } => {
+                    assert!(message.contains(expected), "{message}");
+                }
+                other => panic!("Expected ParseError, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_magic_string_ext_directive() {
+        let rules =
+            parse_magic_string("0 beshort 0xffd8 JPEG image data\n!:ext jpg/jpeg\n").unwrap();
+        assert_eq!(rules[0].extensions, ["jpg", "jpeg"]);
+
+        match parse_magic_string("0 byte 1 one\n!:ext /\n").unwrap_err() {
+            LibmagicError::ParseError { line, message } => {
+                assert_eq!(line, 2);
+                assert!(message.contains("no extensions"), "{message}");
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_magic_string_rejects_bad_apple_directive() {
+        for input in [
+            "0 byte 1 one\n!:apple TEXT\n",
+            "0 byte 1 one\n!:apple TEXTttxt!\n",
+            "0 byte 1 one\n!:apple\n",
+            "0 byte 1 one\n!:apple TE Tttxt\n",
+        ] {
+            match parse_magic_string(input).unwrap_err() {
+                LibmagicError::ParseError { line, message } => {
+                    assert_eq!(line, 2, "{input}");
+                    assert!(message.contains("!:apple"), "{message}");
+                }
+                other => panic!("Expected ParseError, got {other:?}"),
+            }
+        }
+
+        match parse_magic_string("# no rule yet\n!:apple TEXTttxt\n").unwrap_err() {
+            LibmagicError::ParseError { line, message } => {
+                assert_eq!(line, 2);
+                assert!(message.contains("no preceding rule"));
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_magic_string_warns_on_unknown_directive() {
+        let source = "0 string %PDF PDF document\n\
+                      !:mimetype application/pdf\n\
+                      !:mime application/pdf\n\
+                      !:ext pdf\n";
+        let (rules, warnings) =
+            parse_magic_string_with_options(source, ParseOptions::default()).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(
+            warnings,
+            vec![ParseWarning::UnknownDirective {
+                line: 2,
+                directive: "mimetype".to_string(),
+            }]
+        );
+        assert_eq!(parse_magic_string(source).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_magic_string_warns_on_unknown_string_flag() {
+        let source = "0 string/Tc Report report\n>8 string/Tq x %s\n";
+        let (rules, warnings) =
+            parse_magic_string_with_options(source, ParseOptions::default()).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(
+            warnings,
+            vec![ParseWarning::UnknownStringFlag { line: 2, flag: 'q' }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "line 2: unknown string flag 'q' ignored"
+        );
+        let ast::TypeKind::String { flags, .. } = &rules[0].children[0].typ else {
+            panic!("expected a string rule");
+        };
+        assert!(flags.trim);
+    }
+
+    #[test]
+    fn test_parse_magic_string_warns_on_ignored_string_flag() {
+        let source = "0 string/c report report\n>8 string/tf x %s\n";
+        let (rules, warnings) =
+            parse_magic_string_with_options(source, ParseOptions::default()).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                ParseWarning::IgnoredStringFlag { line: 2, flag: 't' },
+                ParseWarning::IgnoredStringFlag { line: 2, flag: 'f' },
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "line 2: unsupported string flag 't' ignored"
+        );
+        let ast::TypeKind::String { flags, .. } = &rules[0].typ else {
+            panic!("expected a string rule");
+        };
+        assert!(flags.ignore_lower_case);
+    }
+
+    #[test]
+    fn test_parse_magic_string_limits_children_per_rule() {
+        let options = ParseOptions {
+            max_children_per_rule: 3,
+            ..ParseOptions::default()
+        };
+        let source = |children: usize| {
+            let mut source = "0 byte 1 parent\n".to_string();
+            source.push_str(&">1 byte 2 child\n".repeat(children));
+            source
+        };
+
+        let (rules, _) = parse_magic_string_with_options(&source(3), options).unwrap();
+        assert_eq!(rules[0].children.len(), 3);
+        match parse_magic_string_with_options(&source(4), options).unwrap_err() {
+            LibmagicError::ParseError { line, message } => {
+                assert_eq!(line, 5);
+                assert!(message.contains("more than 3 children"), "{message}");
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_magic_string_limits_total_rules() {
+        let options = ParseOptions {
+            max_rules: 4,
+            ..ParseOptions::default()
+        };
+        // Children count toward the total as well as top-level rules
+        let source = "0 byte 1 a\n>1 byte 2 b\n0 byte 3 c\n>1 byte 4 d\n";
+        assert_eq!(
+            parse_magic_string_with_options(source, options)
+                .unwrap()
+                .0
+                .len(),
+            2
+        );
+
+        let over = format!("{source}0 byte 5 e\n");
+        match parse_magic_string_with_options(&over, options).unwrap_err() {
+            LibmagicError::ParseError { line, message } => {
+                assert_eq!(line, 5);
+                assert!(message.contains("More than 4 rules"), "{message}");
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_magic_string_strict_directives() {
+        let strict = ParseOptions {
+            strict_directives: true,
+            ..ParseOptions::default()
+        };
+        let source = "0 string %PDF PDF document\n!:mimetype application/pdf\n";
+        match parse_magic_string_with_options(source, strict).unwrap_err() {
+            LibmagicError::ParseError { line, message } => {
+                assert_eq!(line, 2);
+                assert!(message.contains("!:mimetype"), "{message}");
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+
+        // Known directives are still accepted
+        let source = "0 string %PDF PDF document\n!:mime application/pdf\n!:strength +10\n";
+        let (rules, warnings) = parse_magic_string_with_options(source, strict).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_magic_string_orphan_child() {
+        match parse_magic_string("0 byte 1 one\n>>1 byte 2 orphan\n").unwrap_err() {
+            LibmagicError::ParseError { line, .. } => assert_eq!(line, 2),
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_magic_string_invalid_line() {
+        match parse_magic_string("\n0 bogus 1 nope\n").unwrap_err() {
+            LibmagicError::ParseError { line, message } => {
+                assert_eq!(line, 2);
+                assert!(message.contains("0 bogus 1 nope"));
+            }
+            other => panic!("Expected ParseError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_magic_file_source_and_compiled() {
+        let dir = std::env::temp_dir();
+        let source = dir.join(format!("libmagic_rs_load_{}.magic", std::process::id()));
+        let compiled_path = dir.join(format!("libmagic_rs_load_{}.mgc", std::process::id()));
+        std::fs::write(&source, "0 string PK ZIP archive\n>4 byte 20 v2.0\n").unwrap();
+
+        let from_source = load_magic_file(&source).unwrap();
+        compiled::write_compiled(&from_source, &compiled_path).unwrap();
+        let from_compiled = load_magic_file(&compiled_path).unwrap();
+
+        assert_eq!(from_compiled.len(), 1);
+        assert_eq!(from_compiled[0].message, "ZIP archive");
+        assert_eq!(from_compiled[0].children[0].message, "v2.0");
+
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&compiled_path).unwrap();
+    }
+
+    #[test]
+    fn test_load_magic_file_tolerates_non_utf8_comment() {
+        let path =
+            std::env::temp_dir().join(format!("libmagic_rs_latin1_{}.magic", std::process::id()));
+        std::fs::write(
+            &path,
+            b"# Caf\xe9 formats \xff\r\n0 string PK ZIP archive\r\n0 string %PDF- PDF document\n",
+        )
+        .unwrap();
+
+        let rules = load_magic_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].message, "ZIP archive");
+        assert_eq!(rules[1].message, "PDF document");
+    }
+
+    #[test]
+    fn test_parse_magic_file_missing() {
+        let result = parse_magic_file("/nonexistent/path/to/magic");
+        assert!(matches!(result, Err(LibmagicError::IoError(_))));
+    }
+
+    #[test]
+    fn test_parse_rules_iter_matches_batch_parse() {
+        let source = "# header\n\
+                      0 string PK ZIP archive\n\
+                      !:mime application/zip\n\
+                      >4 byte 20 v2.0\n\
+                      >>5 byte 0 \\\n  with a continued message\n\
+                      >4 byte 10 v1.0\n\
+                      \n\
+                      0 string %PDF PDF document\n\
+                      !:ext pdf\n\
+                      >5 byte x version %c\n";
+
+        let streamed: Vec<MagicRule> = parse_rules_iter(source.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        // `MagicRule` has no `PartialEq`, but its debug form covers every field
+        assert_eq!(
+            format!("{streamed:?}"),
+            format!("{:?}", parse_magic_string(source).unwrap())
+        );
+        assert_eq!(streamed.len(), 2);
+        assert_eq!(streamed[0].children.len(), 2);
+        assert_eq!(streamed[0].children[0].children[0].source_line, Some(5));
+        assert_eq!(streamed[1].source_line, Some(9));
+    }
+
+    #[test]
+    fn test_parse_rules_iter_yields_rules_before_an_error() {
+        let mut rules = parse_rules_iter(&b"0 byte 1 one\n0 byte 2 two\n0 bogus 3 three\n"[..]);
+
+        assert_eq!(rules.next().unwrap().unwrap().message, "one");
+        let error = rules.next().unwrap().unwrap_err();
+        assert!(matches!(error, LibmagicError::ParseError { line: 3, .. }));
+        assert!(rules.next().is_none());
+    }
+}